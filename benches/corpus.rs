@@ -0,0 +1,123 @@
+//! Timings for path number formatting, parsing and minification over the
+//! SVG corpus.
+//!
+//! Usage:
+//!   cargo bench --bench corpus [-- DIR]
+//!
+//! DIR defaults to `tests/corpus`; run `cargo xtask fetch-corpus` first for
+//! the full corpus.
+
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use ignore::WalkBuilder;
+use svag::path::{format_number, parse_path, serialize_path};
+use svag::{Element, Node, minify, parse_svg};
+
+/// Runs per measurement; the fastest one is reported.
+const RUNS: usize = 5;
+
+fn main() {
+    let corpus_dir = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus"));
+
+    let files = read_corpus(&corpus_dir);
+    let paths: Vec<_> = files
+        .iter()
+        .filter_map(|content| parse_svg(content).ok())
+        .flat_map(|doc| {
+            let mut ds = Vec::new();
+            collect_paths(&doc.root, &mut ds);
+            ds
+        })
+        .filter_map(|d| parse_path(&d).ok())
+        .collect();
+    let bytes: usize = files.iter().map(String::len).sum();
+    println!(
+        "corpus: {} ({} files, {} bytes, {} paths)",
+        corpus_dir.display(),
+        files.len(),
+        bytes,
+        paths.len()
+    );
+
+    let numbers = numbers(1_000_000);
+    report("format_number x1M", || {
+        for &n in &numbers {
+            black_box(format_number(black_box(n), 3));
+        }
+    });
+    report("serialize_path", || {
+        for path in &paths {
+            black_box(serialize_path(black_box(path), 3));
+        }
+    });
+    report("parse", || {
+        for content in &files {
+            let _ = black_box(parse_svg(black_box(content)));
+        }
+    });
+    report("minify", || {
+        for content in &files {
+            let _ = black_box(minify(black_box(content)));
+        }
+    });
+}
+
+fn read_corpus(dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    for entry in WalkBuilder::new(dir).git_ignore(false).build() {
+        let entry = entry.unwrap();
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "svg")
+            && let Ok(content) = fs::read_to_string(path)
+        {
+            files.push(content);
+        }
+    }
+    files
+}
+
+fn collect_paths(elem: &Element, out: &mut Vec<String>) {
+    if let Some(d) = elem.get_attr("d") {
+        out.push(d.to_string());
+    }
+    for child in &elem.children {
+        if let Node::Element(child) = child {
+            collect_paths(child, out);
+        }
+    }
+}
+
+/// Deterministic spread of magnitudes and fractional parts, from a fixed LCG.
+fn numbers(count: usize) -> Vec<f64> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..count)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            let mantissa = (state >> 11) as f64 / (1u64 << 53) as f64;
+            let scale = 10f64.powi((state % 7) as i32 - 3);
+            let sign = if state & (1 << 10) == 0 { 1.0 } else { -1.0 };
+            sign * mantissa * scale * 100.0
+        })
+        .collect()
+}
+
+fn report(name: &str, mut f: impl FnMut()) {
+    let best = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    println!("{name:<20} {:>10.2?}", best);
+}
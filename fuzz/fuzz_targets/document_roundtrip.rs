@@ -0,0 +1,28 @@
+//! Structured fuzzing of the optimizer: for any generated document whose
+//! plain serialization is valid, the optimized serialization must be too.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svag::{Document, Options, optimize, parse_svg, serialize};
+
+fuzz_target!(|input: (Document, bool)| {
+    let (mut doc, merge_paths) = input;
+    let options = Options {
+        merge_paths,
+        ..Options::default()
+    };
+
+    // Generated text may not be representable (e.g. "--" in a comment), so
+    // only documents that roundtrip unoptimized are interesting
+    let plain = serialize(&doc, &options);
+    if parse_svg(&plain).is_err() {
+        return;
+    }
+
+    optimize(&mut doc, &options);
+    let minified = serialize(&doc, &options);
+    if let Err(e) = parse_svg(&minified) {
+        panic!("optimized output doesn't parse: {e}\ninput: {plain}\noutput: {minified}");
+    }
+});
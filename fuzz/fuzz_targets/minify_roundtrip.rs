@@ -0,0 +1,20 @@
+//! Any input that parses must minify to output that parses again.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svag::{Options, optimize, parse_svg, serialize};
+
+fuzz_target!(|data: &str| {
+    let Ok(mut doc) = parse_svg(data) else {
+        return;
+    };
+
+    let options = Options::default();
+    optimize(&mut doc, &options);
+    let minified = serialize(&doc, &options);
+
+    if let Err(e) = parse_svg(&minified) {
+        panic!("minified output doesn't parse: {e}\n{minified}");
+    }
+});
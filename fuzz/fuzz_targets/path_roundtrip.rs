@@ -0,0 +1,54 @@
+//! Serialized path data must parse back into a path that serializes the
+//! same way: writing a path once settles its geometry.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use svag::path::{Command, Path, parse_path, serialize_path};
+
+fuzz_target!(|input: (Path, u8)| {
+    let (path, precision) = input;
+    let precision = precision % 8;
+
+    let finite = |n: f64| n.is_finite() && n.abs() < 1e12;
+    let all_finite = path.commands.iter().all(|c| match *c {
+        Command::MoveTo { x, y, .. }
+        | Command::LineTo { x, y, .. }
+        | Command::SmoothQuadTo { x, y, .. } => finite(x) && finite(y),
+        Command::HorizontalTo { x, .. } => finite(x),
+        Command::VerticalTo { y, .. } => finite(y),
+        Command::CurveTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            x,
+            y,
+            ..
+        } => [x1, y1, x2, y2, x, y].into_iter().all(finite),
+        Command::SmoothCurveTo { x2, y2, x, y, .. } => [x2, y2, x, y].into_iter().all(finite),
+        Command::QuadTo { x1, y1, x, y, .. } => [x1, y1, x, y].into_iter().all(finite),
+        Command::Arc {
+            rx,
+            ry,
+            x_axis_rotation,
+            x,
+            y,
+            ..
+        } => [rx, ry, x_axis_rotation, x, y].into_iter().all(finite),
+        Command::ClosePath => true,
+    });
+    if !all_finite {
+        return;
+    }
+
+    let d = serialize_path(&path, precision);
+    match parse_path(&d) {
+        Ok(reparsed) => assert_eq!(
+            serialize_path(&reparsed, precision),
+            d,
+            "serialized path isn't a fixpoint"
+        ),
+        Err(e) => panic!("serialized path {d:?} doesn't parse: {e}"),
+    }
+});
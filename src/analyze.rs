@@ -0,0 +1,371 @@
+//! Document statistics.
+//!
+//! Where a file's bytes go, before deciding how to shrink it: element and
+//! attribute counts, which path commands the drawing uses, embedded
+//! assets, and an estimate of the serialized size of each kind of content.
+//!
+//! ```
+//! let doc = svag::parse_svg(r#"<svg><title>Logo</title><path d="M0 0h10v10z"/></svg>"#).unwrap();
+//! let analysis = svag::analyze(&doc);
+//! assert_eq!(analysis.elements["path"], 1);
+//! assert_eq!(analysis.path_commands[&'h'], 1);
+//! assert!(analysis.weight.metadata > 0);
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use crate::css::css_urls;
+use crate::diff::child_paths;
+use crate::{AssetKind, Document, DocumentIndex, Element, Node, extract_embedded_assets};
+
+/// Elements whose whole content is metadata.
+const METADATA_ELEMENTS: &[&str] = &["metadata", "title", "desc"];
+
+/// Namespace prefixes editors keep their own data under.
+const EDITOR_PREFIXES: &[&str] = &["inkscape", "sodipodi", "sketch", "serif", "figma"];
+
+/// Presentation attributes, which style an element like CSS does.
+const PRESENTATION: &[&str] = &[
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "opacity",
+    "color",
+    "clip-path",
+    "clip-rule",
+    "mask",
+    "filter",
+    "display",
+    "visibility",
+    "overflow",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "letter-spacing",
+    "text-anchor",
+    "dominant-baseline",
+    "paint-order",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+    "flood-color",
+    "flood-opacity",
+    "lighting-color",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "mix-blend-mode",
+];
+
+/// What a document is made of. See [`analyze`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// Number of elements by qualified name
+    pub elements: BTreeMap<String, usize>,
+    /// Number of occurrences of each attribute, by qualified name
+    pub attributes: BTreeMap<String, usize>,
+    /// Number of explicit commands in path data (`d`) by letter; implicit
+    /// repetitions of a command aren't counted
+    pub path_commands: BTreeMap<char, usize>,
+    /// Number of elements with an `id`
+    pub ids: usize,
+    /// Number of references to ids (`href="#…"`, `url(#…)`)
+    pub references: usize,
+    /// Embedded `data:` URIs, in document order
+    pub assets: Vec<AssetSize>,
+    /// Estimated serialized size by kind of content
+    pub weight: Weight,
+    /// Every element with its own estimated size (tags, attributes, and
+    /// text, but not child elements), heaviest first
+    pub heaviest: Vec<ElementSize>,
+}
+
+/// The size of an embedded asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetSize {
+    pub kind: AssetKind,
+    pub mime: String,
+    /// Length of the `data:` URI as written
+    pub encoded: usize,
+    /// Length of the decoded payload
+    pub decoded: usize,
+    /// Path of the referencing element, see [`Document::element_at`]
+    pub element: Vec<usize>,
+}
+
+/// Estimated serialized bytes by kind of content. Each byte counts towards
+/// exactly one kind, so [`Weight::total`] approximates the file size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Weight {
+    /// Path data (`d`, `points`)
+    pub paths: usize,
+    /// `style` and `class` attributes, presentation attributes, and
+    /// stylesheets
+    pub styles: usize,
+    /// `data:` URIs (embedded images, fonts, …)
+    pub embedded: usize,
+    /// Metadata, titles, descriptions, comments, and editor-specific
+    /// elements and attributes
+    pub metadata: usize,
+    /// Text content
+    pub text: usize,
+    /// Everything else: tags, ids, geometry, whitespace, …
+    pub structure: usize,
+}
+
+impl Weight {
+    /// All bytes counted.
+    pub fn total(&self) -> usize {
+        self.paths + self.styles + self.embedded + self.metadata + self.text + self.structure
+    }
+
+    fn add(&mut self, other: &Weight) {
+        self.paths += other.paths;
+        self.styles += other.styles;
+        self.embedded += other.embedded;
+        self.metadata += other.metadata;
+        self.text += other.text;
+        self.structure += other.structure;
+    }
+}
+
+/// An element's own estimated size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementSize {
+    /// Qualified name
+    pub name: String,
+    /// Path of the element, see [`Document::element_at`]
+    pub element: Vec<usize>,
+    /// The same path for people, like `/svg/g[2]/path` (see [`Change`])
+    ///
+    /// [`Change`]: crate::Change
+    pub path: String,
+    pub bytes: usize,
+}
+
+/// Gather statistics about `doc`.
+pub fn analyze(doc: &Document) -> Analysis {
+    let mut analysis = Analysis::default();
+
+    for node in &doc.prolog {
+        analysis.weight.metadata += node_len(node);
+    }
+    let path = format!("/{}", doc.root.name.full_name());
+    visit(&doc.root, &mut Vec::new(), &path, false, &mut analysis);
+    // Heaviest first; ties stay in document order
+    analysis.heaviest.sort_by_key(|size| Reverse(size.bytes));
+
+    let index = DocumentIndex::new(doc);
+    analysis.references = index.iter().map(|(id, _)| index.references(id).len()).sum();
+
+    analysis.assets = extract_embedded_assets(doc)
+        .into_iter()
+        .map(|asset| AssetSize {
+            kind: asset.kind,
+            mime: asset.mime,
+            encoded: asset.url.len(),
+            decoded: asset.bytes.len(),
+            element: asset.element,
+        })
+        .collect();
+
+    analysis
+}
+
+fn visit(
+    elem: &Element,
+    element: &mut Vec<usize>,
+    path: &str,
+    in_metadata: bool,
+    analysis: &mut Analysis,
+) {
+    let name = elem.name.full_name();
+    *analysis.elements.entry(name.clone()).or_default() += 1;
+    if elem.get_attr("id").is_some() {
+        analysis.ids += 1;
+    }
+
+    let in_metadata = in_metadata
+        || (elem.name.prefix.is_none() && METADATA_ELEMENTS.contains(&elem.name.local.as_str()))
+        || is_editor_prefix(elem.name.prefix.as_deref());
+    let mut weight = Weight::default();
+
+    // `<name` and `/>`, or `>` and `</name>`
+    let tags = if elem.children.is_empty() {
+        name.len() + 3
+    } else {
+        name.len() * 2 + 5
+    };
+    weight.structure += tags;
+
+    for attr in &elem.attributes {
+        let attr_name = attr.name.full_name();
+        *analysis.attributes.entry(attr_name.clone()).or_default() += 1;
+        // ` name="value"`
+        let len = attr_name.len() + attr.value.len() + 4;
+        let local = attr.name.local.as_str();
+        let editor = is_editor_prefix(attr.name.prefix.as_deref())
+            || (attr.name.prefix.as_deref() == Some("xmlns") && is_editor_prefix(Some(local)))
+            || local == "data-name";
+
+        if local == "d" && attr.name.prefix.is_none() {
+            count_path_commands(&attr.value, &mut analysis.path_commands);
+        }
+
+        if in_metadata || editor {
+            weight.metadata += len;
+        } else if attr.value.trim_start().starts_with("data:") {
+            weight.embedded += len;
+        } else if matches!(local, "d" | "points") {
+            weight.paths += len;
+        } else if local == "style" {
+            let embedded = data_urls_len(&attr.value);
+            weight.embedded += embedded;
+            weight.styles += len - embedded;
+        } else if local == "class" || PRESENTATION.contains(&local) {
+            weight.styles += len;
+        } else {
+            weight.structure += len;
+        }
+    }
+
+    for node in &elem.children {
+        let len = node_len(node);
+        match node {
+            Node::Element(_) => continue,
+            _ if in_metadata => weight.metadata += len,
+            Node::Comment(_) => weight.metadata += len,
+            Node::Text(text) | Node::CData(text) if elem.is("style") => {
+                let embedded = data_urls_len(text);
+                weight.embedded += embedded;
+                weight.styles += len - embedded;
+            }
+            Node::Text(text) if text.trim().is_empty() => weight.structure += len,
+            Node::Text(_) | Node::CData(_) => weight.text += len,
+            Node::ProcessingInstruction { .. } => weight.structure += len,
+        }
+    }
+
+    if in_metadata {
+        // Even its tags are only there for the metadata
+        weight.metadata += weight.structure;
+        weight.structure = 0;
+    }
+    let bytes = weight.total();
+    analysis.weight.add(&weight);
+    analysis.heaviest.push(ElementSize {
+        name,
+        element: element.clone(),
+        path: path.to_string(),
+        bytes,
+    });
+
+    let children: Vec<(usize, &Element)> = elem
+        .children
+        .iter()
+        .enumerate()
+        .filter_map(|(i, node)| match node {
+            Node::Element(child) => Some((i, child)),
+            _ => None,
+        })
+        .collect();
+    let elements: Vec<&Element> = children.iter().map(|&(_, child)| child).collect();
+    for ((i, child), path) in children.into_iter().zip(child_paths(path, &elements)) {
+        element.push(i);
+        visit(child, element, &path, in_metadata, analysis);
+        element.pop();
+    }
+}
+
+fn is_editor_prefix(prefix: Option<&str>) -> bool {
+    prefix.is_some_and(|prefix| EDITOR_PREFIXES.contains(&prefix))
+}
+
+/// Serialized length of a non-element node.
+fn node_len(node: &Node) -> usize {
+    match node {
+        Node::Element(_) => 0,
+        Node::Text(text) => text.len(),
+        // `<!--…-->`
+        Node::Comment(text) => text.len() + 7,
+        // `<![CDATA[…]]>`
+        Node::CData(text) => text.len() + 12,
+        // `<?target content?>`
+        Node::ProcessingInstruction { target, content } => {
+            target.len() + content.as_ref().map_or(0, |c| c.len() + 1) + 4
+        }
+    }
+}
+
+/// Total length of the `data:` URIs in CSS.
+fn data_urls_len(css: &str) -> usize {
+    css_urls(css)
+        .iter()
+        .filter(|url| url.url.starts_with("data:"))
+        .map(|url| url.url.len())
+        .sum()
+}
+
+fn count_path_commands(d: &str, counts: &mut BTreeMap<char, usize>) {
+    for c in d.chars() {
+        // `e` is an exponent, not a command
+        if c.is_ascii_alphabetic() && !matches!(c, 'e' | 'E') {
+            *counts.entry(c).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_analyze() {
+        let svg = r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"><!--made by hand--><metadata><rdf>x</rdf></metadata><g id="a" fill="red" inkscape:label="Layer"><path d="M0 0L1e2 1l2 2zM5 5h1"/><use href="#a"/></g><image href="data:image/png;base64,iVBORw0KGgo="/><text>Hi</text></svg>"##;
+        let doc = parse_svg(svg).unwrap();
+        let analysis = analyze(&doc);
+
+        assert_eq!(analysis.elements["g"], 1);
+        assert_eq!(analysis.elements.len(), 8);
+        assert_eq!(analysis.attributes["href"], 2);
+        assert_eq!(
+            analysis.path_commands.into_iter().collect::<Vec<_>>(),
+            [('L', 1), ('M', 2), ('h', 1), ('l', 1), ('z', 1)]
+        );
+        assert_eq!((analysis.ids, analysis.references), (1, 1));
+
+        assert_eq!(analysis.assets.len(), 1);
+        assert_eq!(analysis.assets[0].decoded, 8);
+        assert_eq!(analysis.assets[0].element, [3]);
+
+        let weight = analysis.weight;
+        assert_eq!(weight.paths, r#" d="M0 0L1e2 1l2 2zM5 5h1""#.len());
+        assert_eq!(weight.styles, r#" fill="red""#.len());
+        assert_eq!(
+            weight.embedded,
+            r#" href="data:image/png;base64,iVBORw0KGgo=""#.len()
+        );
+        assert_eq!(weight.text, 2);
+        assert_eq!(
+            weight.metadata,
+            r#" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"<!--made by hand--><metadata><rdf>x</rdf></metadata> inkscape:label="Layer""#.len()
+        );
+        assert_eq!(weight.total(), svg.len());
+
+        let heaviest: Vec<&str> = analysis.heaviest.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(heaviest[..2], ["svg", "image"]);
+        assert_eq!(analysis.heaviest[1].path, "/svg/image");
+        assert_eq!(analysis.heaviest.len(), 8);
+    }
+}
@@ -0,0 +1,395 @@
+//! Arena-backed document representation.
+//!
+//! [`ArenaDocument`] keeps every node in one `Vec`, every attribute in
+//! another, and interns element and attribute names. Attribute values and
+//! text borrow from the source string when they need no unescaping; only
+//! escaped or modified strings are copied, into a single owned buffer.
+//! Children are linked by index rather than owned, so parsing a
+//! multi-megabyte map makes a handful of allocations instead of several per
+//! element, and walking it stays within a few contiguous buffers.
+//!
+//! This is what the parser builds: [`crate::parse_svg`] parses into an
+//! arena and then materializes the [`Document`] the optimizer works on in
+//! one pass, with every `Vec` allocated at its final size and values
+//! borrowed from the source. The arena doesn't outlive parsing.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use std::ops::Range;
+
+use crate::ast::*;
+use crate::hash::Fnv1a;
+
+/// Identifies a node within an [`ArenaDocument`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct NodeId(usize);
+
+/// Index of an interned name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sym(usize);
+
+/// Byte range into either the source string or the owned buffer.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+    owned: bool,
+}
+
+#[derive(Debug, Clone)]
+enum NodeData {
+    Element { name: Sym, attrs: Range<usize> },
+    Text(Span),
+    Comment(Span),
+    CData(Span),
+    ProcessingInstruction { target: Span, content: Option<Span> },
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    data: NodeData,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    /// Number of children, so materializing can size `Vec`s exactly
+    child_count: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AttrData {
+    name: Sym,
+    value: Span,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Interner {
+    names: Vec<Box<str>>,
+    /// Each name split once, for materializing
+    qnames: Vec<QName>,
+    lookup: HashMap<Box<str>, Sym, BuildHasherDefault<Fnv1a>>,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> Sym {
+        if let Some(&sym) = self.lookup.get(name) {
+            return sym;
+        }
+        let sym = Sym(self.names.len());
+        self.names.push(name.into());
+        self.qnames.push(QName::parse(name));
+        self.lookup.insert(name.into(), sym);
+        sym
+    }
+
+    fn resolve(&self, sym: Sym) -> &str {
+        &self.names[sym.0]
+    }
+
+    fn qname(&self, sym: Sym) -> QName {
+        self.qnames[sym.0].clone()
+    }
+}
+
+/// A document stored in flat buffers, borrowing from its source where
+/// possible. See the [module docs](self).
+#[derive(Debug, Clone)]
+pub(crate) struct ArenaDocument<'input> {
+    /// XML declaration (e.g., `<?xml version="1.0" encoding="UTF-8"?>`)
+    pub xml_declaration: Option<XmlDeclaration>,
+    /// DOCTYPE declaration
+    pub doctype: Option<String>,
+    /// `<?xml-stylesheet?>` processing instructions before the root
+    pub prolog: Vec<Node<'input>>,
+    nodes: Vec<Slot>,
+    attrs: Vec<AttrData>,
+    names: Interner,
+    source: &'input str,
+    owned: String,
+}
+
+impl<'input> ArenaDocument<'input> {
+    pub(crate) fn empty(source: &'input str) -> Self {
+        Self {
+            xml_declaration: None,
+            doctype: None,
+            prolog: Vec::new(),
+            // Rough guess from typical exports: ~40 bytes of source per node
+            nodes: Vec::with_capacity(source.len() / 40),
+            attrs: Vec::with_capacity(source.len() / 40),
+            names: Interner::default(),
+            source,
+            owned: String::new(),
+        }
+    }
+
+    /// Materialize the [`Document`]. Strings the arena borrows from its
+    /// source stay borrowed; only escaped ones are copied.
+    pub(crate) fn to_document(&self) -> Document<'input> {
+        let Node::Element(root) = self.root().to_node() else {
+            unreachable!("the arena root is always an element");
+        };
+        Document {
+            xml_declaration: self.xml_declaration.clone(),
+            doctype: self.doctype.clone(),
+            prolog: self.prolog.clone(),
+            root,
+        }
+    }
+
+    /// The root element.
+    fn root(&self) -> NodeRef<'_, 'input> {
+        self.get(NodeId(0))
+    }
+
+    fn get(&self, id: NodeId) -> NodeRef<'_, 'input> {
+        NodeRef { doc: self, id }
+    }
+
+    /// Whether nothing has been pushed yet, not even a root.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Qualified name of an element (e.g., "svg:rect"), empty for other
+    /// nodes.
+    pub(crate) fn element_name(&self, id: NodeId) -> &str {
+        match self.slot(id).data {
+            NodeData::Element { name, .. } => self.names.resolve(name),
+            _ => "",
+        }
+    }
+
+    fn slot(&self, id: NodeId) -> &Slot {
+        &self.nodes[id.0]
+    }
+
+    /// Append an element with no attributes yet; a `None` parent makes it
+    /// the root.
+    pub(crate) fn push_element(&mut self, parent: Option<NodeId>, name: &str) -> NodeId {
+        let name = self.names.intern(name);
+        let attrs = self.attrs.len()..self.attrs.len();
+        self.push(parent, NodeData::Element { name, attrs })
+    }
+
+    /// Add an attribute to `elem`, which must be the last element pushed.
+    /// One with the same qualified name is removed first, so the last one
+    /// wins; returns whether there was one.
+    pub(crate) fn push_attr(&mut self, elem: NodeId, name: &str, value: Cow<'input, str>) -> bool {
+        let name = self.names.intern(name);
+        let value = self.store(value);
+        let NodeData::Element { attrs, .. } = &mut self.nodes[elem.0].data else {
+            unreachable!("attributes are only pushed onto elements");
+        };
+        debug_assert_eq!(attrs.end, self.attrs.len());
+        let duplicate = self.attrs[attrs.clone()]
+            .iter()
+            .position(|a| a.name == name);
+        if let Some(i) = duplicate {
+            self.attrs.remove(attrs.start + i);
+            attrs.end -= 1;
+        }
+        self.attrs.push(AttrData { name, value });
+        attrs.end += 1;
+        duplicate.is_some()
+    }
+
+    pub(crate) fn push_text(&mut self, parent: NodeId, text: Cow<'input, str>) {
+        let span = self.store(text);
+        self.push(Some(parent), NodeData::Text(span));
+    }
+
+    pub(crate) fn push_comment(&mut self, parent: NodeId, text: Cow<'input, str>) {
+        let span = self.store(text);
+        self.push(Some(parent), NodeData::Comment(span));
+    }
+
+    pub(crate) fn push_cdata(&mut self, parent: NodeId, text: Cow<'input, str>) {
+        let span = self.store(text);
+        self.push(Some(parent), NodeData::CData(span));
+    }
+
+    pub(crate) fn push_processing_instruction(
+        &mut self,
+        parent: NodeId,
+        target: &str,
+        content: Option<&str>,
+    ) {
+        let target = self.push_str(target);
+        let content = content.map(|c| self.push_str(c));
+        self.push(
+            Some(parent),
+            NodeData::ProcessingInstruction { target, content },
+        );
+    }
+
+    /// Whether the node has any children yet.
+    pub(crate) fn has_children(&self, id: NodeId) -> bool {
+        self.slot(id).first_child.is_some()
+    }
+
+    fn span(&self, span: Span) -> &str {
+        let buf = if span.owned { &self.owned } else { self.source };
+        &buf[span.start..span.end]
+    }
+
+    /// The string behind a span, borrowed from the source when it came
+    /// from there.
+    fn cow(&self, span: Span) -> Cow<'input, str> {
+        if span.owned {
+            Cow::Owned(self.owned[span.start..span.end].to_string())
+        } else {
+            Cow::Borrowed(&self.source[span.start..span.end])
+        }
+    }
+
+    /// Record a string the parser produced, borrowing it if it points into
+    /// the source and copying it otherwise.
+    fn store(&mut self, s: Cow<'input, str>) -> Span {
+        let source = self.source.as_bytes().as_ptr_range();
+        let ptr = s.as_ptr();
+        if let Cow::Borrowed(b) = s
+            && source.contains(&ptr)
+            && b.len() <= source.end as usize - ptr as usize
+        {
+            let start = ptr as usize - source.start as usize;
+            return Span {
+                start,
+                end: start + b.len(),
+                owned: false,
+            };
+        }
+        self.push_str(&s)
+    }
+
+    fn push_str(&mut self, s: &str) -> Span {
+        let start = self.owned.len();
+        self.owned.push_str(s);
+        Span {
+            start,
+            end: self.owned.len(),
+            owned: true,
+        }
+    }
+
+    fn push(&mut self, parent: Option<NodeId>, data: NodeData) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Slot {
+            data,
+            first_child: None,
+            last_child: None,
+            next_sibling: None,
+            child_count: 0,
+        });
+        if let Some(parent) = parent {
+            let slot = &mut self.nodes[parent.0];
+            slot.child_count += 1;
+            match slot.last_child.replace(id) {
+                Some(prev) => self.nodes[prev.0].next_sibling = Some(id),
+                None => slot.first_child = Some(id),
+            }
+        }
+        id
+    }
+}
+
+/// A borrowed view of one node in an [`ArenaDocument`], for walking it.
+#[derive(Debug, Clone, Copy)]
+struct NodeRef<'a, 'input> {
+    doc: &'a ArenaDocument<'input>,
+    id: NodeId,
+}
+
+impl<'a, 'input> NodeRef<'a, 'input> {
+    fn slot(&self) -> &'a Slot {
+        self.doc.slot(self.id)
+    }
+
+    fn attr_range(&self) -> Range<usize> {
+        match &self.slot().data {
+            NodeData::Element { attrs, .. } => attrs.clone(),
+            _ => 0..0,
+        }
+    }
+
+    /// Iterate over child nodes in document order.
+    fn children(&self) -> impl Iterator<Item = NodeRef<'a, 'input>> + use<'a, 'input> {
+        let doc = self.doc;
+        std::iter::successors(self.slot().first_child, move |&id| {
+            doc.slot(id).next_sibling
+        })
+        .map(move |id| doc.get(id))
+    }
+
+    /// Materialize this node and its subtree as a [`Node`].
+    fn to_node(self) -> Node<'input> {
+        let doc = self.doc;
+        match &self.slot().data {
+            NodeData::Element { name, .. } => {
+                let mut children = Vec::with_capacity(self.slot().child_count);
+                children.extend(self.children().map(|c| c.to_node()));
+                Node::Element(Element {
+                    name: doc.names.qname(*name),
+                    attributes: doc.attrs[self.attr_range()]
+                        .iter()
+                        .map(|a| Attribute {
+                            name: doc.names.qname(a.name),
+                            value: doc.cow(a.value),
+                        })
+                        .collect(),
+                    children,
+                })
+            }
+            NodeData::Text(span) => Node::Text(doc.cow(*span)),
+            NodeData::Comment(span) => Node::Comment(doc.cow(*span)),
+            NodeData::CData(span) => Node::CData(doc.cow(*span)),
+            NodeData::ProcessingInstruction { target, content } => Node::ProcessingInstruction {
+                target: doc.span(*target).to_string(),
+                content: content.map(|c| doc.span(c).to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_arena;
+    use crate::{Options, serialize};
+
+    #[test]
+    fn test_zero_copy() {
+        let svg = r#"<svg><rect id="a" fill="red"/><text x="1">R&amp;D</text></svg>"#;
+        let (arena, _) = parse_arena(svg, false).unwrap();
+        // Only the unescaped "R&D" needed copying
+        assert_eq!(arena.owned, "R&D");
+        let doc = arena.to_document();
+        let rect = doc.root.child_elements().next().unwrap();
+        assert!(
+            rect.attributes
+                .iter()
+                .all(|a| matches!(a.value, Cow::Borrowed(_)))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_attributes_by_qualified_name() {
+        let svg = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><use href="#a" xlink:href="#b" href="#c"/></svg>"##;
+        let (arena, _) = parse_arena(svg, true).unwrap();
+        let doc = arena.to_document();
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><use href="#c" xlink:href="#b"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_child_counts() {
+        let svg = "<svg><g><rect/><!--c--><circle/></g>text<?pi x?></svg>";
+        let (arena, _) = parse_arena(svg, false).unwrap();
+        let counts: Vec<usize> = arena.nodes.iter().map(|slot| slot.child_count).collect();
+        assert_eq!(counts, [3, 3, 0, 0, 0, 0, 0]);
+        let doc = arena.to_document();
+        assert_eq!(doc.root.children.len(), 3);
+    }
+}
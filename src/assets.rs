@@ -0,0 +1,179 @@
+//! Embedded asset utilities
+//!
+//! Find `data:` URIs embedded in an SVG (images, fonts, ...) and swap them
+//! for external URLs, so build systems can move them into separate,
+//! cacheable files. These mirror the font helpers in [`crate::extract_font_faces`]
+//! and [`crate::replace_font_url`].
+//!
+//! ```ignore
+//! for asset in svag::extract_embedded_assets(&doc) {
+//!     let name = format!("{:016x}.{}", hash(&asset.bytes), ext(&asset.mime));
+//!     std::fs::write(out_dir.join(&name), &asset.bytes)?;
+//!     svag::replace_asset_url(&mut doc, &asset.url, &name);
+//! }
+//! ```
+
+use crate::css::{css_urls, format_css_url};
+use crate::data_uri::parse_data_uri;
+use crate::{Document, Element, Node};
+
+/// What an embedded asset is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Image,
+    Font,
+    Other,
+}
+
+/// A `data:` URI embedded in the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedAsset {
+    pub kind: AssetKind,
+    pub mime: String,
+    /// The decoded payload
+    pub bytes: Vec<u8>,
+    /// The data URI as it appears in the document, for [`replace_asset_url`]
+    pub url: String,
+    /// Path of the referencing element, see [`Document::element_at`]
+    pub element: Vec<usize>,
+}
+
+/// Extract every `data:` URI from `href` attributes, `style` attributes and
+/// `<style>` elements, in document order.
+pub fn extract_embedded_assets(doc: &Document) -> Vec<EmbeddedAsset> {
+    fn visit(elem: &Element, path: &mut Vec<usize>, assets: &mut Vec<EmbeddedAsset>) {
+        for attr in &elem.attributes {
+            if attr.name.local == "href" {
+                assets.extend(asset(&attr.value, path));
+            } else if attr.name.local == "style" {
+                for url in css_urls(&attr.value) {
+                    assets.extend(asset(&url.url, path));
+                }
+            }
+        }
+
+        if elem.is("style") {
+            for child in &elem.children {
+                if let Node::Text(css) | Node::CData(css) = child {
+                    for url in css_urls(css) {
+                        assets.extend(asset(&url.url, path));
+                    }
+                }
+            }
+        }
+
+        for (i, child) in elem.children.iter().enumerate() {
+            if let Node::Element(child) = child {
+                path.push(i);
+                visit(child, path, assets);
+                path.pop();
+            }
+        }
+    }
+
+    let mut assets = Vec::new();
+    visit(&doc.root, &mut Vec::new(), &mut assets);
+    assets
+}
+
+fn asset(url: &str, path: &[usize]) -> Option<EmbeddedAsset> {
+    let data = parse_data_uri(url)?;
+    let mime = data.mime.to_ascii_lowercase();
+
+    let kind = if mime.starts_with("image/") {
+        AssetKind::Image
+    } else if mime.starts_with("font/")
+        || mime.contains("font-")
+        || mime == "application/vnd.ms-fontobject"
+    {
+        AssetKind::Font
+    } else {
+        AssetKind::Other
+    };
+
+    Some(EmbeddedAsset {
+        kind,
+        bytes: data.decode()?,
+        mime,
+        url: url.to_string(),
+        element: path.to_vec(),
+    })
+}
+
+/// Replace an asset URL in `href` attributes, `style` attributes and
+/// `<style>` elements.
+pub fn replace_asset_url(doc: &mut Document, old_url: &str, new_url: &str) {
+    fn replace_in_css(css: &mut String, old_url: &str, new_url: &str) {
+        for url in css_urls(css).into_iter().rev() {
+            if url.url == old_url {
+                css.replace_range(url.token, &format_css_url(new_url));
+            }
+        }
+    }
+
+    fn visit(elem: &mut Element, old_url: &str, new_url: &str) {
+        for attr in &mut elem.attributes {
+            if attr.name.local == "href" && attr.value == old_url {
+                attr.value = new_url.to_string().into();
+            } else if attr.name.local == "style" {
+                replace_in_css(attr.value.to_mut(), old_url, new_url);
+            }
+        }
+
+        if elem.is("style") {
+            for child in &mut elem.children {
+                if let Node::Text(css) | Node::CData(css) = child {
+                    replace_in_css(css.to_mut(), old_url, new_url);
+                }
+            }
+        }
+
+        for child in elem.child_elements_mut() {
+            visit(child, old_url, new_url);
+        }
+    }
+
+    visit(&mut doc.root, old_url, new_url);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg">
+        <style>@font-face { font-family: 'A'; src: url('data:font/woff2;base64,aGk=') format('woff2'); }</style>
+        <g><image href="data:image/png;base64,aGk="/></g>
+        <image href="photo.jpg"/>
+    </svg>"#;
+
+    #[test]
+    fn test_extract_embedded_assets() {
+        let doc = parse_svg(SVG).unwrap();
+        let assets = extract_embedded_assets(&doc);
+        assert_eq!(assets.len(), 2);
+
+        assert_eq!(assets[0].kind, AssetKind::Font);
+        assert_eq!(assets[0].mime, "font/woff2");
+        assert_eq!(assets[0].bytes, b"hi");
+
+        assert_eq!(assets[1].kind, AssetKind::Image);
+        assert!(doc.element_at(&assets[1].element).unwrap().is("image"));
+    }
+
+    #[test]
+    fn test_replace_asset_url() {
+        let mut doc = parse_svg(SVG).unwrap();
+        for asset in extract_embedded_assets(&doc) {
+            let name = match asset.kind {
+                AssetKind::Font => "font.woff2",
+                _ => "image.png",
+            };
+            replace_asset_url(&mut doc, &asset.url, name);
+        }
+
+        assert!(extract_embedded_assets(&doc).is_empty());
+        let faces = crate::extract_font_faces(&doc);
+        assert_eq!(faces[0].url, "font.woff2");
+    }
+}
@@ -1,20 +1,31 @@
 //! SVG Abstract Syntax Tree
+//!
+//! Attribute values and character data are [`Cow`]s: a parsed document
+//! borrows them from the source text wherever no unescaping was needed, and
+//! passes replace them with owned strings as they rewrite them. Use
+//! [`Document::into_owned`] to detach a document from its source.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 /// A complete SVG document.
 #[derive(Debug, Clone)]
-pub struct Document {
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Document<'a> {
     /// XML declaration (e.g., `<?xml version="1.0" encoding="UTF-8"?>`)
     pub xml_declaration: Option<XmlDeclaration>,
     /// DOCTYPE declaration
     pub doctype: Option<String>,
+    /// `<?xml-stylesheet?>` processing instructions before the root. Other
+    /// nodes outside the root are dropped when parsing.
+    pub prolog: Vec<Node<'a>>,
     /// The root SVG element
-    pub root: Element,
+    pub root: Element<'a>,
 }
 
 /// XML declaration attributes.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct XmlDeclaration {
     pub version: String,
     pub encoding: Option<String>,
@@ -23,13 +34,14 @@ pub struct XmlDeclaration {
 
 /// An SVG/XML element.
 #[derive(Debug, Clone)]
-pub struct Element {
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Element<'a> {
     /// Element name with optional prefix (e.g., "svg", "svg:rect", "xlink:href")
     pub name: QName,
     /// Attributes on this element
-    pub attributes: Vec<Attribute>,
+    pub attributes: Vec<Attribute<'a>>,
     /// Child nodes
-    pub children: Vec<Node>,
+    pub children: Vec<Node<'a>>,
 }
 
 /// A qualified name (possibly with namespace prefix).
@@ -79,15 +91,100 @@ impl QName {
     }
 }
 
+/// Generates well-formed XML names, mostly ones svag treats specially, so
+/// fuzzing exercises the optimizer rather than the parser's error paths.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for QName {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        const PREFIXES: &[&str] = &["xlink", "xml", "xmlns", "svg", "inkscape", "sodipodi"];
+        const NAMES: &[&str] = &[
+            "svg",
+            "g",
+            "defs",
+            "use",
+            "path",
+            "rect",
+            "circle",
+            "ellipse",
+            "line",
+            "polyline",
+            "polygon",
+            "text",
+            "tspan",
+            "style",
+            "image",
+            "title",
+            "desc",
+            "metadata",
+            "symbol",
+            "linearGradient",
+            "stop",
+            "clipPath",
+            "mask",
+            "id",
+            "class",
+            "style",
+            "d",
+            "x",
+            "y",
+            "width",
+            "height",
+            "fill",
+            "stroke",
+            "opacity",
+            "transform",
+            "href",
+            "display",
+            "visibility",
+            "viewBox",
+            "xmlns",
+            "fill-opacity",
+            "stroke-width",
+        ];
+
+        let prefix = if u.ratio(1, 8)? {
+            Some(u.choose(PREFIXES)?.to_string())
+        } else {
+            None
+        };
+        let local = if u.ratio(7, 8)? {
+            u.choose(NAMES)?.to_string()
+        } else {
+            let len = u.int_in_range(1..=8)?;
+            let mut name = String::with_capacity(len);
+            for i in 0..len {
+                let alphabet: &[u8] = if i == 0 {
+                    b"abcdefghijklmnopqrstuvwxyz"
+                } else {
+                    b"abcdefghijklmnopqrstuvwxyz0123456789-"
+                };
+                name.push(*u.choose(alphabet)? as char);
+            }
+            name
+        };
+
+        Ok(Self { prefix, local })
+    }
+}
+
+/// Generated strings are always owned: a derived `Cow` would tie the
+/// document's lifetime to the fuzzer's input buffer.
+#[cfg(feature = "arbitrary")]
+fn arbitrary_cow<'a>(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Cow<'a, str>> {
+    <String as arbitrary::Arbitrary>::arbitrary(u).map(Cow::Owned)
+}
+
 /// An attribute on an element.
 #[derive(Debug, Clone)]
-pub struct Attribute {
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Attribute<'a> {
     pub name: QName,
-    pub value: String,
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_cow))]
+    pub value: Cow<'a, str>,
 }
 
-impl Attribute {
-    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+impl<'a> Attribute<'a> {
+    pub fn new(name: impl Into<String>, value: impl Into<Cow<'a, str>>) -> Self {
         Self {
             name: QName::new(name),
             value: value.into(),
@@ -97,15 +194,16 @@ impl Attribute {
 
 /// A node in the SVG tree.
 #[derive(Debug, Clone)]
-pub enum Node {
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Node<'a> {
     /// An element node
-    Element(Element),
+    Element(Element<'a>),
     /// A text node
-    Text(String),
+    Text(#[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_cow))] Cow<'a, str>),
     /// A comment node
-    Comment(String),
+    Comment(#[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_cow))] Cow<'a, str>),
     /// A CDATA section
-    CData(String),
+    CData(#[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_cow))] Cow<'a, str>),
     /// A processing instruction (e.g., `<?xml-stylesheet ... ?>`)
     ProcessingInstruction {
         target: String,
@@ -113,7 +211,13 @@ pub enum Node {
     },
 }
 
-impl Element {
+impl<'a> From<Element<'a>> for Node<'a> {
+    fn from(elem: Element<'a>) -> Self {
+        Node::Element(elem)
+    }
+}
+
+impl<'a> Element<'a> {
     pub fn new(name: impl Into<String>) -> Self {
         Self {
             name: QName::new(name),
@@ -127,11 +231,11 @@ impl Element {
         self.attributes
             .iter()
             .find(|a| a.name.local == name)
-            .map(|a| a.value.as_str())
+            .map(|a| a.value.as_ref())
     }
 
     /// Set an attribute value.
-    pub fn set_attr(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    pub fn set_attr(&mut self, name: impl Into<String>, value: impl Into<Cow<'a, str>>) {
         let name = name.into();
         if let Some(attr) = self.attributes.iter_mut().find(|a| a.name.local == name) {
             attr.value = value.into();
@@ -155,16 +259,16 @@ impl Element {
         let mut ns = HashMap::new();
         for attr in &self.attributes {
             if attr.name.local == "xmlns" && attr.name.prefix.is_none() {
-                ns.insert(None, attr.value.as_str());
+                ns.insert(None, attr.value.as_ref());
             } else if attr.name.prefix.as_deref() == Some("xmlns") {
-                ns.insert(Some(attr.name.local.as_str()), attr.value.as_str());
+                ns.insert(Some(attr.name.local.as_str()), attr.value.as_ref());
             }
         }
         ns
     }
 
     /// Iterate over child elements only (skip text, comments, etc.).
-    pub fn child_elements(&self) -> impl Iterator<Item = &Element> {
+    pub fn child_elements(&self) -> impl Iterator<Item = &Element<'a>> {
         self.children.iter().filter_map(|n| match n {
             Node::Element(e) => Some(e),
             _ => None,
@@ -172,18 +276,139 @@ impl Element {
     }
 
     /// Iterate over child elements mutably.
-    pub fn child_elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+    pub fn child_elements_mut(&mut self) -> impl Iterator<Item = &mut Element<'a>> {
         self.children.iter_mut().filter_map(|n| match n {
             Node::Element(e) => Some(e),
             _ => None,
         })
     }
+
+    /// Append a node after the existing children.
+    pub fn append_child(&mut self, node: impl Into<Node<'a>>) {
+        self.children.push(node.into());
+    }
+
+    /// Insert a node before the child at `index`, or at the end if `index`
+    /// is past the last child.
+    pub fn insert_before(&mut self, index: usize, node: impl Into<Node<'a>>) {
+        let index = index.min(self.children.len());
+        self.children.insert(index, node.into());
+    }
+
+    /// Remove every child matching `pred`, returning the removed nodes in
+    /// document order.
+    pub fn remove_children_where(
+        &mut self,
+        mut pred: impl FnMut(&Node<'a>) -> bool,
+    ) -> Vec<Node<'a>> {
+        let (removed, kept) = self.take_children().into_iter().partition(|n| pred(n));
+        self.children = kept;
+        removed
+    }
+
+    /// Move all children out of this element, leaving it empty.
+    pub fn take_children(&mut self) -> Vec<Node<'a>> {
+        std::mem::take(&mut self.children)
+    }
+
+    /// Replace this element in place, returning the previous one.
+    pub fn replace_with(&mut self, replacement: Element<'a>) -> Element<'a> {
+        std::mem::replace(self, replacement)
+    }
+
+    /// Copy every borrowed string in this element and its descendants.
+    pub fn into_owned(self) -> Element<'static> {
+        Element {
+            name: self.name,
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+            children: self.children.into_iter().map(Node::into_owned).collect(),
+        }
+    }
+
+    /// Wrap this element in `wrapper`, appending it after any children the
+    /// wrapper already has. `self` becomes the wrapper.
+    pub fn wrap_in(&mut self, wrapper: Element<'a>) {
+        let inner = self.replace_with(wrapper);
+        self.append_child(inner);
+    }
 }
 
-impl Document {
+impl<'a> Document<'a> {
+    /// Look up an element by its path of child indices from the root.
+    ///
+    /// Each index points into the parent's `children`; an empty path is the
+    /// root element itself.
+    pub fn element_at(&self, path: &[usize]) -> Option<&Element<'a>> {
+        let mut elem = &self.root;
+        for &index in path {
+            match elem.children.get(index)? {
+                Node::Element(child) => elem = child,
+                _ => return None,
+            }
+        }
+        Some(elem)
+    }
+
+    /// Look up an element mutably by its path of child indices from the root.
+    pub fn element_at_mut(&mut self, path: &[usize]) -> Option<&mut Element<'a>> {
+        let mut elem = &mut self.root;
+        for &index in path {
+            match elem.children.get_mut(index)? {
+                Node::Element(child) => elem = child,
+                _ => return None,
+            }
+        }
+        Some(elem)
+    }
+
+    /// Remove the elements at `paths`, each as accepted by
+    /// [`element_at`](Self::element_at) before any removal. The root can't
+    /// be removed, and paths that lead nowhere are skipped.
+    pub fn remove_elements_at(&mut self, mut paths: Vec<Vec<usize>>) {
+        // Later siblings and descendants first, so earlier paths stay valid
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+        paths.dedup();
+        for path in paths {
+            let Some((&last, parent)) = path.split_last() else {
+                continue;
+            };
+            if let Some(parent) = self.element_at_mut(parent)
+                && matches!(parent.children.get(last), Some(Node::Element(_)))
+            {
+                parent.children.remove(last);
+            }
+        }
+    }
+
+    /// Give the document an accessible name: a `<title>` holding `text` as
+    /// the root's first child, replacing the root's other `<title>`s.
+    pub fn set_title(&mut self, text: impl Into<String>) {
+        self.root.remove_children_where(|n| is_element(n, "title"));
+        self.root
+            .insert_before(0, text_element("title", text.into()));
+    }
+
+    /// Give the document an accessible description: a `<desc>` holding
+    /// `text` right after the root's `<title>` (or first, without one),
+    /// replacing the root's other `<desc>`s.
+    pub fn set_desc(&mut self, text: impl Into<String>) {
+        self.root.remove_children_where(|n| is_element(n, "desc"));
+        let index = self
+            .root
+            .children
+            .first()
+            .is_some_and(|n| is_element(n, "title")) as usize;
+        self.root
+            .insert_before(index, text_element("desc", text.into()));
+    }
+
     /// Recursively visit all elements in the document.
-    pub fn for_each_element(&self, mut f: impl FnMut(&Element)) {
-        fn visit(elem: &Element, f: &mut impl FnMut(&Element)) {
+    pub fn for_each_element(&self, mut f: impl FnMut(&Element<'a>)) {
+        fn visit<'a>(elem: &Element<'a>, f: &mut impl FnMut(&Element<'a>)) {
             f(elem);
             for child in elem.child_elements() {
                 visit(child, f);
@@ -192,9 +417,18 @@ impl Document {
         visit(&self.root, &mut f);
     }
 
+    /// Whether any element in the document satisfies `f`, stopping at the
+    /// first that does.
+    pub fn any_element(&self, mut f: impl FnMut(&Element<'a>) -> bool) -> bool {
+        fn visit<'a>(elem: &Element<'a>, f: &mut impl FnMut(&Element<'a>) -> bool) -> bool {
+            f(elem) || elem.child_elements().any(|child| visit(child, f))
+        }
+        visit(&self.root, &mut f)
+    }
+
     /// Recursively visit all elements mutably.
-    pub fn for_each_element_mut(&mut self, mut f: impl FnMut(&mut Element)) {
-        fn visit(elem: &mut Element, f: &mut impl FnMut(&mut Element)) {
+    pub fn for_each_element_mut(&mut self, mut f: impl FnMut(&mut Element<'a>)) {
+        fn visit<'a>(elem: &mut Element<'a>, f: &mut impl FnMut(&mut Element<'a>)) {
             f(elem);
             for child in elem.child_elements_mut() {
                 visit(child, f);
@@ -202,4 +436,130 @@ impl Document {
         }
         visit(&mut self.root, &mut f);
     }
+
+    /// Copy every borrowed string so the document no longer refers to the
+    /// text it was parsed from.
+    pub fn into_owned(self) -> Document<'static> {
+        Document {
+            xml_declaration: self.xml_declaration,
+            doctype: self.doctype,
+            prolog: self.prolog.into_iter().map(Node::into_owned).collect(),
+            root: self.root.into_owned(),
+        }
+    }
+}
+
+impl Attribute<'_> {
+    /// Copy the value if it is borrowed.
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            name: self.name,
+            value: Cow::Owned(self.value.into_owned()),
+        }
+    }
+}
+
+impl Node<'_> {
+    /// Copy every borrowed string in this node and its descendants.
+    pub fn into_owned(self) -> Node<'static> {
+        let owned = |text: Cow<'_, str>| Cow::Owned(text.into_owned());
+        match self {
+            Node::Element(elem) => Node::Element(elem.into_owned()),
+            Node::Text(text) => Node::Text(owned(text)),
+            Node::Comment(text) => Node::Comment(owned(text)),
+            Node::CData(text) => Node::CData(owned(text)),
+            Node::ProcessingInstruction { target, content } => {
+                Node::ProcessingInstruction { target, content }
+            }
+        }
+    }
+}
+
+fn is_element(node: &Node, name: &str) -> bool {
+    matches!(node, Node::Element(e) if e.is(name))
+}
+
+/// An element holding only `text`.
+fn text_element(name: &str, text: String) -> Element<'static> {
+    let mut elem = Element::new(name);
+    if !text.is_empty() {
+        elem.append_child(Node::Text(text.into()));
+    }
+    elem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn child_names<'a>(elem: &'a Element) -> Vec<&'a str> {
+        elem.child_elements()
+            .map(|e| e.name.local.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_child_mutation() {
+        let mut g = Element::new("g");
+        g.append_child(Element::new("rect"));
+        g.append_child(Node::Text("hi".into()));
+        g.append_child(Element::new("circle"));
+        g.insert_before(0, Element::new("path"));
+        g.insert_before(99, Element::new("line"));
+        assert_eq!(child_names(&g), ["path", "rect", "circle", "line"]);
+
+        let removed = g.remove_children_where(|n| !matches!(n, Node::Element(_)));
+        assert!(matches!(removed.as_slice(), [Node::Text(t)] if t == "hi"));
+        assert_eq!(g.children.len(), 4);
+
+        let taken = g.take_children();
+        assert_eq!(taken.len(), 4);
+        assert!(g.children.is_empty());
+    }
+
+    #[test]
+    fn test_replace_and_wrap() {
+        let mut elem = Element::new("rect");
+        elem.set_attr("id", "r");
+
+        let mut wrapper = Element::new("g");
+        wrapper.set_attr("opacity", ".5");
+        elem.wrap_in(wrapper);
+        assert!(elem.is("g"));
+        assert_eq!(child_names(&elem), ["rect"]);
+        assert_eq!(
+            elem.child_elements().next().unwrap().get_attr("id"),
+            Some("r")
+        );
+
+        let old = elem.replace_with(Element::new("circle"));
+        assert!(old.is("g"));
+        assert!(elem.is("circle"));
+    }
+
+    #[test]
+    fn test_set_title_and_desc() {
+        let mut doc = crate::parse_svg(
+            "<svg><desc>Old</desc><rect/><title>Old</title><g><title>Kept</title></g></svg>",
+        )
+        .unwrap();
+        doc.set_desc("A red square");
+        doc.set_title("Square");
+        doc.set_title("Square & circle");
+        assert_eq!(
+            crate::serialize(&doc, &crate::Options::default()),
+            "<svg><title>Square &amp; circle</title><desc>A red square</desc><rect/><g><title>Kept</title></g></svg>"
+        );
+    }
+
+    #[test]
+    fn test_remove_elements_at() {
+        let mut doc =
+            crate::parse_svg("<svg><g><rect/><circle/></g><path/><ellipse/></svg>").unwrap();
+        doc.remove_elements_at(vec![vec![0, 0], vec![1], vec![0], vec![1], vec![], vec![9]]);
+        assert_eq!(
+            crate::serialize(&doc, &crate::Options::default()),
+            "<svg><ellipse/></svg>"
+        );
+    }
 }
@@ -0,0 +1,245 @@
+//! Security audit.
+//!
+//! What in a document could run code or load something when it's shown:
+//! scripts, event handlers, external URLs, `<foreignObject>` content,
+//! animations that rewrite links or handlers, and `data:` URIs holding
+//! documents or scripts. Upload services can look at the report before
+//! deciding whether to sanitize a file or reject it. Nothing is changed.
+//!
+//! ```
+//! let doc = svag::parse_svg(r#"<svg onload="alert(1)"><image href="https://example.com/a.png"/></svg>"#).unwrap();
+//! let report = svag::audit(&doc);
+//! assert!(!report.is_clean());
+//! assert_eq!(report.findings[0].kind, svag::SecurityIssue::EventHandler);
+//! assert_eq!(report.findings[1].detail, "https://example.com/a.png");
+//! ```
+
+use crate::data_uri::parse_data_uri;
+use crate::external::{RefSource, for_each_url, is_external, is_javascript};
+use crate::{Document, Element, Node};
+
+/// `data:` URI types a browser can run scripts from, directly or inside a
+/// document.
+const EXECUTABLE_MIMES: &[&str] = &[
+    "text/html",
+    "application/xhtml+xml",
+    "image/svg+xml",
+    "text/xml",
+    "application/xml",
+    "text/javascript",
+    "application/javascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "application/ecmascript",
+];
+
+/// Elements that change another element's attributes over time.
+const ANIMATIONS: &[&str] = &["animate", "set", "animateColor", "animateTransform"];
+
+/// What [`audit`] found. See [`SecurityReport::is_clean`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecurityReport {
+    /// Everything found, in document order
+    pub findings: Vec<SecurityFinding>,
+}
+
+impl SecurityReport {
+    /// Whether nothing was found.
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    /// The findings of one kind.
+    pub fn of_kind(&self, kind: SecurityIssue) -> impl Iterator<Item = &SecurityFinding> {
+        self.findings.iter().filter(move |f| f.kind == kind)
+    }
+}
+
+/// One thing in the document that could run code or load something.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityFinding {
+    pub kind: SecurityIssue,
+    /// Path of the element it's on, see [`Document::element_at`]; `None`
+    /// for processing instructions before the root
+    pub element: Option<Vec<usize>>,
+    /// The attribute, URL, or element names, depending on the kind
+    pub detail: String,
+}
+
+/// Kinds of [`SecurityFinding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecurityIssue {
+    /// A `<script>` element (detail: its `href`, or `inline`), or a
+    /// `javascript:` URL (detail: the attribute)
+    Script,
+    /// An `on…` attribute (detail: its name)
+    EventHandler,
+    /// A URL outside the document: links, images, `<use>` of other files,
+    /// `url()`s and `@import`s in styles, linked stylesheets (detail: the
+    /// URL)
+    ExternalUrl,
+    /// A `<foreignObject>` with content (detail: its child element names)
+    ForeignObject,
+    /// An animation of a link or event handler (detail: the animated
+    /// attribute)
+    DangerousAnimation,
+    /// A `data:` URI with a type that can run scripts (detail: the type)
+    ExecutableDataUri,
+}
+
+/// List everything in `doc` that could run code or load something when
+/// it's rendered.
+pub fn audit(doc: &Document) -> SecurityReport {
+    let mut report = SecurityReport::default();
+    report.visit(&doc.root, &mut Vec::new());
+    // URLs are listed after the elements' own findings, so order them by
+    // element again; the sort is stable
+    for_each_url(doc, |url, source, attribute, path| {
+        report.url(url, source, attribute, path);
+    });
+    report.findings.sort_by(|a, b| a.element.cmp(&b.element));
+    report
+}
+
+impl SecurityReport {
+    fn push(&mut self, kind: SecurityIssue, path: Option<&[usize]>, detail: impl Into<String>) {
+        self.findings.push(SecurityFinding {
+            kind,
+            element: path.map(<[usize]>::to_vec),
+            detail: detail.into(),
+        });
+    }
+
+    fn visit(&mut self, elem: &Element, path: &mut Vec<usize>) {
+        let at = Some(path.as_slice());
+        if elem.is("script") {
+            let href = elem
+                .get_attr("href")
+                .or_else(|| elem.get_attr("xlink:href"));
+            self.push(SecurityIssue::Script, at, href.unwrap_or("inline"));
+        }
+        if elem.is("foreignObject") {
+            let children: Vec<String> = elem
+                .child_elements()
+                .map(|child| child.name.full_name())
+                .collect();
+            let has_text = elem.children.iter().any(|child| {
+                matches!(child, Node::Text(text) | Node::CData(text) if !text.trim().is_empty())
+            });
+            if !children.is_empty() || has_text {
+                self.push(SecurityIssue::ForeignObject, at, children.join(" "));
+            }
+        }
+        if ANIMATIONS.contains(&elem.name.local.as_str())
+            && let Some(animated) = elem.get_attr("attributeName")
+            && is_dangerous_attribute(animated)
+        {
+            self.push(SecurityIssue::DangerousAnimation, at, animated);
+        }
+
+        for attr in &elem.attributes {
+            if attr.name.prefix.is_none() && is_event_handler(&attr.name.local) {
+                self.push(SecurityIssue::EventHandler, at, attr.name.full_name());
+            }
+        }
+
+        for (i, child) in elem.children.iter().enumerate() {
+            if let Node::Element(child) = child {
+                path.push(i);
+                self.visit(child, path);
+                path.pop();
+            }
+        }
+    }
+
+    /// Record `url` if it points outside the document or runs scripts.
+    fn url(
+        &mut self,
+        url: &str,
+        source: RefSource,
+        attribute: Option<&str>,
+        path: Option<&[usize]>,
+    ) {
+        if is_javascript(url) {
+            let detail = match (source, attribute) {
+                (RefSource::Href, Some(attribute)) => attribute,
+                _ => url,
+            };
+            self.push(SecurityIssue::Script, path, detail);
+        } else if let Some(data) = parse_data_uri(url) {
+            let mime = data.mime.to_ascii_lowercase();
+            if EXECUTABLE_MIMES.contains(&mime.as_str()) {
+                self.push(SecurityIssue::ExecutableDataUri, path, mime);
+            }
+        } else if is_external(url) {
+            self.push(SecurityIssue::ExternalUrl, path, url);
+        }
+    }
+}
+
+/// Whether an attribute name is an event handler, like `onclick`.
+fn is_event_handler(name: &str) -> bool {
+    name.len() > 2
+        && name
+            .get(..2)
+            .is_some_and(|on| on.eq_ignore_ascii_case("on"))
+}
+
+/// Whether animating the attribute `name` can make the element run code or
+/// load something.
+fn is_dangerous_attribute(name: &str) -> bool {
+    let local = name.rsplit(':').next().unwrap_or(name).trim();
+    local == "href" || local == "src" || is_event_handler(local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_audit() {
+        let svg = r##"<?xml-stylesheet href="https://cdn.example/theme.css"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" onload="go()">
+    <style>@import "fonts.css"; .a { fill: url(#g); background: url(https://t.example/p.gif) }</style>
+    <script>alert(1)</script>
+    <a xlink:href=" JavaScript:alert(1)"><rect OnClick="x()"/></a>
+    <use href="#local"/>
+    <image href="data:image/svg+xml;base64,PHN2Zy8+"/>
+    <image href="data:image/png;base64,iVBORw0KGgo="/>
+    <foreignObject><div xmlns="http://www.w3.org/1999/xhtml">hi</div></foreignObject>
+    <foreignObject> </foreignObject>
+    <a href="/"><set attributeName="xlink:href" to="javascript:alert(1)"/></a>
+    <animate attributeName="opacity" to="0"/>
+</svg>"##;
+        let report = audit(&parse_svg(svg).unwrap());
+        let findings: Vec<(SecurityIssue, &str)> = report
+            .findings
+            .iter()
+            .map(|f| (f.kind, f.detail.as_str()))
+            .collect();
+        use SecurityIssue::*;
+        assert_eq!(
+            findings,
+            [
+                (ExternalUrl, "https://cdn.example/theme.css"),
+                (EventHandler, "onload"),
+                (ExternalUrl, "fonts.css"),
+                (ExternalUrl, "https://t.example/p.gif"),
+                (Script, "inline"),
+                (Script, "xlink:href"),
+                (EventHandler, "OnClick"),
+                (ExecutableDataUri, "image/svg+xml"),
+                (ForeignObject, "div"),
+                (ExternalUrl, "/"),
+                (DangerousAnimation, "xlink:href"),
+            ]
+        );
+        assert_eq!(report.findings[0].element, None);
+        assert_eq!(report.findings[6].element, Some(vec![4, 0]));
+        assert_eq!(report.of_kind(ExternalUrl).count(), 4);
+
+        let clean = parse_svg(r##"<svg><use href="#a"/><path id="a" fill="url(#g)"/></svg>"##);
+        assert!(audit(&clean.unwrap()).is_clean());
+    }
+}
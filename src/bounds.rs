@@ -0,0 +1,324 @@
+//! Shared analysis for passes that reason about what an element paints and
+//! where: computed properties, lengths, and painted bounds.
+//!
+//! Everything here answers `None` when unsure; callers treat that as "leave
+//! the element alone".
+
+use crate::ast::*;
+use crate::geometry::{Point, Rect};
+use crate::path::parse_path;
+
+/// Inherited properties the analyses depend on, as specified values.
+#[derive(Debug, Clone)]
+pub(crate) struct Inherited {
+    pub fill: String,
+    pub fill_opacity: String,
+    pub stroke: String,
+    pub stroke_opacity: String,
+    pub stroke_width: String,
+    pub stroke_miterlimit: String,
+    pub visibility: String,
+    pub has_markers: bool,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Self {
+            fill: "black".into(),
+            fill_opacity: "1".into(),
+            stroke: "none".into(),
+            stroke_opacity: "1".into(),
+            stroke_width: "1".into(),
+            stroke_miterlimit: "4".into(),
+            visibility: "visible".into(),
+            has_markers: false,
+        }
+    }
+}
+
+impl Inherited {
+    /// The values `elem` ends up with, given its parent's.
+    pub fn child(&self, elem: &Element) -> Self {
+        let pick = |name: &str, parent: &String| match property(elem, name) {
+            Some("inherit") | None => parent.clone(),
+            Some(value) => value.to_string(),
+        };
+        Self {
+            fill: pick("fill", &self.fill),
+            fill_opacity: pick("fill-opacity", &self.fill_opacity),
+            stroke: pick("stroke", &self.stroke),
+            stroke_opacity: pick("stroke-opacity", &self.stroke_opacity),
+            stroke_width: pick("stroke-width", &self.stroke_width),
+            stroke_miterlimit: pick("stroke-miterlimit", &self.stroke_miterlimit),
+            visibility: pick("visibility", &self.visibility),
+            has_markers: self.has_markers
+                || ["marker", "marker-start", "marker-mid", "marker-end"]
+                    .iter()
+                    .any(|m| property(elem, m).is_some_and(|v| v != "none")),
+        }
+    }
+}
+
+/// Whether the document can change what's painted at render time, through
+/// stylesheets (including ones linked with `<?xml-stylesheet?>`), scripts,
+/// or animation.
+pub(crate) fn has_dynamic_content(doc: &Document) -> bool {
+    let is_stylesheet = |node: &Node| matches!(node, Node::ProcessingInstruction { target, .. } if target == "xml-stylesheet");
+    doc.prolog.iter().any(is_stylesheet) || element_has_dynamic_content(&doc.root)
+}
+
+/// Whether scripts or animations can change the document at render time.
+/// Unlike [`has_dynamic_content`], stylesheets don't count: they restyle
+/// elements but can't add or rewire references.
+pub(crate) fn has_scripts_or_animation(doc: &Document) -> bool {
+    let mut found = false;
+    doc.for_each_element(|elem| {
+        found |= matches!(
+            elem.name.local.as_str(),
+            "script" | "animate" | "animateColor" | "animateMotion" | "animateTransform" | "set"
+        );
+    });
+    found
+}
+
+fn element_has_dynamic_content(elem: &Element) -> bool {
+    const DYNAMIC: [&str; 7] = [
+        "style",
+        "script",
+        "animate",
+        "animateColor",
+        "animateMotion",
+        "animateTransform",
+        "set",
+    ];
+    DYNAMIC.contains(&elem.name.local.as_str())
+        || elem.children.iter().any(|child| match child {
+            Node::Element(e) => element_has_dynamic_content(e),
+            Node::ProcessingInstruction { target, .. } => target == "xml-stylesheet",
+            _ => false,
+        })
+}
+
+/// Whether `elem` is a basic shape, path, or image.
+pub(crate) fn is_shape(elem: &Element) -> bool {
+    matches!(
+        elem.name.local.as_str(),
+        "rect" | "image" | "circle" | "ellipse" | "line" | "polyline" | "polygon" | "path"
+    )
+}
+
+/// Geometry bounds of a shape, in its own user space, without stroke.
+pub(crate) fn shape_bounds(elem: &Element) -> Option<Rect> {
+    let bounds = match elem.name.local.as_str() {
+        "rect" | "image" => box_bounds(elem)?,
+        "circle" => {
+            let (cx, cy) = (coord(elem, "cx")?, coord(elem, "cy")?);
+            let r = length(property(elem, "r")?)?;
+            Rect {
+                min_x: cx - r,
+                min_y: cy - r,
+                max_x: cx + r,
+                max_y: cy + r,
+            }
+        }
+        "ellipse" => {
+            let (cx, cy) = (coord(elem, "cx")?, coord(elem, "cy")?);
+            let (rx, ry) = (
+                length(property(elem, "rx")?)?,
+                length(property(elem, "ry")?)?,
+            );
+            Rect {
+                min_x: cx - rx,
+                min_y: cy - ry,
+                max_x: cx + rx,
+                max_y: cy + ry,
+            }
+        }
+        "line" => {
+            let (x1, y1) = (coord(elem, "x1")?, coord(elem, "y1")?);
+            let (x2, y2) = (coord(elem, "x2")?, coord(elem, "y2")?);
+            Rect {
+                min_x: x1.min(x2),
+                min_y: y1.min(y2),
+                max_x: x1.max(x2),
+                max_y: y1.max(y2),
+            }
+        }
+        "polyline" | "polygon" => {
+            let numbers = elem
+                .get_attr("points")?
+                .split(|c: char| c.is_ascii_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(length)
+                .collect::<Option<Vec<_>>>()?;
+            let mut points = numbers.chunks_exact(2);
+            let first = points.next()?;
+            let mut rect = Rect::from_point(Point::new(first[0], first[1]));
+            for p in points {
+                rect.include(Point::new(p[0], p[1]));
+            }
+            rect
+        }
+        "path" => parse_path(elem.get_attr("d")?).ok()?.bounding_box()?,
+        _ => return None,
+    };
+    Some(bounds)
+}
+
+/// Bounds of everything a shape or group paints, in its parent's user
+/// space. `inherited` must already include `elem`'s own properties.
+///
+/// Transforms, filters, markers, and anything other than shapes and
+/// groups make the answer unknown.
+pub(crate) fn painted_bounds(elem: &Element, inherited: &Inherited) -> Option<Rect> {
+    if property(elem, "transform").is_some_and(|v| v != "none") {
+        return None;
+    }
+    local_painted_bounds(elem, inherited)
+}
+
+/// Like [`painted_bounds`], but in `elem`'s own user space, after its
+/// `transform`. This is the space its `clip-path` and `mask` apply in.
+pub(crate) fn local_painted_bounds(elem: &Element, inherited: &Inherited) -> Option<Rect> {
+    if inherited.has_markers || property(elem, "filter").is_some_and(|v| v != "none") {
+        return None;
+    }
+
+    if elem.is("g") {
+        let mut bounds: Option<Rect> = None;
+        for child in elem.child_elements() {
+            if paints_nothing(child) {
+                continue;
+            }
+            let child_bounds = painted_bounds(child, &inherited.child(child))?;
+            bounds = Some(bounds.map_or(child_bounds, |b| b.union(&child_bounds)));
+        }
+        return bounds;
+    }
+
+    if !is_shape(elem) {
+        return None;
+    }
+    let mut bounds = shape_bounds(elem)?;
+
+    // Miter joins can reach miterlimit × half the stroke width past the
+    // geometry; square caps reach further than half the width too.
+    if inherited.stroke.trim() != "none" {
+        let width = length(&inherited.stroke_width)?;
+        let miter = length(&inherited.stroke_miterlimit)?.max(std::f64::consts::SQRT_2);
+        let pad = width * miter / 2.0;
+        bounds.min_x -= pad;
+        bounds.min_y -= pad;
+        bounds.max_x += pad;
+        bounds.max_y += pad;
+    }
+
+    Some(bounds)
+}
+
+/// Elements that never render anything themselves.
+pub(crate) fn paints_nothing(elem: &Element) -> bool {
+    matches!(elem.name.local.as_str(), "title" | "desc" | "metadata")
+        || property(elem, "display") == Some("none")
+}
+
+/// The `x`/`y`/`width`/`height` box of a rect-like element.
+pub(crate) fn box_bounds(elem: &Element) -> Option<Rect> {
+    let (x, y) = (coord(elem, "x")?, coord(elem, "y")?);
+    let width = length(property(elem, "width")?)?;
+    let height = length(property(elem, "height")?)?;
+    (width > 0.0 && height > 0.0).then_some(Rect {
+        min_x: x,
+        min_y: y,
+        max_x: x + width,
+        max_y: y + height,
+    })
+}
+
+/// A position attribute, which defaults to 0.
+pub(crate) fn coord(elem: &Element, name: &str) -> Option<f64> {
+    property(elem, name).map_or(Some(0.0), length)
+}
+
+/// A plain number, optionally in `px`.
+pub(crate) fn length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+}
+
+/// A number or percentage, as a fraction (`50%` is `0.5`).
+pub(crate) fn fraction(value: &str) -> Option<f64> {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok().map(|p| p / 100.0),
+        None => value.parse::<f64>().ok(),
+    }
+    .filter(|n| n.is_finite())
+}
+
+/// A color that is known to paint with full alpha.
+pub(crate) fn is_opaque_color(value: &str) -> bool {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 6) && hex.bytes().all(|b| b.is_ascii_hexdigit());
+    }
+    let lower = value.to_ascii_lowercase();
+    if lower.starts_with("rgb(") || lower.starts_with("hsl(") {
+        // Space-separated syntax can carry alpha after a slash
+        return !lower.contains('/');
+    }
+    !matches!(
+        lower.as_str(),
+        "none" | "transparent" | "currentcolor" | "inherit" | "context-fill" | "context-stroke"
+    ) && !lower.is_empty()
+        && lower.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// A property from the `style` attribute, falling back to the presentation
+/// attribute of the same name.
+pub(crate) fn property<'a>(elem: &'a Element, name: &str) -> Option<&'a str> {
+    let from_style = elem.get_attr("style").and_then(|style| {
+        style.split(';').rev().find_map(|decl| {
+            let (prop, value) = decl.split_once(':')?;
+            (prop.trim() == name).then(|| value.trim().trim_end_matches("!important").trim())
+        })
+    });
+    from_style.or_else(|| elem.get_attr(name).map(str::trim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_painted_bounds() {
+        let doc = parse_svg(
+            r#"<svg><g stroke="red" stroke-width="2" stroke-linejoin="round"><title>t</title><rect x="10" y="10" width="10" height="10"/><circle cx="40" cy="15" r="5" stroke="none"/></g><g><text>x</text></g><g><rect width="1" height="1" transform="scale(2)"/></g></svg>"#,
+        )
+        .unwrap();
+        let groups: Vec<_> = doc.root.child_elements().collect();
+        let bounds = |g: &Element| painted_bounds(g, &Inherited::default().child(g));
+
+        // Stroke padding assumes the default miter limit of 4
+        let b = bounds(groups[0]).unwrap();
+        assert_eq!((b.min_x, b.min_y, b.max_x, b.max_y), (6.0, 6.0, 45.0, 24.0));
+        assert!(bounds(groups[1]).is_none());
+        assert!(bounds(groups[2]).is_none());
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(length("12px"), Some(12.0));
+        assert_eq!(length("50%"), None);
+        assert_eq!(fraction("50%"), Some(0.5));
+        assert_eq!(fraction(".25"), Some(0.25));
+        assert!(is_opaque_color("#abc") && is_opaque_color("rgb(1, 2, 3)"));
+        assert!(!is_opaque_color("#abcd") && !is_opaque_color("rgb(1 2 3 / .5)"));
+    }
+}
@@ -0,0 +1,283 @@
+//! Helpers for build scripts.
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//!     svag::build::minify_dir("assets/icons", out.join("icons")).unwrap();
+//! }
+//! ```
+//!
+//! Outputs mirror the source tree. A manifest in the output directory records
+//! the content and options hash of each source, so unchanged files are not
+//! minified again, and the outputs written, so ones whose source was deleted
+//! or renamed are removed.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+use crate::{CacheEntry, Options, SvagError, content_hash, minify_with_options, would_change};
+
+/// Name of the cache manifest written to the output directory.
+pub const MANIFEST_NAME: &str = ".svag-manifest";
+
+/// What [`minify_dir`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildReport {
+    /// Files that were minified and written
+    pub minified: Vec<PathBuf>,
+    /// Files whose cached output was still up to date
+    pub unchanged: Vec<PathBuf>,
+    /// Outputs of an earlier run whose source is gone, which were deleted
+    pub removed: Vec<PathBuf>,
+}
+
+/// Minify every `.svg` file under `src` into `out` with default options.
+///
+/// Prints `cargo:rerun-if-changed` lines for the directory and each file.
+pub fn minify_dir(src: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<BuildReport, SvagError> {
+    minify_dir_with_options(src, out, &Options::default())
+}
+
+/// Minify every `.svg` file under `src` into `out` with custom options.
+///
+/// Paths in the report are relative to `src`. Options without a
+/// [fingerprint](Options::fingerprint), such as ones with resolvers, aren't
+/// cached: every file is minified, and none recorded in the manifest.
+pub fn minify_dir_with_options(
+    src: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+    options: &Options,
+) -> Result<BuildReport, SvagError> {
+    let (src, out) = (src.as_ref(), out.as_ref());
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    fs::create_dir_all(out)?;
+    let manifest_path = out.join(MANIFEST_NAME);
+    let old_manifest = fs::read_to_string(&manifest_path)
+        .map(|s| parse_manifest(&s))
+        .unwrap_or_default();
+
+    let mut files: Vec<PathBuf> = WalkBuilder::new(src)
+        .git_ignore(false)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "svg"))
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    files.sort();
+
+    let fingerprint = options.fingerprint();
+    let mut report = BuildReport::default();
+    let mut manifest = String::new();
+    let mut sources = HashSet::new();
+
+    for path in files {
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let relative = path.strip_prefix(src).unwrap_or(&path).to_path_buf();
+        sources.insert(relative.clone());
+        let target = out.join(&relative);
+        let input = fs::read(&path)?;
+        let entry =
+            fingerprint.map(|fingerprint| CacheEntry::new(content_hash(&input), fingerprint));
+        let cached = old_manifest.get(&relative).and_then(Option::as_ref);
+
+        let up_to_date = entry.is_some_and(|entry| {
+            !would_change(entry.input_hash, entry.options_fingerprint, cached)
+        });
+        if up_to_date && target.exists() {
+            report.unchanged.push(relative.clone());
+        } else {
+            let minified = std::str::from_utf8(&input)
+                .map_err(SvagError::from)
+                .and_then(|svg| minify_with_options(svg, options))
+                .map_err(|e| SvagError::InFile {
+                    path: path.clone(),
+                    error: Box::new(e),
+                })?;
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, minified)?;
+            report.minified.push(relative.clone());
+        }
+
+        match entry {
+            Some(entry) => manifest.push_str(&format!(
+                "{:016x} {:016x} {}\n",
+                entry.input_hash,
+                entry.options_fingerprint,
+                relative.display()
+            )),
+            None => manifest.push_str(&format!("- - {}\n", relative.display())),
+        }
+    }
+
+    let mut stale: Vec<&PathBuf> = old_manifest
+        .keys()
+        .filter(|relative| !sources.contains(*relative))
+        .collect();
+    stale.sort();
+    for relative in stale {
+        let target = out.join(relative);
+        match fs::remove_file(&target) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        // Drop directories the removal emptied, up to the output root
+        for dir in target.ancestors().skip(1) {
+            if dir == out || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+        report.removed.push(relative.clone());
+    }
+
+    fs::write(&manifest_path, manifest)?;
+    Ok(report)
+}
+
+/// Parse manifest lines of the form `<content hash> <options hash> <path>`.
+/// Outputs written without a cache entry have `-` for both hashes.
+fn parse_manifest(s: &str) -> HashMap<PathBuf, Option<CacheEntry>> {
+    s.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let (content, options) = (parts.next()?, parts.next()?);
+            let entry = if (content, options) == ("-", "-") {
+                None
+            } else {
+                Some(CacheEntry::new(
+                    u64::from_str_radix(content, 16).ok()?,
+                    u64::from_str_radix(options, 16).ok()?,
+                ))
+            };
+            Some((PathBuf::from(parts.next()?), entry))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_dir_caches() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        fs::create_dir(src.path().join("nested")).unwrap();
+        fs::write(
+            src.path().join("a.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><!-- a --><rect/></svg>"#,
+        )
+        .unwrap();
+        fs::write(
+            src.path().join("nested/b.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><circle r="1"/></svg>"#,
+        )
+        .unwrap();
+
+        let report = minify_dir(src.path(), out.path()).unwrap();
+        assert_eq!(report.minified.len(), 2);
+        assert_eq!(
+            fs::read_to_string(out.path().join("a.svg")).unwrap(),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect/></svg>"#
+        );
+        assert!(out.path().join("nested/b.svg").exists());
+
+        let report = minify_dir(src.path(), out.path()).unwrap();
+        assert!(report.minified.is_empty());
+        assert_eq!(report.unchanged.len(), 2);
+
+        // Changing content or options invalidates the cache
+        fs::write(
+            src.path().join("a.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M 0 0"/></svg>"#,
+        )
+        .unwrap();
+        let report = minify_dir(src.path(), out.path()).unwrap();
+        assert_eq!(report.minified, vec![PathBuf::from("a.svg")]);
+
+        let options = Options {
+            precision: 1,
+            ..Options::default()
+        };
+        let report = minify_dir_with_options(src.path(), out.path(), &options).unwrap();
+        assert_eq!(report.minified.len(), 2);
+
+        // Resolvers can load something else every time
+        let options = Options {
+            image_resolver: Some(crate::ImageResolver::new(|_| None)),
+            ..Options::default()
+        };
+        for _ in 0..2 {
+            let report = minify_dir_with_options(src.path(), out.path(), &options).unwrap();
+            assert_eq!(report.minified.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_minify_dir_removes_stale_outputs() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        fs::create_dir(src.path().join("nested")).unwrap();
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect/></svg>"#;
+        for name in ["a.svg", "b.svg", "nested/c.svg"] {
+            fs::write(src.path().join(name), svg).unwrap();
+        }
+        // Not written by svag, so never removed
+        fs::write(out.path().join("other.svg"), svg).unwrap();
+
+        let report = minify_dir(src.path(), out.path()).unwrap();
+        assert_eq!(report.minified.len(), 3);
+        assert!(report.removed.is_empty());
+
+        fs::rename(src.path().join("b.svg"), src.path().join("d.svg")).unwrap();
+        fs::remove_file(src.path().join("nested/c.svg")).unwrap();
+        let report = minify_dir(src.path(), out.path()).unwrap();
+        assert_eq!(report.minified, vec![PathBuf::from("d.svg")]);
+        assert_eq!(
+            report.removed,
+            vec![PathBuf::from("b.svg"), PathBuf::from("nested/c.svg")]
+        );
+        assert!(!out.path().join("b.svg").exists());
+        assert!(!out.path().join("nested").exists());
+        assert!(out.path().join("other.svg").exists());
+
+        let manifest = fs::read_to_string(out.path().join(MANIFEST_NAME)).unwrap();
+        let entries: Vec<_> = parse_manifest(&manifest).into_keys().collect();
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains(&PathBuf::from("b.svg")));
+
+        // Uncached outputs are tracked too
+        let options = Options {
+            image_resolver: Some(crate::ImageResolver::new(|_| None)),
+            ..Options::default()
+        };
+        minify_dir_with_options(src.path(), out.path(), &options).unwrap();
+        fs::remove_file(src.path().join("d.svg")).unwrap();
+        let report = minify_dir_with_options(src.path(), out.path(), &options).unwrap();
+        assert_eq!(report.removed, vec![PathBuf::from("d.svg")]);
+        assert!(!out.path().join("d.svg").exists());
+    }
+
+    #[test]
+    fn test_minify_dir_reports_file() {
+        let src = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        fs::write(src.path().join("bad.svg"), "<svg><g></svg>").unwrap();
+
+        let err = minify_dir(src.path(), out.path()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("bad.svg"));
+        assert!(message.contains("XML parsing error"));
+        // Reporters walking the chain mustn't print the parse error twice
+        assert!(std::error::Error::source(&err).is_none());
+    }
+}
@@ -0,0 +1,247 @@
+//! Canonical form for comparing documents.
+//!
+//! Minified output is small, but two minifications of equivalent files
+//! can still differ byte for byte. The canonical form doesn't try to be
+//! small: it settles every choice that doesn't change the drawing
+//! (attribute order, number and whitespace formatting, absolute or
+//! relative path commands, namespace prefixes) the same way every time,
+//! so equal drawings serialize equal and diffs only show what matters.
+
+use crate::ast::*;
+use crate::error::SvagError;
+use crate::namespaces::{hoist_namespaces, unprefix_elements};
+use crate::optimize::remove_unused_namespaces;
+use crate::path::{format_number, parse_path, serialize_path};
+use crate::sprite::SVG_NS;
+use crate::transform::{parse_transform, serialize_transform};
+use crate::{OptimizeFor, Options, parse_svg, serialize};
+
+/// Decimals kept in numbers by [`canonicalize`]: enough that nothing an
+/// editor exports is rounded away.
+const PRECISION: u8 = 6;
+
+/// Geometric and numeric presentation attributes whose numbers are
+/// rewritten. Anything else (`version`, `tabindex`, `aria-*`, `data-*`,
+/// names) stays as written even when it looks like a number, since a
+/// change there is a real change.
+const NUMERIC_ATTRIBUTES: &[&str] = &[
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "fx",
+    "fy",
+    "fr",
+    "dx",
+    "dy",
+    "width",
+    "height",
+    "viewBox",
+    "points",
+    "rotate",
+    "offset",
+    "pathLength",
+    "refX",
+    "refY",
+    "markerWidth",
+    "markerHeight",
+    "stdDeviation",
+    "opacity",
+    "fill-opacity",
+    "stroke-opacity",
+    "stop-opacity",
+    "flood-opacity",
+    "stroke-width",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-miterlimit",
+    "font-size",
+];
+
+/// Rewrite `svg` in a canonical form, for diffing, deduplication, and
+/// hashing rather than serving. Attributes are sorted, numbers written in
+/// their shortest form, whitespace collapsed, path data made absolute,
+/// the SVG namespace made the default one (with the `xmlns` it implies),
+/// and comments, the XML declaration, and the doctype dropped. Nothing
+/// else is optimized, so the result is usually larger than minified
+/// output.
+///
+/// ```
+/// let a = svag::canonicalize(r#"<?xml version="1.0"?>
+/// <!-- exported -->
+/// <svg:svg xmlns:svg="http://www.w3.org/2000/svg" viewBox="0,0,24,24">
+///   <svg:path d="m2 2 h 20.000" fill="red" />
+/// </svg:svg>"#).unwrap();
+/// let b = svag::canonicalize(
+///     r#"<svg viewBox="0 0 24 24"><path fill="red" d="M2,2 H22"/></svg>"#,
+/// )
+/// .unwrap();
+/// assert_eq!(a, b);
+/// assert_eq!(
+///     a,
+///     r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><path d="M2 2H22" fill="red"/></svg>"#
+/// );
+/// ```
+pub fn canonicalize(svg: &str) -> Result<String, SvagError> {
+    let mut doc = parse_svg(svg)?;
+    canonicalize_document(&mut doc, PRECISION);
+    Ok(serialize(&doc, &canonical_options()))
+}
+
+/// Options that serialize a canonicalized document as it is, with sorted
+/// attributes.
+pub(crate) fn canonical_options() -> Options {
+    Options {
+        sort_attrs: true,
+        optimize_for: OptimizeFor::RawSize,
+        ..Options::default()
+    }
+}
+
+/// Bring `doc` to its canonical form, writing numbers with `precision`
+/// decimals. Attribute order is left to the serializer.
+pub(crate) fn canonicalize_document(doc: &mut Document, precision: u8) {
+    doc.xml_declaration = None;
+    doc.doctype = None;
+    doc.prolog
+        .retain(|node| matches!(node, Node::ProcessingInstruction { .. }));
+    resolve_default_namespace(&mut doc.root);
+    normalize_element(&mut doc.root, precision, false);
+}
+
+/// Make the SVG namespace the default one: declare it where it's implied,
+/// drop element prefixes that name it, and hoist and prune the other
+/// declarations.
+fn resolve_default_namespace(root: &mut Element) {
+    let declares_default = root
+        .attributes
+        .iter()
+        .any(|a| a.name.prefix.is_none() && a.name.local == "xmlns");
+    if !declares_default {
+        let root_is_svg = match root.name.prefix.as_deref() {
+            None => true,
+            Some(prefix) => root.attributes.iter().any(|a| {
+                a.name.prefix.as_deref() == Some("xmlns")
+                    && a.name.local == prefix
+                    && a.value == SVG_NS
+            }),
+        };
+        if root_is_svg {
+            root.set_attr("xmlns", SVG_NS);
+        }
+    }
+    unprefix_elements(root);
+    hoist_namespaces(root);
+    remove_unused_namespaces(root);
+}
+
+fn normalize_element(elem: &mut Element, precision: u8, preserve_space: bool) {
+    let space = elem
+        .attributes
+        .iter()
+        .find(|a| a.name.prefix.as_deref() == Some("xml") && a.name.local == "space");
+    let preserve_space = match space {
+        Some(space) => space.value == "preserve",
+        None => preserve_space,
+    };
+    for attr in &mut elem.attributes {
+        if !attr.name.is_xmlns() {
+            attr.value = normalize_value(&attr.name, &attr.value, precision).into();
+        }
+    }
+
+    let raw_text =
+        elem.name.prefix.is_none() && matches!(elem.name.local.as_str(), "style" | "script");
+    elem.children.retain_mut(|node| match node {
+        Node::Comment(_) => false,
+        Node::Text(text) if !raw_text && !preserve_space => {
+            *text = collapse_whitespace(text).into();
+            !text.is_empty()
+        }
+        Node::Element(child) => {
+            normalize_element(child, precision, preserve_space);
+            true
+        }
+        _ => true,
+    });
+}
+
+fn normalize_value(name: &QName, value: &str, precision: u8) -> String {
+    let value = collapse_whitespace(value);
+    if name.prefix.is_some() {
+        return value;
+    }
+    match name.local.as_str() {
+        "d" => match parse_path(&value) {
+            Ok(path) => serialize_path(&path.to_absolute(), precision),
+            Err(_) => value,
+        },
+        "transform" | "gradientTransform" | "patternTransform" => match parse_transform(&value) {
+            Ok(transform) => serialize_transform(&transform, precision, precision),
+            Err(_) => value,
+        },
+        local if NUMERIC_ATTRIBUTES.contains(&local) => {
+            normalize_numbers(&value, precision).unwrap_or(value)
+        }
+        _ => value,
+    }
+}
+
+/// A list of numbers (like `width`, `viewBox`, or `points`), rewritten
+/// with `precision` decimals and separated by spaces. `None` if `value`
+/// isn't one.
+fn normalize_numbers(value: &str, precision: u8) -> Option<String> {
+    let numbers: Vec<String> = value
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|n| {
+            let n: f64 = n.parse().ok().filter(|n: &f64| n.is_finite())?;
+            Some(format_number(n, precision))
+        })
+        .collect::<Option<_>>()?;
+    (!numbers.is_empty()).then(|| numbers.join(" "))
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        // Inner declarations, other namespaces, and preserved text
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:unused="urn:x"><g xmlns:xlink="http://www.w3.org/1999/xlink" transform="translate(10 , 0.50)"><use xlink:href="#a"/></g><text>  a
+  b </text><text xml:space="preserve">a  b</text><rect id="01" class="2" width="1e1" height="10.0"/></svg>"##;
+        assert_eq!(
+            canonicalize(svg).unwrap(),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><g transform="translate(10 .5)"><use xlink:href="#a"/></g><text>a b</text><text xml:space="preserve">a  b</text><rect class="2" height="10" id="01" width="10"/></svg>"##
+        );
+
+        // Nothing is optimized away
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg"><g><rect fill="#ff0000"/></g><metadata>x</metadata></svg>"##;
+        assert_eq!(canonicalize(svg).unwrap(), svg);
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_non_geometric_numbers() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.0"><a tabindex="01" aria-level="2.0" data-n="1e1" name="3.50"><rect x="1.0" opacity="0.50"/></a></svg>"#;
+        assert_eq!(
+            canonicalize(svg).unwrap(),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.0"><a aria-level="2.0" data-n="1e1" name="3.50" tabindex="01"><rect opacity=".5" x="1"/></a></svg>"#
+        );
+        assert_ne!(
+            canonicalize(svg).unwrap(),
+            canonicalize(&svg.replace(r#"version="1.0""#, r#"version="1""#)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Invariant checks for embedders.
+//!
+//! [`roundtrip`] verifies that serializing a document and parsing it back
+//! yields the same structure, which catches serializer bugs (bad escaping,
+//! dropped nodes) independently of what the optimizer did.
+//!
+//! [`idempotent`] verifies that minifying already-minified output changes
+//! nothing, so build caches keyed on content settle after one run.
+
+use std::fmt;
+
+use crate::{
+    Change, Document, Options, SvagError, diff_documents, minify_with_options, parse_svg, serialize,
+};
+
+/// A way in which a document didn't survive a serialize/parse roundtrip,
+/// or minified output didn't survive being minified again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The serialized output couldn't be parsed at all
+    Unparseable { error: String, output: String },
+    /// The reparsed document differs structurally
+    Changed(Change),
+    /// Minifying again gave different bytes with the same structure
+    Unstable { first: String, second: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Unparseable { error, .. } => {
+                write!(f, "serialized output doesn't parse: {}", error)
+            }
+            Violation::Changed(change) => write!(f, "{}", change),
+            Violation::Unstable { first, second } => write!(
+                f,
+                "minifying again changed the output ({} -> {} bytes)",
+                first.len(),
+                second.len()
+            ),
+        }
+    }
+}
+
+/// Serialize `doc` with `options`, re-parse it, and report any structural
+/// differences: elements, attribute sets, and text content (with whitespace
+/// collapsed, since the serializer trims text nodes).
+///
+/// An empty result means the document roundtrips.
+pub fn roundtrip(doc: &Document, options: &Options) -> Vec<Violation> {
+    let output = serialize(doc, options);
+    match parse_svg(&output) {
+        Ok(reparsed) => diff_documents(doc, &reparsed)
+            .into_iter()
+            .map(Violation::Changed)
+            .collect(),
+        Err(e) => vec![Violation::Unparseable {
+            error: e.to_string(),
+            output,
+        }],
+    }
+}
+
+/// Minify `svg`, minify the result again, and report how the second run
+/// differed from the first: structural changes if there are any, otherwise
+/// [`Violation::Unstable`] when the bytes differ.
+///
+/// An empty result means the output is a fixpoint. Errors are from
+/// minifying `svg` itself.
+pub fn idempotent(svg: &str, options: &Options) -> Result<Vec<Violation>, SvagError> {
+    let first = minify_with_options(svg, options)?;
+    let second = match minify_with_options(&first, options) {
+        Ok(second) => second,
+        Err(e) => {
+            return Ok(vec![Violation::Unparseable {
+                error: e.to_string(),
+                output: first,
+            }]);
+        }
+    };
+    if first == second {
+        return Ok(Vec::new());
+    }
+
+    let changes = match (parse_svg(&first), parse_svg(&second)) {
+        (Ok(a), Ok(b)) => diff_documents(&a, &b),
+        _ => Vec::new(),
+    };
+    if changes.is_empty() {
+        return Ok(vec![Violation::Unstable { first, second }]);
+    }
+    Ok(changes.into_iter().map(Violation::Changed).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Element, Node, optimize};
+
+    #[test]
+    fn test_roundtrip_ok() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <text x="1">a &amp; b &lt; c</text>
+            <g fill="#ff0000" data-x="&quot;q&quot;"><rect/></g>
+        </svg>"##;
+        let mut doc = parse_svg(svg).unwrap();
+        let options = Options::default();
+        assert!(roundtrip(&doc, &options).is_empty());
+
+        optimize(&mut doc, &options);
+        assert!(roundtrip(&doc, &options).is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip_violations() {
+        let options = Options {
+            remove_comments: false,
+            ..Options::default()
+        };
+
+        // A comment that ends early leaks its tail into the text content
+        let mut doc = parse_svg(r#"<svg><g/></svg>"#).unwrap();
+        doc.root
+            .children
+            .push(Node::Comment("a-->b".to_string().into()));
+        let violations = roundtrip(&doc, &options);
+        assert_eq!(
+            violations,
+            vec![Violation::Changed(Change::TextChanged {
+                path: "/svg".to_string(),
+                old: String::new(),
+                new: "b-->".to_string(),
+            })]
+        );
+
+        // An unescaped element name breaks the markup entirely
+        let mut doc = parse_svg(r#"<svg/>"#).unwrap();
+        let mut g = Element::new("g");
+        g.children.push(Node::Element(Element::new("a></g><b")));
+        doc.root.children.push(Node::Element(g));
+        let violations = roundtrip(&doc, &options);
+        assert!(
+            matches!(violations[..], [Violation::Unparseable { .. }]),
+            "{:?}",
+            violations
+        );
+    }
+
+    #[test]
+    fn test_idempotent() {
+        // Whitespace between siblings used to hide a single-child group, and
+        // a run of shared attributes, until the second run
+        let svg = r#"<svg>
+            <g font-size="32">
+                <text>Hi</text>
+            </g>
+            <g>
+                <rect fill="red" stroke="blue" stroke-width="2" x="1"/>
+                <rect fill="red" stroke="blue" stroke-width="2" x="2"/>
+                <rect fill="red" stroke="blue" stroke-width="3"/>
+            </g>
+        </svg>"#;
+        for options in [
+            Options::default(),
+            Options {
+                regroup_siblings: true,
+                ..Options::default()
+            },
+        ] {
+            assert_eq!(idempotent(svg, &options).unwrap(), vec![]);
+        }
+
+        assert!(idempotent("<svg", &Options::default()).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Move repeated presentation attributes into a stylesheet.
+//!
+//! Charts and exported icon sets often repeat the same `fill`/`stroke`/
+//! `stroke-width` combination on hundreds of elements. Each distinct
+//! combination that pays for itself becomes a rule like
+//! `.a{fill:red;stroke:#000}`, and the elements get `class="a"` instead.
+//!
+//! Rules in a stylesheet take precedence over presentation attributes, so
+//! this is only done when the document has no stylesheet, script, or
+//! animation of its own. It's still opt-in: CSS from a page embedding the
+//! SVG inline competes with the new rules where it used to beat the
+//! attributes, and some rasterizers don't support `<style>` at all.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::bounds::has_dynamic_content;
+use crate::optimize::IdGenerator;
+
+/// Presentation attributes whose values are valid as-is in CSS. Lengths
+/// that CSS wants units for (`font-size`) and `transform`, whose CSS syntax
+/// differs, stay attributes.
+const CONVERTIBLE: &[&str] = &[
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "opacity",
+    "clip-rule",
+    "color",
+    "display",
+    "visibility",
+    "font-family",
+    "font-style",
+    "font-weight",
+    "text-anchor",
+    "paint-order",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+];
+
+/// `<style></style>`
+const STYLE_ELEMENT_LEN: usize = 15;
+
+/// The convertible attributes of one element, sorted by name.
+type AttrSet = Vec<(String, String)>;
+
+/// Replace attribute sets that repeat often enough with generated classes.
+pub(crate) fn extract_classes(doc: &mut Document) {
+    if has_dynamic_content(doc) {
+        return;
+    }
+
+    let mut counts: HashMap<AttrSet, usize> = HashMap::new();
+    let mut classes: HashSet<String> = HashSet::new();
+    doc.for_each_element(|elem| {
+        if let Some(set) = attr_set(elem) {
+            *counts.entry(set).or_default() += 1;
+        }
+        if let Some(class) = elem.get_attr("class") {
+            classes.extend(class.split_ascii_whitespace().map(str::to_string));
+        }
+    });
+
+    // The most frequent sets get the shortest names
+    let mut counts: Vec<(AttrSet, usize)> = counts.into_iter().filter(|(_, n)| *n > 1).collect();
+    counts.sort_by(|(a, n), (b, m)| m.cmp(n).then_with(|| a.cmp(b)));
+
+    let mut names = IdGenerator::avoiding(classes);
+    let mut rules: HashMap<AttrSet, String> = HashMap::new();
+    let mut css = String::new();
+    let mut saved = -(STYLE_ELEMENT_LEN as isize);
+    let mut unused: Option<String> = None;
+    for (set, count) in counts {
+        let name = unused.take().unwrap_or_else(|| names.next_id());
+        let rule = rule(&name, &set);
+        let saving = (attrs_len(&set) as isize - class_len(&name) as isize) * count as isize
+            - rule.len() as isize;
+        if saving <= 0 {
+            unused = Some(name);
+            continue;
+        }
+        saved += saving;
+        css.push_str(&rule);
+        rules.insert(set, name);
+    }
+    if saved <= 0 {
+        return;
+    }
+
+    apply(&mut doc.root, &rules);
+    let mut style = Element::new("style");
+    style.append_child(Node::Text(css.into()));
+    doc.root.insert_before(0, style);
+}
+
+fn apply(elem: &mut Element, rules: &HashMap<AttrSet, String>) {
+    if let Some(name) = attr_set(elem).and_then(|set| rules.get(&set)) {
+        elem.attributes.retain(|a| !is_convertible(&a.name));
+        let class = match elem.get_attr("class") {
+            Some(existing) => format!("{existing} {name}"),
+            None => name.clone(),
+        };
+        elem.set_attr("class", class);
+    }
+
+    for child in elem.child_elements_mut() {
+        apply(child, rules);
+    }
+}
+
+fn attr_set(elem: &Element) -> Option<AttrSet> {
+    let mut set: AttrSet = elem
+        .attributes
+        .iter()
+        .filter(|a| is_convertible(&a.name))
+        .map(|a| (a.name.local.clone(), a.value.trim().to_string()))
+        .collect();
+    if set.is_empty() || elem.is("style") {
+        return None;
+    }
+    set.sort();
+    Some(set)
+}
+
+fn is_convertible(name: &QName) -> bool {
+    name.prefix.is_none() && CONVERTIBLE.contains(&name.local.as_str())
+}
+
+/// `.name{prop:value;…}`
+fn rule(name: &str, set: &AttrSet) -> String {
+    let decls: Vec<String> = set
+        .iter()
+        .map(|(prop, value)| format!("{prop}:{value}"))
+        .collect();
+    format!(".{name}{{{}}}", decls.join(";"))
+}
+
+/// Bytes the attributes take on an element: ` name="value"` each.
+fn attrs_len(set: &AttrSet) -> usize {
+    set.iter()
+        .map(|(name, value)| name.len() + value.len() + 4)
+        .sum()
+}
+
+/// Bytes a ` class="name"` attribute takes.
+fn class_len(name: &str) -> usize {
+    name.len() + 9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn run(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        extract_classes(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_extracts_repeated_sets() {
+        let path = r##"<path d="M0 0" fill="none" stroke="#4e79a7" stroke-width="1.5"/>"##;
+        let svg = format!(
+            r##"<svg><rect class="bg" fill="#eee"/>{}<circle r="2" stroke="#4e79a7" fill="none" stroke-width="1.5"/></svg>"##,
+            path.repeat(3)
+        );
+        let out = run(&svg);
+        assert_eq!(
+            out,
+            format!(
+                r##"<svg><style>.a{{fill:none;stroke:#4e79a7;stroke-width:1.5}}</style><rect class="bg" fill="#eee"/>{}<circle class="a" r="2"/></svg>"##,
+                r#"<path class="a" d="M0 0"/>"#.repeat(3)
+            )
+        );
+    }
+
+    #[test]
+    fn test_skips_unprofitable_and_styled() {
+        // Two short fills don't pay for a stylesheet
+        let svg = r#"<svg><rect fill="red"/><rect fill="red"/></svg>"#;
+        assert_eq!(run(svg), svg);
+
+        // Existing stylesheets would interact with the new rules
+        let rects = r##"<rect fill="#123456" stroke="#abcdef"/>"##.repeat(5);
+        let svg = format!("<svg><style>rect{{fill:red}}</style>{rects}</svg>");
+        assert_eq!(run(&svg), svg);
+    }
+
+    #[test]
+    fn test_avoids_existing_class_names() {
+        let rects = r##"<rect fill="#123456" stroke="#abcdef"/>"##.repeat(5);
+        let out = run(&format!(r#"<svg><g class="a"/>{rects}</svg>"#));
+        assert!(out.contains(r#"<rect class="b"/>"#), "{out}");
+        assert!(out.contains(".b{fill:#123456;stroke:#abcdef}"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Output of `svag analyze`: where a file's bytes go, as a table or JSON.
+
+use svag::{Analysis, Weight};
+
+use super::report::json_string;
+
+/// The kinds of content in a [`Weight`], in display order.
+fn categories(weight: &Weight) -> [(&'static str, usize); 6] {
+    [
+        ("paths", weight.paths),
+        ("styles", weight.styles),
+        ("embedded", weight.embedded),
+        ("metadata", weight.metadata),
+        ("text", weight.text),
+        ("structure", weight.structure),
+    ]
+}
+
+/// A size breakdown by kind of content, then the `top` heaviest elements.
+pub fn to_text(path: &str, analysis: &Analysis, top: usize) -> String {
+    let total = analysis.weight.total();
+    let percent = |bytes: usize| {
+        if total > 0 {
+            bytes as f64 / total as f64 * 100.0
+        } else {
+            0.0
+        }
+    };
+
+    let mut out = format!("{}: {} bytes\n", path, total);
+    for (name, bytes) in categories(&analysis.weight) {
+        out.push_str(&format!(
+            "  {:<10} {:>10} {:>6.1}%\n",
+            name,
+            bytes,
+            percent(bytes)
+        ));
+    }
+    if !analysis.heaviest.is_empty() {
+        out.push_str("\nHeaviest elements:\n");
+        for size in analysis.heaviest.iter().take(top) {
+            out.push_str(&format!(
+                "  {:>10} {:>6.1}%  {}\n",
+                size.bytes,
+                percent(size.bytes),
+                size.path
+            ));
+        }
+    }
+    out
+}
+
+/// The same as [`to_text`], as a single JSON object.
+pub fn to_json(path: &str, analysis: &Analysis, top: usize) -> String {
+    let weight: Vec<String> = categories(&analysis.weight)
+        .iter()
+        .map(|(name, bytes)| format!("{}:{}", json_string(name), bytes))
+        .collect();
+    let heaviest: Vec<String> = analysis
+        .heaviest
+        .iter()
+        .take(top)
+        .map(|size| {
+            format!(
+                r#"{{"path":{},"name":{},"bytes":{}}}"#,
+                json_string(&size.path),
+                json_string(&size.name),
+                size.bytes
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"path":{},"total":{},"weight":{{{}}},"heaviest":[{}]}}"#,
+        json_string(path),
+        analysis.weight.total(),
+        weight.join(","),
+        heaviest.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_output() {
+        let doc = svag::parse_svg(
+            r#"<svg><title>Logo</title><path d="M0 0h10v10z"/><path d="M5 5h1"/></svg>"#,
+        )
+        .unwrap();
+        let analysis = svag::analyze(&doc);
+
+        let text = to_text("logo.svg", &analysis, 1);
+        assert!(text.starts_with(&format!("logo.svg: {} bytes\n", analysis.weight.total())));
+        assert!(text.contains("\n  paths "));
+        assert_eq!(text.matches("/svg").count(), 1);
+
+        let json = to_json("logo.svg", &analysis, 2);
+        assert!(json.starts_with(r#"{"path":"logo.svg","total":"#));
+        assert!(json.contains(&format!(r#""paths":{},"#, analysis.weight.paths)));
+        assert_eq!(json.matches(r#"{"path":"/svg"#).count(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+//! `--compare-with`: run another minifier on the same inputs and report how
+//! svag's sizes and times compare.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One file minified by svag and by the other command.
+#[derive(Debug)]
+pub struct Comparison {
+    pub path: String,
+    pub original: usize,
+    /// svag's output size and time, or why it failed
+    pub svag: Result<(usize, Duration), String>,
+    /// The other command's output size and time, or why it failed
+    pub other: Result<(usize, Duration), String>,
+}
+
+/// Run `command` through the shell with `input` on stdin, returning its
+/// stdout and how long it took, startup included.
+pub fn run_command(command: &str, input: &[u8]) -> io::Result<(Vec<u8>, Duration)> {
+    let start = Instant::now();
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread, so a command that streams its output
+    // doesn't block on a full stdout pipe while we block on its stdin
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    let elapsed = start.elapsed();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().next().unwrap_or("").trim();
+        return Err(io::Error::other(format!(
+            "`{}` failed ({}){}{}",
+            command,
+            output.status,
+            if message.is_empty() { "" } else { ": " },
+            message
+        )));
+    }
+    // A command that exits without reading all of its input is fine
+    match writer.join().expect("stdin writer panicked") {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+        _ => {}
+    }
+    Ok((output.stdout, elapsed))
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// The header of the table [`row`] writes rows of.
+pub fn header() -> String {
+    format!(
+        "{:>10} {:>10} {:>10} {:>8} {:>10} {:>10}  path",
+        "original", "svag", "other", "delta", "svag ms", "other ms"
+    )
+}
+
+/// One file's sizes and times. The delta is svag's size relative to the
+/// other command's, so negative means svag's output is smaller.
+pub fn row(comparison: &Comparison) -> String {
+    let size = |result: &Result<(usize, Duration), String>| match result {
+        Ok((size, _)) => size.to_string(),
+        Err(_) => "failed".to_string(),
+    };
+    let ms = |result: &Result<(usize, Duration), String>| match result {
+        Ok((_, time)) => format!("{:.1}", time.as_secs_f64() * 1000.0),
+        Err(_) => "-".to_string(),
+    };
+    let delta = match (&comparison.svag, &comparison.other) {
+        (Ok((svag, _)), Ok((other, _))) => percent_delta(*svag, *other),
+        _ => "-".to_string(),
+    };
+    format!(
+        "{:>10} {:>10} {:>10} {:>8} {:>10} {:>10}  {}",
+        comparison.original,
+        size(&comparison.svag),
+        size(&comparison.other),
+        delta,
+        ms(&comparison.svag),
+        ms(&comparison.other),
+        comparison.path
+    )
+}
+
+/// Totals over the files both minifiers handled, and how many each failed
+/// on.
+pub fn totals(comparisons: &[Comparison]) -> Vec<String> {
+    let (mut original, mut svag, mut other) = (0, 0, 0);
+    let (mut svag_time, mut other_time) = (Duration::ZERO, Duration::ZERO);
+    for comparison in comparisons {
+        if let (Ok((svag_size, svag_elapsed)), Ok((other_size, other_elapsed))) =
+            (&comparison.svag, &comparison.other)
+        {
+            original += comparison.original;
+            svag += svag_size;
+            other += other_size;
+            svag_time += *svag_elapsed;
+            other_time += *other_elapsed;
+        }
+    }
+    let both = comparisons
+        .iter()
+        .filter(|c| c.svag.is_ok() && c.other.is_ok())
+        .count();
+    let failed = |side: fn(&Comparison) -> bool| comparisons.iter().filter(|c| side(c)).count();
+
+    let mut lines = vec![
+        format!(
+            "{} files: {} -> svag {} bytes, other {} bytes ({} for svag)",
+            both,
+            original,
+            svag,
+            other,
+            percent_delta(svag, other)
+        ),
+        format!(
+            "time: svag {:.1}ms, other {:.1}ms",
+            svag_time.as_secs_f64() * 1000.0,
+            other_time.as_secs_f64() * 1000.0
+        ),
+    ];
+    let (svag_failed, other_failed) = (failed(|c| c.svag.is_err()), failed(|c| c.other.is_err()));
+    if svag_failed + other_failed > 0 {
+        lines.push(format!(
+            "failed: svag {}, other {}",
+            svag_failed, other_failed
+        ));
+    }
+    lines
+}
+
+/// `value` relative to `base`, like `+3.2%`.
+fn percent_delta(value: usize, base: usize) -> String {
+    if base == 0 {
+        return "-".to_string();
+    }
+    let percent = (value as f64 - base as f64) / base as f64 * 100.0;
+    format!("{:+.1}%", percent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command() {
+        let (output, _) = run_command("tr a-z A-Z", b"<svg/>").unwrap();
+        assert_eq!(output, b"<SVG/>");
+
+        let error = run_command("echo oops >&2; exit 3", b"<svg/>").unwrap_err();
+        assert!(error.to_string().ends_with(": oops"), "{}", error);
+    }
+
+    #[test]
+    fn test_rows_and_totals() {
+        let ms = Duration::from_millis;
+        let comparisons = [
+            Comparison {
+                path: "a.svg".to_string(),
+                original: 1000,
+                svag: Ok((400, ms(2))),
+                other: Ok((500, ms(100))),
+            },
+            Comparison {
+                path: "b.svg".to_string(),
+                original: 50,
+                svag: Ok((40, ms(1))),
+                other: Err("`svgo` failed".to_string()),
+            },
+        ];
+        assert!(row(&comparisons[0]).contains(" -20.0% "));
+        assert!(row(&comparisons[1]).contains(" failed "));
+        assert_eq!(
+            totals(&comparisons),
+            vec![
+                "1 files: 1000 -> svag 400 bytes, other 500 bytes (-20.0% for svag)",
+                "time: svag 2.0ms, other 100.0ms",
+                "failed: svag 0, other 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Leveled diagnostics on stderr, with an optional progress line.
+//!
+//! Everything the CLI says to a human goes through the macros here, so
+//! `-q`/`-v` apply uniformly and messages never garble the progress line.
+
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Verbosity levels, from least to most chatty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Whether a progress line is currently drawn (and must be cleared before
+/// printing anything else).
+static PROGRESS_DRAWN: Mutex<bool> = Mutex::new(false);
+
+/// Pick the level from `-q` and the number of `-v` flags.
+pub fn init(quiet: bool, verbose: u8) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::Error,
+        (false, 0) => Level::Info,
+        (false, 1) => Level::Debug,
+        (false, _) => Level::Trace,
+    };
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+pub fn log(level: Level, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let prefix = match level {
+        Level::Error => "error: ",
+        Level::Warn => "warning: ",
+        _ => "",
+    };
+
+    let mut drawn = PROGRESS_DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stderr = io::stderr().lock();
+    if *drawn {
+        let _ = write!(stderr, "\r\x1b[2K");
+        *drawn = false;
+    }
+    let _ = writeln!(stderr, "{}{}", prefix, args);
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::cli::log::log($crate::cli::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::cli::log::log($crate::cli::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::cli::log::log($crate::cli::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::cli::log::log($crate::cli::log::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        $crate::cli::log::log($crate::cli::log::Level::Trace, format_args!($($arg)*))
+    };
+}
+
+pub(crate) use {debug, error, info, trace, warning};
+
+/// A `[done/total] current-file` line, redrawn in place on a terminal.
+pub struct Progress {
+    total: usize,
+    done: AtomicUsize,
+    enabled: bool,
+}
+
+impl Progress {
+    /// Only draws when requested, stderr is a terminal and not in `-q` mode.
+    pub fn new(total: usize, requested: bool) -> Self {
+        Self {
+            total,
+            done: AtomicUsize::new(0),
+            enabled: requested && enabled(Level::Info) && io::stderr().is_terminal(),
+        }
+    }
+
+    /// Record a finished file and redraw the line.
+    pub fn tick(&self, current: &str) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.enabled {
+            return;
+        }
+
+        let mut drawn = PROGRESS_DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[2K[{}/{}] {}", done, self.total, current);
+        let _ = stderr.flush();
+        *drawn = true;
+    }
+
+    /// Erase the progress line.
+    pub fn finish(&self) {
+        let mut drawn = PROGRESS_DRAWN.lock().unwrap_or_else(|e| e.into_inner());
+        if *drawn {
+            let _ = write!(io::stderr().lock(), "\r\x1b[2K");
+            *drawn = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels() {
+        init(true, 0);
+        assert!(enabled(Level::Error));
+        assert!(!enabled(Level::Warn));
+
+        init(false, 1);
+        assert!(enabled(Level::Debug));
+        assert!(!enabled(Level::Trace));
+
+        init(false, 0);
+        assert!(enabled(Level::Info));
+        assert!(!enabled(Level::Debug));
+    }
+}
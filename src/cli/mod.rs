@@ -0,0 +1,8 @@
+//! Command-line helpers that don't belong in the library.
+
+pub mod analyze;
+pub mod compare;
+pub mod log;
+pub mod read;
+pub mod report;
+pub mod write;
@@ -0,0 +1,95 @@
+//! Reading inputs, which may be gzip-compressed (`.svgz`, or gzip bytes on
+//! stdin).
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Largest decompressed size accepted from gzipped input, so a small gzip
+/// bomb can't exhaust memory.
+const MAX_DECOMPRESSED: u64 = 256 * 1024 * 1024;
+
+/// A file or stdin, decompressed if it was gzipped.
+pub struct Input {
+    pub text: String,
+    /// Whether the bytes read were gzip-compressed
+    pub gzipped: bool,
+    /// Size of the bytes read, before decompression
+    pub len: usize,
+}
+
+impl Input {
+    /// Read `path`, or stdin for `-`.
+    pub fn read(path: &Path) -> io::Result<Input> {
+        let mut bytes = Vec::new();
+        if path.as_os_str() == "-" {
+            io::stdin().read_to_end(&mut bytes)?;
+        } else {
+            bytes = fs::read(path)?;
+        }
+        Input::decode(bytes, MAX_DECOMPRESSED)
+    }
+
+    /// Decode `bytes`, gunzipping them if needed, up to `limit` bytes
+    /// decompressed.
+    fn decode(bytes: Vec<u8>, limit: u64) -> io::Result<Input> {
+        let len = bytes.len();
+        let gzipped = bytes.starts_with(GZIP_MAGIC);
+        let bytes = if gzipped {
+            let mut decompressed = Vec::new();
+            MultiGzDecoder::new(bytes.as_slice())
+                .take(limit + 1)
+                .read_to_end(&mut decompressed)?;
+            if decompressed.len() as u64 > limit {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("decompressed input is larger than {} bytes", limit),
+                ));
+            }
+            decompressed
+        } else {
+            bytes
+        };
+        let text =
+            String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Input { text, gzipped, len })
+    }
+}
+
+/// Gzip `text` for writing back to an `.svgz` file.
+pub fn gzip(text: &str) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(text.as_bytes())?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let input = Input::decode(b"<svg/>".to_vec(), MAX_DECOMPRESSED).unwrap();
+        assert_eq!(input.text, "<svg/>");
+        assert!(!input.gzipped);
+
+        let compressed = gzip("<svg/>").unwrap();
+        let input = Input::decode(compressed.clone(), MAX_DECOMPRESSED).unwrap();
+        assert_eq!(input.text, "<svg/>");
+        assert!(input.gzipped);
+        assert_eq!(input.len, compressed.len());
+
+        assert!(Input::decode(GZIP_MAGIC.to_vec(), MAX_DECOMPRESSED).is_err());
+
+        // Exactly at the limit is fine, one byte over is not
+        assert!(Input::decode(compressed.clone(), 6).is_ok());
+        let err = Input::decode(compressed, 5).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
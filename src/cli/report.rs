@@ -0,0 +1,343 @@
+//! Machine-readable per-file reports (`--report json|ndjson|csv`) and the
+//! directory summary printed with `--stats`.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use svag::PassStats;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// A single JSON object with a `files` array
+    Json,
+    /// One JSON object per line, per file
+    Ndjson,
+    /// A header row, then one row per file
+    Csv,
+}
+
+/// Why a file could not be processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The input could not be parsed or minified
+    Parse,
+    /// The file could not be read or written
+    Io,
+}
+
+impl FailureKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            FailureKind::Parse => "parse",
+            FailureKind::Io => "io",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileError {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl FileError {
+    pub fn new(kind: FailureKind, message: impl ToString) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The outcome of processing one file.
+#[derive(Debug, Default)]
+pub struct FileRecord {
+    pub path: String,
+    pub original: usize,
+    pub minified: usize,
+    pub passes: Vec<PassStats>,
+    pub duration: Duration,
+    pub error: Option<FileError>,
+}
+
+impl FileRecord {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let passes: Vec<String> = self
+            .passes
+            .iter()
+            .map(|p| format!("{}:{}", json_string(&p.name), p.saved))
+            .collect();
+        let (error, error_kind) = match &self.error {
+            Some(e) => (json_string(&e.message), json_string(e.kind.as_str())),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        format!(
+            r#"{{"path":{},"original":{},"minified":{},"saved":{},"passes":{{{}}},"duration_ms":{:.3},"error":{},"error_kind":{}}}"#,
+            json_string(&self.path),
+            self.original,
+            self.minified,
+            self.original as isize - self.minified as isize,
+            passes.join(","),
+            self.duration.as_secs_f64() * 1000.0,
+            error,
+            error_kind
+        )
+    }
+}
+
+impl FileRecord {
+    pub fn to_csv(&self) -> String {
+        let (error, error_kind) = match &self.error {
+            Some(e) => (csv_field(&e.message), e.kind.as_str()),
+            None => (String::new(), ""),
+        };
+        format!(
+            "{},{},{},{},{:.3},{},{}",
+            csv_field(&self.path),
+            self.original,
+            self.minified,
+            self.original as isize - self.minified as isize,
+            self.duration.as_secs_f64() * 1000.0,
+            error_kind,
+            error
+        )
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote, or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Write all records in the given format.
+pub fn write_report(
+    out: &mut dyn Write,
+    format: ReportFormat,
+    records: &[FileRecord],
+) -> io::Result<()> {
+    match format {
+        ReportFormat::Json => {
+            let files: Vec<String> = records.iter().map(FileRecord::to_json).collect();
+            let original: usize = records.iter().map(|r| r.original).sum();
+            let minified: usize = records.iter().map(|r| r.minified).sum();
+            let failed = records.iter().filter(|r| r.error.is_some()).count();
+            writeln!(
+                out,
+                r#"{{"files":[{}],"original":{},"minified":{},"failed":{}}}"#,
+                files.join(","),
+                original,
+                minified,
+                failed
+            )
+        }
+        ReportFormat::Ndjson => {
+            for record in records {
+                writeln!(out, "{}", record.to_json())?;
+            }
+            Ok(())
+        }
+        ReportFormat::Csv => {
+            writeln!(
+                out,
+                "path,original,minified,saved,duration_ms,error_kind,error"
+            )?;
+            for record in records {
+                writeln!(out, "{}", record.to_csv())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Lines summarizing a directory run: totals, the biggest savings, files
+/// that didn't shrink, and failures with their reasons. Lists are cut off
+/// after `limit` entries.
+pub fn summary(records: &[FileRecord], limit: usize) -> Vec<String> {
+    let (failed, ok): (Vec<&FileRecord>, Vec<&FileRecord>) =
+        records.iter().partition(|r| r.error.is_some());
+    let original: usize = ok.iter().map(|r| r.original).sum();
+    let minified: usize = ok.iter().map(|r| r.minified).sum();
+    let percent = if original > 0 {
+        original.saturating_sub(minified) as f64 / original as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    let mut lines = vec![
+        format!("Processed {} files, {} failed", ok.len(), failed.len()),
+        format!("{original} -> {minified} bytes ({percent:.1}% smaller)"),
+    ];
+    let mut list = |heading: String, items: Vec<String>| {
+        if items.is_empty() {
+            return;
+        }
+        lines.push(heading);
+        let more = items.len().saturating_sub(limit);
+        lines.extend(
+            items
+                .into_iter()
+                .take(limit)
+                .map(|item| format!("  {item}")),
+        );
+        if more > 0 {
+            lines.push(format!("  ... and {more} more"));
+        }
+    };
+
+    // Sorted stably, so ties stay in path order
+    let mut wins: Vec<&&FileRecord> = ok.iter().filter(|r| r.minified < r.original).collect();
+    wins.sort_by_key(|r| std::cmp::Reverse(r.original - r.minified));
+    list(
+        "Biggest savings:".to_string(),
+        wins.iter()
+            .map(|r| {
+                format!(
+                    "{}: {} -> {} bytes (-{})",
+                    r.path,
+                    r.original,
+                    r.minified,
+                    r.original - r.minified
+                )
+            })
+            .collect(),
+    );
+
+    let unchanged: Vec<String> = ok
+        .iter()
+        .filter(|r| r.minified >= r.original)
+        .map(|r| format!("{} ({} bytes)", r.path, r.original))
+        .collect();
+    list(format!("Not smaller ({}):", unchanged.len()), unchanged);
+
+    list(
+        format!("Failed ({}):", failed.len()),
+        failed
+            .iter()
+            .filter_map(|r| Some(format!("{}: {}", r.path, r.error.as_ref()?.message)))
+            .collect(),
+    );
+    lines
+}
+
+/// Quote and escape a string as a JSON string literal.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_json() {
+        let record = FileRecord {
+            path: "icons/\"a\".svg".to_string(),
+            original: 100,
+            minified: 60,
+            passes: vec![PassStats {
+                name: "minify_paths".into(),
+                saved: 40,
+            }],
+            duration: Duration::from_micros(1500),
+            error: None,
+        };
+        assert_eq!(
+            record.to_json(),
+            r#"{"path":"icons/\"a\".svg","original":100,"minified":60,"saved":40,"passes":{"minify_paths":40},"duration_ms":1.500,"error":null,"error_kind":null}"#
+        );
+    }
+
+    #[test]
+    fn test_write_report() {
+        let mut failed = FileRecord::new("bad.svg");
+        failed.error = Some(FileError::new(
+            FailureKind::Parse,
+            "Invalid SVG: no root element",
+        ));
+        let records = [FileRecord::new("a.svg"), failed];
+
+        let mut out = Vec::new();
+        write_report(&mut out, ReportFormat::Ndjson, &records).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        assert!(
+            out.ends_with("\"error\":\"Invalid SVG: no root element\",\"error_kind\":\"parse\"}\n")
+        );
+
+        let mut out = Vec::new();
+        write_report(&mut out, ReportFormat::Json, &records).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with(r#"{"files":[{"path":"a.svg""#));
+        assert!(out.ends_with(",\"original\":0,\"minified\":0,\"failed\":1}\n"));
+
+        let mut out = Vec::new();
+        write_report(&mut out, ReportFormat::Csv, &records).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "path,original,minified,saved,duration_ms,error_kind,error\n\
+             a.svg,0,0,0,0.000,,\n\
+             bad.svg,0,0,0,0.000,parse,Invalid SVG: no root element\n"
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_summary() {
+        let record = |path: &str, original, minified| FileRecord {
+            original,
+            minified,
+            ..FileRecord::new(path)
+        };
+        let mut failed = FileRecord::new("bad.svg");
+        failed.error = Some(FileError::new(FailureKind::Io, "permission denied"));
+        let records = [
+            record("a.svg", 100, 90),
+            record("b.svg", 300, 100),
+            record("c.svg", 50, 50),
+            record("d.svg", 200, 150),
+            failed,
+        ];
+
+        assert_eq!(
+            summary(&records, 2),
+            [
+                "Processed 4 files, 1 failed",
+                "650 -> 390 bytes (40.0% smaller)",
+                "Biggest savings:",
+                "  b.svg: 300 -> 100 bytes (-200)",
+                "  d.svg: 200 -> 150 bytes (-50)",
+                "  ... and 1 more",
+                "Not smaller (1):",
+                "  c.svg (50 bytes)",
+                "Failed (1):",
+                "  bad.svg: permission denied",
+            ]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Safe in-place writes.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Write `contents` to `path` atomically: write a temporary file next to it,
+/// then rename it over the target, so an interrupted run never leaves a
+/// truncated file behind.
+///
+/// If `backup` is given (e.g. `.bak`), the existing file is first copied to
+/// `path` + `backup`.
+pub fn write_atomic(path: &Path, contents: &[u8], backup: Option<&str>) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let existing = fs::metadata(path).ok();
+    if let Some(suffix) = backup
+        && existing.is_some()
+    {
+        fs::copy(path, backup_path(path, suffix))?;
+    }
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(
+        ".{}.svag-{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(metadata) = &existing {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn backup_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("icon.svg");
+        fs::write(&path, "original").unwrap();
+
+        write_atomic(&path, b"minified", Some(".bak")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "minified");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("icon.svg.bak")).unwrap(),
+            "original"
+        );
+
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_write_atomic_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.svg");
+        write_atomic(&path, b"<svg/>", Some(".bak")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<svg/>");
+        assert!(!dir.path().join("new.svg.bak").exists());
+    }
+}
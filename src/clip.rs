@@ -0,0 +1,396 @@
+//! Remove `clip-path` and `mask` applications that don't hide anything.
+//!
+//! Design tools wrap whole artboards in a clip the size of the frame, or in
+//! a mask that is a single white rectangle. When the clip rectangle (or the
+//! mask's region and its opaque content) contains everything the target
+//! paints, the reference is dropped, and so is the definition once nothing
+//! else uses it.
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::bounds::{
+    Inherited, fraction, has_dynamic_content, is_opaque_color, length, local_painted_bounds,
+    paints_nothing, property, shape_bounds,
+};
+use crate::geometry::Rect;
+use crate::index::{DocumentIndex, ElementId, referenced_ids};
+
+/// Drop no-op `clip-path`/`mask` references and their orphaned definitions.
+pub(crate) fn remove_noop_clips(doc: &mut Document) {
+    let has_defs = doc.any_element(|elem| elem.is("clipPath") || elem.is("mask"));
+    if !has_defs || has_dynamic_content(doc) {
+        return;
+    }
+
+    let index = DocumentIndex::new(doc);
+    let mut noops: Vec<(Vec<usize>, &'static str)> = Vec::new();
+    let mut defs: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for (id, elem) in index.iter() {
+        for prop in ["clip-path", "mask"] {
+            let Some(def_id) = property(elem, prop).and_then(url_fragment) else {
+                continue;
+            };
+            if is_noop(&index, id, prop, def_id) == Some(true)
+                && let Some(def) = index.by_id(def_id)
+            {
+                noops.push((index.path(id), prop));
+                defs.entry(def_id).or_insert_with(|| index.path(def));
+            }
+        }
+    }
+    // Dropping references leaves the tree as it is, so the paths hold
+    let defs: Vec<(String, Vec<usize>)> = defs
+        .into_iter()
+        .map(|(id, path)| (id.to_string(), path))
+        .collect();
+
+    for (path, prop) in &noops {
+        if let Some(elem) = doc.element_at_mut(path) {
+            remove_property(elem, prop);
+        }
+    }
+
+    let referenced = referenced_ids(doc);
+    let orphans: Vec<Vec<usize>> = defs
+        .into_iter()
+        .filter(|(id, _)| !referenced.contains(id))
+        .map(|(_, path)| path)
+        .collect();
+    doc.remove_elements_at(orphans);
+}
+
+/// Whether applying definition `def_id` through `prop` to `target` leaves
+/// its rendering unchanged. `None` means unknown.
+fn is_noop(index: &DocumentIndex, target: ElementId, prop: &str, def_id: &str) -> Option<bool> {
+    let def = index.by_id(def_id)?;
+    let elem = index.get(target);
+    let painted = local_painted_bounds(elem, &inherited_at(index, target))?;
+    let units = Units {
+        bbox: shape_bounds(elem),
+        viewport: viewport(index, target),
+    };
+
+    match (prop, index.get(def).name.local.as_str()) {
+        ("clip-path", "clipPath") => clip_is_noop(index.get(def), &painted, &units),
+        ("mask", "mask") => {
+            mask_is_noop(index.get(def), &inherited_at(index, def), &painted, &units)
+        }
+        _ => None,
+    }
+}
+
+fn clip_is_noop(clip: &Element, painted: &Rect, units: &Units) -> Option<bool> {
+    if has_effect(clip, &["transform", "clip-path"]) {
+        return None;
+    }
+    let rect = only_rect(clip)?;
+    if has_effect(rect, &["transform", "clip-path"])
+        || property(rect, "visibility").is_some_and(|v| v != "visible")
+        || is_rounded(rect)
+    {
+        return None;
+    }
+
+    let obb = clip.get_attr("clipPathUnits") == Some("objectBoundingBox");
+    let area = units.rect(rect, obb, [None; 4])?;
+    Some(area.contains(painted))
+}
+
+fn mask_is_noop(
+    mask: &Element,
+    inherited: &Inherited,
+    painted: &Rect,
+    units: &Units,
+) -> Option<bool> {
+    if has_effect(mask, &["clip-path", "mask"]) {
+        return None;
+    }
+
+    // The mask region clips on its own, even where the content is white
+    let obb = mask.get_attr("maskUnits") != Some("userSpaceOnUse");
+    let region = units.rect(
+        mask,
+        obb,
+        [Some("-10%"), Some("-10%"), Some("120%"), Some("120%")],
+    )?;
+    if !region.contains(painted) {
+        return Some(false);
+    }
+
+    let rect = only_rect(mask)?;
+    if has_effect(
+        rect,
+        &["transform", "clip-path", "mask", "filter", "opacity"],
+    ) || is_rounded(rect)
+    {
+        return None;
+    }
+    let paint = inherited.child(rect);
+    let luminance = property(mask, "mask-type").is_none_or(|t| t == "luminance");
+    let full_coverage = paint.stroke.trim() == "none"
+        && fraction(&paint.fill_opacity)? >= 1.0
+        && if luminance {
+            is_white(&paint.fill)
+        } else {
+            is_opaque_color(&paint.fill)
+        };
+    if !full_coverage {
+        return None;
+    }
+
+    let obb = mask.get_attr("maskContentUnits") == Some("objectBoundingBox");
+    let area = units.rect(rect, obb, [None; 4])?;
+    Some(area.contains(painted))
+}
+
+/// What lengths in a definition resolve against.
+struct Units {
+    /// Geometry bounds of the target, for `objectBoundingBox`
+    bbox: Option<Rect>,
+    /// The nearest viewport, for percentages in user space
+    viewport: Option<Rect>,
+}
+
+impl Units {
+    /// Resolve an element's `x`/`y`/`width`/`height` into user space, using
+    /// `defaults` for missing attributes (`None` meaning 0, or required for
+    /// sizes).
+    fn rect(&self, elem: &Element, obb: bool, defaults: [Option<&str>; 4]) -> Option<Rect> {
+        let mut values = [0.0; 4];
+        for (i, name) in ["x", "y", "width", "height"].into_iter().enumerate() {
+            let value = match property(elem, name).or(defaults[i]) {
+                Some(value) => value,
+                None if i < 2 => "0",
+                None => return None,
+            };
+            let horizontal = i % 2 == 0;
+            values[i] = if obb {
+                fraction(value)?
+            } else if let Some(percent) = value.trim().strip_suffix('%') {
+                let viewport = self.viewport?;
+                let size = if horizontal {
+                    viewport.width()
+                } else {
+                    viewport.height()
+                };
+                let offset = match i {
+                    0 => viewport.min_x,
+                    1 => viewport.min_y,
+                    _ => 0.0,
+                };
+                offset + percent.trim().parse::<f64>().ok()? / 100.0 * size
+            } else {
+                length(value)?
+            };
+        }
+
+        let [x, y, width, height] = values;
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        if !obb {
+            return Some(Rect {
+                min_x: x,
+                min_y: y,
+                max_x: x + width,
+                max_y: y + height,
+            });
+        }
+
+        let bbox = self.bbox?;
+        if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+            return None;
+        }
+        Some(Rect {
+            min_x: bbox.min_x + x * bbox.width(),
+            min_y: bbox.min_y + y * bbox.height(),
+            max_x: bbox.min_x + (x + width) * bbox.width(),
+            max_y: bbox.min_y + (y + height) * bbox.height(),
+        })
+    }
+}
+
+/// The single rendering child of a definition, if it's a `<rect>`.
+fn only_rect<'a>(def: &'a Element<'a>) -> Option<&'a Element<'a>> {
+    let mut children = def.child_elements().filter(|e| !paints_nothing(e));
+    let rect = children.next().filter(|e| e.is("rect"))?;
+    children.next().is_none().then_some(rect)
+}
+
+fn has_effect(elem: &Element, props: &[&str]) -> bool {
+    props.iter().any(|p| match property(elem, p) {
+        Some("none") | None => false,
+        Some(value) => !(*p == "opacity" && fraction(value).is_some_and(|o| o >= 1.0)),
+    })
+}
+
+fn is_rounded(rect: &Element) -> bool {
+    ["rx", "ry"]
+        .iter()
+        .any(|r| property(rect, r).is_some_and(|v| v != "auto" && length(v) != Some(0.0)))
+}
+
+fn is_white(color: &str) -> bool {
+    let color: String = color
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    matches!(
+        color.as_str(),
+        "white" | "#fff" | "#ffffff" | "rgb(255,255,255)" | "rgb(100%,100%,100%)"
+    )
+}
+
+/// Inherited properties at `id`, folded down from the root.
+pub(crate) fn inherited_at(index: &DocumentIndex, id: ElementId) -> Inherited {
+    let mut chain: Vec<ElementId> = index.ancestors(id).collect();
+    chain.reverse();
+    chain.push(id);
+    chain
+        .into_iter()
+        .fold(Inherited::default(), |inherited, id| {
+            inherited.child(index.get(id))
+        })
+}
+
+/// The viewport established by the nearest `<svg>` ancestor of `id`.
+fn viewport(index: &DocumentIndex, id: ElementId) -> Option<Rect> {
+    let svg = index.get(index.ancestors(id).find(|&a| index.get(a).is("svg"))?);
+    if let Some(view_box) = svg.get_attr("viewBox") {
+        let numbers: Vec<f64> = view_box
+            .split(|c: char| c.is_ascii_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(length)
+            .collect::<Option<_>>()?;
+        let [x, y, width, height] = numbers[..] else {
+            return None;
+        };
+        return Some(Rect {
+            min_x: x,
+            min_y: y,
+            max_x: x + width,
+            max_y: y + height,
+        });
+    }
+    let width = length(svg.get_attr("width")?)?;
+    let height = length(svg.get_attr("height")?)?;
+    Some(Rect {
+        min_x: 0.0,
+        min_y: 0.0,
+        max_x: width,
+        max_y: height,
+    })
+}
+
+/// The id in a plain `url(#id)` reference.
+pub(crate) fn url_fragment(value: &str) -> Option<&str> {
+    let inner = value.trim().strip_prefix("url(")?.strip_suffix(')')?;
+    inner.trim().trim_matches(['"', '\'']).strip_prefix('#')
+}
+
+/// Remove a property from both the `style` attribute and the presentation
+/// attribute.
+pub(crate) fn remove_property(elem: &mut Element, name: &str) {
+    elem.remove_attr(name);
+    let Some(style) = elem.get_attr("style") else {
+        return;
+    };
+    let rest: Vec<&str> = style
+        .split(';')
+        .filter(|decl| {
+            let prop = decl.split_once(':').map_or(*decl, |(prop, _)| prop);
+            !decl.trim().is_empty() && prop.trim() != name
+        })
+        .collect();
+    if rest.is_empty() {
+        elem.remove_attr("style");
+    } else {
+        let style = rest.join(";");
+        elem.set_attr("style", style);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn run(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        remove_noop_clips(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_removes_frame_clip() {
+        let out = run(
+            r##"<svg viewBox="0 0 24 24"><g clip-path="url(#clip0)"><path d="M2 2h20v20H2z" fill="#000"/></g><defs><clipPath id="clip0"><rect width="24" height="24" fill="white"/></clipPath></defs></svg>"##,
+        );
+        assert_eq!(
+            out,
+            r##"<svg viewBox="0 0 24 24"><g><path d="M2 2h20v20H2z" fill="#000"/></g><defs/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_removes_percentage_and_bbox_clips() {
+        // Percentages resolve against the viewBox
+        let out = run(
+            r##"<svg viewBox="0 0 10 10"><clipPath id="c"><rect width="100%" height="100%"/></clipPath><rect width="10" height="10" style="fill:red;clip-path:url(#c)"/></svg>"##,
+        );
+        assert_eq!(
+            out,
+            r#"<svg viewBox="0 0 10 10"><rect height="10" style="fill:red" width="10"/></svg>"#
+        );
+
+        // objectBoundingBox covering the unit square
+        let out = run(
+            r##"<svg><clipPath id="c" clipPathUnits="objectBoundingBox"><rect width="1" height="1"/></clipPath><circle r="5" clip-path="url(#c)"/></svg>"##,
+        );
+        assert!(!out.contains("clip"));
+    }
+
+    #[test]
+    fn test_removes_white_mask() {
+        let out = run(
+            r##"<svg><mask id="m" maskUnits="userSpaceOnUse" x="0" y="0" width="20" height="20"><rect width="20" height="20" fill="#FFF"/></mask><g mask="url(#m)"><rect x="5" y="5" width="5" height="5"/></g></svg>"##,
+        );
+        assert_eq!(
+            out,
+            r#"<svg><g><rect height="5" width="5" x="5" y="5"/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keeps_effective_clips() {
+        let cases = [
+            // Content pokes out of the clip (stroke included)
+            r##"<svg><clipPath id="c"><rect width="10" height="10"/></clipPath><rect width="10" height="10" stroke="red" clip-path="url(#c)"/></svg>"##,
+            // Rounded, transformed, or non-rect clips
+            r##"<svg><clipPath id="c"><rect width="10" height="10" rx="2"/></clipPath><rect width="5" height="5" clip-path="url(#c)"/></svg>"##,
+            r##"<svg><clipPath id="c" transform="scale(.1)"><rect width="10" height="10"/></clipPath><rect width="5" height="5" clip-path="url(#c)"/></svg>"##,
+            r##"<svg><clipPath id="c"><circle r="100"/></clipPath><rect width="5" height="5" clip-path="url(#c)"/></svg>"##,
+            // Grey or translucent masks, and the default mask region
+            r##"<svg><mask id="m" maskUnits="userSpaceOnUse" width="9" height="9"><rect width="9" height="9" fill="#eee"/></mask><rect width="5" height="5" mask="url(#m)"/></svg>"##,
+            r##"<svg><mask id="m"><rect width="9" height="9" fill="#fff"/></mask><rect width="5" height="5" stroke="red" stroke-width="4" mask="url(#m)"/></svg>"##,
+            // Unknown target bounds
+            r##"<svg><clipPath id="c"><rect width="10" height="10"/></clipPath><text clip-path="url(#c)">hi</text></svg>"##,
+        ];
+        for svg in cases {
+            let out = run(svg);
+            assert!(out.contains("url(#"), "{svg} -> {out}");
+        }
+    }
+
+    #[test]
+    fn test_keeps_shared_definition() {
+        let out = run(
+            r##"<svg><clipPath id="c"><rect width="10" height="10"/></clipPath><rect width="5" height="5" clip-path="url(#c)"/><rect width="50" height="50" clip-path="url(#c)"/></svg>"##,
+        );
+        assert_eq!(out.matches("url(#c)").count(), 1);
+        assert!(out.contains("<clipPath"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Rust code generation: embed minified icons as Rust constants.
+//!
+//! Web frameworks like Leptos, Dioxus and Yew can `include!` the generated
+//! module from a build script and render icons without touching the
+//! filesystem at runtime.
+
+use crate::serialize::{escape_attr, serialize_children};
+use crate::{Document, Options, serialize};
+
+/// The shape of the generated constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RustStyle {
+    /// `pub const NAME: &str = "<svg ...>";`
+    #[default]
+    Str,
+    /// `pub const NAME: Icon = Icon { view_box, attrs, body };`, plus the
+    /// `Icon` struct definition
+    Struct,
+}
+
+/// Generate a Rust module with one constant per icon.
+///
+/// Icons are optimized documents paired with a name (usually the file stem);
+/// names are converted to `SCREAMING_SNAKE_CASE` and deduplicated.
+pub fn generate_rust(icons: &[(&str, &Document)], options: &Options, style: RustStyle) -> String {
+    let options = &options.without_framing();
+    let mut out = String::from("// @generated by svag. Do not edit.\n");
+
+    if style == RustStyle::Struct {
+        out.push_str(
+            "\n#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Icon {
+    /// The root `viewBox`, if any
+    pub view_box: Option<&'static str>,
+    /// Other root attributes (excluding `xmlns`, `width` and `height`)
+    pub attrs: &'static [(&'static str, &'static str)],
+    /// Inner markup of the root `<svg>`
+    pub body: &'static str,
+}
+",
+        );
+    }
+
+    let mut used = Vec::new();
+    for (name, doc) in icons {
+        let base = const_name(name);
+        let mut ident = base.clone();
+        let mut n = 2;
+        while used.contains(&ident) {
+            ident = format!("{}_{}", base, n);
+            n += 1;
+        }
+
+        out.push('\n');
+        match style {
+            RustStyle::Str => {
+                out.push_str(&format!(
+                    "pub const {}: &str = {};\n",
+                    ident,
+                    raw_string(&serialize(doc, options))
+                ));
+            }
+            RustStyle::Struct => {
+                let root = &doc.root;
+                let view_box = match root.get_attr("viewBox") {
+                    Some(v) => format!("Some({})", raw_string(v)),
+                    None => "None".to_string(),
+                };
+
+                let attrs: Vec<String> = root
+                    .attributes
+                    .iter()
+                    .filter(|a| {
+                        !a.name.is_xmlns()
+                            && a.name.prefix.is_none()
+                            && !matches!(a.name.local.as_str(), "viewBox" | "width" | "height")
+                    })
+                    .map(|a| {
+                        format!(
+                            "({}, {})",
+                            raw_string(&a.name.local),
+                            raw_string(&escape_attr(&a.value))
+                        )
+                    })
+                    .collect();
+
+                out.push_str(&format!(
+                    "pub const {}: Icon = Icon {{\n    view_box: {},\n    attrs: &[{}],\n    body: {},\n}};\n",
+                    ident,
+                    view_box,
+                    attrs.join(", "),
+                    raw_string(&serialize_children(root, options))
+                ));
+            }
+        }
+        used.push(ident);
+    }
+
+    out
+}
+
+/// Convert an icon name like `arrow-left` or `ArrowLeft` to `ARROW_LEFT`.
+fn const_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_uppercase());
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+
+    let out = out.trim_end_matches('_').to_string();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("ICON_{}", out)
+    } else {
+        out
+    }
+}
+
+/// Format a string as a Rust raw string literal with enough `#`s.
+fn raw_string(s: &str) -> String {
+    let mut hashes = 0;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{}\"{}\"{}", hashes, s, hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_const_name() {
+        assert_eq!(const_name("arrow-left"), "ARROW_LEFT");
+        assert_eq!(const_name("ArrowLeft"), "ARROW_LEFT");
+        assert_eq!(const_name("icon 2x"), "ICON_2X");
+        assert_eq!(const_name("404"), "ICON_404");
+    }
+
+    #[test]
+    fn test_raw_string() {
+        assert_eq!(raw_string("abc"), "r\"abc\"");
+        assert_eq!(raw_string(r#"a="b""#), r####"r#"a="b""#"####);
+        assert_eq!(
+            raw_string(r##"fill="#fff""##),
+            r####"r##"fill="#fff""##"####
+        );
+    }
+
+    #[test]
+    fn test_generate_rust() {
+        let a = parse_svg(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24" fill="none" width="24"><path d="M0 0h24"/></svg>"#).unwrap();
+        let b =
+            parse_svg(r#"<svg xmlns="http://www.w3.org/2000/svg"><circle r="1"/></svg>"#).unwrap();
+        let icons = [("arrow-left", &a), ("arrow_left", &b)];
+        let options = Options::default();
+
+        let out = generate_rust(&icons, &options, RustStyle::Str);
+        assert!(out.contains(r##"pub const ARROW_LEFT: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" width="24"><path d="M0 0h24"/></svg>"#;"##));
+        assert!(out.contains("pub const ARROW_LEFT_2: &str"));
+
+        let out = generate_rust(&icons, &options, RustStyle::Struct);
+        assert!(out.contains("pub struct Icon {"));
+        assert!(out.contains(
+            r##"pub const ARROW_LEFT: Icon = Icon {
+    view_box: Some(r"0 0 24 24"),
+    attrs: &[(r"fill", r"none")],
+    body: r#"<path d="M0 0h24"/>"#,
+};"##
+        ));
+        assert!(out.contains("    view_box: None,\n    attrs: &[],\n"));
+    }
+}
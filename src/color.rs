@@ -0,0 +1,498 @@
+//! Parsed colors, for the passes that match and replace colors rather than
+//! just shorten them.
+//!
+//! The same color is spelled many ways (`red`, `#F00`, `#ff0000ff`,
+//! `rgb(255 0 0)`, `rgba(100%, 0%, 0%, 1)`, `hsl(0, 100%, 50%)`), so they're
+//! compared as [`Color`]s, and the values written back are found in
+//! attributes, `style` attributes, and `<style>` rules alike.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::SvgTarget;
+use crate::ast::*;
+use crate::error::SvagError;
+use crate::optimize::minify_color;
+use crate::styles::{map_declarations, map_stylesheet};
+
+/// An sRGB color with 8-bit channels.
+///
+/// ```
+/// let color: svag::Color = "rgb(255 0 0 / 50%)".parse().unwrap();
+/// assert_eq!(color, svag::Color::rgba(255, 0, 0, 128));
+/// assert_eq!(color, "#ff000080".parse().unwrap());
+/// assert_eq!("RED".parse::<svag::Color>().unwrap().to_string(), "#ff0000");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Alpha, 255 for opaque
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Parse a CSS color: a hex color, a named color, or `rgb()`, `rgba()`,
+    /// `hsl()`, or `hsla()` in comma or space syntax. `currentColor`,
+    /// `var()`, and wide-gamut colors aren't a fixed sRGB color, and give
+    /// `None`.
+    pub fn parse(value: &str) -> Option<Color> {
+        let value = value.trim();
+        let lower = value.to_ascii_lowercase();
+        if let Some(hex) = lower.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some(open) = lower.find('(') {
+            let args = lower[open + 1..].strip_suffix(')')?;
+            return match lower[..open].trim_end() {
+                "rgb" | "rgba" => parse_rgb(args),
+                "hsl" | "hsla" => parse_hsl(args),
+                _ => None,
+            };
+        }
+        NAMED_COLORS
+            .binary_search_by_key(&lower.as_str(), |(name, _)| name)
+            .ok()
+            .map(|i| {
+                let [r, g, b] = NAMED_COLORS[i].1;
+                Color::rgb(r, g, b)
+            })
+            .or_else(|| (lower == "transparent").then_some(Color::rgba(0, 0, 0, 0)))
+    }
+
+    pub fn is_opaque(self) -> bool {
+        self.a == 255
+    }
+
+    /// The largest difference between a channel of `self` and of `other`,
+    /// alpha included.
+    pub fn distance(self, other: Color) -> u8 {
+        [
+            self.r.abs_diff(other.r),
+            self.g.abs_diff(other.g),
+            self.b.abs_diff(other.b),
+            self.a.abs_diff(other.a),
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+}
+
+impl FromStr for Color {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::parse(s).ok_or_else(|| SvagError::InvalidColor(s.trim().to_string()))
+    }
+}
+
+/// Written as `#rrggbb` when opaque, and as `rgba()` otherwise, which every
+/// renderer understands; color minification shortens either.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Color { r, g, b, a } = *self;
+        if a == 255 {
+            write!(f, "#{r:02x}{g:02x}{b:02x}")
+        } else {
+            let alpha = crate::path::format_number(f64::from(a) / 255.0, 3);
+            write!(f, "rgba({r},{g},{b},{alpha})")
+        }
+    }
+}
+
+/// The shortest way to write `color` that SVG 1.1 renderers understand
+/// too.
+pub(crate) fn css_color(color: Color) -> String {
+    minify_color(&color.to_string(), SvgTarget::Svg11)
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok().map(|d| d * 0x11);
+    let pair = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    let channel: &dyn Fn(usize) -> Option<u8> = match hex.len() {
+        3 | 4 => &digit,
+        6 | 8 => &pair,
+        _ => return None,
+    };
+    let alpha = match hex.len() {
+        4 | 8 => channel(3)?,
+        _ => 255,
+    };
+    Some(Color::rgba(channel(0)?, channel(1)?, channel(2)?, alpha))
+}
+
+/// The arguments of a color function, in comma syntax (`1, 2, 3, .5`) or
+/// space syntax (`1 2 3 / .5`), with the alpha separately.
+fn color_args(args: &str) -> Option<([&str; 3], Option<&str>)> {
+    let (parts, alpha): (Vec<&str>, Option<&str>) = if args.contains(',') {
+        let mut parts: Vec<&str> = args.split(',').map(str::trim).collect();
+        let alpha = (parts.len() == 4).then(|| parts.pop().unwrap());
+        (parts, alpha)
+    } else {
+        let (channels, alpha) = match args.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (args, None),
+        };
+        (channels.split_whitespace().collect(), alpha)
+    };
+    let [a, b, c] = parts[..] else {
+        return None;
+    };
+    Some(([a, b, c], alpha))
+}
+
+/// A number, or a percentage of `full`.
+fn number_or_percent(value: &str, full: f64) -> Option<f64> {
+    let n = match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().ok()? / 100.0 * full,
+        None => value.parse::<f64>().ok()?,
+    };
+    n.is_finite().then_some(n)
+}
+
+fn alpha_channel(alpha: Option<&str>) -> Option<u8> {
+    alpha.map_or(Some(255), |alpha| {
+        let alpha = number_or_percent(alpha, 1.0)?;
+        Some((alpha.clamp(0.0, 1.0) * 255.0).round() as u8)
+    })
+}
+
+fn to_channel(value: f64) -> u8 {
+    value.clamp(0.0, 255.0).round() as u8
+}
+
+fn parse_rgb(args: &str) -> Option<Color> {
+    let (channels, alpha) = color_args(args)?;
+    let [r, g, b] = channels.map(|c| number_or_percent(c, 255.0).map(to_channel));
+    Some(Color::rgba(r?, g?, b?, alpha_channel(alpha)?))
+}
+
+fn parse_hsl(args: &str) -> Option<Color> {
+    let ([h, s, l], alpha) = color_args(args)?;
+    let hue = h.strip_suffix("deg").unwrap_or(h).parse::<f64>().ok()?;
+    let saturation = number_or_percent(s, 100.0)?.clamp(0.0, 100.0) / 100.0;
+    let lightness = number_or_percent(l, 100.0)?.clamp(0.0, 100.0) / 100.0;
+    if !hue.is_finite() {
+        return None;
+    }
+
+    // https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+    let channel = |n: f64| {
+        let k = (n + hue.rem_euclid(360.0) / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    let [r, g, b] = [0.0, 8.0, 4.0].map(|n| to_channel(channel(n) * 255.0));
+    Some(Color::rgba(r, g, b, alpha_channel(alpha)?))
+}
+
+/// Properties whose value is a color (or paint, which can be one).
+pub(crate) const COLOR_PROPERTIES: &[&str] = &[
+    "fill",
+    "stroke",
+    "stop-color",
+    "flood-color",
+    "lighting-color",
+    "color",
+];
+
+/// Rewrite the colors of `properties` throughout the document: attributes,
+/// `style` attributes, and the rules of `<style>` elements. `f` is given
+/// the property and its color, and returns the value to write instead, or
+/// `None` to leave it.
+pub(crate) fn rewrite_colors(
+    elem: &mut Element,
+    properties: &[&str],
+    f: &mut dyn FnMut(&str, Color) -> Option<String>,
+) {
+    let mut rewrite = |prop: &str, value: &str| {
+        if !properties.contains(&prop) {
+            return None;
+        }
+        f(prop, Color::parse(value)?)
+    };
+
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_none()
+            && let Some(value) = rewrite(&attr.name.local, &attr.value)
+        {
+            attr.value = value.into();
+        }
+    }
+
+    if let Some(style) = elem.get_attr("style") {
+        let style = map_declarations(style, &mut rewrite);
+        elem.set_attr("style", style);
+    }
+
+    if elem.is("style") && elem.get_attr("type").is_none_or(|t| t == "text/css") {
+        for child in &mut elem.children {
+            if let Node::Text(css) | Node::CData(css) = child {
+                *css =
+                    map_stylesheet(css, &mut |block| map_declarations(block, &mut rewrite)).into();
+            }
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        rewrite_colors(child, properties, f);
+    }
+}
+
+/// Call `f` with every value of `properties` in the places
+/// [`rewrite_colors`] looks, without changing anything. `f` is given the
+/// property and its raw value, color or not.
+pub(crate) fn visit_property_values(
+    elem: &Element,
+    properties: &[&str],
+    f: &mut dyn FnMut(&str, &str),
+) {
+    let mut visit = |prop: &str, value: &str| {
+        if properties.contains(&prop) {
+            f(prop, value);
+        }
+        None
+    };
+
+    for attr in &elem.attributes {
+        if attr.name.prefix.is_none() {
+            visit(&attr.name.local, &attr.value);
+        }
+    }
+
+    if let Some(style) = elem.get_attr("style") {
+        map_declarations(style, &mut visit);
+    }
+
+    if elem.is("style") && elem.get_attr("type").is_none_or(|t| t == "text/css") {
+        for child in &elem.children {
+            if let Node::Text(css) | Node::CData(css) = child {
+                map_stylesheet(css, &mut |block| map_declarations(block, &mut visit));
+            }
+        }
+    }
+
+    for child in elem.child_elements() {
+        visit_property_values(child, properties, f);
+    }
+}
+
+/// CSS named colors, sorted by name.
+const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let red = Color::rgb(255, 0, 0);
+        for value in [
+            "red",
+            "Red",
+            "#f00",
+            "#F00F",
+            "#ff0000",
+            "#ff0000ff",
+            "rgb(255,0,0)",
+            "rgb( 255 , 0 , 0 )",
+            "rgb(255 0 0)",
+            "rgba(255, 0, 0, 1)",
+            "rgb(100%, 0%, 0%)",
+            "rgb(255 0 0 / 100%)",
+            "hsl(0, 100%, 50%)",
+            "hsl(360deg 100% 50%)",
+        ] {
+            assert_eq!(Color::parse(value), Some(red), "{value}");
+        }
+        assert_eq!(
+            Color::parse("rgba(0,0,255,.5)"),
+            Some(Color::rgba(0, 0, 255, 128))
+        );
+        assert_eq!(
+            Color::parse("hsl(120 100% 25%)"),
+            Some(Color::rgb(0, 128, 0))
+        );
+        for value in [
+            "none",
+            "currentColor",
+            "url(#a)",
+            "#ff000",
+            "oklch(0.5 0.1 0)",
+        ] {
+            assert_eq!(Color::parse(value), None, "{value}");
+        }
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Color::rgb(255, 0, 0).to_string(), "#ff0000");
+        assert_eq!(
+            Color::rgba(0, 0, 255, 128).to_string(),
+            "rgba(0,0,255,.502)"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Compressed size estimates for [`OptimizeFor`] decisions.
+
+use std::io::Write;
+
+use crate::OptimizeFor;
+
+/// How big `data` ends up under `optimize_for`.
+///
+/// Compression levels are a notch below the maximum: the estimate only
+/// has to rank candidates, and it runs several times per document.
+pub(crate) fn compressed_len(data: &str, optimize_for: OptimizeFor) -> usize {
+    match optimize_for {
+        OptimizeFor::RawSize => data.len(),
+        OptimizeFor::GzipSize => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::new(8));
+            encoder
+                .write_all(data.as_bytes())
+                .and_then(|()| encoder.finish())
+                .map_or(data.len(), |out| out.len())
+        }
+        OptimizeFor::BrotliSize => {
+            let mut out = Vec::new();
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: 9,
+                ..Default::default()
+            };
+            match brotli::BrotliCompress(&mut data.as_bytes(), &mut out, &params) {
+                Ok(_) => out.len(),
+                Err(_) => data.len(),
+            }
+        }
+    }
+}
+
+/// The candidate that's smallest under `optimize_for`, keeping the
+/// earliest on ties.
+pub(crate) fn smallest<T>(
+    candidates: impl IntoIterator<Item = T>,
+    optimize_for: OptimizeFor,
+    text: impl Fn(&T) -> &str,
+) -> Option<T> {
+    candidates
+        .into_iter()
+        .map(|c| (compressed_len(text(&c), optimize_for), c))
+        .reduce(|best, next| if next.0 < best.0 { next } else { best })
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_len() {
+        let repetitive = "M0 0h10v10H0z".repeat(100);
+        assert_eq!(compressed_len(&repetitive, OptimizeFor::RawSize), 1300);
+        assert!(compressed_len(&repetitive, OptimizeFor::GzipSize) < 100);
+        assert!(compressed_len(&repetitive, OptimizeFor::BrotliSize) < 100);
+    }
+}
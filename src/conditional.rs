@@ -0,0 +1,260 @@
+//! Static evaluation of conditional processing attributes.
+//!
+//! `systemLanguage`, `requiredExtensions`, and `requiredFeatures` decide
+//! whether an element renders, and `<switch>` renders only its first child
+//! whose conditions all hold. Conditions that always hold are removed, and
+//! elements whose conditions never hold are dropped, along with the
+//! branches after the one a `<switch>` is known to render. Multilingual
+//! documents (Wikimedia maps, for one) carry a branch per language; once
+//! the reader's languages are known, every branch but theirs can go.
+
+use std::collections::HashSet;
+
+use crate::SvgTarget;
+use crate::ast::*;
+use crate::index::referenced_ids;
+
+/// The conditional processing attributes.
+const CONDITIONAL: &[&str] = &["systemLanguage", "requiredExtensions", "requiredFeatures"];
+
+/// Elements that render nothing when their conditions don't hold. Elsewhere
+/// (gradients, clip paths, …) the attributes mean nothing or vary between
+/// versions.
+const CONDITIONAL_ELEMENTS: &[&str] = &[
+    "a",
+    "g",
+    "switch",
+    "svg",
+    "use",
+    "image",
+    "foreignObject",
+    "text",
+    "tspan",
+    "textPath",
+    "tref",
+    "path",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "animate",
+    "animateColor",
+    "animateMotion",
+    "animateTransform",
+    "set",
+    "audio",
+    "video",
+];
+
+/// Remove conditions that always hold, and elements whose conditions never
+/// do. `languages` are the reader's, if known; otherwise only an empty
+/// `systemLanguage` can be evaluated.
+pub(crate) fn evaluate_conditions(
+    doc: &mut Document,
+    languages: Option<&[String]>,
+    target: SvgTarget,
+) {
+    let has_conditions = doc.any_element(|elem| {
+        elem.is("switch")
+            || elem
+                .attributes
+                .iter()
+                .any(|a| a.name.prefix.is_none() && CONDITIONAL.contains(&a.name.local.as_str()))
+    });
+    if !has_conditions {
+        return;
+    }
+    let referenced = referenced_ids(doc);
+
+    let eval = Evaluator {
+        languages,
+        target,
+        referenced,
+    };
+    eval.visit(&mut doc.root);
+}
+
+struct Evaluator<'a> {
+    languages: Option<&'a [String]>,
+    target: SvgTarget,
+    /// Ids referenced elsewhere: their elements can render outside of where
+    /// they stand (through `<use>`), so they're kept
+    referenced: HashSet<String>,
+}
+
+impl Evaluator<'_> {
+    fn visit(&self, elem: &mut Element) {
+        let is_switch = elem.is("switch");
+        // Whether the child a switch renders has been found
+        let mut chosen = false;
+        for node in elem.take_children() {
+            let Node::Element(mut child) = node else {
+                elem.append_child(node);
+                continue;
+            };
+            let applies = CONDITIONAL_ELEMENTS.contains(&child.name.local.as_str())
+                && child.name.prefix.is_none();
+            if !applies {
+                elem.append_child(child);
+                continue;
+            }
+            if chosen || self.condition(&child) == Some(false) {
+                let referenced = child
+                    .get_attr("id")
+                    .is_some_and(|id| self.referenced.contains(id));
+                if referenced {
+                    elem.append_child(child);
+                }
+                continue;
+            }
+
+            child.attributes.retain(|a| {
+                a.name.prefix.is_some()
+                    || !CONDITIONAL.contains(&a.name.local.as_str())
+                    || self.holds(&a.name.local, &a.value) != Some(true)
+            });
+            chosen = is_switch && self.condition(&child) == Some(true);
+            elem.append_child(child);
+        }
+
+        // A switch with only the child it renders is a group
+        if chosen && elem.child_elements().count() == 1 {
+            elem.name = QName::new("g");
+        }
+
+        for child in elem.child_elements_mut() {
+            self.visit(child);
+        }
+    }
+
+    /// Whether all of `elem`'s conditions hold. `None` means it depends on
+    /// the renderer or reader.
+    fn condition(&self, elem: &Element) -> Option<bool> {
+        let mut known = true;
+        for attr in &elem.attributes {
+            if attr.name.prefix.is_some() {
+                continue;
+            }
+            match self.holds(&attr.name.local, &attr.value) {
+                Some(false) => return Some(false),
+                None => known = false,
+                Some(true) => {}
+            }
+        }
+        known.then_some(true)
+    }
+
+    /// Whether attribute `name` holds with `value`; other attributes always
+    /// do.
+    fn holds(&self, name: &str, value: &str) -> Option<bool> {
+        // Empty lists never hold
+        let empty = value.trim().is_empty();
+        match name {
+            "systemLanguage" if empty => Some(false),
+            "systemLanguage" => self
+                .languages
+                .map(|languages| matches_language(value, languages)),
+            // Which extensions are supported depends on the renderer
+            "requiredExtensions" if empty => Some(false),
+            "requiredExtensions" => None,
+            // SVG 2 renderers ignore it
+            "requiredFeatures" if self.target.is_svg2() => Some(true),
+            "requiredFeatures" if empty => Some(false),
+            "requiredFeatures" => None,
+            _ => Some(true),
+        }
+    }
+}
+
+/// Whether a `systemLanguage` list names one of `languages`. Like browsers,
+/// a tag matches a more specific one in either direction: `en` matches
+/// `en-US` and the reverse.
+fn matches_language(list: &str, languages: &[String]) -> bool {
+    let is_prefix = |prefix: &str, tag: &str| {
+        tag.get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+            && matches!(tag.as_bytes().get(prefix.len()), None | Some(b'-'))
+    };
+    list.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .any(|tag| {
+            languages
+                .iter()
+                .any(|lang| is_prefix(lang, tag) || is_prefix(tag, lang))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn evaluated(svg: &str, languages: Option<&[&str]>, target: SvgTarget) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        let languages: Option<Vec<String>> =
+            languages.map(|l| l.iter().map(|l| l.to_string()).collect());
+        evaluate_conditions(&mut doc, languages.as_deref(), target);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_evaluate_switches() {
+        let evaluated = |svg, languages| evaluated(svg, Some(languages), SvgTarget::Browsers);
+        let svg = r#"<svg><switch fill="red"><text systemLanguage="de,de-CH">Karte</text><text systemLanguage="en-US">Map</text><text>Carte</text></switch></svg>"#;
+        assert_eq!(
+            evaluated(svg, &["de"]),
+            r#"<svg><g fill="red"><text>Karte</text></g></svg>"#
+        );
+        assert_eq!(
+            evaluated(svg, &["EN"]),
+            r#"<svg><g fill="red"><text>Map</text></g></svg>"#
+        );
+        assert_eq!(
+            evaluated(svg, &["fr-CA"]),
+            r#"<svg><g fill="red"><text>Carte</text></g></svg>"#
+        );
+
+        // Renderer-dependent conditions are kept, with what may render
+        // after them
+        let svg = r#"<svg><switch><title>Map</title><g systemLanguage="fr"/><foreignObject requiredExtensions="http://www.w3.org/1999/xhtml"/><text systemLanguage="en">Map</text><text>Karte</text></switch></svg>"#;
+        assert_eq!(
+            evaluated(svg, &["en"]),
+            r#"<svg><switch><title>Map</title><foreignObject requiredExtensions="http://www.w3.org/1999/xhtml"/><text>Map</text></switch></svg>"#
+        );
+
+        // The title stays with the chosen branch
+        let svg = r#"<svg><switch><title>Map</title><text systemLanguage="en">Map</text><text>Karte</text></switch></svg>"#;
+        assert_eq!(
+            evaluated(svg, &["en"]),
+            r#"<svg><switch><title>Map</title><text>Map</text></switch></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_evaluate_conditions() {
+        // Without languages, only what holds (or fails) everywhere goes;
+        // referenced elements stay, since a <use> may render them
+        let svg = r##"<svg><rect systemLanguage=""/><rect id="a" requiredExtensions=""/><use href="#a"/><circle systemLanguage="en"/><linearGradient systemLanguage=""/><switch><path requiredFeatures="http://www.w3.org/TR/SVG11/feature#Shape"/><circle/></switch></svg>"##;
+        assert_eq!(
+            evaluated(svg, None, SvgTarget::Browsers),
+            r##"<svg><rect id="a" requiredExtensions=""/><use href="#a"/><circle systemLanguage="en"/><linearGradient systemLanguage=""/><g><path/></g></svg>"##
+        );
+        assert_eq!(
+            evaluated(svg, None, SvgTarget::Svg11),
+            r##"<svg><rect id="a" requiredExtensions=""/><use href="#a"/><circle systemLanguage="en"/><linearGradient systemLanguage=""/><switch><path requiredFeatures="http://www.w3.org/TR/SVG11/feature#Shape"/><circle/></switch></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_matches_language() {
+        let languages = ["en-GB".to_string(), "pt".to_string()];
+        assert!(matches_language("fr, en", &languages));
+        assert!(matches_language("pt-BR", &languages));
+        assert!(!matches_language("eng", &languages));
+        assert!(!matches_language("en-US", &languages));
+        assert!(!matches_language("", &languages));
+    }
+}
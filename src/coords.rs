@@ -0,0 +1,302 @@
+//! Moving and scaling content by rewriting its coordinates.
+//!
+//! Cropping and resizing a document can be done by wrapping it in a
+//! transform or adjusting the `viewBox`, but icons often need coordinates
+//! in a particular space. Shapes, paths, and stroke lengths are rewritten
+//! in place, for documents simple enough that nothing else depends on
+//! where they are.
+
+use crate::ast::*;
+use crate::bounds::{length, paints_nothing};
+use crate::path::{Command, format_number, parse_path, serialize_path};
+
+/// An axis-aligned map of user space: `x' = sx·x + dx`, `y' = sy·y + dy`,
+/// with positive scales.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AxisMap {
+    pub sx: f64,
+    pub sy: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl AxisMap {
+    pub fn translate(dx: f64, dy: f64) -> Self {
+        AxisMap {
+            sx: 1.0,
+            sy: 1.0,
+            dx,
+            dy,
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        AxisMap {
+            sx,
+            sy,
+            dx: 0.0,
+            dy: 0.0,
+        }
+    }
+
+    fn x(&self, x: f64) -> f64 {
+        self.sx * x + self.dx
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        self.sy * y + self.dy
+    }
+
+    /// The scale of lengths that don't have a direction, like stroke
+    /// widths, if it's the same along both axes.
+    fn uniform(&self) -> Option<f64> {
+        ((self.sx - self.sy).abs() < 1e-9).then_some(self.sx)
+    }
+}
+
+/// Attributes that make an element's rendering depend on more than its own
+/// coordinates.
+const POSITION_DEPENDENT: &[&str] = &[
+    "style",
+    "transform",
+    "clip-path",
+    "mask",
+    "filter",
+    "id",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+];
+
+/// Stroke lengths, which scale with the content.
+const STROKE_LENGTHS: &[&str] = &["stroke-width", "stroke-dasharray", "stroke-dashoffset"];
+
+/// Map the coordinates of everything in `root` through `map`, writing
+/// numbers with `precision` decimals. The root's own stroke width is scaled
+/// too, since its content inherits it.
+///
+/// Returns `false`, leaving `root` as it was, when some content can't be
+/// rewritten: anything but shapes, paths, and groups, transforms,
+/// references and `url()` paints, `style` attributes, or strokes under a
+/// non-uniform scale.
+pub(crate) fn map_content(root: &mut Element, map: AxisMap, precision: u8) -> bool {
+    let mut mapped = root.clone();
+    if !map_stroke(&mut mapped, &map, precision, true)
+        || !mapped
+            .child_elements_mut()
+            .all(|child| map_element(child, &map, precision))
+    {
+        return false;
+    }
+    *root = mapped;
+    true
+}
+
+/// Scale the stroke lengths `elem` sets. On the root, the default stroke
+/// width is made explicit so what inherits it scales too.
+fn map_stroke(elem: &mut Element, map: &AxisMap, precision: u8, is_root: bool) -> bool {
+    if map.sx == 1.0 && map.sy == 1.0 {
+        return true;
+    }
+    if elem.get_attr("style").is_some() {
+        return false;
+    }
+    let Some(scale) = map.uniform() else {
+        // Non-uniform scales would make strokes uneven
+        let stroke = elem.get_attr("stroke");
+        return stroke.is_none_or(|s| s.trim() == "none");
+    };
+    if is_root && elem.get_attr("stroke-width").is_none() && has_stroke(elem) {
+        elem.set_attr("stroke-width", "1");
+    }
+    for name in STROKE_LENGTHS {
+        let Some(value) = elem.get_attr(name) else {
+            continue;
+        };
+        if value.trim() == "none" {
+            continue;
+        }
+        let Some(lengths) = numbers(value) else {
+            return false;
+        };
+        let scaled: Vec<String> = lengths
+            .iter()
+            .map(|n| format_number(n * scale, precision))
+            .collect();
+        elem.set_attr(*name, scaled.join(" "));
+    }
+    true
+}
+
+fn map_element(elem: &mut Element, map: &AxisMap, precision: u8) -> bool {
+    if paints_nothing(elem) {
+        return true;
+    }
+    let depends_on_position = POSITION_DEPENDENT
+        .iter()
+        .any(|name| elem.get_attr(name).is_some())
+        || ["fill", "stroke"]
+            .iter()
+            .any(|name| elem.get_attr(name).is_some_and(|v| v.contains("url(")));
+    if depends_on_position || !map_stroke(elem, map, precision, false) {
+        return false;
+    }
+
+    let set = |elem: &mut Element, name: &str, f: &dyn Fn(f64) -> f64, default: bool| {
+        let value = match elem.get_attr(name) {
+            Some(value) => match length(value) {
+                Some(n) => n,
+                None => return false,
+            },
+            // Positions default to 0; sizes left out stay left out
+            None if default => 0.0,
+            None => return true,
+        };
+        elem.set_attr(name, format_number(f(value), precision));
+        true
+    };
+    let x = |n: f64| map.x(n);
+    let y = |n: f64| map.y(n);
+    let width = |n: f64| n * map.sx;
+    let height = |n: f64| n * map.sy;
+
+    match elem.name.local.as_str() {
+        "g" | "a" => elem
+            .child_elements_mut()
+            .all(|child| map_element(child, map, precision)),
+        "rect" | "image" => {
+            // A single corner radius applies to both axes
+            let one_radius = elem.get_attr("rx").is_some() != elem.get_attr("ry").is_some();
+            (!one_radius || map.uniform().is_some())
+                && set(elem, "x", &x, true)
+                && set(elem, "y", &y, true)
+                && set(elem, "width", &width, false)
+                && set(elem, "height", &height, false)
+                && set(elem, "rx", &width, false)
+                && set(elem, "ry", &height, false)
+        }
+        "circle" => {
+            map.uniform().is_some()
+                && set(elem, "cx", &x, true)
+                && set(elem, "cy", &y, true)
+                && set(elem, "r", &width, false)
+        }
+        "ellipse" => {
+            set(elem, "cx", &x, true)
+                && set(elem, "cy", &y, true)
+                && set(elem, "rx", &width, false)
+                && set(elem, "ry", &height, false)
+        }
+        "line" => {
+            set(elem, "x1", &x, true)
+                && set(elem, "y1", &y, true)
+                && set(elem, "x2", &x, true)
+                && set(elem, "y2", &y, true)
+        }
+        "polyline" | "polygon" => {
+            let Some(points) = elem.get_attr("points") else {
+                return true;
+            };
+            let Some(numbers) = numbers(points) else {
+                return false;
+            };
+            let mapped: Vec<String> = numbers
+                .iter()
+                .enumerate()
+                .map(|(i, &n)| {
+                    let n = if i % 2 == 0 { map.x(n) } else { map.y(n) };
+                    format_number(n, precision)
+                })
+                .collect();
+            elem.set_attr("points", mapped.join(" "));
+            true
+        }
+        "path" => {
+            let Some(d) = elem.get_attr("d") else {
+                return true;
+            };
+            let Ok(path) = parse_path(d) else {
+                return false;
+            };
+            let mut path = path.to_absolute();
+            for command in &mut path.commands {
+                if !map_command(command, map) {
+                    return false;
+                }
+            }
+            elem.set_attr("d", serialize_path(&path, precision));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Map an absolute path command. Rotated arcs only keep their shape under
+/// a uniform scale.
+fn map_command(command: &mut Command, map: &AxisMap) -> bool {
+    let point = |x: &mut f64, y: &mut f64| {
+        *x = map.x(*x);
+        *y = map.y(*y);
+    };
+    match command {
+        Command::MoveTo { x, y, .. }
+        | Command::LineTo { x, y, .. }
+        | Command::SmoothQuadTo { x, y, .. } => point(x, y),
+        Command::HorizontalTo { x, .. } => *x = map.x(*x),
+        Command::VerticalTo { y, .. } => *y = map.y(*y),
+        Command::CurveTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            x,
+            y,
+            ..
+        } => {
+            point(x1, y1);
+            point(x2, y2);
+            point(x, y);
+        }
+        Command::SmoothCurveTo { x2, y2, x, y, .. } => {
+            point(x2, y2);
+            point(x, y);
+        }
+        Command::QuadTo { x1, y1, x, y, .. } => {
+            point(x1, y1);
+            point(x, y);
+        }
+        Command::Arc {
+            rx,
+            ry,
+            x_axis_rotation,
+            x,
+            y,
+            ..
+        } => {
+            if *x_axis_rotation % 180.0 != 0.0 && map.uniform().is_none() {
+                return false;
+            }
+            *rx *= map.sx;
+            *ry *= map.sy;
+            point(x, y);
+        }
+        Command::ClosePath => {}
+    }
+    true
+}
+
+/// Whether `elem` or anything in it sets a stroke.
+fn has_stroke(elem: &Element) -> bool {
+    elem.get_attr("stroke").is_some_and(|s| s.trim() != "none")
+        || elem.child_elements().any(has_stroke)
+}
+
+/// A list of plain numbers, separated by whitespace or commas.
+fn numbers(value: &str) -> Option<Vec<f64>> {
+    value
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(length)
+        .collect()
+}
@@ -0,0 +1,242 @@
+//! Cropping the canvas to the content.
+//!
+//! Exports often place a small drawing on a large empty artboard. The
+//! painted bounds of everything in the root are computed, and the
+//! `viewBox` (with `width` and `height`, scaled to match) is shrunk to
+//! them. When every element's coordinates can be shifted, the content is
+//! also moved so the `viewBox` starts at `0 0`, if that's shorter.
+
+use crate::ast::*;
+use crate::bounds::{
+    Inherited, has_dynamic_content, length, local_painted_bounds, paints_nothing, property,
+};
+use crate::coords::{AxisMap, map_content};
+use crate::geometry::{Point, Rect};
+use crate::path::format_number;
+use crate::transform::parse_transform;
+use crate::{Options, serialize};
+
+/// Elements that are never rendered where they are, only when referenced.
+pub(crate) const NON_RENDERING: &[&str] = &[
+    "defs",
+    "symbol",
+    "clipPath",
+    "mask",
+    "marker",
+    "pattern",
+    "linearGradient",
+    "radialGradient",
+    "filter",
+    "style",
+    "script",
+];
+
+/// Shrink the root `viewBox` to the painted content, plus `margin` on every
+/// side. Nothing changes when the bounds aren't known, as with text,
+/// `<use>`, filters, markers, or dynamic content, or when the root has
+/// neither a `viewBox` nor a numeric `width` and `height` to crop.
+pub(crate) fn autocrop(doc: &mut Document, margin: f64, options: &Options) {
+    if has_dynamic_content(doc) {
+        return;
+    }
+    let Some(viewport) = viewport(&doc.root) else {
+        return;
+    };
+    let Some(content) = content_bounds(&doc.root) else {
+        return;
+    };
+
+    // Content outside the viewport is clipped, and stays hidden
+    let precision = options.precision;
+    let step = 10f64.powi(-i32::from(precision));
+    let crop = Rect {
+        min_x: (content.min_x.max(viewport.min_x) / step).floor() * step - margin,
+        min_y: (content.min_y.max(viewport.min_y) / step).floor() * step - margin,
+        max_x: (content.max_x.min(viewport.max_x) / step).ceil() * step + margin,
+        max_y: (content.max_y.min(viewport.max_y) / step).ceil() * step + margin,
+    };
+    if crop.width() <= 0.0 || crop.height() <= 0.0 {
+        return;
+    }
+
+    for (name, old, new) in [
+        ("width", viewport.width(), crop.width()),
+        ("height", viewport.height(), crop.height()),
+    ] {
+        if let Some(size) = doc.root.get_attr(name).and_then(length) {
+            let size = format_number(size * new / old, precision);
+            doc.root.set_attr(name, size);
+        }
+    }
+    set_view_box(&mut doc.root, &crop, precision);
+
+    // Moving the content to the origin is only worth it when the numbers
+    // that change come out shorter
+    if crop.min_x != 0.0 || crop.min_y != 0.0 {
+        let mut moved = doc.root.clone();
+        let to_origin = AxisMap::translate(-crop.min_x, -crop.min_y);
+        if map_content(&mut moved, to_origin, precision) {
+            let origin = Rect {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: crop.width(),
+                max_y: crop.height(),
+            };
+            set_view_box(&mut moved, &origin, precision);
+            let moved = Document {
+                root: moved,
+                ..doc.clone()
+            };
+            if serialize(&moved, options).len() <= serialize(doc, options).len() {
+                *doc = moved;
+            }
+        }
+    }
+}
+
+/// The painted bounds of what's in `root`, in its user space. `None` when
+/// they aren't known, or nothing is painted.
+pub(crate) fn content_bounds(root: &Element) -> Option<Rect> {
+    let inherited = Inherited::default().child(root);
+    let mut content = None;
+    for child in root.child_elements() {
+        if !add_bounds(child, &inherited.child(child), &mut content) {
+            return None;
+        }
+    }
+    content
+}
+
+/// The root's viewport in user space: its `viewBox`, or its size when it
+/// has none.
+pub(crate) fn viewport(root: &Element) -> Option<Rect> {
+    if root.get_attr("viewBox").is_some() {
+        return view_box(root);
+    }
+    let width = root.get_attr("width").and_then(length)?;
+    let height = root.get_attr("height").and_then(length)?;
+    (width > 0.0 && height > 0.0).then_some(Rect {
+        min_x: 0.0,
+        min_y: 0.0,
+        max_x: width,
+        max_y: height,
+    })
+}
+
+/// An element's `viewBox`, if it has a valid one.
+pub(crate) fn view_box(elem: &Element) -> Option<Rect> {
+    let numbers = elem
+        .get_attr("viewBox")?
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+    let [x, y, width, height] = numbers[..] else {
+        return None;
+    };
+    (width > 0.0 && height > 0.0).then_some(Rect {
+        min_x: x,
+        min_y: y,
+        max_x: x + width,
+        max_y: y + height,
+    })
+}
+
+pub(crate) fn set_view_box(root: &mut Element, rect: &Rect, precision: u8) {
+    let numbers = [rect.min_x, rect.min_y, rect.width(), rect.height()];
+    let numbers: Vec<String> = numbers
+        .iter()
+        .map(|&n| format_number(n, precision))
+        .collect();
+    root.set_attr("viewBox", numbers.join(" "));
+}
+
+/// Add what `elem` paints, in its parent's user space, to `bounds`.
+/// Returns `false` if that isn't known.
+fn add_bounds(elem: &Element, inherited: &Inherited, bounds: &mut Option<Rect>) -> bool {
+    if paints_nothing(elem) || NON_RENDERING.contains(&elem.name.local.as_str()) {
+        return true;
+    }
+    let matrix = match property(elem, "transform") {
+        Some(value) if value != "none" => match parse_transform(value) {
+            Ok(transform) => Some(transform.to_matrix()),
+            Err(_) => return false,
+        },
+        _ => None,
+    };
+
+    let mut local = None;
+    if matches!(elem.name.local.as_str(), "g" | "a") {
+        if inherited.has_markers || property(elem, "filter").is_some_and(|v| v != "none") {
+            return false;
+        }
+        for child in elem.child_elements() {
+            if !add_bounds(child, &inherited.child(child), &mut local) {
+                return false;
+            }
+        }
+    } else {
+        match local_painted_bounds(elem, inherited) {
+            Some(rect) => local = Some(rect),
+            None => return false,
+        }
+    }
+
+    if let Some(rect) = local {
+        let rect = match matrix {
+            Some(m) => transform_rect(&rect, &m),
+            None => rect,
+        };
+        *bounds = Some(bounds.map_or(rect, |b| b.union(&rect)));
+    }
+    true
+}
+
+/// The bounds of `rect` mapped through the matrix `m`.
+fn transform_rect(rect: &Rect, m: &[f64; 6]) -> Rect {
+    let [a, b, c, d, e, f] = *m;
+    let map = |x: f64, y: f64| Point::new(a * x + c * y + e, b * x + d * y + f);
+    let mut out = Rect::from_point(map(rect.min_x, rect.min_y));
+    out.include(map(rect.max_x, rect.min_y));
+    out.include(map(rect.min_x, rect.max_y));
+    out.include(map(rect.max_x, rect.max_y));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Options, minify_with_options};
+
+    fn cropped(svg: &str, margin: f64) -> String {
+        let options = Options {
+            autocrop: Some(margin),
+            ..Options::default()
+        };
+        minify_with_options(svg, &options).unwrap()
+    }
+
+    #[test]
+    fn test_autocrop() {
+        // Moved to the origin, with width and height scaled to match
+        assert_eq!(
+            cropped(
+                r#"<svg viewBox="0 0 1000 1000" width="500" height="500"><path d="M400 400h100v100h-100z"/></svg>"#,
+                0.0
+            ),
+            r#"<svg height="50" viewBox="0 0 100 100" width="50"><path d="M0 0H100V100H0z"/></svg>"#
+        );
+
+        // Strokes and transforms count; references keep the content in place
+        assert_eq!(
+            cropped(
+                r#"<svg width="100" height="100"><rect id="r" x="10" y="10" width="10" height="10" stroke="red" stroke-width="2" transform="translate(5)"/></svg>"#,
+                1.0
+            ),
+            r#"<svg height="20" viewBox="10 5 20 20" width="20"><rect height="10" id="r" stroke="red" stroke-width="2" transform="translate(5)" width="10" x="10" y="10"/></svg>"#
+        );
+
+        // Unknown bounds leave the document alone
+        let svg = r#"<svg viewBox="0 0 100 100"><text x="10" y="10">Hi</text></svg>"#;
+        assert_eq!(cropped(svg, 0.0), svg);
+    }
+}
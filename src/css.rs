@@ -0,0 +1,148 @@
+//! Small CSS helpers shared by the style-aware passes.
+
+use std::ops::Range;
+
+/// A `url(...)` token in a CSS string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CssUrl {
+    /// Byte range of the whole `url(...)` token
+    pub token: Range<usize>,
+    /// The URL with surrounding whitespace and quotes removed
+    pub url: String,
+}
+
+/// Find every `url(...)` token, honoring quoted arguments.
+pub(crate) fn css_urls(css: &str) -> Vec<CssUrl> {
+    let mut urls = Vec::new();
+    let bytes = css.as_bytes();
+    let mut offset = 0;
+
+    while let Some(found) = find_ignore_case(&css[offset..], "url(") {
+        let start = offset + found;
+        let args = start + 4;
+
+        // Skip identifiers that merely end in "url", like "myurl("
+        if start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b'-') {
+            offset = args;
+            continue;
+        }
+
+        let mut quote = None;
+        let mut end = None;
+        for (i, c) in css[args..].char_indices() {
+            match (quote, c) {
+                (Some('\\'), _) => quote = None,
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, ')') => {
+                    end = Some(args + i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        let Some(end) = end else {
+            break;
+        };
+
+        let url = css[args..end]
+            .trim()
+            .trim_matches('"')
+            .trim_matches('\'')
+            .to_string();
+        urls.push(CssUrl {
+            token: start..end + 1,
+            url,
+        });
+        offset = end + 1;
+    }
+
+    urls
+}
+
+/// Serialize a `url()` token, quoting only when required.
+pub(crate) fn format_css_url(url: &str) -> String {
+    let needs_quotes = url
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"' | '\'' | '\\'));
+    if !needs_quotes {
+        format!("url({})", url)
+    } else if url.contains('"') {
+        format!("url('{}')", url)
+    } else {
+        format!("url(\"{}\")", url)
+    }
+}
+
+/// The name of every `#name` token, with escapes resolved. These are id
+/// selectors, and hex colors, which aren't told apart.
+pub(crate) fn hash_names(css: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '#' {
+            continue;
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii() {
+                name.push(c);
+                chars.next();
+            } else if c == '\\' {
+                chars.next();
+                let hex: String = std::iter::from_fn(|| chars.next_if(char::is_ascii_hexdigit))
+                    .take(6)
+                    .collect();
+                if hex.is_empty() {
+                    name.extend(chars.next());
+                } else {
+                    let code = u32::from_str_radix(&hex, 16).unwrap_or(0xfffd);
+                    name.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                    chars.next_if(char::is_ascii_whitespace);
+                }
+            } else {
+                break;
+            }
+        }
+        if !name.is_empty() {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_urls() {
+        let css = r#"a{background:url( "x.png" )} b{fill:URL(#grad)} c{mask:myurl(y)}"#;
+        let urls = css_urls(css);
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].url, "x.png");
+        assert_eq!(&css[urls[0].token.clone()], r#"url( "x.png" )"#);
+        assert_eq!(urls[1].url, "#grad");
+    }
+
+    #[test]
+    fn test_hash_names() {
+        let css = r"#a,#b.c>#d\.e #\31 23 {fill:#fff}#";
+        assert_eq!(hash_names(css), ["a", "b", "d.e", "123", "fff"]);
+    }
+
+    #[test]
+    fn test_format_css_url() {
+        assert_eq!(format_css_url("a.png"), "url(a.png)");
+        assert_eq!(format_css_url("a b.png"), "url(\"a b.png\")");
+    }
+}
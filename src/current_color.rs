@@ -0,0 +1,166 @@
+//! `currentColor` parameterization.
+//!
+//! Icons drawn in one color become themable from CSS when that color is
+//! `currentColor`: they then take the `color` of the element they're
+//! embedded in. The color is matched however it's spelled, so `#000`,
+//! `black`, and `rgb(0 0 0)` are all replaced.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::*;
+use crate::color::{Color, rewrite_colors, visit_property_values};
+use crate::error::SvagError;
+
+/// Properties whose color is replaced.
+const PAINT_PROPERTIES: &[&str] = &["fill", "stroke"];
+
+/// Which fill and stroke color [`Options::current_color`](crate::Options)
+/// replaces with `currentColor`.
+///
+/// ```
+/// let dominant: svag::CurrentColor = "dominant".parse().unwrap();
+/// assert_eq!(dominant, svag::CurrentColor::Dominant);
+/// let black: svag::CurrentColor = "#000".parse().unwrap();
+/// assert_eq!(black, svag::CurrentColor::Color(svag::Color::rgb(0, 0, 0)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurrentColor {
+    /// The opaque color most fills and strokes use (the first of them on
+    /// a tie)
+    Dominant,
+    /// This color
+    Color(Color),
+}
+
+impl FromStr for CurrentColor {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("dominant") {
+            Ok(CurrentColor::Dominant)
+        } else {
+            s.parse().map(CurrentColor::Color)
+        }
+    }
+}
+
+impl fmt::Display for CurrentColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurrentColor::Dominant => f.write_str("dominant"),
+            CurrentColor::Color(color) => write!(f, "{color}"),
+        }
+    }
+}
+
+/// Replace fills and strokes of the chosen color with `currentColor`.
+///
+/// Nothing is replaced in documents that set `color` themselves, where
+/// `currentColor` wouldn't be the embedding page's color.
+pub(crate) fn use_current_color(doc: &mut Document, which: CurrentColor) {
+    if sets_color(doc) {
+        return;
+    }
+
+    let color = match which {
+        CurrentColor::Color(color) => color,
+        CurrentColor::Dominant => match dominant_color(doc) {
+            Some(color) => color,
+            None => return,
+        },
+    };
+    rewrite_colors(&mut doc.root, PAINT_PROPERTIES, &mut |_, found| {
+        (found == color).then(|| "currentColor".to_string())
+    });
+}
+
+/// Whether the document sets `color` anywhere: to a color, but also to
+/// `inherit`, a `var()`, or any other value that isn't `currentColor`
+/// itself.
+fn sets_color(doc: &Document) -> bool {
+    let mut sets_color = false;
+    visit_property_values(&doc.root, &["color"], &mut |_, value| {
+        let value = value.trim();
+        sets_color |= !value.is_empty() && !value.eq_ignore_ascii_case("currentColor");
+    });
+    sets_color
+}
+
+/// The opaque color used by the most fills and strokes.
+fn dominant_color(doc: &Document) -> Option<Color> {
+    let mut counts: HashMap<Color, (usize, usize)> = HashMap::new();
+    visit_property_values(&doc.root, PAINT_PROPERTIES, &mut |_, value| {
+        if let Some(color) = Color::parse(value)
+            && color.is_opaque()
+        {
+            let first_seen = counts.len();
+            counts.entry(color).or_insert((0, first_seen)).0 += 1;
+        }
+    });
+    counts
+        .into_iter()
+        .max_by_key(|&(_, (count, first_seen))| (count, std::cmp::Reverse(first_seen)))
+        .map(|(color, _)| color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, minify_with_options};
+
+    fn minified(svg: &str, which: CurrentColor) -> String {
+        let options = Options {
+            current_color: Some(which),
+            ..Options::default()
+        };
+        minify_with_options(svg, &options).unwrap()
+    }
+
+    #[test]
+    fn test_dominant() {
+        let svg = r##"<svg><style>.a{stroke:rgb(0 0 0)}</style><path d="M0 0h1" fill="red"/><path d="M0 0h2" fill="#000"/><path d="M0 0h3" style="fill:rgba(0,0,0,1)"/></svg>"##;
+        assert_eq!(
+            minified(svg, CurrentColor::Dominant),
+            r#"<svg><style>.a{stroke:currentColor}</style><path d="M0 0h1" fill="red"/><path d="M0 0h2" fill="currentColor"/><path d="M0 0h3" style="fill:currentColor"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_given_color() {
+        let svg = r##"<svg><path d="M0 0h1" fill="#FF0000" stroke="#ff000080"/><stop stop-color="red"/></svg>"##;
+        assert_eq!(
+            minified(svg, CurrentColor::Color(Color::rgb(255, 0, 0))),
+            r##"<svg><path d="M0 0h1" fill="currentColor" stroke="#ff000080"/><stop stop-color="red"/></svg>"##
+        );
+
+        // The document's own `color` would win over the page's
+        let svg = r#"<svg color="red"><path d="M0 0h1" fill="red"/></svg>"#;
+        assert_eq!(minified(svg, CurrentColor::Dominant), svg);
+    }
+
+    #[test]
+    fn test_sets_color() {
+        for svg in [
+            r#"<svg color="inherit"><path d="M0 0h1" fill="red"/></svg>"#,
+            r#"<svg><path d="M0 0h1" fill="red" style="color:var(--ink)"/></svg>"#,
+            r#"<svg><style>path{color:inherit}</style><path d="M0 0h1" fill="red"/></svg>"#,
+        ] {
+            assert!(sets_color(&crate::parse_svg(svg).unwrap()), "{svg}");
+        }
+        let svg = r#"<svg color="currentColor"><path d="M0 0h1" fill="red"/></svg>"#;
+        assert!(!sets_color(&crate::parse_svg(svg).unwrap()));
+
+        let svg = r#"<svg><path d="M0 0h1" fill="red" style="color:var(--ink)"/></svg>"#;
+        assert_eq!(minified(svg, CurrentColor::Dominant), svg);
+    }
+
+    #[test]
+    fn test_dominant_color_is_read_only() {
+        let svg = r##"<svg><style>.a { stroke : #000 }</style><path d="M0 0h1" fill="#000" style="fill : black"/></svg>"##;
+        let doc = crate::parse_svg(svg).unwrap();
+        assert_eq!(dominant_color(&doc), Some(Color::rgb(0, 0, 0)));
+        assert_eq!(crate::serialize(&doc, &Options::default()), svg);
+    }
+}
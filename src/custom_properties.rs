@@ -0,0 +1,167 @@
+//! Extraction of colors into CSS custom properties.
+//!
+//! A static export becomes themable when its brand colors are written as
+//! `var(--brand, #e34)`: the fallback keeps it rendering as before, and a
+//! page (or the `:root` rule added to the document) can set `--brand` to
+//! restyle it.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::*;
+use crate::color::{COLOR_PROPERTIES, Color, css_color, rewrite_colors};
+use crate::error::SvagError;
+
+/// A color to write as the custom property `--name`.
+///
+/// ```
+/// let property: svag::CustomProperty = "brand=#e34".parse().unwrap();
+/// assert_eq!(property.name, "brand");
+/// assert_eq!(property.color, svag::Color::rgb(0xee, 0x33, 0x44));
+/// assert_eq!(property.to_string(), "--brand=#ee3344");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomProperty {
+    /// Property name, without the leading `--`
+    pub name: String,
+    pub color: Color,
+}
+
+impl CustomProperty {
+    pub fn new(name: impl Into<String>, color: Color) -> Self {
+        let name = name.into();
+        CustomProperty {
+            name: name.strip_prefix("--").map(str::to_string).unwrap_or(name),
+            color,
+        }
+    }
+}
+
+/// Parsed from `name=color`, where the name may start with `--`.
+impl FromStr for CustomProperty {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SvagError::InvalidCustomProperty(s.to_string());
+        let (name, color) = s.split_once('=').ok_or_else(invalid)?;
+        let name = name.trim();
+        let name = name.strip_prefix("--").unwrap_or(name);
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(invalid());
+        }
+        Ok(CustomProperty::new(name, color.parse()?))
+    }
+}
+
+impl fmt::Display for CustomProperty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--{}={}", self.name, self.color)
+    }
+}
+
+/// Replace the colors of `properties` with `var(--name, color)` wherever
+/// fills, strokes, and other color properties use them, and declare them in
+/// a `:root` rule of a new `<style>` element, first in the root.
+///
+/// Returns the palette: the properties whose color the document used, in
+/// the order given. Nothing is added when it's empty, so running this again
+/// changes nothing.
+///
+/// ```
+/// let mut doc = svag::parse_svg(r##"<svg><path fill="#E34"/></svg>"##).unwrap();
+/// let brand = svag::CustomProperty::new("brand", "#e34".parse().unwrap());
+/// let palette = svag::extract_custom_properties(&mut doc, &[brand.clone()]);
+/// assert_eq!(palette, [brand]);
+/// assert_eq!(
+///     svag::serialize(&doc, &svag::Options::default()),
+///     r#"<svg><style>:root{--brand:#e34}</style><path fill="var(--brand,#e34)"/></svg>"#
+/// );
+/// ```
+pub fn extract_custom_properties(
+    doc: &mut Document,
+    properties: &[CustomProperty],
+) -> Vec<CustomProperty> {
+    let mut used = vec![false; properties.len()];
+    rewrite_colors(&mut doc.root, COLOR_PROPERTIES, &mut |_, color| {
+        let i = properties.iter().position(|p| p.color == color)?;
+        used[i] = true;
+        let property = &properties[i];
+        Some(format!(
+            "var(--{},{})",
+            property.name,
+            css_color(property.color)
+        ))
+    });
+
+    let palette: Vec<CustomProperty> = properties
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| *used)
+        .map(|(property, _)| property.clone())
+        .collect();
+    if palette.is_empty() {
+        return palette;
+    }
+
+    let declarations: Vec<String> = palette
+        .iter()
+        .map(|p| format!("--{}:{}", p.name, css_color(p.color)))
+        .collect();
+    let mut style = Element::new("style");
+    style.append_child(Node::Text(
+        format!(":root{{{}}}", declarations.join(";")).into(),
+    ));
+    doc.root.insert_before(0, style);
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, SvgTarget, minify_with_options};
+
+    #[test]
+    fn test_custom_properties_option() {
+        let options = Options {
+            custom_properties: vec![
+                "brand=#e34".parse().unwrap(),
+                "--ink=black".parse().unwrap(),
+                "unused=blue".parse().unwrap(),
+            ],
+            ..Options::default()
+        };
+        let svg = r##"<svg><style>.a{stroke:rgb(238,51,68)}</style><path d="M0 0h1" fill="#000" stroke="#ee3344"/><stop style="stop-color:#ee3344"/></svg>"##;
+        assert_eq!(
+            minify_with_options(svg, &options).unwrap(),
+            r##"<svg><style>:root{--brand:#e34;--ink:#000}</style><style>.a{stroke:var(--brand,#e34)}</style><path d="M0 0h1" fill="var(--ink,#000)" stroke="var(--brand,#e34)"/><stop style="stop-color:var(--brand,#e34)"/></svg>"##
+        );
+
+        // Renderers without custom properties keep the literal colors
+        let options = Options {
+            target: SvgTarget::Svg2,
+            ..options
+        };
+        let svg = r##"<svg><path d="M0 0h1" fill="#000"/></svg>"##;
+        assert_eq!(minify_with_options(svg, &options).unwrap(), svg);
+    }
+
+    #[test]
+    fn test_parse() {
+        for invalid in ["brand", "=red", "a b=red"] {
+            let err = invalid.parse::<CustomProperty>().unwrap_err();
+            assert!(
+                matches!(err, SvagError::InvalidCustomProperty(_)),
+                "{invalid}: {err}"
+            );
+            assert!(err.to_string().contains("name=color"));
+        }
+        assert!(matches!(
+            "brand=nope".parse::<CustomProperty>(),
+            Err(SvagError::InvalidColor(_))
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+//! `data:` URI parsing.
+
+/// The parts of a `data:[<mime>][;param=value]*[;base64],<data>` URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DataUri<'a> {
+    /// MIME type, lowercased by the caller if needed (defaults to `text/plain`)
+    pub mime: &'a str,
+    /// Whether the payload is base64-encoded
+    pub base64: bool,
+    /// The raw payload after the comma
+    pub data: &'a str,
+}
+
+/// Split a `data:` URI into its parts.
+pub(crate) fn parse_data_uri(uri: &str) -> Option<DataUri<'_>> {
+    let rest = uri.trim_start();
+    if !rest.get(..5)?.eq_ignore_ascii_case("data:") {
+        return None;
+    }
+    let (header, data) = rest[5..].split_once(',')?;
+
+    let mut params = header.split(';');
+    let mime = params.next().unwrap_or("").trim();
+    let base64 = params.any(|p| p.trim().eq_ignore_ascii_case("base64"));
+
+    Some(DataUri {
+        mime: if mime.is_empty() { "text/plain" } else { mime },
+        base64,
+        data,
+    })
+}
+
+impl DataUri<'_> {
+    /// Decode the payload (base64 or percent-encoded) into bytes.
+    pub fn decode(&self) -> Option<Vec<u8>> {
+        use base64::Engine;
+
+        if self.base64 {
+            let data: String = self.data.split_ascii_whitespace().collect();
+            base64::prelude::BASE64_STANDARD.decode(data).ok()
+        } else {
+            Some(percent_decode(self.data))
+        }
+    }
+}
+
+/// Decode `%XX` escapes, leaving malformed escapes as-is.
+pub(crate) fn percent_decode(s: &str) -> Vec<u8> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = s.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_data_uri() {
+        let uri = parse_data_uri("data:image/png;base64,iVBOR").unwrap();
+        assert_eq!(uri.mime, "image/png");
+        assert!(uri.base64);
+        assert_eq!(uri.data, "iVBOR");
+
+        let uri = parse_data_uri("DATA:,hello%20world").unwrap();
+        assert_eq!(uri.mime, "text/plain");
+        assert!(!uri.base64);
+
+        assert!(parse_data_uri("image.png").is_none());
+    }
+
+    #[test]
+    fn test_decode_data_uri() {
+        let uri = parse_data_uri("data:text/plain;base64,aGk=").unwrap();
+        assert_eq!(uri.decode().unwrap(), b"hi");
+
+        let uri = parse_data_uri("data:image/svg+xml,%3Csvg%3E").unwrap();
+        assert_eq!(uri.decode().unwrap(), b"<svg>");
+    }
+}
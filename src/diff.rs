@@ -0,0 +1,295 @@
+//! Structural diffs between two documents.
+//!
+//! Meant for reviewing what optimization did to a file: which elements were
+//! removed or added and which attributes and text were rewritten. Child
+//! elements are aligned by name (longest common subsequence), so a removed
+//! `<title>` doesn't make every following sibling look changed.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Document, Element, Node, QName};
+
+/// Longest attribute value or text shown in a [`Change`]'s `Display` output.
+const MAX_DISPLAY_LEN: usize = 60;
+
+/// One difference between two documents.
+///
+/// Paths look like `/svg/g[2]/path`; the index is 1-based among same-named
+/// siblings and only shown when there is more than one. Paths of removed
+/// elements refer to the first document, paths of added elements to the
+/// second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    ElementRemoved {
+        path: String,
+    },
+    ElementAdded {
+        path: String,
+    },
+    AttributeRemoved {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeAdded {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeChanged {
+        path: String,
+        name: String,
+        old: String,
+        new: String,
+    },
+    TextChanged {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::ElementRemoved { path } => write!(f, "- {}", path),
+            Change::ElementAdded { path } => write!(f, "+ {}", path),
+            Change::AttributeRemoved { path, name, value } => {
+                write!(f, "- {} @{}={}", path, name, Quoted(value))
+            }
+            Change::AttributeAdded { path, name, value } => {
+                write!(f, "+ {} @{}={}", path, name, Quoted(value))
+            }
+            Change::AttributeChanged {
+                path,
+                name,
+                old,
+                new,
+            } => write!(
+                f,
+                "~ {} @{}: {} -> {}",
+                path,
+                name,
+                Quoted(old),
+                Quoted(new)
+            ),
+            Change::TextChanged { path, old, new } => {
+                write!(f, "~ {} text: {} -> {}", path, Quoted(old), Quoted(new))
+            }
+        }
+    }
+}
+
+/// Displays a value quoted, truncated to [`MAX_DISPLAY_LEN`] characters.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.chars().count() > MAX_DISPLAY_LEN {
+            let truncated: String = self.0.chars().take(MAX_DISPLAY_LEN).collect();
+            write!(f, "{:?}…", truncated)
+        } else {
+            write!(f, "{:?}", self.0)
+        }
+    }
+}
+
+/// Compute the structural changes from `before` to `after`.
+pub fn diff_documents(before: &Document, after: &Document) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let path = format!("/{}", before.root.name.full_name());
+    if before.root.name == after.root.name {
+        diff_elements(&before.root, &after.root, &path, &mut changes);
+    } else {
+        changes.push(Change::ElementRemoved { path });
+        changes.push(Change::ElementAdded {
+            path: format!("/{}", after.root.name.full_name()),
+        });
+    }
+    changes
+}
+
+fn diff_elements(before: &Element, after: &Element, path: &str, changes: &mut Vec<Change>) {
+    // Attributes, in the order they appear
+    for attr in &before.attributes {
+        let name = attr.name.full_name();
+        match after.attributes.iter().find(|a| a.name == attr.name) {
+            None => changes.push(Change::AttributeRemoved {
+                path: path.to_string(),
+                name,
+                value: attr.value.to_string(),
+            }),
+            Some(new) if new.value != attr.value => changes.push(Change::AttributeChanged {
+                path: path.to_string(),
+                name,
+                old: attr.value.to_string(),
+                new: new.value.to_string(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for attr in &after.attributes {
+        if !before.attributes.iter().any(|a| a.name == attr.name) {
+            changes.push(Change::AttributeAdded {
+                path: path.to_string(),
+                name: attr.name.full_name(),
+                value: attr.value.to_string(),
+            });
+        }
+    }
+
+    // Direct text content, modulo whitespace
+    let (old_text, new_text) = (direct_text(before), direct_text(after));
+    if old_text != new_text {
+        changes.push(Change::TextChanged {
+            path: path.to_string(),
+            old: old_text,
+            new: new_text,
+        });
+    }
+
+    // Child elements, aligned by name
+    let old_children: Vec<&Element> = before.child_elements().collect();
+    let new_children: Vec<&Element> = after.child_elements().collect();
+    let old_paths = child_paths(path, &old_children);
+    let new_paths = child_paths(path, &new_children);
+
+    let (mut i, mut j) = (0, 0);
+    for (oi, nj) in align(&old_children, &new_children) {
+        for path in &old_paths[i..oi] {
+            changes.push(Change::ElementRemoved { path: path.clone() });
+        }
+        for path in &new_paths[j..nj] {
+            changes.push(Change::ElementAdded { path: path.clone() });
+        }
+        diff_elements(old_children[oi], new_children[nj], &old_paths[oi], changes);
+        (i, j) = (oi + 1, nj + 1);
+    }
+    for path in &old_paths[i..] {
+        changes.push(Change::ElementRemoved { path: path.clone() });
+    }
+    for path in &new_paths[j..] {
+        changes.push(Change::ElementAdded { path: path.clone() });
+    }
+}
+
+/// Pair up children with equal names via longest common subsequence.
+fn align(old: &[&Element], new: &[&Element]) -> Vec<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] = LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i].name == new[j].name {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i].name == new[j].name {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Paths of `children` under `parent`, indexed among same-named siblings.
+pub(crate) fn child_paths(parent: &str, children: &[&Element]) -> Vec<String> {
+    let mut totals: HashMap<&QName, usize> = HashMap::new();
+    for child in children {
+        *totals.entry(&child.name).or_default() += 1;
+    }
+    let mut seen: HashMap<&QName, usize> = HashMap::new();
+    children
+        .iter()
+        .map(|child| {
+            let name = child.name.full_name();
+            let index = seen.entry(&child.name).or_default();
+            *index += 1;
+            if totals[&child.name] > 1 {
+                format!("{}/{}[{}]", parent, name, index)
+            } else {
+                format!("{}/{}", parent, name)
+            }
+        })
+        .collect()
+}
+
+/// The element's own text and CDATA, with whitespace collapsed.
+fn direct_text(elem: &Element) -> String {
+    let mut text = String::new();
+    for child in &elem.children {
+        if let Node::Text(t) | Node::CData(t) = child {
+            for word in t.split_whitespace() {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.push_str(word);
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, optimize, parse_svg};
+
+    #[test]
+    fn test_diff_documents() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <title>Icon</title>
+            <path d="M 0 0 L 10 10" fill="#ff0000" fill-opacity="1"/>
+            <path d="M 1 1"/>
+            <text>  hello   world </text>
+        </svg>"##;
+        let before = parse_svg(svg).unwrap();
+        let mut after = before.clone();
+        optimize(&mut after, &Options::default());
+
+        let changes: Vec<String> = diff_documents(&before, &after)
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                "- /svg/title",
+                r##"~ /svg/path[1] @d: "M 0 0 L 10 10" -> "M0 0 10 10""##,
+                r##"~ /svg/path[1] @fill: "#ff0000" -> "red""##,
+                r#"- /svg/path[1] @fill-opacity="1""#,
+                r#"~ /svg/path[2] @d: "M 1 1" -> "M1 1""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_added_and_truncated() {
+        let before = parse_svg(r#"<svg><g/></svg>"#).unwrap();
+        let long = "x".repeat(100);
+        let after = format!(r#"<svg><rect/><g id="{}"/></svg>"#, long);
+        let after = parse_svg(&after).unwrap();
+
+        let changes = diff_documents(&before, &after);
+        assert_eq!(
+            changes[0],
+            Change::ElementAdded {
+                path: "/svg/rect".to_string()
+            }
+        );
+        assert!(changes[1].to_string().ends_with("\"…"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Removal of shapes drawn twice.
+//!
+//! Copy-pasting in design tools leaves exact copies of a shape stacked on
+//! top of each other. When a copy paints only opaque pixels, the one above
+//! it paints over all of them again, so the earlier one can go; with nothing
+//! between them, that's the same as dropping the later one. Translucent
+//! copies, or ones blended with what's below, darken or tint each other and
+//! are kept.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::bounds::{
+    Inherited, fraction, has_dynamic_content, is_opaque_color, is_shape, property,
+};
+use crate::index::referenced_ids;
+use crate::markers::definition_key;
+
+/// Remove shapes that an identical, opaque later sibling paints over.
+pub(crate) fn remove_duplicate_shapes(doc: &mut Document) {
+    // Stylesheets can tell siblings apart by position, and scripts by
+    // anything
+    if has_dynamic_content(doc) {
+        return;
+    }
+
+    let referenced = referenced_ids(doc);
+    visit(&mut doc.root, &Inherited::default(), &referenced);
+}
+
+fn visit(elem: &mut Element, inherited: &Inherited, referenced: &HashSet<String>) {
+    let inherited = inherited.child(elem);
+
+    // The last position each duplicated shape appears at
+    let mut last: HashMap<String, usize> = HashMap::new();
+    let mut keys: Vec<Option<String>> = Vec::with_capacity(elem.children.len());
+    for (i, node) in elem.children.iter().enumerate() {
+        let key = match node {
+            Node::Element(child) if is_opaque_shape(child, &inherited.child(child)) => Some(
+                format!("{}:{}", child.name.full_name(), definition_key(child)),
+            ),
+            _ => None,
+        };
+        if let Some(key) = &key {
+            last.insert(key.clone(), i);
+        }
+        keys.push(key);
+    }
+    let mut i = 0;
+    elem.children.retain(|_| {
+        let keep = keys[i].as_ref().is_none_or(|key| last[key] == i);
+        i += 1;
+        keep
+    });
+
+    // Referenced groups also render through <use>, where they inherit
+    // different properties, so only plain rendering containers are entered
+    for child in elem.child_elements_mut() {
+        let is_referenced = child
+            .get_attr("id")
+            .is_some_and(|id| referenced.contains(id));
+        if matches!(child.name.local.as_str(), "g" | "svg" | "a") && !is_referenced {
+            visit(child, &inherited, referenced);
+        }
+    }
+}
+
+/// Whether `elem` is a shape that paints nothing but fully opaque pixels,
+/// composited normally. `inherited` includes `elem`'s own properties.
+fn is_opaque_shape(elem: &Element, inherited: &Inherited) -> bool {
+    if !is_shape(elem) || elem.is("image") || elem.get_attr("id").is_some() {
+        return false;
+    }
+    let opaque = |value: &str| fraction(value).is_some_and(|n| n >= 1.0);
+    let paint_ok = |paint: &str, opacity: &str| {
+        paint.trim() == "none" || (is_opaque_color(paint) && opaque(opacity))
+    };
+    let opacity_ok = property(elem, "opacity").is_none_or(opaque);
+    let blend_ok = property(elem, "mix-blend-mode").is_none_or(|m| m == "normal");
+    // Masks, filters, and markers can paint translucent pixels
+    let effects = ["mask", "filter"]
+        .iter()
+        .any(|p| property(elem, p).is_some_and(|v| v != "none"));
+    opacity_ok
+        && blend_ok
+        && !effects
+        && !inherited.has_markers
+        && paint_ok(&inherited.fill, &inherited.fill_opacity)
+        && paint_ok(&inherited.stroke, &inherited.stroke_opacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn deduped(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        remove_duplicate_shapes(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_remove_duplicate_shapes() {
+        assert_eq!(
+            deduped(
+                r#"<svg><path d="M0 0h9v9z" fill="red"/><path fill="red" d="M0 0h9v9z"/><g stroke="blue"><circle r="5"/><rect width="2" height="2"/><circle r="5"/></g></svg>"#
+            ),
+            r#"<svg><path d="M0 0h9v9z" fill="red"/><g stroke="blue"><rect height="2" width="2"/><circle r="5"/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keep_translucent_duplicates() {
+        for svg in [
+            r#"<svg><rect width="2" height="2" opacity=".5"/><rect width="2" height="2" opacity=".5"/></svg>"#,
+            r#"<svg><g fill-opacity=".5"><rect width="2" height="2"/><rect width="2" height="2"/></g></svg>"#,
+            r#"<svg><g stroke="red" stroke-opacity=".5"><rect width="2" height="2"/><rect width="2" height="2"/></g></svg>"#,
+            r#"<svg><rect width="2" height="2" fill="url(#g)"/><rect width="2" height="2" fill="url(#g)"/></svg>"#,
+            r#"<svg><rect width="2" height="2" style="mix-blend-mode:multiply"/><rect width="2" height="2" style="mix-blend-mode:multiply"/></svg>"#,
+            r##"<svg><g id="a"><rect width="2" height="2"/><rect width="2" height="2"/></g><use href="#a" fill-opacity=".5"/></svg>"##,
+            r#"<svg><style>rect+rect{fill:red}</style><rect width="2" height="2"/><rect width="2" height="2"/></svg>"#,
+        ] {
+            assert_eq!(
+                deduped(svg),
+                serialize(&parse_svg(svg).unwrap(), &Options::default())
+            );
+        }
+    }
+}
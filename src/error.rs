@@ -11,9 +11,42 @@ pub enum SvagError {
     #[error("Invalid path data: {0}")]
     InvalidPath(String),
 
+    #[error("Invalid transform: {0}")]
+    InvalidTransform(String),
+
     #[error("UTF-8 error: {0}")]
     Utf8(#[from] std::str::Utf8Error),
 
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("Invalid color: {0}")]
+    InvalidColor(String),
+
+    #[error("Invalid custom property {0:?}, expected name=color")]
+    InvalidCustomProperty(String),
+
+    #[error("No element matches {0}")]
+    NoMatch(String),
+
+    #[error("Unknown pass: {0}")]
+    UnknownPass(String),
+
+    #[error("Font subsetting error: {0}")]
+    FontSubset(String),
+
+    #[error("Rendering error: {0}")]
+    Render(String),
+
+    /// An error in one of several files. The message already includes the
+    /// inner error, so it isn't exposed again as the
+    /// [`source`](std::error::Error::source).
+    #[error("{}: {error}", path.display())]
+    InFile {
+        path: std::path::PathBuf,
+        error: Box<SvagError>,
+    },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
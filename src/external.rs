@@ -0,0 +1,261 @@
+//! External references.
+//!
+//! Every URL a document points to outside itself: links, images, `<use>`
+//! and filter sources in other files, `url()`s and `@import`s in styles,
+//! and stylesheets linked before the root. A file is self-contained when
+//! [`external_references`] comes back empty. References to ids in the
+//! document (`#name`) and embedded `data:` URIs don't count.
+//!
+//! ```
+//! let doc = svag::parse_svg(r##"<svg><use href="#a"/><image href="photo.jpg"/><rect style="filter:url(f.svg#blur)"/></svg>"##).unwrap();
+//! let urls: Vec<String> = svag::external_references(&doc).into_iter().map(|r| r.url).collect();
+//! assert_eq!(urls, ["photo.jpg", "f.svg#blur"]);
+//! ```
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::css::css_urls;
+use crate::data_uri::parse_data_uri;
+use crate::xml_stylesheet::pseudo_attributes;
+use crate::{Document, Element, Node};
+
+/// A URL pointing outside the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalRef {
+    /// The URL as written, trimmed
+    pub url: String,
+    pub source: RefSource,
+    /// The attribute the URL is in; `None` in stylesheets and processing
+    /// instructions
+    pub attribute: Option<String>,
+    /// Path of the referencing element, see [`Document::element_at`];
+    /// `None` for processing instructions before the root
+    pub element: Option<Vec<usize>>,
+}
+
+/// Where an [`ExternalRef`] is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RefSource {
+    /// An `href`, `xlink:href`, or `src` attribute
+    Href,
+    /// A CSS `url()`, in a `style` attribute, a presentation attribute
+    /// like `filter` or `fill`, or a `<style>` element
+    CssUrl,
+    /// An `@import` in a `<style>` element
+    Import,
+    /// An `<?xml-stylesheet?>` processing instruction
+    XmlStylesheet,
+}
+
+/// The file a relative `url` names in `dir`, for loading what a document
+/// links to from next to it. `None` for URLs with a scheme, absolute paths,
+/// and paths that lead outside `dir` (with `..`, or through a symbolic
+/// link), which an untrusted document could otherwise use to read any file,
+/// and for files that don't exist.
+///
+/// ```
+/// let dir = std::env::current_dir().unwrap();
+/// assert!(svag::local_path(&dir, "Cargo.toml").is_some());
+/// assert!(svag::local_path(&dir, "../Cargo.toml").is_none());
+/// assert!(svag::local_path(&dir, "/etc/passwd").is_none());
+/// assert!(svag::local_path(&dir, "https://example.com/a.svg").is_none());
+/// ```
+pub fn local_path(dir: &Path, url: &str) -> Option<PathBuf> {
+    let relative = Path::new(url);
+    if url.is_empty()
+        || url.contains(':')
+        || url.starts_with(['/', '\\'])
+        || relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    let dir = dir.canonicalize().ok()?;
+    let path = dir.join(relative).canonicalize().ok()?;
+    path.starts_with(&dir).then_some(path)
+}
+
+/// List every URL in `doc` that points outside it, in document order.
+pub fn external_references(doc: &Document) -> Vec<ExternalRef> {
+    let mut refs = Vec::new();
+    for_each_url(doc, |url, source, attribute, element| {
+        if is_external(url) {
+            refs.push(ExternalRef {
+                url: url.to_string(),
+                source,
+                attribute: attribute.map(str::to_string),
+                element: element.map(<[usize]>::to_vec),
+            });
+        }
+    });
+    refs
+}
+
+/// Whether `url` points outside the document: not empty, not a fragment,
+/// and not a `data:` or `javascript:` URI.
+pub(crate) fn is_external(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with('#') && parse_data_uri(url).is_none() && !is_javascript(url)
+}
+
+/// Whether `url` uses the `javascript:` scheme, which browsers match
+/// ignoring case and any whitespace or control characters inside it.
+pub(crate) fn is_javascript(url: &str) -> bool {
+    let scheme: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take("javascript:".len())
+        .collect();
+    scheme.eq_ignore_ascii_case("javascript:")
+}
+
+/// Call `f` with every URL in `doc`, trimmed, with where it's written, the
+/// attribute it's in, and the path of its element.
+pub(crate) fn for_each_url(
+    doc: &Document,
+    mut f: impl FnMut(&str, RefSource, Option<&str>, Option<&[usize]>),
+) {
+    for node in &doc.prolog {
+        if let Node::ProcessingInstruction { target, content } = node
+            && target == "xml-stylesheet"
+        {
+            let attrs = content.as_deref().and_then(pseudo_attributes);
+            for (_, href) in attrs.iter().flatten().filter(|(name, _)| name == "href") {
+                f(href.trim(), RefSource::XmlStylesheet, None, None);
+            }
+        }
+    }
+    visit(&doc.root, &mut Vec::new(), &mut f);
+}
+
+fn visit(
+    elem: &Element,
+    path: &mut Vec<usize>,
+    f: &mut impl FnMut(&str, RefSource, Option<&str>, Option<&[usize]>),
+) {
+    for attr in &elem.attributes {
+        let name = attr.name.full_name();
+        if attr.name.local == "href" || attr.name.local == "src" {
+            f(attr.value.trim(), RefSource::Href, Some(&name), Some(path));
+        } else if !attr.name.is_xmlns() {
+            for url in css_urls(&attr.value) {
+                f(url.url.trim(), RefSource::CssUrl, Some(&name), Some(path));
+            }
+        }
+    }
+
+    if elem.is("style") {
+        for child in &elem.children {
+            if let Node::Text(css) | Node::CData(css) = child {
+                stylesheet_urls(css, |url, source| f(url, source, None, Some(path)));
+            }
+        }
+    }
+
+    for (i, child) in elem.children.iter().enumerate() {
+        if let Node::Element(child) = child {
+            path.push(i);
+            visit(child, path, f);
+            path.pop();
+        }
+    }
+}
+
+/// The `url()`s and `@import`s in a stylesheet, in order.
+fn stylesheet_urls(css: &str, mut f: impl FnMut(&str, RefSource)) {
+    // The source of the `url()` starting at `start`
+    let source = |start: usize| {
+        let before = css[..start].trim_end().as_bytes();
+        if before.len() >= 7 && before[before.len() - 7..].eq_ignore_ascii_case(b"@import") {
+            RefSource::Import
+        } else {
+            RefSource::CssUrl
+        }
+    };
+
+    let mut urls = css_urls(css).into_iter().peekable();
+    let lower = css.to_ascii_lowercase();
+    for (at, _) in lower.match_indices("@import") {
+        let rest = css[at + "@import".len()..].trim_start();
+        // `@import "…"`; an `@import url(…)` is found with the other `url()`s
+        let Some(quote) = rest.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            continue;
+        };
+        let Some((url, _)) = rest[1..].split_once(quote) else {
+            continue;
+        };
+        while let Some(css_url) = urls.next_if(|u| u.token.start < at) {
+            f(css_url.url.trim(), source(css_url.token.start));
+        }
+        f(url.trim(), RefSource::Import);
+    }
+    for css_url in urls {
+        f(css_url.url.trim(), source(css_url.token.start));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_external_references() {
+        let svg = r##"<?xml-stylesheet href="theme.css"?>
+<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+    <style>@import url(a.css); @IMPORT "b.css"; .x { fill: url(#g); background: url( "c.png" ) }</style>
+    <a href="https://example.com/"><use xlink:href="icons.svg#arrow"/></a>
+    <filter id="f"><feImage href="texture.png"/></filter>
+    <rect filter="url(filters.svg#blur)" fill="url(#g)" style="mask:url(m.svg#m)"/>
+    <image href="data:image/png;base64,AA=="/>
+    <a href="javascript:void(0)"/>
+</svg>"##;
+        let refs = external_references(&parse_svg(svg).unwrap());
+        let found: Vec<(&str, RefSource, Option<&str>)> = refs
+            .iter()
+            .map(|r| (r.url.as_str(), r.source, r.attribute.as_deref()))
+            .collect();
+        use RefSource::*;
+        assert_eq!(
+            found,
+            [
+                ("theme.css", XmlStylesheet, None),
+                ("a.css", Import, None),
+                ("b.css", Import, None),
+                ("c.png", CssUrl, None),
+                ("https://example.com/", Href, Some("href")),
+                ("icons.svg#arrow", Href, Some("xlink:href")),
+                ("texture.png", Href, Some("href")),
+                ("filters.svg#blur", CssUrl, Some("filter")),
+                ("m.svg#m", CssUrl, Some("style")),
+            ]
+        );
+        assert_eq!(refs[0].element, None);
+        assert_eq!(refs[5].element, Some(vec![2, 0]));
+        assert_eq!(refs[6].element, Some(vec![4, 0]));
+
+        let svg = r##"<svg><use href="#a"/><path id="a" fill="url(#g)"/></svg>"##;
+        assert!(external_references(&parse_svg(svg).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_local_path() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("icons");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.css"), "").unwrap();
+        std::fs::write(root.path().join("secret"), "").unwrap();
+
+        assert!(local_path(&dir, "sub/a.css").is_some());
+        assert!(local_path(&dir, "./sub/../sub/a.css").is_none());
+        assert!(local_path(&dir, "../secret").is_none());
+        assert!(local_path(&dir, &root.path().join("secret").to_string_lossy()).is_none());
+        assert!(local_path(&dir, "missing.css").is_none());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.path().join("secret"), dir.join("link")).unwrap();
+            assert!(local_path(&dir, "link").is_none());
+        }
+    }
+}
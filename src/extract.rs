@@ -0,0 +1,241 @@
+//! Extracting part of a document as a document of its own.
+//!
+//! "Export just this layer": an element is copied out with what it needs
+//! to render the same way alone. Its ancestors come along without their
+//! other children, for their transforms and inherited styles, and so do
+//! the stylesheets and everything it references, directly or through
+//! other references (a gradient's `href`, a clip path's own `clip-path`).
+
+use std::collections::BTreeSet;
+
+use crate::ast::*;
+use crate::bounds::{has_dynamic_content, length};
+use crate::crop::{NON_RENDERING, content_bounds, set_view_box, viewport};
+use crate::error::SvagError;
+use crate::geometry::Rect;
+use crate::glob::Selector;
+use crate::index::{DocumentIndex, ElementId};
+use crate::merge::VIEWPORT;
+use crate::path::format_number;
+
+/// Decimals kept in the fitted `viewBox`, which is rounded outward.
+const PRECISION: u8 = 3;
+
+/// Copy the element with id `selector_or_id`, or else the first one
+/// matching it as a [`Selector`], into a standalone document, with the
+/// definitions it transitively references (in a `<defs>`), the
+/// document's stylesheets, and its ancestors (unless one doesn't render,
+/// like `<defs>`) holding only it.
+///
+/// The `viewBox` is fitted to what the element paints, and `width` and
+/// `height` scaled to keep the drawing the same size. When that's not
+/// known, as with text, the original viewport is kept.
+///
+/// ```
+/// let doc = svag::parse_svg(r##"<svg viewBox="0 0 100 100" width="200" height="200">
+///   <linearGradient id="base"><stop stop-color="red"/></linearGradient>
+///   <linearGradient id="fade" href="#base"/>
+///   <g id="background"><rect width="100" height="100"/></g>
+///   <g id="logo" transform="translate(10 10)"><circle cx="20" cy="20" r="20" fill="url(#fade)"/></g>
+/// </svg>"##).unwrap();
+/// let logo = svag::extract_subtree(&doc, "#logo").unwrap();
+/// assert_eq!(
+///     svag::serialize(&logo, &svag::Options::default()),
+///     r##"<svg height="80" viewBox="10 10 40 40" width="80"><defs><linearGradient id="base"><stop stop-color="red"/></linearGradient><linearGradient href="#base" id="fade"/></defs><g id="logo" transform="translate(10 10)"><circle cx="20" cy="20" fill="url(#fade)" r="20"/></g></svg>"##
+/// );
+/// ```
+pub fn extract_subtree<'a>(
+    doc: &'a Document<'a>,
+    selector_or_id: &str,
+) -> Result<Document<'a>, SvagError> {
+    let index = DocumentIndex::new(doc);
+    let target = match index.by_id(selector_or_id.trim_start_matches('#')) {
+        Some(target) => target,
+        None => {
+            let selector: Selector = selector_or_id.parse()?;
+            index
+                .iter()
+                .skip(1)
+                .find(|(_, elem)| selector.matches(elem))
+                .map(|(id, _)| id)
+                .ok_or_else(|| SvagError::NoMatch(selector_or_id.to_string()))?
+        }
+    };
+    if target == index.root() {
+        return Ok(doc.clone());
+    }
+
+    let within = |id: ElementId, of: ElementId| id == of || index.ancestors(id).any(|a| a == of);
+    // Innermost first
+    let mut chain: Vec<ElementId> = index
+        .ancestors(target)
+        .filter(|&a| a != index.root())
+        .collect();
+    if chain
+        .iter()
+        .any(|&a| NON_RENDERING.contains(&index.get(a).name.local.as_str()))
+    {
+        chain.clear();
+    }
+    let kept = |id: ElementId| id == index.root() || chain.contains(&id) || within(id, target);
+
+    // What's kept, then what that references, until nothing new turns up
+    let mut pending: Vec<ElementId> = index
+        .iter()
+        .filter(|&(id, elem)| kept(id) || elem.is("style"))
+        .map(|(id, _)| id)
+        .collect();
+    let mut copied = BTreeSet::new();
+    while let Some(id) = pending.pop() {
+        for reference in index.references(id) {
+            let Some(referenced) = index.by_id(reference) else {
+                continue;
+            };
+            if kept(referenced) || !copied.insert(referenced) {
+                continue;
+            }
+            pending.extend(
+                index
+                    .iter()
+                    .map(|(id, _)| id)
+                    .filter(|&id| within(id, referenced)),
+            );
+        }
+    }
+    let copied_within = |id: ElementId| copied.iter().any(|&c| within(id, c));
+
+    let mut root = Element {
+        name: doc.root.name.clone(),
+        attributes: doc
+            .root
+            .attributes
+            .iter()
+            .filter(|a| a.name.prefix.is_some() || !VIEWPORT.contains(&a.name.local.as_str()))
+            .cloned()
+            .collect(),
+        children: Vec::new(),
+    };
+    for (id, elem) in index.iter() {
+        if elem.is("style") && !kept(id) && !copied_within(id) {
+            root.append_child(elem.clone());
+        }
+    }
+    let mut defs = Element::new("defs");
+    for &id in &copied {
+        let nested = index.ancestors(id).any(|a| copied.contains(&a));
+        if !nested {
+            defs.append_child(index.get(id).clone());
+        }
+    }
+    if !defs.children.is_empty() {
+        root.append_child(defs);
+    }
+    let mut content = index.get(target).clone();
+    for &ancestor in &chain {
+        let ancestor = index.get(ancestor);
+        content = Element {
+            name: ancestor.name.clone(),
+            attributes: ancestor.attributes.clone(),
+            children: vec![Node::Element(content)],
+        };
+    }
+    root.append_child(content);
+
+    let mut out = Document {
+        root,
+        ..doc.clone()
+    };
+    let bounds = if has_dynamic_content(&out) {
+        None
+    } else {
+        content_bounds(&out.root)
+    };
+    match bounds.zip(viewport(&doc.root)) {
+        Some((bounds, viewport)) => fit(&mut out.root, &doc.root, &bounds, &viewport),
+        None => {
+            for name in ["width", "height", "viewBox"] {
+                if let Some(value) = doc.root.get_attr(name) {
+                    out.root.set_attr(name, value);
+                }
+            }
+        }
+    }
+    if let Some(value) = doc.root.get_attr("preserveAspectRatio") {
+        out.root.set_attr("preserveAspectRatio", value);
+    }
+    Ok(out)
+}
+
+/// Set `root`'s `viewBox` to `bounds`, rounded outward, and its size to
+/// what that part of `original`'s `viewport` measured.
+fn fit(root: &mut Element, original: &Element, bounds: &Rect, viewport: &Rect) {
+    let step = 10f64.powi(-i32::from(PRECISION));
+    let fitted = Rect {
+        min_x: (bounds.min_x / step).floor() * step,
+        min_y: (bounds.min_y / step).floor() * step,
+        max_x: (bounds.max_x / step).ceil() * step,
+        max_y: (bounds.max_y / step).ceil() * step,
+    };
+    for (name, old, new) in [
+        ("width", viewport.width(), fitted.width()),
+        ("height", viewport.height(), fitted.height()),
+    ] {
+        if let Some(size) = original.get_attr(name).and_then(length) {
+            root.set_attr(name, format_number(size * new / old, PRECISION));
+        }
+    }
+    set_view_box(root, &fitted, PRECISION);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    #[test]
+    fn test_extract_subtree() {
+        let doc = parse_svg(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" viewBox="0 0 100 100" fill="blue">
+  <defs><clipPath id="c" clip-path="url(#d)"><rect width="50" height="50"/></clipPath><clipPath id="d"><circle r="40"/></clipPath><path id="unused"/></defs>
+  <g transform="scale(2)" opacity=".5">
+    <g inkscape:label="Roads" clip-path="url(#c)"><path d="M0 0h20v10" stroke="gray"/></g>
+    <g inkscape:label="Rivers"/>
+  </g>
+  <text id="label" x="5" y="5">Hi</text>
+</svg>"##,
+        )
+        .unwrap();
+
+        let roads = extract_subtree(&doc, "g[inkscape:label=Roads]").unwrap();
+        assert_eq!(
+            serialize(&roads, &Options::default()),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" fill="blue" viewBox="-4 -4 48 28"><defs><clipPath clip-path="url(#d)" id="c"><rect height="50" width="50"/></clipPath><clipPath id="d"><circle r="40"/></clipPath></defs><g opacity=".5" transform="scale(2)"><g clip-path="url(#c)" inkscape:label="Roads"><path d="M0 0h20v10" stroke="gray"/></g></g></svg>"##
+        );
+
+        // Text has no known bounds, and keeps the original viewport
+        let label = extract_subtree(&doc, "label").unwrap();
+        assert_eq!(
+            serialize(&label, &Options::default()),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" fill="blue" viewBox="0 0 100 100"><text id="label" x="5" y="5">Hi</text></svg>"##
+        );
+
+        // Stylesheets come along, and keep the original viewport too
+        let doc = parse_svg(
+            r#"<svg width="10" height="10"><style>.a{fill:red}</style><g><rect class="a" width="5" height="5"/></g></svg>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            serialize(&extract_subtree(&doc, ".a").unwrap(), &Options::default()),
+            r#"<svg height="10" width="10"><style>.a{fill:red}</style><g><rect class="a" height="5" width="5"/></g></svg>"#
+        );
+
+        assert!(matches!(
+            extract_subtree(&doc, "#missing"),
+            Err(SvagError::NoMatch(_))
+        ));
+        assert!(matches!(
+            extract_subtree(&doc, "g["),
+            Err(SvagError::InvalidPattern(_))
+        ));
+    }
+}
@@ -0,0 +1,488 @@
+//! `<filter>` cleanup.
+//!
+//! Exported drop shadows and glows come with a generic filter graph: every
+//! primitive gets a `result` name whether anything reads it or not, inputs
+//! spell out the previous primitive, and whole branches feed nothing. This
+//! pass prunes primitives the filter's output doesn't depend on, drops
+//! `in`/`result` names the implicit chaining already implies, rounds
+//! numeric attributes, and removes filters that leave their element as it
+//! is.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::bounds::{
+    has_dynamic_content, local_painted_bounds, paints_nothing, property, shape_bounds,
+};
+use crate::clip::{inherited_at, remove_property, url_fragment};
+use crate::geometry::Rect;
+use crate::index::{DocumentIndex, referenced_ids};
+use crate::path::format_number;
+
+/// Primitives that take no input.
+const SOURCES: &[&str] = &["feFlood", "feImage", "feTurbulence"];
+
+/// Primitives that take a second input, `in2`.
+const BINARY: &[&str] = &["feBlend", "feComposite", "feDisplacementMap"];
+
+const PRIMITIVES: &[&str] = &[
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDropShadow",
+    "feFlood",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMorphology",
+    "feOffset",
+    "feSpecularLighting",
+    "feTile",
+    "feTurbulence",
+];
+
+/// Inputs every filter has, which can't be produced by a primitive.
+const STANDARD_INPUTS: &[&str] = &[
+    "SourceGraphic",
+    "SourceAlpha",
+    "BackgroundImage",
+    "BackgroundAlpha",
+    "FillPaint",
+    "StrokePaint",
+];
+
+/// Numeric attributes in the filter's coordinate system, rounded to
+/// `precision`.
+const LENGTHS: &[&str] = &[
+    "x",
+    "y",
+    "z",
+    "width",
+    "height",
+    "dx",
+    "dy",
+    "stdDeviation",
+    "radius",
+    "scale",
+    "surfaceScale",
+    "pointsAtX",
+    "pointsAtY",
+    "pointsAtZ",
+];
+
+/// Unitless coefficients, rounded to `transform_precision` like matrix
+/// coefficients.
+const COEFFICIENTS: &[&str] = &[
+    "values",
+    "tableValues",
+    "kernelMatrix",
+    "k1",
+    "k2",
+    "k3",
+    "k4",
+    "slope",
+    "intercept",
+    "amplitude",
+    "exponent",
+    "offset",
+    "baseFrequency",
+    "divisor",
+    "bias",
+    "specularConstant",
+    "specularExponent",
+    "diffuseConstant",
+    "azimuth",
+    "elevation",
+    "limitingConeAngle",
+];
+
+/// Simplify every `<filter>`, and drop references to the ones that do
+/// nothing.
+pub(crate) fn optimize_filters(doc: &mut Document, precision: u8, transform_precision: u8) {
+    // Scripts and animations can rewire a filter graph
+    let dynamic = has_dynamic_content(doc);
+    let mut has_identity = false;
+    doc.for_each_element_mut(|elem| {
+        if !elem.is("filter") {
+            return;
+        }
+        if !dynamic {
+            prune_graph(elem);
+        }
+        round_numbers(elem, precision, transform_precision);
+        has_identity |= is_identity(elem);
+    });
+
+    if has_identity && !dynamic {
+        remove_identity_filters(doc);
+    }
+}
+
+/// Where a primitive's input comes from.
+#[derive(Clone, Copy, PartialEq)]
+enum Input<'n> {
+    /// A standard input like `SourceGraphic`
+    Standard(&'n str),
+    /// The primitive at this position
+    Primitive(usize),
+}
+
+/// The graph of a filter's primitives, in order.
+struct Graph<'a> {
+    primitives: Vec<&'a Element<'a>>,
+}
+
+impl<'a> Graph<'a> {
+    /// The graph of `filter`, if it's made of known primitives with inputs
+    /// that resolve.
+    fn new(filter: &'a Element<'a>) -> Option<Self> {
+        if filter.get_attr("href").is_some() || filter.get_attr("xlink:href").is_some() {
+            return None;
+        }
+        let mut primitives = Vec::new();
+        for child in filter.child_elements() {
+            if PRIMITIVES.contains(&child.name.local.as_str()) && child.name.prefix.is_none() {
+                primitives.push(child);
+            } else if !paints_nothing(child) {
+                return None;
+            }
+        }
+        let graph = Graph { primitives };
+
+        // Results named like standard inputs would shadow them
+        let shadowed = graph.primitives.iter().any(|p| {
+            p.get_attr("result")
+                .is_some_and(|r| STANDARD_INPUTS.contains(&r))
+        });
+        let resolves = (0..graph.primitives.len()).all(|i| {
+            graph
+                .input_names(i)
+                .iter()
+                .all(|&name| graph.resolve(i, name).is_some())
+        });
+        (!shadowed && resolves).then_some(graph)
+    }
+
+    /// The `in`, `in2`, and `<feMergeNode in>` values of primitive `i`.
+    fn input_names(&self, i: usize) -> Vec<Option<&'a str>> {
+        let primitive = self.primitives[i];
+        let name = primitive.name.local.as_str();
+        if name == "feMerge" {
+            return primitive
+                .child_elements()
+                .filter(|node| node.is("feMergeNode"))
+                .map(|node| node.get_attr("in"))
+                .collect();
+        }
+        if SOURCES.contains(&name) {
+            return Vec::new();
+        }
+        let mut names = vec![primitive.get_attr("in")];
+        if BINARY.contains(&name) {
+            names.push(primitive.get_attr("in2"));
+        }
+        names
+    }
+
+    /// What input `name` of primitive `i` reads: unset, it's the previous
+    /// primitive's result (or `SourceGraphic` for the first), otherwise the
+    /// latest earlier primitive with that `result`.
+    fn resolve<'n>(&self, i: usize, name: Option<&'n str>) -> Option<Input<'n>> {
+        match name.map(str::trim).filter(|n| !n.is_empty()) {
+            None if i == 0 => Some(Input::Standard("SourceGraphic")),
+            None => Some(Input::Primitive(i - 1)),
+            Some(name) if STANDARD_INPUTS.contains(&name) => Some(Input::Standard(name)),
+            Some(name) => (0..i)
+                .rev()
+                .find(|&j| self.primitives[j].get_attr("result") == Some(name))
+                .map(Input::Primitive),
+        }
+    }
+
+    /// Which primitives the filter's output depends on.
+    fn live(&self) -> Vec<bool> {
+        let mut live = vec![false; self.primitives.len()];
+        let mut pending: Vec<usize> = self.primitives.len().checked_sub(1).into_iter().collect();
+        while let Some(i) = pending.pop() {
+            if std::mem::replace(&mut live[i], true) {
+                continue;
+            }
+            for name in self.input_names(i) {
+                if let Some(Input::Primitive(j)) = self.resolve(i, name) {
+                    pending.push(j);
+                }
+            }
+        }
+        live
+    }
+}
+
+/// Remove the primitives `filter`'s output doesn't depend on, then the
+/// `in` and `result` names that implicit chaining makes unnecessary.
+fn prune_graph(filter: &mut Element) {
+    let Some(graph) = Graph::new(filter) else {
+        return;
+    };
+    let live = graph.live();
+    let mut is_live = live.into_iter();
+    filter.children.retain(|node| match node {
+        Node::Element(e) if PRIMITIVES.contains(&e.name.local.as_str()) => {
+            is_live.next().unwrap_or(true)
+        }
+        _ => true,
+    });
+
+    // An input naming the previous primitive's result is the default
+    let Some(graph) = Graph::new(filter) else {
+        return;
+    };
+    let implicit: Vec<bool> = (0..graph.primitives.len())
+        .map(|i| {
+            let primitive = graph.primitives[i];
+            let name = primitive.get_attr("in");
+            let reads_in =
+                !primitive.is("feMerge") && !SOURCES.contains(&primitive.name.local.as_str());
+            reads_in && name.is_some() && graph.resolve(i, name) == graph.resolve(i, None)
+        })
+        .collect();
+    let mut implicit = implicit.into_iter();
+    for primitive in filter.child_elements_mut() {
+        if PRIMITIVES.contains(&primitive.name.local.as_str()) && implicit.next() == Some(true) {
+            primitive.remove_attr("in");
+        }
+    }
+
+    let Some(graph) = Graph::new(filter) else {
+        return;
+    };
+    let read: HashSet<String> = (0..graph.primitives.len())
+        .flat_map(|i| graph.input_names(i))
+        .flatten()
+        .map(|name| name.trim().to_string())
+        .collect();
+    for primitive in filter.child_elements_mut() {
+        if primitive
+            .get_attr("result")
+            .is_some_and(|r| !read.contains(r))
+        {
+            primitive.remove_attr("result");
+        }
+    }
+}
+
+/// Round the numeric attributes of `filter`'s primitives and light sources.
+fn round_numbers(filter: &mut Element, precision: u8, transform_precision: u8) {
+    // In bounding box units, lengths are fractions of the element's size
+    let bbox_units = filter.get_attr("primitiveUnits") == Some("objectBoundingBox");
+    fn visit(elem: &mut Element, precision: Option<u8>, transform_precision: u8) {
+        for attr in &mut elem.attributes {
+            if attr.name.prefix.is_some() {
+                continue;
+            }
+            let name = attr.name.local.as_str();
+            let precision = if COEFFICIENTS.contains(&name) {
+                transform_precision
+            } else if let Some(precision) = precision
+                && LENGTHS.contains(&name)
+            {
+                precision
+            } else {
+                continue;
+            };
+            if let Some(rounded) = round_list(&attr.value, precision) {
+                attr.value = rounded.into();
+            }
+        }
+        for child in elem.child_elements_mut() {
+            visit(child, precision, transform_precision);
+        }
+    }
+    for child in filter.child_elements_mut() {
+        visit(
+            child,
+            (!bbox_units).then_some(precision),
+            transform_precision,
+        );
+    }
+}
+
+/// A list of plain numbers, rounded and space-separated. `None` if any item
+/// isn't a plain number (a percentage, a keyword).
+fn round_list(value: &str, precision: u8) -> Option<String> {
+    let numbers: Vec<f64> = value
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<_>>()?;
+    (!numbers.is_empty()).then(|| {
+        numbers
+            .iter()
+            .map(|&n| format_number(n, precision))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+/// Remove `filter` references to filters that only offset by zero, where
+/// the default filter region holds everything the element paints, and the
+/// filters nothing references anymore.
+fn remove_identity_filters(doc: &mut Document) {
+    let index = DocumentIndex::new(doc);
+    let mut noops: Vec<Vec<usize>> = Vec::new();
+    let mut filters: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (_, elem) in index.iter() {
+        let Some(filter_id) = elem.get_attr("id").filter(|_| elem.is("filter")) else {
+            continue;
+        };
+        if !is_identity(elem) {
+            continue;
+        }
+        for &referrer in index.referrers(filter_id) {
+            let target = index.get(referrer);
+            let references = property(target, "filter").and_then(url_fragment);
+            if references != Some(filter_id) {
+                continue;
+            }
+            let inherited = inherited_at(&index, referrer);
+            let mut unfiltered = target.clone();
+            remove_property(&mut unfiltered, "filter");
+            let fits = local_painted_bounds(&unfiltered, &inherited)
+                .zip(geometry_bounds(target))
+                .is_some_and(|(painted, bbox)| default_region(&bbox).contains(&painted));
+            if fits && let Some(filter) = index.by_id(filter_id) {
+                noops.push(index.path(referrer));
+                filters
+                    .entry(filter_id)
+                    .or_insert_with(|| index.path(filter));
+            }
+        }
+    }
+    // Dropping references leaves the tree as it is, so the paths hold
+    let filters: Vec<(String, Vec<usize>)> = filters
+        .into_iter()
+        .map(|(id, path)| (id.to_string(), path))
+        .collect();
+
+    for path in &noops {
+        if let Some(elem) = doc.element_at_mut(path) {
+            remove_property(elem, "filter");
+        }
+    }
+
+    let referenced = referenced_ids(doc);
+    let orphans: Vec<Vec<usize>> = filters
+        .into_iter()
+        .filter(|(id, _)| !referenced.contains(id))
+        .map(|(_, path)| path)
+        .collect();
+    doc.remove_elements_at(orphans);
+}
+
+/// Whether `filter` outputs its `SourceGraphic` unchanged, within its
+/// default region: a lone `feOffset` by zero.
+fn is_identity(filter: &Element) -> bool {
+    const REGION: &[&str] = &["x", "y", "width", "height"];
+    let default_region = REGION.iter().all(|a| filter.get_attr(a).is_none())
+        && filter
+            .get_attr("filterUnits")
+            .is_none_or(|u| u == "objectBoundingBox");
+    let Some(graph) = Graph::new(filter) else {
+        return false;
+    };
+    let [offset] = graph.primitives[..] else {
+        return false;
+    };
+    let zero = |name| {
+        offset
+            .get_attr(name)
+            .is_none_or(|v| v.trim().parse() == Ok(0.0))
+    };
+    default_region
+        && offset.is("feOffset")
+        && offset
+            .get_attr("in")
+            .is_none_or(|i| i.trim() == "SourceGraphic")
+        && REGION.iter().all(|a| offset.get_attr(a).is_none())
+        && zero("dx")
+        && zero("dy")
+}
+
+/// The default filter region: the bounding box grown by 10% on each side.
+fn default_region(bbox: &Rect) -> Rect {
+    let (dx, dy) = (bbox.width() * 0.1, bbox.height() * 0.1);
+    Rect {
+        min_x: bbox.min_x - dx,
+        min_y: bbox.min_y - dy,
+        max_x: bbox.max_x + dx,
+        max_y: bbox.max_y + dy,
+    }
+}
+
+/// The geometry bounding box of a shape or a group of shapes, without
+/// stroke. `None` when unknown, e.g. through a transform.
+fn geometry_bounds(elem: &Element) -> Option<Rect> {
+    if !elem.is("g") {
+        return shape_bounds(elem);
+    }
+    let mut bounds: Option<Rect> = None;
+    for child in elem.child_elements() {
+        if paints_nothing(child) {
+            continue;
+        }
+        if child.get_attr("transform").is_some() {
+            return None;
+        }
+        let child_bounds = geometry_bounds(child)?;
+        bounds = Some(bounds.map_or(child_bounds, |b| b.union(&child_bounds)));
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn optimized(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        optimize_filters(&mut doc, 2, 5);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_prune_filter_graph() {
+        // A drop shadow with a dead branch and spelled-out chaining
+        assert_eq!(
+            optimized(
+                r#"<svg><filter id="s"><feFlood flood-color="red" result="unused"/><feGaussianBlur in="SourceAlpha" stdDeviation="2.00001" result="blur"/><feOffset in="blur" dx="1.234" dy="1" result="offset"/><feColorMatrix values="1 0 0 0 0, 0 1 0 0 0 0 0 1 0 0 0 0 0 .333333 0" result="shadow"/><feMerge><feMergeNode in="shadow"/><feMergeNode in="SourceGraphic"/></feMerge></filter></svg>"#
+            ),
+            r#"<svg><filter id="s"><feGaussianBlur in="SourceAlpha" stdDeviation="2"/><feOffset dx="1.23" dy="1"/><feColorMatrix result="shadow" values="1 0 0 0 0 0 1 0 0 0 0 0 1 0 0 0 0 0 .33333 0"/><feMerge><feMergeNode in="shadow"/><feMergeNode in="SourceGraphic"/></feMerge></filter></svg>"#
+        );
+
+        // Unresolved references leave the graph alone
+        let svg =
+            r#"<svg><filter id="f"><feFlood result="a"/><feOffset in="missing"/></filter></svg>"#;
+        assert_eq!(optimized(svg), svg);
+    }
+
+    #[test]
+    fn test_remove_identity_filters() {
+        let svg = r##"<svg><filter id="f"><feGaussianBlur stdDeviation="3" result="dead"/><feOffset in="SourceGraphic" dx="0"/></filter><rect width="10" height="10" filter="url(#f)"/><rect width="10" height="10" stroke="#000" stroke-width="4" filter="url(#f)"/></svg>"##;
+        assert_eq!(
+            optimized(svg),
+            r##"<svg><filter id="f"><feOffset dx="0"/></filter><rect height="10" width="10"/><rect filter="url(#f)" height="10" stroke="#000" stroke-width="4" width="10"/></svg>"##
+        );
+
+        // Filters nothing references anymore go too
+        assert_eq!(
+            optimized(
+                r##"<svg><filter id="f"><feOffset/></filter><g filter="url(#f)"><rect width="10" height="10"/></g></svg>"##
+            ),
+            r#"<svg><g><rect height="10" width="10"/></g></svg>"#
+        );
+    }
+}
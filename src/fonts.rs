@@ -11,13 +11,16 @@
 //!
 //! ## Example: Embedding subsetted fonts
 //!
-//! ```ignore
-//! use svag::{parse_svg, serialize, extract_text_chars, extract_font_faces, replace_font_url};
+//! ```no_run
+//! # #[cfg(feature = "subset")]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use svag::{parse_svg, serialize, extract_text_chars, extract_font_faces, replace_font_url, Options};
 //! use fontcull::subset_font_to_woff2;
 //! use base64::Engine;
 //!
 //! // 1. Parse the SVG
-//! let mut doc = parse_svg(svg_content)?;
+//! let svg_content = std::fs::read_to_string("diagram.svg")?;
+//! let mut doc = parse_svg(&svg_content)?;
 //!
 //! // 2. Find which characters are used in <text> elements
 //! let chars = extract_text_chars(&doc);
@@ -26,7 +29,7 @@
 //! for face in extract_font_faces(&doc) {
 //!     // 4. Load and subset the font
 //!     let font_data = std::fs::read(&face.url)?;
-//!     let subsetted = subset_font_to_woff2(&font_data, &chars)?;
+//!     let subsetted = subset_font_to_woff2(&font_data, &chars, &[])?;
 //!
 //!     // 5. Embed as data URL
 //!     let encoded = base64::prelude::BASE64_STANDARD.encode(&subsetted);
@@ -36,58 +39,197 @@
 //!
 //! // 6. Serialize back to SVG
 //! let result = serialize(&doc, &Options::default());
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "subset"))]
+//! # fn main() {}
+//! ```
+//!
+//! ## Built-in pipeline
+//!
+//! With the `subset` feature enabled, svag runs the whole loop above itself:
+//!
+//! ```no_run
+//! # #[cfg(feature = "subset")]
+//! # fn main() -> Result<(), svag::SvagError> {
+//! # let mut doc = svag::parse_svg("<svg/>")?;
+//! svag::subset_embedded_fonts(&mut doc, &svag::FontResolver::from_dir("assets"))?;
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "subset"))]
+//! # fn main() {}
 //! ```
 //!
 //! ## Salsa Integration
 //!
-//! In a Salsa-based build system, wrap these in tracked queries:
+//! In a Salsa-based build system, wrap these in tracked queries. The body of
+//! one (here without the `#[salsa::tracked]` attribute and database
+//! argument) is just:
+//!
+//! ```
+//! use std::collections::HashSet;
 //!
-//! ```ignore
-//! #[salsa::tracked]
-//! fn svg_text_chars(db: &dyn Db, file: StaticFile) -> HashSet<char> {
-//!     let doc = svag::parse_svg(file.content(db)).unwrap();
+//! fn svg_text_chars(content: &str) -> HashSet<char> {
+//!     let doc = svag::parse_svg(content).unwrap();
 //!     svag::extract_text_chars(&doc)
 //! }
+//! # assert!(svg_text_chars("<svg><text>a</text></svg>").contains(&'a'));
 //! ```
 //!
 //! Salsa memoizes based on input changes - svag functions are pure, so caching
-//! is handled by the caller.
+//! is handled by the caller. Outside Salsa, [`crate::content_hash`],
+//! [`crate::Options::fingerprint`] and [`crate::would_change`] give stable keys
+//! for deciding when to re-minify.
 
 use crate::{Document, Element, Node};
-use std::collections::HashSet;
-
-/// Extract all text content from `<text>` elements in the document
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+#[cfg(feature = "subset")]
+use crate::SvagError;
+#[cfg(feature = "subset")]
+use crate::data_uri::parse_data_uri;
+#[cfg(feature = "subset")]
+use std::io;
+#[cfg(feature = "subset")]
+use std::path::PathBuf;
+
+/// Extract all characters rendered by `<text>` elements in the document.
+///
+/// Walks every text-content descendant (`tspan`, `textPath`, `a`, ...),
+/// follows `<use>` and `<tref>` references, and maps whitespace the way
+/// renderers do, so a font subset built from the result isn't missing glyphs.
 pub fn extract_text_chars(doc: &Document) -> HashSet<char> {
+    let mut ids = HashMap::new();
+    collect_ids(&doc.root, &mut ids);
+
     let mut chars = HashSet::new();
+    let mut visited = HashSet::new();
+    visit_text(&doc.root, false, &ids, &mut visited, &mut chars);
+    chars
+}
 
-    fn visit(elem: &Element, chars: &mut HashSet<char>) {
-        if elem.is("text") || elem.is("tspan") || elem.is("textPath") {
-            for child in &elem.children {
-                if let Node::Text(t) = child {
-                    for c in t.chars() {
-                        chars.insert(c);
-                    }
-                }
+/// How a referenced element's text was collected. The character set is a
+/// union, so a target walked once in a given way adds nothing the second
+/// time; remembering that keeps fan-out `<use>` chains linear and stops
+/// reference cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Walk {
+    /// Through `<use>` outside any `<text>`
+    Use,
+    /// Through `<use>` inside a `<text>`
+    UseInText,
+    /// Through `<tref>`, taking all character data
+    Tref,
+}
+
+fn collect_ids<'a>(elem: &'a Element<'a>, ids: &mut HashMap<&'a str, &'a Element<'a>>) {
+    if let Some(id) = elem.get_attr("id") {
+        ids.entry(id).or_insert(elem);
+    }
+    for child in elem.child_elements() {
+        collect_ids(child, ids);
+    }
+}
+
+fn visit_text<'a>(
+    elem: &'a Element,
+    in_text: bool,
+    ids: &HashMap<&'a str, &'a Element>,
+    visited: &mut HashSet<(&'a str, Walk)>,
+    chars: &mut HashSet<char>,
+) {
+    let in_text = in_text || elem.is("text");
+
+    if in_text {
+        for child in &elem.children {
+            if let Node::Text(t) | Node::CData(t) = child {
+                insert_chars(t, chars);
             }
         }
-        for child in elem.child_elements() {
-            visit(child, chars);
+    }
+
+    // <use> instantiates its target; <tref> (SVG 1.1) renders the
+    // target's character data inside the referencing text.
+    if (elem.is("use") || (in_text && elem.is("tref")))
+        && let Some(id) = elem.get_attr("href").and_then(|h| h.strip_prefix('#'))
+        && let Some(target) = ids.get(id)
+    {
+        let walk = match (elem.is("tref"), in_text) {
+            (true, _) => Walk::Tref,
+            (false, true) => Walk::UseInText,
+            (false, false) => Walk::Use,
+        };
+        if visited.insert((id, walk)) {
+            if walk == Walk::Tref {
+                collect_all_text(target, chars);
+            } else {
+                visit_text(target, in_text, ids, visited, chars);
+            }
         }
     }
 
-    visit(&doc.root, &mut chars);
-    chars
+    for child in elem.child_elements() {
+        visit_text(child, in_text, ids, visited, chars);
+    }
+}
+
+fn collect_all_text(elem: &Element, chars: &mut HashSet<char>) {
+    for child in &elem.children {
+        match child {
+            Node::Text(t) | Node::CData(t) => insert_chars(t, chars),
+            Node::Element(e) => collect_all_text(e, chars),
+            _ => {}
+        }
+    }
+}
+
+/// Newlines and tabs are rendered as spaces under both `xml:space` modes.
+fn insert_chars(text: &str, chars: &mut HashSet<char>) {
+    for c in text.chars() {
+        if c.is_whitespace() && c.is_ascii() {
+            chars.insert(' ');
+        } else {
+            chars.insert(c);
+        }
+    }
 }
 
 /// A parsed @font-face reference
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FontFaceRef {
     pub family: String,
+    /// URL of the first `url()` entry in `sources`
     pub url: String,
+    /// Every entry of the `src` list, in order of preference
+    pub sources: Vec<FontSource>,
     pub weight: Option<String>,
     pub style: Option<String>,
 }
 
+/// One entry of an `@font-face` `src` list.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FontSource {
+    /// `url(...)` with optional `format(...)` and `tech(...)` hints
+    Url {
+        url: String,
+        format: Option<String>,
+        tech: Option<String>,
+    },
+    /// `local(...)`, a font installed on the viewer's system
+    Local(String),
+}
+
+impl FontSource {
+    /// The URL of a `url()` source.
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            FontSource::Url { url, .. } => Some(url),
+            FontSource::Local(_) => None,
+        }
+    }
+}
+
 /// Extract `@font-face` rules from `<style>` elements
 pub fn extract_font_faces(doc: &Document) -> Vec<FontFaceRef> {
     let mut faces = Vec::new();
@@ -96,8 +238,8 @@ pub fn extract_font_faces(doc: &Document) -> Vec<FontFaceRef> {
         if elem.is("style") {
             for child in &elem.children {
                 let css = match child {
-                    Node::Text(t) => t.as_str(),
-                    Node::CData(t) => t.as_str(),
+                    Node::Text(t) => t.as_ref(),
+                    Node::CData(t) => t.as_ref(),
                     _ => continue,
                 };
                 faces.extend(parse_font_faces(css));
@@ -113,60 +255,229 @@ pub fn extract_font_faces(doc: &Document) -> Vec<FontFaceRef> {
 }
 
 /// Replace a font URL in the document's `<style>` elements
+///
+/// Only `url()` sources whose URL is exactly `old_url` are rewritten; the
+/// other entries of the `src` list and their hints are kept.
 pub fn replace_font_url(doc: &mut Document, old_url: &str, new_url: &str) {
-    fn visit(elem: &mut Element, old_url: &str, new_url: &str) {
+    rewrite_font_sources(doc, &mut |sources| {
+        for source in sources {
+            if let FontSource::Url { url, .. } = source
+                && url == old_url
+            {
+                *url = new_url.to_string();
+            }
+        }
+    });
+}
+
+/// Replace one `src` entry (including its hints) in every `@font-face` rule
+pub fn replace_font_source(doc: &mut Document, old: &FontSource, new: &FontSource) {
+    rewrite_font_sources(doc, &mut |sources| {
+        for source in sources {
+            if source == old {
+                *source = new.clone();
+            }
+        }
+    });
+}
+
+/// Run `f` on the `src` list of every `@font-face` rule, re-serializing the
+/// lists it changes.
+fn rewrite_font_sources(doc: &mut Document, f: &mut dyn FnMut(&mut Vec<FontSource>)) {
+    fn visit(elem: &mut Element, f: &mut dyn FnMut(&mut Vec<FontSource>)) {
         if elem.is("style") {
             for child in &mut elem.children {
-                let css = match child {
-                    Node::Text(t) => t,
-                    Node::CData(t) => t,
-                    _ => continue,
-                };
-
-                // Replace url('old') or url("old") or url(old)
-                let patterns = [
-                    format!("url('{}')", old_url),
-                    format!("url(\"{}\")", old_url),
-                    format!("url({})", old_url),
-                ];
-
-                for pattern in &patterns {
-                    if css.contains(pattern) {
-                        *css = css.replace(pattern, &format!("url('{}')", new_url));
-                        return;
-                    }
+                if let Node::Text(css) | Node::CData(css) = child
+                    && let Some(rewritten) = rewrite_css_sources(css, f)
+                {
+                    *css = rewritten.into();
                 }
             }
         }
         for child in elem.child_elements_mut() {
-            visit(child, old_url, new_url);
+            visit(child, f);
+        }
+    }
+
+    visit(&mut doc.root, f);
+}
+
+fn rewrite_css_sources(css: &str, f: &mut dyn FnMut(&mut Vec<FontSource>)) -> Option<String> {
+    let mut edits = Vec::new();
+
+    for block in font_face_blocks(css) {
+        for decl in split_top_level(&css[block.clone()], ';') {
+            let decl = (block.start + decl.start)..(block.start + decl.end);
+            let Some((name, _)) = css[decl.clone()].split_once(':') else {
+                continue;
+            };
+            if name.trim() != "src" {
+                continue;
+            }
+
+            let value = (decl.start + name.len() + 1)..decl.end;
+            let original = parse_src_list(&css[value.clone()]);
+            let mut sources = original.clone();
+            f(&mut sources);
+            if sources != original {
+                edits.push((value, serialize_src_list(&sources)));
+            }
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    let mut out = css.to_string();
+    for (range, replacement) in edits.into_iter().rev() {
+        out.replace_range(range, &replacement);
+    }
+    Some(out)
+}
+
+#[cfg(feature = "subset")]
+type LoadFn = dyn Fn(&str) -> io::Result<Vec<u8>> + Send + Sync;
+
+/// Loads the font files referenced by `@font-face` rules.
+#[cfg(feature = "subset")]
+pub struct FontResolver {
+    load: Box<LoadFn>,
+}
+
+#[cfg(feature = "subset")]
+impl FontResolver {
+    /// Create a resolver from a function mapping a font URL to its bytes.
+    pub fn new(load: impl Fn(&str) -> io::Result<Vec<u8>> + Send + Sync + 'static) -> Self {
+        Self {
+            load: Box::new(load),
         }
     }
 
-    visit(&mut doc.root, old_url, new_url);
+    /// Resolve font URLs as paths relative to `root`. URLs that aren't
+    /// files within it (see [`crate::local_path`]) fail to load.
+    pub fn from_dir(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self::new(move |url| match crate::local_path(&root, url) {
+            Some(path) => std::fs::read(path),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{url}: not a file in {}", root.display()),
+            )),
+        })
+    }
+
+    /// Load a font, decoding `data:` URLs without calling the resolver.
+    fn resolve(&self, url: &str) -> io::Result<Vec<u8>> {
+        if let Some(data) = parse_data_uri(url) {
+            return data
+                .decode()
+                .ok_or_else(|| io::Error::other("invalid data URL"));
+        }
+
+        (self.load)(url)
+    }
+}
+
+/// Subset every `@font-face` font to the characters used in the document
+/// and embed it as a WOFF2 data URL.
+///
+/// Sources are tried in `src` order; the first one that loads and parses is
+/// subsetted and replaces the whole list. Returns the number of font files
+/// that were embedded.
+#[cfg(feature = "subset")]
+pub fn subset_embedded_fonts(
+    doc: &mut Document,
+    resolver: &FontResolver,
+) -> Result<usize, SvagError> {
+    use base64::Engine;
+
+    let chars = extract_text_chars(doc);
+    let mut seen = HashSet::new();
+
+    for face in extract_font_faces(doc) {
+        if !seen.insert(face.sources.clone()) {
+            continue;
+        }
+
+        let mut last_error = None;
+        let mut subsetted = None;
+        for url in face.sources.iter().filter_map(FontSource::url) {
+            let result = resolver
+                .resolve(url)
+                .map_err(|e| e.to_string())
+                .and_then(|data| fontcull::decompress_font(&data).map_err(|e| e.to_string()))
+                .and_then(|data| {
+                    fontcull::subset_font_to_woff2(&data, &chars, &[]).map_err(|e| e.to_string())
+                });
+            match result {
+                Ok(data) => {
+                    subsetted = Some(data);
+                    break;
+                }
+                Err(e) => last_error = Some(format!("{}: {}", url, e)),
+            }
+        }
+
+        let subsetted = subsetted.ok_or_else(|| {
+            SvagError::FontSubset(last_error.unwrap_or_else(|| face.family.clone()))
+        })?;
+
+        let encoded = base64::prelude::BASE64_STANDARD.encode(&subsetted);
+        let embedded = vec![FontSource::Url {
+            url: format!("data:font/woff2;base64,{}", encoded),
+            format: Some("woff2".into()),
+            tech: None,
+        }];
+        rewrite_font_sources(doc, &mut |sources| {
+            if *sources == face.sources {
+                *sources = embedded.clone();
+            }
+        });
+    }
+
+    Ok(seen.len())
 }
 
 fn parse_font_faces(css: &str) -> Vec<FontFaceRef> {
-    let mut faces = Vec::new();
-    let mut remaining = css;
+    font_face_blocks(css)
+        .into_iter()
+        .filter_map(|block| parse_font_face_block(&css[block]))
+        .collect()
+}
+
+/// Byte ranges of the contents of every `@font-face { ... }` block.
+fn font_face_blocks(css: &str) -> Vec<Range<usize>> {
+    font_face_rules(css)
+        .into_iter()
+        .map(|(_, block)| block)
+        .collect()
+}
 
-    while let Some(start) = remaining.find("@font-face") {
-        remaining = &remaining[start + "@font-face".len()..];
+/// Byte ranges of every `@font-face` rule, paired with the range of its
+/// block contents.
+pub(crate) fn font_face_rules(css: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut rules = Vec::new();
+    let mut offset = 0;
 
-        let Some(brace_start) = remaining.find('{') else {
+    while let Some(start) = css[offset..].find("@font-face") {
+        let rule_start = offset + start;
+        offset = rule_start + "@font-face".len();
+
+        let Some(brace_start) = css[offset..].find('{') else {
             break;
         };
-        remaining = &remaining[brace_start + 1..];
+        offset += brace_start + 1;
 
         let mut depth = 1;
-        let mut block_end = 0;
-        for (i, c) in remaining.char_indices() {
+        let mut block_end = None;
+        for (i, c) in css[offset..].char_indices() {
             match c {
                 '{' => depth += 1,
                 '}' => {
                     depth -= 1;
                     if depth == 0 {
-                        block_end = i;
+                        block_end = Some(offset + i);
                         break;
                     }
                 }
@@ -174,69 +485,171 @@ fn parse_font_faces(css: &str) -> Vec<FontFaceRef> {
             }
         }
 
-        if block_end == 0 {
+        let Some(block_end) = block_end else {
             break;
-        }
-
-        let block = &remaining[..block_end];
-        remaining = &remaining[block_end + 1..];
-
-        if let Some(face) = parse_font_face_block(block) {
-            faces.push(face);
-        }
+        };
+        rules.push((rule_start..block_end + 1, offset..block_end));
+        offset = block_end + 1;
     }
 
-    faces
+    rules
 }
 
 fn parse_font_face_block(block: &str) -> Option<FontFaceRef> {
     let mut family = None;
-    let mut url = None;
+    let mut sources = Vec::new();
     let mut weight = None;
     let mut style = None;
 
-    for decl in block.split(';') {
-        let decl = decl.trim();
-        if let Some(v) = decl.strip_prefix("font-family:") {
-            family = Some(parse_value(v));
-        } else if let Some(v) = decl.strip_prefix("src:") {
-            url = parse_url(v);
-        } else if let Some(v) = decl.strip_prefix("font-weight:") {
-            weight = Some(v.trim().to_string());
-        } else if let Some(v) = decl.strip_prefix("font-style:") {
-            style = Some(v.trim().to_string());
+    for decl in split_top_level(block, ';') {
+        let Some((name, v)) = block[decl].split_once(':') else {
+            continue;
+        };
+        match name.trim() {
+            "font-family" => family = Some(parse_value(v)),
+            "src" => sources = parse_src_list(v),
+            "font-weight" => weight = Some(v.trim().to_string()),
+            "font-style" => style = Some(v.trim().to_string()),
+            _ => {}
         }
     }
 
+    let url = sources.iter().find_map(FontSource::url)?.to_string();
+
     Some(FontFaceRef {
         family: family?,
-        url: url?,
+        url,
+        sources,
         weight,
         style,
     })
 }
 
+/// Split on `sep`, ignoring separators inside quotes and parentheses (data
+/// URLs contain `;base64,`).
+pub(crate) fn split_top_level(s: &str, sep: char) -> Vec<Range<usize>> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, c) if c == sep && depth == 0 => {
+                parts.push(start..i);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(start..s.len());
+
+    parts
+}
+
+fn parse_src_list(v: &str) -> Vec<FontSource> {
+    let mut sources = Vec::new();
+
+    for entry in split_top_level(v, ',') {
+        let mut url = None;
+        let mut local = None;
+        let mut format = None;
+        let mut tech = None;
+
+        for (name, arg) in css_functions(&v[entry]) {
+            match name.to_ascii_lowercase().as_str() {
+                "url" => url = Some(arg),
+                "local" => local = Some(arg),
+                "format" => format = Some(arg),
+                "tech" => tech = Some(arg),
+                _ => {}
+            }
+        }
+
+        if let Some(url) = url {
+            sources.push(FontSource::Url { url, format, tech });
+        } else if let Some(name) = local {
+            sources.push(FontSource::Local(name));
+        }
+    }
+
+    sources
+}
+
+/// Parse a sequence of `name(argument)` function tokens, unquoting arguments.
+fn css_functions(s: &str) -> Vec<(&str, String)> {
+    let mut functions = Vec::new();
+    let mut rest = s;
+
+    while let Some(open) = rest.find('(') {
+        let name = rest[..open].trim();
+        let name = name
+            .rsplit(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .next()
+            .unwrap_or(name);
+        let args = &rest[open + 1..];
+
+        let mut quote = None;
+        let mut close = args.len();
+        for (i, c) in args.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, ')') => {
+                    close = i;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        functions.push((name, parse_value_unquoted(&args[..close])));
+        rest = args.get(close + 1..).unwrap_or("");
+    }
+
+    functions
+}
+
+fn serialize_src_list(sources: &[FontSource]) -> String {
+    let entries: Vec<String> = sources
+        .iter()
+        .map(|source| match source {
+            FontSource::Url { url, format, tech } => {
+                let mut entry = format!("url({})", quote_css(url));
+                if let Some(format) = format {
+                    entry.push_str(&format!(" format({})", quote_css(format)));
+                }
+                if let Some(tech) = tech {
+                    entry.push_str(&format!(" tech({})", tech));
+                }
+                entry
+            }
+            FontSource::Local(name) => format!("local({})", quote_css(name)),
+        })
+        .collect();
+    entries.join(",")
+}
+
+fn quote_css(s: &str) -> String {
+    if s.contains('\'') {
+        format!("\"{}\"", s)
+    } else {
+        format!("'{}'", s)
+    }
+}
+
 fn parse_value(v: &str) -> String {
-    v.trim()
-        .split(',')
-        .next()
-        .unwrap_or(v)
-        .trim()
-        .trim_matches('"')
-        .trim_matches('\'')
-        .to_string()
+    parse_value_unquoted(v.trim().split(',').next().unwrap_or(v))
 }
 
-fn parse_url(v: &str) -> Option<String> {
-    let start = v.find("url(")? + 4;
-    let end = v[start..].find(')')? + start;
-    Some(
-        v[start..end]
-            .trim()
-            .trim_matches('"')
-            .trim_matches('\'')
-            .to_string(),
-    )
+pub(crate) fn parse_value_unquoted(v: &str) -> String {
+    v.trim().trim_matches('"').trim_matches('\'').to_string()
 }
 
 #[cfg(test)]
@@ -257,6 +670,47 @@ mod tests {
         assert!(!chars.contains(&'X'));
     }
 
+    #[test]
+    fn test_extract_text_chars_nested_and_referenced() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs>
+                <path id="curve" d="M0 0L10 10"/>
+                <text id="label"><textPath xlink:href="#curve">Path</textPath></text>
+                <desc id="ref">Ref</desc>
+            </defs>
+            <text><tspan>a<tspan>b<a>c</a></tspan></tspan><![CDATA[d]]></text>
+            <text><tref xlink:href="#ref"/></text>
+            <text xml:space="preserve">e	f
+g</text>
+            <use xlink:href="#label"/>
+        </svg>"##;
+        let doc = parse_svg(svg).unwrap();
+        let chars = extract_text_chars(&doc);
+        for c in "PathabcdRefefg ".chars() {
+            assert!(chars.contains(&c), "missing {:?}", c);
+        }
+        assert!(!chars.contains(&'\t'));
+        assert!(!chars.contains(&'\n'));
+    }
+
+    #[test]
+    fn test_extract_text_chars_use_fan_out() {
+        // Each level uses the next twice: 2^64 paths if targets were walked
+        // once per reference
+        let mut svg = String::from(r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><defs>"#);
+        for i in 0..64 {
+            svg.push_str(&format!(
+                r##"<g id="l{i}"><use xlink:href="#l{n}"/><use xlink:href="#l{n}"/></g>"##,
+                n = i + 1
+            ));
+        }
+        svg.push_str(r##"<text id="l64">deep</text></defs><text><use href="#l0"/></text><use href="#l0"/></svg>"##);
+        let chars = extract_text_chars(&parse_svg(&svg).unwrap());
+        for c in "deep".chars() {
+            assert!(chars.contains(&c), "missing {:?}", c);
+        }
+    }
+
     #[test]
     fn test_extract_font_faces() {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
@@ -296,4 +750,103 @@ mod tests {
         assert_eq!(faces.len(), 1);
         assert_eq!(faces[0].url, "new.woff2");
     }
+
+    #[test]
+    fn test_extract_font_faces_data_url() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>@font-face { font-family: 'Test'; src: url('data:font/woff2;base64,AAAA'); }</style>
+        </svg>"#;
+        let doc = parse_svg(svg).unwrap();
+        let faces = extract_font_faces(&doc);
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].url, "data:font/woff2;base64,AAAA");
+    }
+
+    #[test]
+    fn test_extract_font_face_src_list() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>
+                @font-face {
+                    font-family: "Inter";
+                    src: local("Inter"),
+                         url("inter.woff2") format("woff2") tech(variations),
+                         url(inter.woff) format("woff");
+                }
+            </style>
+        </svg>"#;
+        let doc = parse_svg(svg).unwrap();
+        let faces = extract_font_faces(&doc);
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].url, "inter.woff2");
+        assert_eq!(
+            faces[0].sources,
+            vec![
+                FontSource::Local("Inter".into()),
+                FontSource::Url {
+                    url: "inter.woff2".into(),
+                    format: Some("woff2".into()),
+                    tech: Some("variations".into()),
+                },
+                FontSource::Url {
+                    url: "inter.woff".into(),
+                    format: Some("woff".into()),
+                    tech: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_font_source() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>@font-face { font-family: 'Test'; src: url('a.woff2') format('woff2'), url('a.woff') format('woff'); }</style>
+        </svg>"#;
+        let mut doc = parse_svg(svg).unwrap();
+
+        // Only the exact URL is touched
+        replace_font_url(&mut doc, "a.woff", "b.woff");
+        let faces = extract_font_faces(&doc);
+        assert_eq!(faces[0].url, "a.woff2");
+        assert_eq!(faces[0].sources[1].url(), Some("b.woff"));
+
+        let old = faces[0].sources[0].clone();
+        let new = FontSource::Url {
+            url: "c.ttf".into(),
+            format: Some("truetype".into()),
+            tech: None,
+        };
+        replace_font_source(&mut doc, &old, &new);
+        let faces = extract_font_faces(&doc);
+        assert_eq!(faces[0].sources[0], new);
+        assert_eq!(faces[0].sources.len(), 2);
+    }
+
+    #[cfg(feature = "subset")]
+    #[test]
+    fn test_subset_embedded_fonts_resolver_error() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+            <style>@font-face { font-family: 'Test'; src: url('missing.woff2'); }</style>
+            <text>Hi</text>
+        </svg>"#;
+        let mut doc = parse_svg(svg).unwrap();
+        let resolver = FontResolver::new(|_| Err(io::Error::from(io::ErrorKind::NotFound)));
+        let err = subset_embedded_fonts(&mut doc, &resolver).unwrap_err();
+        assert!(matches!(err, SvagError::FontSubset(_)));
+    }
+
+    #[cfg(feature = "subset")]
+    #[test]
+    fn test_font_resolver_from_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let fonts = root.path().join("fonts");
+        std::fs::create_dir(&fonts).unwrap();
+        std::fs::write(fonts.join("a.woff2"), b"font").unwrap();
+        std::fs::write(root.path().join("secret"), b"secret").unwrap();
+
+        let resolver = FontResolver::from_dir(&fonts);
+        assert_eq!(resolver.resolve("a.woff2").unwrap(), b"font");
+        for url in ["../secret", &root.path().join("secret").to_string_lossy()] {
+            assert!(resolver.resolve(url).is_err());
+        }
+    }
 }
@@ -0,0 +1,591 @@
+//! Path geometry: bounding boxes, arc length, and point sampling.
+//!
+//! Paths are first resolved into absolute segments (quadratics are
+//! raised to cubics, arcs are converted to center parameterization per
+//! SVG 2 appendix B.2.4), which the measurements then work on.
+
+use crate::path::{Command, Path};
+
+/// A point in user space.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn lerp(self, other: Point, t: f64) -> Point {
+        Point::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+
+    fn distance(self, other: Point) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
+}
+
+/// An axis-aligned rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Rect {
+    /// The rectangle covering a single point.
+    pub fn from_point(p: Point) -> Self {
+        Self {
+            min_x: p.x,
+            min_y: p.y,
+            max_x: p.x,
+            max_y: p.y,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Grow to include `p`.
+    pub fn include(&mut self, p: Point) {
+        self.min_x = self.min_x.min(p.x);
+        self.min_y = self.min_y.min(p.y);
+        self.max_x = self.max_x.max(p.x);
+        self.max_y = self.max_y.max(p.y);
+    }
+
+    /// The smallest rectangle containing both.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    /// Whether `other` lies entirely inside this rectangle (edges included).
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.min_x <= other.min_x
+            && self.min_y <= other.min_y
+            && self.max_x >= other.max_x
+            && self.max_y >= other.max_y
+    }
+}
+
+/// One drawn piece of a path, in absolute coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Segment {
+    Line(Point, Point),
+    Cubic(Point, Point, Point, Point),
+    /// Elliptical arc in center parameterization; `rotation` is in radians.
+    Arc {
+        center: Point,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        start_angle: f64,
+        sweep_angle: f64,
+    },
+}
+
+impl Path {
+    /// Exact bounding box of the drawn geometry (stroke not included), or
+    /// `None` if the path draws nothing.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        let mut segments = segments(self).into_iter();
+        let mut bbox = segments.next()?.bounding_box();
+        for segment in segments {
+            bbox = bbox.union(&segment.bounding_box());
+        }
+        Some(bbox)
+    }
+
+    /// Total length of all subpaths.
+    pub fn total_length(&self) -> f64 {
+        // Summing floats starts from -0.0, which an empty path would return
+        segments(self)
+            .iter()
+            .map(Segment::length)
+            .fold(0.0, |a, b| a + b)
+    }
+
+    /// The point `length` units along the path, clamped to its ends, or
+    /// `None` if the path draws nothing.
+    ///
+    /// Distance is measured along the drawn segments, so the jump between
+    /// subpaths doesn't count.
+    pub fn point_at_length(&self, length: f64) -> Option<Point> {
+        let segments = segments(self);
+        let last = segments.last()?;
+        let mut remaining = length.max(0.0);
+        for segment in &segments {
+            let len = segment.length();
+            if remaining <= len {
+                return Some(segment.point(segment.t_at_length(remaining, len)));
+            }
+            remaining -= len;
+        }
+        Some(last.point(1.0))
+    }
+}
+
+/// Resolve a path into absolute segments.
+pub(crate) fn segments(path: &Path) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current = Point::default();
+    let mut subpath_start = Point::default();
+    // Second control point of the previous C/S, or control point of the
+    // previous Q/T, for reflecting into S/T
+    let mut last_cubic_ctrl: Option<Point> = None;
+    let mut last_quad_ctrl: Option<Point> = None;
+
+    for command in &path.commands {
+        let abs = |rel: bool, x: f64, y: f64| {
+            if rel {
+                Point::new(current.x + x, current.y + y)
+            } else {
+                Point::new(x, y)
+            }
+        };
+        let reflect = |ctrl: Option<Point>| {
+            ctrl.map_or(current, |c| {
+                Point::new(2.0 * current.x - c.x, 2.0 * current.y - c.y)
+            })
+        };
+        let (mut cubic_ctrl, mut quad_ctrl) = (None, None);
+
+        let end = match *command {
+            Command::MoveTo { rel, x, y } => {
+                let p = abs(rel, x, y);
+                subpath_start = p;
+                p
+            }
+            Command::LineTo { rel, x, y } => {
+                let p = abs(rel, x, y);
+                segments.push(Segment::Line(current, p));
+                p
+            }
+            Command::HorizontalTo { rel, x } => {
+                let p = Point::new(if rel { current.x + x } else { x }, current.y);
+                segments.push(Segment::Line(current, p));
+                p
+            }
+            Command::VerticalTo { rel, y } => {
+                let p = Point::new(current.x, if rel { current.y + y } else { y });
+                segments.push(Segment::Line(current, p));
+                p
+            }
+            Command::CurveTo {
+                rel,
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (c1, c2, p) = (abs(rel, x1, y1), abs(rel, x2, y2), abs(rel, x, y));
+                segments.push(Segment::Cubic(current, c1, c2, p));
+                cubic_ctrl = Some(c2);
+                p
+            }
+            Command::SmoothCurveTo { rel, x2, y2, x, y } => {
+                let c1 = reflect(last_cubic_ctrl);
+                let (c2, p) = (abs(rel, x2, y2), abs(rel, x, y));
+                segments.push(Segment::Cubic(current, c1, c2, p));
+                cubic_ctrl = Some(c2);
+                p
+            }
+            Command::QuadTo { rel, x1, y1, x, y } => {
+                let (c, p) = (abs(rel, x1, y1), abs(rel, x, y));
+                segments.push(quad_to_cubic(current, c, p));
+                quad_ctrl = Some(c);
+                p
+            }
+            Command::SmoothQuadTo { rel, x, y } => {
+                let c = reflect(last_quad_ctrl);
+                let p = abs(rel, x, y);
+                segments.push(quad_to_cubic(current, c, p));
+                quad_ctrl = Some(c);
+                p
+            }
+            Command::Arc {
+                rel,
+                rx,
+                ry,
+                x_axis_rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                let p = abs(rel, x, y);
+                segments.extend(arc_segment(
+                    current,
+                    p,
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                ));
+                p
+            }
+            Command::ClosePath => {
+                segments.push(Segment::Line(current, subpath_start));
+                subpath_start
+            }
+        };
+
+        current = end;
+        last_cubic_ctrl = cubic_ctrl;
+        last_quad_ctrl = quad_ctrl;
+    }
+
+    segments
+}
+
+/// Degree-elevate a quadratic Bézier to the equivalent cubic.
+fn quad_to_cubic(p0: Point, c: Point, p1: Point) -> Segment {
+    Segment::Cubic(p0, p0.lerp(c, 2.0 / 3.0), p1.lerp(c, 2.0 / 3.0), p1)
+}
+
+/// Convert an endpoint-parameterized arc to a segment, following the
+/// out-of-range parameter rules: zero radii draw a line, and identical
+/// endpoints draw nothing.
+fn arc_segment(
+    from: Point,
+    to: Point,
+    rx: f64,
+    ry: f64,
+    x_axis_rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+) -> Option<Segment> {
+    if from == to {
+        return None;
+    }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 {
+        return Some(Segment::Line(from, to));
+    }
+
+    let rotation = x_axis_rotation.to_radians();
+    let (sin, cos) = rotation.sin_cos();
+
+    // Step 1: midpoint in the ellipse's rotated frame
+    let dx = (from.x - to.x) / 2.0;
+    let dy = (from.y - to.y) / 2.0;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+
+    // Scale radii up if they can't span the endpoints
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    // Step 2: center in the rotated frame
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let num = rx2 * ry2 - rx2 * y1 * y1 - ry2 * x1 * x1;
+    let den = rx2 * y1 * y1 + ry2 * x1 * x1;
+    let mut coef = (num / den).max(0.0).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+
+    // Step 3: center in user space
+    let center = Point::new(
+        cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+        sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+    );
+
+    // Step 4: start and sweep angles
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let (ux, uy) = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let (vx, vy) = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let start_angle = angle(1.0, 0.0, ux, uy);
+    let mut sweep_angle = angle(ux, uy, vx, vy);
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= std::f64::consts::TAU;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += std::f64::consts::TAU;
+    }
+
+    Some(Segment::Arc {
+        center,
+        rx,
+        ry,
+        rotation,
+        start_angle,
+        sweep_angle,
+    })
+}
+
+impl Segment {
+    /// Position at parameter `t` in `0..=1`.
+    pub(crate) fn point(&self, t: f64) -> Point {
+        match *self {
+            Segment::Line(a, b) => a.lerp(b, t),
+            Segment::Cubic(p0, c1, c2, p1) => {
+                let mt = 1.0 - t;
+                let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+                Point::new(
+                    a * p0.x + b * c1.x + c * c2.x + d * p1.x,
+                    a * p0.y + b * c1.y + c * c2.y + d * p1.y,
+                )
+            }
+            Segment::Arc {
+                center,
+                rx,
+                ry,
+                rotation,
+                start_angle,
+                sweep_angle,
+            } => {
+                let (sin, cos) = rotation.sin_cos();
+                let (s, c) = (start_angle + sweep_angle * t).sin_cos();
+                Point::new(
+                    center.x + rx * cos * c - ry * sin * s,
+                    center.y + rx * sin * c + ry * cos * s,
+                )
+            }
+        }
+    }
+
+    /// Speed (magnitude of the derivative) at parameter `t`.
+    fn speed(&self, t: f64) -> f64 {
+        match *self {
+            Segment::Line(a, b) => a.distance(b),
+            Segment::Cubic(p0, c1, c2, p1) => {
+                let mt = 1.0 - t;
+                let (a, b, c) = (3.0 * mt * mt, 6.0 * mt * t, 3.0 * t * t);
+                let dx = a * (c1.x - p0.x) + b * (c2.x - c1.x) + c * (p1.x - c2.x);
+                let dy = a * (c1.y - p0.y) + b * (c2.y - c1.y) + c * (p1.y - c2.y);
+                dx.hypot(dy)
+            }
+            Segment::Arc {
+                rx,
+                ry,
+                start_angle,
+                sweep_angle,
+                ..
+            } => {
+                // Rotation doesn't change speed
+                let (s, c) = (start_angle + sweep_angle * t).sin_cos();
+                (rx * s).hypot(ry * c) * sweep_angle.abs()
+            }
+        }
+    }
+
+    pub(crate) fn bounding_box(&self) -> Rect {
+        let mut bbox = Rect::from_point(self.point(0.0));
+        bbox.include(self.point(1.0));
+
+        match *self {
+            Segment::Line(..) => {}
+            Segment::Cubic(p0, c1, c2, p1) => {
+                // Extrema where one coordinate's derivative is zero
+                for (a, b, c, d) in [(p0.x, c1.x, c2.x, p1.x), (p0.y, c1.y, c2.y, p1.y)] {
+                    let qa = d - 3.0 * c + 3.0 * b - a;
+                    let qb = 2.0 * (c - 2.0 * b + a);
+                    let qc = b - a;
+                    for t in quadratic_roots(qa, qb, qc) {
+                        if t > 0.0 && t < 1.0 {
+                            bbox.include(self.point(t));
+                        }
+                    }
+                }
+            }
+            Segment::Arc {
+                rx,
+                ry,
+                rotation,
+                start_angle,
+                sweep_angle,
+                ..
+            } => {
+                let (sin, cos) = rotation.sin_cos();
+                // Angles where dx/dθ and dy/dθ vanish, each repeating every π
+                let x_extreme = (-ry * sin).atan2(rx * cos);
+                let y_extreme = (ry * cos).atan2(rx * sin);
+                for base in [x_extreme, y_extreme] {
+                    for k in -4..=4 {
+                        let theta = base + f64::from(k) * std::f64::consts::PI;
+                        let t = (theta - start_angle) / sweep_angle;
+                        if t > 0.0 && t < 1.0 {
+                            bbox.include(self.point(t));
+                        }
+                    }
+                }
+            }
+        }
+
+        bbox
+    }
+
+    pub(crate) fn length(&self) -> f64 {
+        match *self {
+            Segment::Line(a, b) => a.distance(b),
+            _ => self.length_between(0.0, 1.0),
+        }
+    }
+
+    fn length_between(&self, a: f64, b: f64) -> f64 {
+        let whole = self.gauss_legendre(a, b);
+        self.adaptive_length(a, b, whole, 12)
+    }
+
+    fn adaptive_length(&self, a: f64, b: f64, whole: f64, depth: u32) -> f64 {
+        let mid = (a + b) / 2.0;
+        let left = self.gauss_legendre(a, mid);
+        let right = self.gauss_legendre(mid, b);
+        let halves = left + right;
+        if depth == 0 || (halves - whole).abs() <= 1e-10 * halves.max(1.0) {
+            halves
+        } else {
+            self.adaptive_length(a, mid, left, depth - 1)
+                + self.adaptive_length(mid, b, right, depth - 1)
+        }
+    }
+
+    /// Five-point Gauss–Legendre estimate of the length over `a..b`.
+    fn gauss_legendre(&self, a: f64, b: f64) -> f64 {
+        const NODES: [(f64, f64); 5] = [
+            (0.0, 0.568_888_888_888_888_9),
+            (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+            (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+            (-0.906_179_845_938_664, 0.236_926_885_056_189_1),
+            (0.906_179_845_938_664, 0.236_926_885_056_189_1),
+        ];
+        let half = (b - a) / 2.0;
+        let mid = (a + b) / 2.0;
+        NODES
+            .iter()
+            .map(|&(x, w)| w * self.speed(mid + half * x))
+            .sum::<f64>()
+            * half
+    }
+
+    /// Parameter at which the distance along the segment reaches `target`,
+    /// given the segment's total `length`.
+    fn t_at_length(&self, target: f64, length: f64) -> f64 {
+        if length <= 0.0 {
+            return 0.0;
+        }
+        if let Segment::Line(..) = self {
+            return target / length;
+        }
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..50 {
+            let mid = (lo + hi) / 2.0;
+            if self.length_between(0.0, mid) < target {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo + hi) / 2.0
+    }
+}
+
+/// Real roots of `a·t² + b·t + c`, degrading to the linear case.
+fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        return if b.abs() < 1e-12 {
+            Vec::new()
+        } else {
+            vec![-c / b]
+        };
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return Vec::new();
+    }
+    let sqrt = disc.sqrt();
+    vec![(-b + sqrt) / (2.0 * a), (-b - sqrt) / (2.0 * a)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::parse_path;
+    use std::f64::consts::PI;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn bbox(d: &str) -> (f64, f64, f64, f64) {
+        let r = parse_path(d).unwrap().bounding_box().unwrap();
+        (r.min_x, r.min_y, r.max_x, r.max_y)
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        assert_eq!(bbox("M10 10h20v5H10z"), (10.0, 10.0, 30.0, 15.0));
+        // Cubic bulges past its endpoints: y peaks at 7.5
+        let (_, _, _, max_y) = bbox("M0 0C0 10 10 10 10 0");
+        assert!(close(max_y, 7.5));
+        // Quadratic peak at half the control height
+        let (_, _, _, max_y) = bbox("M0 0Q5 10 10 0");
+        assert!(close(max_y, 5.0));
+        // Lower semicircle of radius 5 centered at (5, 0)
+        let (min_x, min_y, max_x, max_y) = bbox("M0 0A5 5 0 0 0 10 0");
+        assert!(close(min_x, 0.0) && close(min_y, 0.0));
+        assert!(close(max_x, 10.0) && close(max_y, 5.0));
+        // Relative moves with a smooth curve reflecting its control point
+        let (_, min_y, _, _) = bbox("m0 0c0-10 10-10 10 0s10 10 10 0");
+        assert!(close(min_y, -7.5));
+
+        assert!(parse_path("M5 5").unwrap().bounding_box().is_none());
+    }
+
+    #[test]
+    fn test_total_length() {
+        let len = |d: &str| parse_path(d).unwrap().total_length();
+        assert!(close(len("M0 0h10v10h-10z"), 40.0));
+        assert!(close(len("M0 0A5 5 0 0 1 10 0"), 5.0 * PI));
+        // Full circle from two half arcs, radius scaled up from 1 to 5
+        assert!(close(len("M0 0A1 1 0 0 0 10 0A1 1 0 0 0 0 0"), 10.0 * PI));
+        // A straight cubic is as long as its chord
+        assert!(close(len("M0 0C1 0 2 0 3 0"), 3.0));
+        // Zero radius arcs are lines; zero-length arcs are dropped
+        assert!(close(len("M0 0A0 5 0 0 1 3 4A5 5 0 0 1 3 4"), 5.0));
+        // Nothing drawn is positive zero
+        assert!(len("M5 5").is_sign_positive());
+        assert_eq!(len("M5 5").to_string(), "0");
+    }
+
+    #[test]
+    fn test_point_at_length() {
+        let square = parse_path("M0 0h10v10h-10z").unwrap();
+        let at = |d: f64| square.point_at_length(d).unwrap();
+        assert_eq!(at(0.0), Point::new(0.0, 0.0));
+        assert_eq!(at(15.0), Point::new(10.0, 5.0));
+        assert_eq!(at(-1.0), Point::new(0.0, 0.0));
+        assert_eq!(at(100.0), Point::new(0.0, 0.0));
+
+        let arc = parse_path("M0 0A5 5 0 0 1 10 0").unwrap();
+        let top = arc.point_at_length(5.0 * PI / 2.0).unwrap();
+        assert!(close(top.x, 5.0) && close(top.y, -5.0));
+
+        assert!(parse_path("M1 1").unwrap().point_at_length(0.0).is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Name patterns for options that select attributes or elements.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::{Attribute, Element, QName};
+use crate::error::SvagError;
+
+/// A shell-style glob over names: `*` matches any run of characters and
+/// `?` any single one. Names are matched with their prefix, as in
+/// `xlink:href` or `inkscape:*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob(String);
+
+impl Glob {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Glob(pattern.into())
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+
+        // Backtrack to the last `*` on a mismatch, letting it eat one more
+        // character
+        let (mut p, mut n) = (0, 0);
+        let mut star: Option<(usize, usize)> = None;
+        while n < name.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    star = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match star {
+                    Some((sp, sn)) => {
+                        p = sp + 1;
+                        n = sn + 1;
+                        star = Some((sp, sn + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+impl FromStr for Glob {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s.contains(['[', ']', '=']) {
+            return Err(SvagError::InvalidPattern(s.to_string()));
+        }
+        Ok(Glob::new(s))
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Selects attributes to remove by name, optionally only on some elements
+/// or with some values. Written like a CSS attribute selector:
+///
+/// - `data-figma-*`: the attribute on any element
+/// - `path[class]`: only on `<path>` elements
+/// - `*[fill=none]`: only with that value (the value is a glob too)
+///
+/// ```
+/// let pattern: svag::AttrPattern = "g[data-*]".parse().unwrap();
+/// assert_eq!(pattern.to_string(), "g[data-*]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttrPattern {
+    /// Elements the attribute is removed from (all if `None`)
+    pub element: Option<Glob>,
+    /// Attribute name
+    pub name: Glob,
+    /// Attribute values that are removed (all if `None`)
+    pub value: Option<Glob>,
+}
+
+impl AttrPattern {
+    /// Whether `attr` on an element named `element` is selected.
+    pub fn matches(&self, element: &QName, attr: &Attribute) -> bool {
+        self.element
+            .as_ref()
+            .is_none_or(|glob| glob.matches(&element.full_name()))
+            && self.name.matches(&attr.name.full_name())
+            && self
+                .value
+                .as_ref()
+                .is_none_or(|glob| glob.matches(&attr.value))
+    }
+}
+
+impl FromStr for AttrPattern {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SvagError::InvalidPattern(s.to_string());
+        let s = s.trim();
+
+        let Some((element, rest)) = s.split_once('[') else {
+            if s.is_empty() || s.contains([']', '=']) {
+                return Err(invalid());
+            }
+            return Ok(AttrPattern {
+                element: None,
+                name: Glob::new(s),
+                value: None,
+            });
+        };
+        let selector = rest.strip_suffix(']').ok_or_else(invalid)?;
+        let (name, value) = match selector.split_once('=') {
+            Some((name, value)) => (name, Some(value.trim_matches(['"', '\'']))),
+            None => (selector, None),
+        };
+        if name.is_empty() || name.contains(['[', ']']) {
+            return Err(invalid());
+        }
+
+        Ok(AttrPattern {
+            element: (!element.is_empty()).then(|| Glob::new(element)),
+            name: Glob::new(name),
+            value: value.map(Glob::new),
+        })
+    }
+}
+
+impl fmt::Display for AttrPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.element, &self.value) {
+            (None, None) => write!(f, "{}", self.name),
+            (element, value) => {
+                if let Some(element) = element {
+                    write!(f, "{element}")?;
+                }
+                write!(f, "[{}", self.name)?;
+                if let Some(value) = value {
+                    write!(f, "={value}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+/// Selects elements, written like a simple CSS selector: an element name
+/// (a glob, and optional), then any number of `#id`, `.class`, `[attr]`,
+/// and `[attr=value]` (name and value are globs too):
+///
+/// - `#logo`: the element with that id
+/// - `g[inkscape:label=Roads]`: a group with that attribute
+/// - `path.outline[fill=none]`: everything must match
+///
+/// ```
+/// let selector: svag::Selector = "g.layer[data-name=Roads]".parse().unwrap();
+/// assert_eq!(selector.to_string(), "g.layer[data-name=Roads]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    /// Element name (any if `None`)
+    pub element: Option<Glob>,
+    pub id: Option<String>,
+    /// Classes the element must all have
+    pub classes: Vec<String>,
+    /// Attributes the element must all have, with values if given
+    pub attributes: Vec<(Glob, Option<Glob>)>,
+}
+
+impl Selector {
+    pub fn matches(&self, elem: &Element) -> bool {
+        let classes = || {
+            elem.get_attr("class")
+                .unwrap_or("")
+                .split_ascii_whitespace()
+        };
+        self.element
+            .as_ref()
+            .is_none_or(|glob| glob.matches(&elem.name.full_name()))
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| elem.get_attr("id") == Some(id))
+            && self
+                .classes
+                .iter()
+                .all(|class| classes().any(|c| c == class))
+            && self.attributes.iter().all(|(name, value)| {
+                elem.attributes.iter().any(|attr| {
+                    name.matches(&attr.name.full_name())
+                        && value.as_ref().is_none_or(|glob| glob.matches(&attr.value))
+                })
+            })
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || SvagError::InvalidPattern(s.to_string());
+        let s = s.trim();
+        let name_end = |s: &str| s.find(['#', '.', '[']).unwrap_or(s.len());
+
+        let (element, mut rest) = s.split_at(name_end(s));
+        let mut selector = Selector {
+            element: (!element.is_empty()).then(|| Glob::new(element)),
+            id: None,
+            classes: Vec::new(),
+            attributes: Vec::new(),
+        };
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            if c == '[' {
+                let (inner, after) = rest.split_once(']').ok_or_else(invalid)?;
+                let (name, value) = match inner.split_once('=') {
+                    Some((name, value)) => (name, Some(value.trim_matches(['"', '\'']))),
+                    None => (inner, None),
+                };
+                if name.is_empty() || name.contains('[') {
+                    return Err(invalid());
+                }
+                selector
+                    .attributes
+                    .push((Glob::new(name), value.map(Glob::new)));
+                rest = after;
+                continue;
+            }
+            let (name, after) = rest.split_at(name_end(rest));
+            if name.is_empty() || name.contains([']', '=']) {
+                return Err(invalid());
+            }
+            match c {
+                '#' if selector.id.is_none() => selector.id = Some(name.to_string()),
+                '.' => selector.classes.push(name.to_string()),
+                _ => return Err(invalid()),
+            }
+            rest = after;
+        }
+        if s.is_empty() || element.contains([']', '=', ' ']) {
+            return Err(invalid());
+        }
+        Ok(selector)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(element) = &self.element {
+            write!(f, "{element}")?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, "#{id}")?;
+        }
+        for class in &self.classes {
+            write!(f, ".{class}")?;
+        }
+        for (name, value) in &self.attributes {
+            match value {
+                Some(value) => write!(f, "[{name}={value}]")?,
+                None => write!(f, "[{name}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let cases = [
+            ("data-*", "data-figma-id", true),
+            ("data-*", "data-", true),
+            ("data-*", "xdata-a", false),
+            ("*:*", "xlink:href", true),
+            ("*:*", "href", false),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "aXbYbZ", false),
+            ("fill?", "fill", false),
+            ("*", "", true),
+        ];
+        for (pattern, name, expected) in cases {
+            assert_eq!(
+                Glob::new(pattern).matches(name),
+                expected,
+                "{pattern} {name}"
+            );
+        }
+
+        assert!("".parse::<Glob>().is_err());
+        assert!("foreignObject".parse::<Glob>().is_ok());
+    }
+
+    #[test]
+    fn test_attr_pattern() {
+        let path = QName::new("path");
+        let attr = |name: &str, value: &'static str| Attribute::new(name, value);
+
+        let pattern: AttrPattern = "data-figma-*".parse().unwrap();
+        assert!(pattern.matches(&path, &attr("data-figma-id", "1")));
+        assert!(!pattern.matches(&path, &attr("data-id", "1")));
+
+        let pattern: AttrPattern = "path[class]".parse().unwrap();
+        assert!(pattern.matches(&path, &attr("class", "a")));
+        assert!(!pattern.matches(&QName::new("rect"), &attr("class", "a")));
+
+        let pattern: AttrPattern = "[fill='none']".parse().unwrap();
+        assert!(pattern.matches(&path, &attr("fill", "none")));
+        assert!(!pattern.matches(&path, &attr("fill", "red")));
+        assert_eq!(pattern.to_string(), "[fill=none]");
+
+        for invalid in ["", "path[", "path[]", "a]", "[a]b]"] {
+            assert!(invalid.parse::<AttrPattern>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_selector() {
+        let elem = crate::parse_svg(
+            r#"<svg><g id="roads" class="layer visible" inkscape:label="Roads"/></svg>"#,
+        )
+        .unwrap()
+        .root
+        .child_elements()
+        .next()
+        .unwrap()
+        .clone();
+
+        for matching in [
+            "#roads",
+            "g",
+            "*.layer.visible",
+            "[inkscape:label=R*]",
+            "g#roads[class]",
+        ] {
+            let selector: Selector = matching.parse().unwrap();
+            assert!(selector.matches(&elem), "{matching}");
+            assert_eq!(selector.to_string(), matching);
+        }
+        for other in [
+            "#road",
+            "path",
+            ".hidden",
+            "[label]",
+            "g[inkscape:label=Rivers]",
+        ] {
+            assert!(
+                !other.parse::<Selector>().unwrap().matches(&elem),
+                "{other}"
+            );
+        }
+        for invalid in ["", "g[", "#", "g.", "#a#b", "g]", "a b"] {
+            assert!(invalid.parse::<Selector>().is_err(), "{invalid}");
+        }
+    }
+}
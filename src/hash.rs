@@ -0,0 +1,568 @@
+//! Stable, dependency-free hashing for caches and fingerprints.
+//!
+//! `std`'s `DefaultHasher` is randomly seeded and may change between Rust
+//! releases, so anything persisted to disk uses FNV-1a instead.
+//!
+//! ## Skipping unchanged inputs
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use svag::{CacheEntry, Options};
+//!
+//! # fn main() -> Result<(), svag::SvagError> {
+//! let options = Options::default();
+//! let mut cache: HashMap<&str, CacheEntry> = HashMap::new();
+//! let mut written = Vec::new();
+//! # let (path, input) = ("icon.svg", "<svg><!-- x --><rect/></svg>");
+//! # for _ in 0..2 {
+//! let fingerprint = options.fingerprint().expect("no resolvers or hooks");
+//! let entry = CacheEntry::new(svag::content_hash(input.as_bytes()), fingerprint);
+//! if svag::would_change(entry.input_hash, entry.options_fingerprint, cache.get(path)) {
+//!     written.push(svag::minify_with_options(input, &options)?);
+//!     cache.insert(path, entry);
+//! }
+//! # }
+//! // The second run found the input and options unchanged
+//! assert_eq!(written, ["<svg><rect/></svg>"]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Finding near-duplicates
+//!
+//! [`fingerprint`] hashes what a document draws rather than its bytes, so
+//! the same icon exported by two editors hashes equal.
+
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+use crate::bounds::has_dynamic_content;
+use crate::canonical::{canonical_options, canonicalize_document};
+use crate::index::{DocumentIndex, rename_references};
+use crate::ordering::sort_defs;
+use crate::{
+    AttrPattern, Color, CurrentColor, CustomProperty, Document, Glob, OptimizeFor, Options, Pass,
+    Pipeline, Size, SvgTarget, optimize, serialize,
+};
+
+/// What a previous minification ran on, as recorded by the caller's cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheEntry {
+    /// [`content_hash`] of the input
+    pub input_hash: u64,
+    /// [`Options::fingerprint`] of the options used
+    pub options_fingerprint: u64,
+}
+
+impl CacheEntry {
+    pub fn new(input_hash: u64, options_fingerprint: u64) -> Self {
+        Self {
+            input_hash,
+            options_fingerprint,
+        }
+    }
+}
+
+/// Stable hash of an input, suitable for persisting across runs.
+pub fn content_hash(input: &[u8]) -> u64 {
+    fnv1a(input)
+}
+
+/// Whether minifying an input could produce different output than the cached
+/// run, i.e. there is no cache entry or the input or options changed.
+pub fn would_change(input_hash: u64, options_fingerprint: u64, cache: Option<&CacheEntry>) -> bool {
+    cache != Some(&CacheEntry::new(input_hash, options_fingerprint))
+}
+
+/// Stable hash of a canonical form of `doc`: minified with the default
+/// options (so whitespace, paths, colors, and default values are
+/// normalized and metadata is gone), then brought to the form of
+/// [`canonicalize`](crate::canonicalize) with numbers rounded to the
+/// default precision, ids renamed in the order they're first referenced,
+/// unreferenced ids dropped, and `<defs>` sorted. Files that differ only
+/// in those ways hash equal.
+///
+/// Ids are kept as they are in documents with stylesheets, scripts, or
+/// animation, which can refer to them in ways that aren't followed.
+///
+/// ```
+/// let a = svag::parse_svg(r##"<?xml version="1.0"?>
+/// <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24">
+///   <defs><linearGradient id="gradient-1"><stop offset="0" stop-color="#FF0000"/></linearGradient></defs>
+///   <rect width="24.0001" height="24" fill="url(#gradient-1)" id="background"/>
+/// </svg>"##).unwrap();
+/// let b = svag::parse_svg(r##"<svg viewBox="0,0,24,24" xmlns="http://www.w3.org/2000/svg"><defs><linearGradient id="g"><stop stop-color="red" offset="0"/></linearGradient></defs><rect height="24" fill="url(#g)" width="24"/></svg>"##).unwrap();
+/// assert_eq!(svag::fingerprint(&a), svag::fingerprint(&b));
+/// ```
+pub fn fingerprint(doc: &Document) -> u64 {
+    let options = Options::default();
+    let mut doc = doc.clone();
+    optimize(&mut doc, &options);
+    canonicalize_document(&mut doc, options.precision);
+    if !has_dynamic_content(&doc) {
+        renumber_ids(&mut doc);
+        sort_defs(&mut doc);
+    }
+    fnv1a(serialize(&doc, &canonical_options()).as_bytes())
+}
+
+/// Rename referenced ids to `0`, `1`, ... in the order they're first
+/// referenced, and drop the others.
+fn renumber_ids(doc: &mut Document) {
+    let index = DocumentIndex::new(doc);
+    let mut renames: HashMap<String, String> = HashMap::new();
+    for (element, _) in index.iter() {
+        for &id in index.references(element) {
+            let next = renames.len().to_string();
+            renames.entry(id.to_string()).or_insert(next);
+        }
+    }
+    doc.for_each_element_mut(|elem| {
+        if let Some(id) = elem.get_attr("id") {
+            match renames.get(id) {
+                Some(new) => {
+                    let new = new.clone();
+                    elem.set_attr("id", new);
+                }
+                None => elem.remove_attr("id"),
+            }
+        }
+        rename_references(elem, &renames);
+    });
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a hasher.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash a byte string with FNV-1a.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+impl Options {
+    /// A stable hash of these options and the svag version.
+    ///
+    /// Output produced with equal fingerprints from equal inputs is identical,
+    /// so incremental builds can skip re-minification (see [`would_change`]).
+    ///
+    /// Options that run code of their own (resolvers, custom passes, or an
+    /// [`on_pass`](Options::on_pass) hook) can't be hashed, and have no
+    /// fingerprint: output made with them is never known to be up to date.
+    pub fn fingerprint(&self) -> Option<u64> {
+        // Every field is named, so a new one can't be left out
+        let Options {
+            precision,
+            transform_precision,
+            opacity_precision,
+            target,
+            optimize_for,
+            lenient,
+            stylesheet_resolver,
+            image_resolver,
+            remove_comments,
+            remove_metadata,
+            remove_xml_declaration,
+            remove_doctype,
+            remove_unused_namespaces,
+            hoist_namespaces,
+            unprefix_elements,
+            modernize_href,
+            remove_duplicate_shapes,
+            collapse_groups,
+            remove_hidden,
+            remove_empty,
+            remove_empty_attrs,
+            remove_attrs,
+            remove_elements,
+            keep_elements,
+            prune_conditions,
+            system_language,
+            remove_xml_lang,
+            remove_xml_base,
+            remove_xml_space,
+            minify_colors,
+            remove_defaults,
+            convert_ellipses,
+            minify_paths,
+            minify_transforms,
+            normalize_lists,
+            minify_numbers,
+            normalize_keywords,
+            hex_alpha,
+            current_color,
+            custom_properties,
+            minify_styles,
+            sort_defs,
+            merge_paths,
+            sort_attrs,
+            convert_cdata,
+            final_newline,
+            banner,
+            title,
+            dedupe_data_uris,
+            remove_noop_clips,
+            flatten_nested_svg,
+            optimize_filters,
+            optimize_markers,
+            optimize_patterns,
+            regroup_siblings,
+            extract_classes,
+            autocrop,
+            resize,
+            remove_occluded,
+            passes,
+            on_pass,
+        } = self;
+        let custom_passes = passes
+            .as_ref()
+            .is_some_and(|pipeline| pipeline.0.iter().any(|p| matches!(p, Pass::Custom(_))));
+        if stylesheet_resolver.is_some()
+            || image_resolver.is_some()
+            || on_pass.is_some()
+            || custom_passes
+        {
+            return None;
+        }
+
+        let mut hasher = Fnv1a::default();
+        env!("CARGO_PKG_VERSION").stable_hash(&mut hasher);
+        precision.stable_hash(&mut hasher);
+        transform_precision.stable_hash(&mut hasher);
+        opacity_precision.stable_hash(&mut hasher);
+        target.stable_hash(&mut hasher);
+        optimize_for.stable_hash(&mut hasher);
+        lenient.stable_hash(&mut hasher);
+        remove_comments.stable_hash(&mut hasher);
+        remove_metadata.stable_hash(&mut hasher);
+        remove_xml_declaration.stable_hash(&mut hasher);
+        remove_doctype.stable_hash(&mut hasher);
+        remove_unused_namespaces.stable_hash(&mut hasher);
+        hoist_namespaces.stable_hash(&mut hasher);
+        unprefix_elements.stable_hash(&mut hasher);
+        modernize_href.stable_hash(&mut hasher);
+        remove_duplicate_shapes.stable_hash(&mut hasher);
+        collapse_groups.stable_hash(&mut hasher);
+        remove_hidden.stable_hash(&mut hasher);
+        remove_empty.stable_hash(&mut hasher);
+        remove_empty_attrs.stable_hash(&mut hasher);
+        remove_attrs.stable_hash(&mut hasher);
+        remove_elements.stable_hash(&mut hasher);
+        keep_elements.stable_hash(&mut hasher);
+        prune_conditions.stable_hash(&mut hasher);
+        system_language.stable_hash(&mut hasher);
+        remove_xml_lang.stable_hash(&mut hasher);
+        remove_xml_base.stable_hash(&mut hasher);
+        remove_xml_space.stable_hash(&mut hasher);
+        minify_colors.stable_hash(&mut hasher);
+        remove_defaults.stable_hash(&mut hasher);
+        convert_ellipses.stable_hash(&mut hasher);
+        minify_paths.stable_hash(&mut hasher);
+        minify_transforms.stable_hash(&mut hasher);
+        normalize_lists.stable_hash(&mut hasher);
+        minify_numbers.stable_hash(&mut hasher);
+        normalize_keywords.stable_hash(&mut hasher);
+        hex_alpha.stable_hash(&mut hasher);
+        current_color.stable_hash(&mut hasher);
+        custom_properties.stable_hash(&mut hasher);
+        minify_styles.stable_hash(&mut hasher);
+        sort_defs.stable_hash(&mut hasher);
+        merge_paths.stable_hash(&mut hasher);
+        sort_attrs.stable_hash(&mut hasher);
+        convert_cdata.stable_hash(&mut hasher);
+        final_newline.stable_hash(&mut hasher);
+        banner.stable_hash(&mut hasher);
+        title.stable_hash(&mut hasher);
+        dedupe_data_uris.stable_hash(&mut hasher);
+        remove_noop_clips.stable_hash(&mut hasher);
+        flatten_nested_svg.stable_hash(&mut hasher);
+        optimize_filters.stable_hash(&mut hasher);
+        optimize_markers.stable_hash(&mut hasher);
+        optimize_patterns.stable_hash(&mut hasher);
+        regroup_siblings.stable_hash(&mut hasher);
+        extract_classes.stable_hash(&mut hasher);
+        autocrop.stable_hash(&mut hasher);
+        resize.stable_hash(&mut hasher);
+        remove_occluded.stable_hash(&mut hasher);
+        passes.stable_hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+/// Feeding a value to a persisted hash, in a format of its own rather than
+/// `Debug`'s, which may change between releases.
+trait StableHash {
+    fn stable_hash(&self, hasher: &mut Fnv1a);
+}
+
+impl StableHash for u8 {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write_u8(*self);
+    }
+}
+
+impl StableHash for bool {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write_u8(u8::from(*self));
+    }
+}
+
+impl StableHash for f64 {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+/// Length first, so that consecutive strings can't run into each other.
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&(self.len() as u64).to_le_bytes());
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        match self {
+            None => hasher.write_u8(0),
+            Some(value) => {
+                hasher.write_u8(1);
+                value.stable_hash(hasher);
+            }
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&(self.len() as u64).to_le_bytes());
+        for item in self {
+            item.stable_hash(hasher);
+        }
+    }
+}
+
+impl StableHash for SvgTarget {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write_u8(match self {
+            SvgTarget::Svg11 => 0,
+            SvgTarget::Svg2 => 1,
+            SvgTarget::Browsers => 2,
+        });
+    }
+}
+
+impl StableHash for OptimizeFor {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write_u8(match self {
+            OptimizeFor::RawSize => 0,
+            OptimizeFor::GzipSize => 1,
+            OptimizeFor::BrotliSize => 2,
+        });
+    }
+}
+
+impl StableHash for Glob {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.to_string().stable_hash(hasher);
+    }
+}
+
+impl StableHash for AttrPattern {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.element.stable_hash(hasher);
+        self.name.stable_hash(hasher);
+        self.value.stable_hash(hasher);
+    }
+}
+
+impl StableHash for Color {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&[self.r, self.g, self.b, self.a]);
+    }
+}
+
+impl StableHash for CurrentColor {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        match self {
+            CurrentColor::Dominant => hasher.write_u8(0),
+            CurrentColor::Color(color) => {
+                hasher.write_u8(1);
+                color.stable_hash(hasher);
+            }
+        }
+    }
+}
+
+impl StableHash for CustomProperty {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.name.stable_hash(hasher);
+        self.color.stable_hash(hasher);
+    }
+}
+
+impl StableHash for Size {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        self.width.stable_hash(hasher);
+        self.height.stable_hash(hasher);
+    }
+}
+
+/// Passes by name. That's enough for built-in passes, but not for custom
+/// ones: two of them can share a name and do different things. Options with
+/// custom passes have no [fingerprint](Options::fingerprint) for that
+/// reason, so their pipelines are never hashed.
+impl StableHash for Pipeline {
+    fn stable_hash(&self, hasher: &mut Fnv1a) {
+        hasher.write(&(self.0.len() as u64).to_le_bytes());
+        for pass in &self.0 {
+            pass.name().stable_hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_options_fingerprint() {
+        let options = Options::default();
+        assert!(options.fingerprint().is_some());
+        assert_eq!(options.fingerprint(), options.clone().fingerprint());
+
+        let others = [
+            Options {
+                precision: 3,
+                ..Options::default()
+            },
+            Options {
+                remove_attrs: vec!["data-*".parse().unwrap()],
+                ..Options::default()
+            },
+            Options {
+                remove_attrs: vec!["data-*".parse().unwrap(), "id".parse().unwrap()],
+                ..Options::default()
+            },
+            Options {
+                passes: Some("collapse_groups".parse().unwrap()),
+                ..Options::default()
+            },
+            Options {
+                passes: Some("collapse_groups,collapse_groups".parse().unwrap()),
+                ..Options::default()
+            },
+        ];
+        for (i, a) in others.iter().enumerate() {
+            assert_ne!(a.fingerprint(), options.fingerprint());
+            for b in &others[i + 1..] {
+                assert_ne!(a.fingerprint(), b.fingerprint());
+            }
+        }
+
+        // Resolvers and hooks behave however their code does
+        let uncacheable = [
+            Options {
+                stylesheet_resolver: Some(crate::StylesheetResolver::new(|_| None)),
+                ..Options::default()
+            },
+            Options {
+                on_pass: Some(crate::PassHook::new(|_| {})),
+                ..Options::default()
+            },
+            Options {
+                passes: Some(crate::Pipeline(vec![Pass::custom(Named("tidy"))])),
+                ..Options::default()
+            },
+        ];
+        for options in uncacheable {
+            assert_eq!(options.fingerprint(), None);
+        }
+    }
+
+    /// A custom pass that does nothing under any name.
+    struct Named(&'static str);
+
+    impl crate::OptimizePass for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn run(&self, _: &mut Document, _: &crate::PassContext) {}
+    }
+
+    #[test]
+    fn test_would_change() {
+        let input = content_hash(b"<svg/>");
+        let options = Options::default().fingerprint().unwrap();
+        let entry = CacheEntry::new(input, options);
+
+        assert!(would_change(input, options, None));
+        assert!(!would_change(input, options, Some(&entry)));
+        assert!(would_change(
+            content_hash(b"<svg></svg>"),
+            options,
+            Some(&entry)
+        ));
+        assert!(would_change(input, options ^ 1, Some(&entry)));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = |svg: &str| fingerprint(&crate::parse_svg(svg).unwrap());
+        // Defs in another order, with other ids
+        assert_eq!(
+            fingerprint(
+                r##"<svg><defs><clipPath id="c"><rect width="5"/></clipPath><linearGradient id="l"/></defs><g clip-path="url(#c)"><rect fill="url(#l)" width="10"/></g></svg>"##
+            ),
+            fingerprint(
+                r##"<svg><defs><linearGradient id="x1"/><clipPath id="x2"><rect width="5"/></clipPath></defs><g clip-path="url(#x2)"><rect width="10" fill="url(#x1)"/></g></svg>"##
+            )
+        );
+        assert_ne!(
+            fingerprint(r#"<svg><rect width="10"/></svg>"#),
+            fingerprint(r#"<svg><rect width="11"/></svg>"#)
+        );
+        // A stylesheet could select the id
+        assert_ne!(
+            fingerprint(r#"<svg><style>#a{fill:red}</style><rect id="a"/></svg>"#),
+            fingerprint(r#"<svg><style>#a{fill:red}</style><rect id="b"/></svg>"#)
+        );
+    }
+}
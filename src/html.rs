@@ -0,0 +1,203 @@
+//! HTML mode: minify inline `<svg>` islands inside an HTML document.
+//!
+//! Only the bytes of each `<svg>...</svg>` element are rewritten; everything
+//! around them (including whitespace, comments and `<script>`/`<style>`
+//! contents) is copied through untouched.
+
+use crate::{Options, minify_with_options};
+
+/// Elements whose content is raw text, so `<svg` inside them is not markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Minify every inline `<svg>` element in an HTML document.
+///
+/// Islands that fail to parse as SVG (e.g. because they rely on HTML-only
+/// syntax like unquoted attributes) are left as they were.
+pub fn minify_html(html: &str, options: &Options) -> String {
+    let options = &options.without_framing();
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+
+    for island in svg_islands(html) {
+        out.push_str(&html[copied..island.start]);
+        let svg = &html[island.clone()];
+        match minify_with_options(svg, options) {
+            Ok(minified) => out.push_str(&minified),
+            Err(_) => out.push_str(svg),
+        }
+        copied = island.end;
+    }
+
+    out.push_str(&html[copied..]);
+    out
+}
+
+/// Find the byte ranges of all top-level `<svg>` elements.
+fn svg_islands(html: &str) -> Vec<std::ops::Range<usize>> {
+    let mut islands = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = html[pos..].find('<') {
+        let start = pos + found;
+        let rest = &html[start..];
+
+        if rest.starts_with("<!--") {
+            pos = skip_comment(html, start);
+            continue;
+        }
+
+        let Some(name) = tag_name(rest) else {
+            pos = start + 1;
+            continue;
+        };
+        let Some(end) = tag_end(html, start) else {
+            break;
+        };
+
+        if name.eq_ignore_ascii_case("svg") {
+            let Some(island_end) = svg_end(html, start, end) else {
+                break;
+            };
+            islands.push(start..island_end);
+            pos = island_end;
+        } else if let Some(raw) = RAW_TEXT_ELEMENTS
+            .iter()
+            .find(|raw| name.eq_ignore_ascii_case(raw))
+        {
+            pos = find_close_tag(html, end, raw).map_or(html.len(), |(_, close_end)| close_end);
+        } else {
+            pos = end;
+        }
+    }
+
+    islands
+}
+
+/// Find the end of an `<svg>` element whose start tag spans `start..open_end`.
+fn svg_end(html: &str, start: usize, open_end: usize) -> Option<usize> {
+    if html[start..open_end].ends_with("/>") {
+        return Some(open_end);
+    }
+
+    let mut depth = 1;
+    let mut pos = open_end;
+    while let Some(found) = html[pos..].find('<') {
+        let at = pos + found;
+        let rest = &html[at..];
+
+        if rest.starts_with("<!--") {
+            pos = skip_comment(html, at);
+        } else if let Some(name) = rest.strip_prefix("</").and_then(tag_name_at) {
+            let end = tag_end(html, at)?;
+            if name.eq_ignore_ascii_case("svg") {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(end);
+                }
+            }
+            pos = end;
+        } else if let Some(name) = tag_name(rest) {
+            let end = tag_end(html, at)?;
+            if name.eq_ignore_ascii_case("svg") && !html[at..end].ends_with("/>") {
+                depth += 1;
+            }
+            pos = end;
+        } else {
+            pos = at + 1;
+        }
+    }
+
+    None
+}
+
+/// Name of the start tag at the beginning of `s` (which starts with `<`).
+fn tag_name(s: &str) -> Option<&str> {
+    tag_name_at(s.strip_prefix('<')?)
+}
+
+fn tag_name_at(s: &str) -> Option<&str> {
+    let len = s
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != ':')
+        .unwrap_or(s.len());
+    let name = &s[..len];
+    let next = s[len..].chars().next();
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && matches!(
+            next,
+            Some('>' | '/') | Some(' ' | '\t' | '\n' | '\r' | '\x0c')
+        );
+    valid.then_some(name)
+}
+
+/// Index just past the `>` that closes the tag starting at `start`, skipping
+/// quoted attribute values.
+fn tag_end(html: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(start + i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn skip_comment(html: &str, start: usize) -> usize {
+    html[start + 4..]
+        .find("-->")
+        .map_or(html.len(), |i| start + 4 + i + 3)
+}
+
+/// Find the `</name>` closing tag at or after `from`.
+fn find_close_tag(html: &str, from: usize, name: &str) -> Option<(usize, usize)> {
+    let mut pos = from;
+    while let Some(found) = html[pos..].find("</") {
+        let at = pos + found;
+        if tag_name_at(&html[at + 2..]).is_some_and(|n| n.eq_ignore_ascii_case(name)) {
+            return Some((at, tag_end(html, at)?));
+        }
+        pos = at + 2;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_html() {
+        let html = r##"<!DOCTYPE html>
+<html>
+<body>
+  <p>Icon:</p>
+  <SVG width="10" height="10"><!-- comment --><rect x="0" y="0" width="10" height="10" fill="#ff0000"/></SVG>
+  <script>const s = "<svg></svg>";</script>
+  <!-- <svg><circle r="1"/></svg> -->
+</body>
+</html>"##;
+        let result = minify_html(html, &Options::default());
+
+        assert!(result.starts_with("<!DOCTYPE html>\n<html>\n<body>\n  <p>Icon:</p>\n  <"));
+        assert!(!result.contains("<!-- comment -->"));
+        assert!(result.contains("fill=\"red\""));
+        assert!(result.contains(r#"const s = "<svg></svg>";"#));
+        assert!(result.contains("<!-- <svg><circle r=\"1\"/></svg> -->"));
+        assert!(result.ends_with("</body>\n</html>"));
+    }
+
+    #[test]
+    fn test_nested_and_invalid_islands() {
+        let html = r#"<div><svg><svg x="1"><rect width="1" height="1"/></svg></svg><svg><rect width=1></svg></div>"#;
+        let islands = svg_islands(html);
+        assert_eq!(islands.len(), 2);
+        assert!(html[islands[0].clone()].ends_with("</svg></svg>"));
+
+        // The second island isn't well-formed XML, so it is kept as-is
+        let result = minify_html(html, &Options::default());
+        assert!(result.ends_with("<svg><rect width=1></svg></div>"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Recompression of embedded raster images.
+//!
+//! Embedded screenshots routinely dominate an SVG's size, and the XML-level
+//! passes can't touch them. [`recompress_images`] decodes base64 `<image>`
+//! payloads, re-encodes them (optionally in another format) and keeps the
+//! result only when it is smaller.
+//!
+//! Requires the `images` feature.
+
+use std::io::Cursor;
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageFormat};
+
+use crate::data_uri::parse_data_uri;
+use crate::{Document, Element};
+
+/// A format to transcode embedded images to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transcode {
+    /// Lossy JPEG at the given quality (1-100), only used for opaque images
+    Jpeg { quality: u8 },
+    /// Lossless WebP
+    WebP,
+}
+
+/// Image recompression options.
+#[derive(Debug, Clone, Default)]
+pub struct RecompressOptions {
+    /// Also try this format, keeping whichever encoding is smallest
+    pub transcode: Option<Transcode>,
+}
+
+/// Re-encode base64 `<image>`/`<feImage>` payloads with better compression.
+///
+/// Payloads that fail to decode are left alone. Returns the number of images
+/// that were rewritten.
+pub fn recompress_images(doc: &mut Document, options: &RecompressOptions) -> usize {
+    fn visit(elem: &mut Element, options: &RecompressOptions, count: &mut usize) {
+        if (elem.is("image") || elem.is("feImage"))
+            && let Some(attr) = elem.attributes.iter_mut().find(|a| a.name.local == "href")
+            && let Some(recompressed) = recompress_data_uri(&attr.value, options)
+        {
+            attr.value = recompressed.into();
+            *count += 1;
+        }
+
+        for child in elem.child_elements_mut() {
+            visit(child, options, count);
+        }
+    }
+
+    let mut count = 0;
+    visit(&mut doc.root, options, &mut count);
+    count
+}
+
+/// Return a smaller data URI for the same image, if one can be found.
+fn recompress_data_uri(uri: &str, options: &RecompressOptions) -> Option<String> {
+    let parsed = parse_data_uri(uri)?;
+    if !parsed.base64 {
+        return None;
+    }
+
+    let format = match parsed.mime.to_ascii_lowercase().as_str() {
+        "image/png" => ImageFormat::Png,
+        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
+        "image/webp" => ImageFormat::WebP,
+        _ => return None,
+    };
+
+    let original = BASE64_STANDARD.decode(parsed.data.trim()).ok()?;
+    let img = image::load_from_memory_with_format(&original, format).ok()?;
+
+    let mut candidates = Vec::new();
+    if format == ImageFormat::Png {
+        candidates.extend(encode_png(&img).map(|data| ("image/png", data)));
+    }
+    match options.transcode {
+        Some(Transcode::Jpeg { quality }) if is_opaque(&img) => {
+            candidates.extend(encode_jpeg(&img, quality).map(|data| ("image/jpeg", data)));
+        }
+        Some(Transcode::WebP) => {
+            candidates.extend(encode_webp(&img).map(|data| ("image/webp", data)));
+        }
+        _ => {}
+    }
+
+    let (mime, data) = candidates.into_iter().min_by_key(|(_, data)| data.len())?;
+    if data.len() >= original.len() {
+        return None;
+    }
+
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        BASE64_STANDARD.encode(data)
+    ))
+}
+
+fn encode_png(img: &DynamicImage) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let encoder =
+        PngEncoder::new_with_quality(&mut out, CompressionType::Best, FilterType::Adaptive);
+    img.write_with_encoder(encoder).ok()?;
+    Some(out)
+}
+
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100));
+    DynamicImage::ImageRgb8(img.to_rgb8())
+        .write_with_encoder(encoder)
+        .ok()?;
+    Some(out)
+}
+
+fn encode_webp(img: &DynamicImage) -> Option<Vec<u8>> {
+    let mut out = Cursor::new(Vec::new());
+    let encoder = WebPEncoder::new_lossless(&mut out);
+    DynamicImage::ImageRgba8(img.to_rgba8())
+        .write_with_encoder(encoder)
+        .ok()?;
+    Some(out.into_inner())
+}
+
+fn is_opaque(img: &DynamicImage) -> bool {
+    !img.color().has_alpha() || img.to_rgba8().pixels().all(|p| p[3] == u8::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+    use image::{Rgb, RgbImage};
+
+    fn png_data_uri(img: &DynamicImage) -> String {
+        let mut out = Vec::new();
+        let encoder = PngEncoder::new_with_quality(
+            &mut out,
+            CompressionType::Uncompressed,
+            FilterType::NoFilter,
+        );
+        img.write_with_encoder(encoder).unwrap();
+        format!("data:image/png;base64,{}", BASE64_STANDARD.encode(out))
+    }
+
+    #[test]
+    fn test_recompress_png() {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(64, 64, Rgb([200, 10, 10])));
+        let uri = png_data_uri(&img);
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><image href="{}"/></svg>"#,
+            uri
+        );
+        let mut doc = parse_svg(&svg).unwrap();
+
+        assert_eq!(
+            recompress_images(&mut doc, &RecompressOptions::default()),
+            1
+        );
+        let href = doc
+            .root
+            .child_elements()
+            .next()
+            .unwrap()
+            .get_attr("href")
+            .unwrap();
+        assert!(href.starts_with("data:image/png;base64,"));
+        assert!(href.len() < uri.len());
+    }
+
+    #[test]
+    fn test_transcode_skips_transparent_jpeg() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            32,
+            32,
+            image::Rgba([0, 0, 0, 0]),
+        ));
+        let options = RecompressOptions {
+            transcode: Some(Transcode::Jpeg { quality: 80 }),
+        };
+        let uri = png_data_uri(&img);
+        let recompressed = recompress_data_uri(&uri, &options).unwrap();
+        assert!(recompressed.starts_with("data:image/png;"));
+    }
+}
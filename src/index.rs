@@ -0,0 +1,303 @@
+//! Indexed, read-only view of a document for passes that need id lookups,
+//! parent links, or the id reference graph.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::css::{css_urls, format_css_url};
+use crate::{Document, Element, Node};
+
+/// Identifies an element within a [`DocumentIndex`].
+///
+/// Elements are numbered in document order, so the root is always
+/// `ElementId(0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ElementId(pub usize);
+
+#[derive(Debug)]
+struct Entry<'a> {
+    element: &'a Element<'a>,
+    parent: Option<ElementId>,
+    /// Position among the parent's child nodes
+    position: usize,
+    references: Vec<&'a str>,
+}
+
+/// Id map, parent links and reference graph for a [`Document`].
+///
+/// The index borrows the document, so it must be rebuilt after mutation.
+/// Use [`DocumentIndex::path`] with [`Document::element_at_mut`] to edit an
+/// element found through the index.
+#[derive(Debug)]
+pub struct DocumentIndex<'a> {
+    entries: Vec<Entry<'a>>,
+    ids: HashMap<&'a str, ElementId>,
+    referrers: HashMap<&'a str, Vec<ElementId>>,
+}
+
+impl<'a> DocumentIndex<'a> {
+    pub fn new(doc: &'a Document<'a>) -> Self {
+        let mut index = Self {
+            entries: Vec::new(),
+            ids: HashMap::new(),
+            referrers: HashMap::new(),
+        };
+        index.visit(&doc.root, None, 0);
+        index
+    }
+
+    fn visit(&mut self, element: &'a Element<'a>, parent: Option<ElementId>, position: usize) {
+        let id = ElementId(self.entries.len());
+
+        // The first element with a given id wins, as in browsers.
+        if let Some(value) = element.get_attr("id") {
+            self.ids.entry(value).or_insert(id);
+        }
+
+        let references = element_references(element);
+        for &target in &references {
+            let referrers = self.referrers.entry(target).or_default();
+            if referrers.last() != Some(&id) {
+                referrers.push(id);
+            }
+        }
+
+        self.entries.push(Entry {
+            element,
+            parent,
+            position,
+            references,
+        });
+
+        for (i, child) in element.children.iter().enumerate() {
+            if let Node::Element(child) = child {
+                self.visit(child, Some(id), i);
+            }
+        }
+    }
+
+    /// The root element's id.
+    pub fn root(&self) -> ElementId {
+        ElementId(0)
+    }
+
+    /// Number of indexed elements.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the index is empty (never true for a parsed document).
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get an indexed element.
+    pub fn get(&self, id: ElementId) -> &'a Element<'a> {
+        self.entries[id.0].element
+    }
+
+    /// Iterate over all elements in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (ElementId, &'a Element<'a>)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (ElementId(i), entry.element))
+    }
+
+    /// Find the element carrying an `id` attribute.
+    pub fn by_id(&self, id: &str) -> Option<ElementId> {
+        self.ids.get(id).copied()
+    }
+
+    /// The element's parent, or `None` for the root.
+    pub fn parent(&self, id: ElementId) -> Option<ElementId> {
+        self.entries[id.0].parent
+    }
+
+    /// Iterate from the element's parent up to the root.
+    pub fn ancestors(&self, id: ElementId) -> impl Iterator<Item = ElementId> + '_ {
+        std::iter::successors(self.parent(id), |&id| self.parent(id))
+    }
+
+    /// Child indices from the root, as accepted by [`Document::element_at`].
+    pub fn path(&self, id: ElementId) -> Vec<usize> {
+        let mut path: Vec<usize> = std::iter::once(id)
+            .chain(self.ancestors(id))
+            .filter(|&id| id != self.root())
+            .map(|id| self.entries[id.0].position)
+            .collect();
+        path.reverse();
+        path
+    }
+
+    /// Ids referenced by this element through `href`, `url(#...)` in
+    /// attributes, or `url(#...)` in a `<style>` element's text.
+    pub fn references(&self, id: ElementId) -> &[&'a str] {
+        &self.entries[id.0].references
+    }
+
+    /// Elements referencing `id`, in document order.
+    pub fn referrers(&self, id: &str) -> &[ElementId] {
+        self.referrers.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether anything in the document references `id`.
+    pub fn is_referenced(&self, id: &str) -> bool {
+        self.referrers.contains_key(id)
+    }
+}
+
+/// Every id something in the document references, for passes that need
+/// only that and not a whole [`DocumentIndex`].
+pub(crate) fn referenced_ids(doc: &Document) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+    doc.for_each_element(|elem| {
+        referenced.extend(element_references(elem).into_iter().map(str::to_string));
+    });
+    referenced
+}
+
+/// Collect the fragment ids an element points at, deduplicated in order.
+fn element_references<'a>(element: &'a Element<'a>) -> Vec<&'a str> {
+    let mut refs = Vec::new();
+    for attr in &element.attributes {
+        if attr.name.local == "href" {
+            if let Some(id) = attr.value.trim().strip_prefix('#') {
+                push(id, &mut refs);
+            }
+        } else {
+            for_each_url_fragment(&attr.value, |id| push(id, &mut refs));
+        }
+    }
+
+    if element.is("style") {
+        for child in &element.children {
+            if let Node::Text(css) | Node::CData(css) = child {
+                for_each_url_fragment(css, |id| push(id, &mut refs));
+            }
+        }
+    }
+
+    refs
+}
+
+fn push<'e>(id: &'e str, refs: &mut Vec<&'e str>) {
+    if !id.is_empty() && !refs.contains(&id) {
+        refs.push(id);
+    }
+}
+
+/// Call `f` with the id of every `url(#id)` token in `css`.
+fn for_each_url_fragment<'s>(css: &'s str, mut f: impl FnMut(&'s str)) {
+    for url in css_urls(css) {
+        // `url` is unquoted; find the same fragment inside the borrowed token
+        // so the reference can outlive the temporary.
+        let Some(fragment) = url.url.strip_prefix('#') else {
+            continue;
+        };
+        if let Some(offset) = css[url.token.clone()].find(fragment) {
+            let start = url.token.start + offset;
+            f(&css[start..start + fragment.len()]);
+        }
+    }
+}
+
+/// Point references to the ids in `renames` at their new ids, in `href`s
+/// and `url(#id)` tokens of `element`'s attributes.
+pub(crate) fn rename_references(element: &mut Element, renames: &HashMap<String, String>) {
+    for attr in &mut element.attributes {
+        if attr.name.local == "href" {
+            let id = attr.value.trim().strip_prefix('#');
+            if let Some(new) = id.and_then(|id| renames.get(id)) {
+                attr.value = format!("#{new}").into();
+            }
+            continue;
+        }
+        // Backwards, so earlier token ranges stay valid
+        for url in css_urls(&attr.value).into_iter().rev() {
+            let id = url.url.strip_prefix('#');
+            if let Some(new) = id.and_then(|id| renames.get(id)) {
+                attr.value
+                    .to_mut()
+                    .replace_range(url.token, &format_css_url(&format!("#{new}")));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_ids_and_parents() {
+        let doc = parse_svg(
+            r#"<svg><defs><path id="p" d="M0 0"/></defs><g id="g"><rect id="p"/></g></svg>"#,
+        )
+        .unwrap();
+        let index = DocumentIndex::new(&doc);
+
+        assert_eq!(index.len(), 5);
+        let p = index.by_id("p").unwrap();
+        assert!(index.get(p).is("path"));
+        assert_eq!(index.path(p), [0, 0]);
+        assert!(index.get(index.parent(p).unwrap()).is("defs"));
+        assert_eq!(index.ancestors(p).last(), Some(index.root()));
+        assert_eq!(index.parent(index.root()), None);
+        assert!(index.by_id("missing").is_none());
+
+        let g = index.by_id("g").unwrap();
+        assert!(std::ptr::eq(
+            doc.element_at(&index.path(g)).unwrap(),
+            index.get(g)
+        ));
+    }
+
+    #[test]
+    fn test_reference_graph() {
+        let doc = parse_svg(
+            r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink">
+                <style>.a { fill: url( "#grad" ) }</style>
+                <linearGradient id="grad"/>
+                <use xlink:href="#icon"/>
+                <use href=" #icon"/>
+                <rect fill="url(#grad)" style="clip-path:url(#clip);mask:url(other.svg#m)"/>
+            </svg>"##,
+        )
+        .unwrap();
+        let index = DocumentIndex::new(&doc);
+
+        assert_eq!(index.referrers("icon").len(), 2);
+        assert_eq!(index.referrers("grad").len(), 2);
+        assert!(index.is_referenced("clip"));
+        assert!(!index.is_referenced("m"));
+        assert!(index.referrers("nothing").is_empty());
+
+        let rect = index.iter().find(|(_, e)| e.is("rect")).unwrap().0;
+        assert_eq!(index.references(rect), ["grad", "clip"]);
+
+        let referenced = referenced_ids(&doc);
+        assert_eq!(referenced.len(), 3);
+        assert!(
+            ["grad", "icon", "clip"]
+                .iter()
+                .all(|id| referenced.contains(*id))
+        );
+        assert!(doc.any_element(|e| e.is("use")));
+        assert!(!doc.any_element(|e| e.is("marker")));
+    }
+
+    #[test]
+    fn test_rename_references() {
+        let mut doc = parse_svg(
+            r##"<svg><use href="#a"/><rect fill="url('#a') red" style="stroke:url(#b);mask:url(#a)"/></svg>"##,
+        )
+        .unwrap();
+        let renames = HashMap::from([("a".to_string(), "z".to_string())]);
+        doc.for_each_element_mut(|elem| rename_references(elem, &renames));
+        assert_eq!(
+            crate::serialize(&doc, &crate::Options::default()),
+            r##"<svg><use href="#z"/><rect fill="url(#z) red" style="stroke:url(#b);mask:url(#z)"/></svg>"##
+        );
+    }
+}
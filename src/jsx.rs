@@ -0,0 +1,391 @@
+//! JSX serialization: emit an SVG as a React component.
+//!
+//! Attributes are renamed to their React spelling (`stroke-width` becomes
+//! `strokeWidth`, `class` becomes `className`, `xlink:href` becomes
+//! `xlinkHref`), `style` attributes become style objects, and the root element
+//! spreads the component's props so callers can override anything.
+//!
+//! Event handler attributes (`onclick`, `onload`, ...) are dropped: React
+//! only accepts functions for its handler props and ignores string ones with
+//! a warning, so the markup's handlers can't carry over.
+//!
+//! Text is collapsed to single spaces the way SVG renders it; spaces that
+//! separate text from neighbouring elements are written as `{" "}` so JSX
+//! doesn't trim them. Under `xml:space="preserve"` text is kept verbatim as
+//! a string expression.
+
+use crate::ast::*;
+
+/// Options for [`serialize_jsx`].
+#[derive(Debug, Clone)]
+pub struct JsxOptions {
+    /// Name of the generated component
+    pub component_name: String,
+}
+
+impl Default for JsxOptions {
+    fn default() -> Self {
+        Self {
+            component_name: "SvgComponent".to_string(),
+        }
+    }
+}
+
+/// Serialize a Document as a React component module.
+///
+/// Comments, processing instructions and the XML prolog are dropped.
+pub fn serialize_jsx(doc: &Document, options: &JsxOptions) -> String {
+    let mut out = String::new();
+    out.push_str("const ");
+    out.push_str(&options.component_name);
+    out.push_str(" = (props) => ");
+    serialize_element(&mut out, &doc.root, true, TextMode::Strip);
+    out.push_str(";\n\nexport default ");
+    out.push_str(&options.component_name);
+    out.push_str(";\n");
+    out
+}
+
+/// How text nodes inside an element are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextMode {
+    /// Outside `<text>`: collapse whitespace and trim it at the edges
+    Strip,
+    /// Inside `<text>`: collapse whitespace, keeping spaces next to elements
+    Collapse,
+    /// Under `xml:space="preserve"`: keep text verbatim
+    Preserve,
+}
+
+fn serialize_element(out: &mut String, elem: &Element, is_root: bool, mode: TextMode) {
+    let mode = match elem.get_attr("space") {
+        Some("preserve") => TextMode::Preserve,
+        Some("default") if mode == TextMode::Preserve => TextMode::Collapse,
+        _ if elem.is("text") && mode == TextMode::Strip => TextMode::Collapse,
+        _ => mode,
+    };
+    let name = jsx_element_name(&elem.name);
+
+    out.push('<');
+    out.push_str(&name);
+
+    for attr in &elem.attributes {
+        if is_event_handler(&attr.name) {
+            continue;
+        }
+        out.push(' ');
+        if attr.name.prefix.is_none() && attr.name.local == "style" {
+            out.push_str("style={");
+            push_style_object(out, &attr.value);
+            out.push('}');
+            continue;
+        }
+        out.push_str(&jsx_attr_name(&attr.name));
+        out.push_str("=\"");
+        push_escaped_attr(out, &attr.value);
+        out.push('"');
+    }
+
+    if is_root {
+        out.push_str(" {...props}");
+    }
+
+    let raw_text = elem.is("style") || elem.is("script");
+    let has_content = elem.children.iter().any(|child| match child {
+        Node::Element(_) | Node::CData(_) => true,
+        Node::Text(text) => mode == TextMode::Preserve || !text.trim().is_empty(),
+        _ => false,
+    });
+
+    if !has_content {
+        out.push_str("/>");
+        return;
+    }
+
+    out.push('>');
+    if raw_text {
+        // CSS and scripts are full of braces, so emit them as a template literal
+        let mut content = String::new();
+        for child in &elem.children {
+            if let Node::Text(text) | Node::CData(text) = child {
+                content.push_str(text);
+            }
+        }
+        out.push_str("{`");
+        push_template_literal(out, content.trim());
+        out.push_str("`}");
+    } else {
+        let last = elem.children.len().saturating_sub(1);
+        for (i, child) in elem.children.iter().enumerate() {
+            match child {
+                Node::Element(child) => serialize_element(out, child, false, mode),
+                Node::Text(text) | Node::CData(text) => match mode {
+                    TextMode::Preserve => {
+                        out.push('{');
+                        push_js_string(out, text);
+                        out.push('}');
+                    }
+                    TextMode::Strip => {
+                        let collapsed = collapse_whitespace(text);
+                        push_escaped_text(out, collapsed.trim());
+                    }
+                    TextMode::Collapse => push_collapsed_text(out, text, i == 0, i == last),
+                },
+                Node::Comment(_) | Node::ProcessingInstruction { .. } => {}
+            }
+        }
+    }
+    out.push_str("</");
+    out.push_str(&name);
+    out.push('>');
+}
+
+/// Write text inside `<text>` with whitespace runs collapsed, spelling a
+/// leading or trailing space as `{" "}` unless it is at the element's edge.
+fn push_collapsed_text(out: &mut String, text: &str, first: bool, last: bool) {
+    let collapsed = collapse_whitespace(text);
+    if collapsed == " " {
+        if !first && !last {
+            out.push_str(r#"{" "}"#);
+        }
+        return;
+    }
+    let inner = collapsed.trim_matches(' ');
+    if collapsed.starts_with(' ') && !first {
+        out.push_str(r#"{" "}"#);
+    }
+    push_escaped_text(out, inner);
+    if collapsed.ends_with(' ') && !last {
+        out.push_str(r#"{" "}"#);
+    }
+}
+
+/// Replace each run of whitespace with a single space.
+fn collapse_whitespace(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut in_space = false;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+/// Whether an attribute is an inline event handler such as `onclick`.
+fn is_event_handler(name: &QName) -> bool {
+    name.prefix.is_none() && name.local.starts_with("on")
+}
+
+/// The JSX spelling of an element name: prefixed names are joined the way
+/// [`jsx_attr_name`] joins attribute names, since React has no namespaced
+/// tags.
+fn jsx_element_name(name: &QName) -> String {
+    match name.prefix.as_deref() {
+        None => name.local.clone(),
+        Some(prefix) => join_prefix(prefix, &name.local),
+    }
+}
+
+/// The React spelling of an attribute name.
+fn jsx_attr_name(name: &QName) -> String {
+    match (name.prefix.as_deref(), name.local.as_str()) {
+        (None, "class") => "className".to_string(),
+        (None, local) if local.starts_with("data-") || local.starts_with("aria-") => {
+            local.to_string()
+        }
+        (None, local) => camel_case(local),
+        (Some(prefix), local) => join_prefix(prefix, &camel_case(local)),
+    }
+}
+
+/// `prefix` followed by `local` with its first letter uppercased.
+fn join_prefix(prefix: &str, local: &str) -> String {
+    let mut out = prefix.to_string();
+    let mut chars = local.chars();
+    if let Some(first) = chars.next() {
+        out.extend(first.to_uppercase());
+        out.push_str(chars.as_str());
+    }
+    out
+}
+
+/// Convert `kebab-case` to `camelCase`.
+fn camel_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut upper = false;
+    for c in s.chars() {
+        if c == '-' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Convert a CSS declaration list into a JS object literal.
+fn push_style_object(out: &mut String, style: &str) {
+    out.push('{');
+    let mut first = true;
+    for decl in style.split(';') {
+        let Some((prop, value)) = decl.split_once(':') else {
+            continue;
+        };
+        let (prop, value) = (prop.trim(), value.trim());
+        if prop.is_empty() {
+            continue;
+        }
+        if !first {
+            out.push(',');
+        }
+        first = false;
+
+        if prop.starts_with("--") {
+            // Custom properties keep their name and need a quoted key
+            push_js_string(out, prop);
+        } else {
+            out.push_str(&camel_case(prop));
+        }
+        out.push(':');
+        push_js_string(out, value);
+    }
+    out.push('}');
+}
+
+fn push_js_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_template_literal(out: &mut String, s: &str) {
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => out.push_str("\\`"),
+            '\\' => out.push_str("\\\\"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn push_escaped_attr(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("&quot;"),
+            '&' => out.push_str("&amp;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn push_escaped_text(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '{' => out.push_str("&#123;"),
+            '}' => out.push_str("&#125;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_svg;
+
+    #[test]
+    fn test_serialize_jsx() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 10 10">
+            <!-- comment -->
+            <path class="icon" stroke-width="2" fill-rule="evenodd" data-name="x" style="stop-color: red; --accent: #fff" d="M0 0"/>
+            <use xlink:href="#a"/>
+            <text xml:space="preserve">a {b}</text>
+        </svg>"##;
+        let doc = parse_svg(svg).unwrap();
+        let out = serialize_jsx(&doc, &JsxOptions::default());
+
+        assert_eq!(
+            out,
+            concat!(
+                r##"const SvgComponent = (props) => <svg xmlns="http://www.w3.org/2000/svg" xmlnsXlink="http://www.w3.org/1999/xlink" viewBox="0 0 10 10" {...props}>"##,
+                r##"<path className="icon" strokeWidth="2" fillRule="evenodd" data-name="x" style={{stopColor:"red","--accent":"#fff"}} d="M0 0"/>"##,
+                r##"<use xlinkHref="#a"/>"##,
+                r##"<text xmlSpace="preserve">{"a {b}"}</text></svg>;"##,
+                "\n\nexport default SvgComponent;\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialize_jsx_style_element() {
+        let svg = r#"<svg><style>.a { fill: red; } .b::after { content: "`${x}`"; }</style></svg>"#;
+        let doc = parse_svg(svg).unwrap();
+        let options = JsxOptions {
+            component_name: "Icon".to_string(),
+        };
+        let out = serialize_jsx(&doc, &options);
+        assert!(
+            out.starts_with("const Icon = (props) => <svg {...props}><style>{`.a { fill: red; }")
+        );
+        assert!(out.contains(r#"content: "\`\${x}\`"; }`}</style>"#));
+    }
+
+    #[test]
+    fn test_serialize_jsx_text_whitespace() {
+        let svg = r#"<svg>
+            <text>
+                Hello   <tspan>world</tspan>
+                <tspan>again</tspan> !
+            </text>
+            <text xml:space="preserve">  two  spaces
+<tspan xml:space="default">  x  </tspan></text>
+            <title>  A
+                title </title>
+        </svg>"#;
+        let out = serialize_jsx(&parse_svg(svg).unwrap(), &JsxOptions::default());
+        assert!(out.contains(
+            r#"<text>Hello{" "}<tspan>world</tspan>{" "}<tspan>again</tspan>{" "}!</text>"#
+        ));
+        assert!(out.contains(
+            r#"<text xmlSpace="preserve">{"  two  spaces\n"}<tspan xmlSpace="default">x</tspan></text>"#
+        ));
+        assert!(out.contains("<title>A title</title>"));
+    }
+
+    #[test]
+    fn test_serialize_jsx_prefixed_element() {
+        let svg = r#"<svg xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"><sodipodi:namedview pagecolor="white"/></svg>"#;
+        let out = serialize_jsx(&parse_svg(svg).unwrap(), &JsxOptions::default());
+        assert!(out.contains(r#"<sodipodiNamedview pagecolor="white"/>"#));
+    }
+
+    #[test]
+    fn test_serialize_jsx_drops_event_handlers() {
+        let svg = r#"<svg onload="init()"><rect onclick="alert(1)" width="1" opacity=".5"/></svg>"#;
+        let out = serialize_jsx(&parse_svg(svg).unwrap(), &JsxOptions::default());
+        assert!(out.starts_with(r#"const SvgComponent = (props) => <svg {...props}><rect width="1" opacity=".5"/></svg>;"#));
+    }
+}
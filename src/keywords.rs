@@ -0,0 +1,349 @@
+//! Keyword value normalization.
+//!
+//! Editors and hand-written markup spell keywords every which way
+//! (`fill="None"`, `fill="transparent"`, `stroke-dasharray="0"`,
+//! `font-weight="inherit"`). Keywords are lowercased, transparent paint
+//! becomes `none`, and values that restate what an element would get anyway
+//! are removed: `inherit` on inherited properties, and dash arrays that draw
+//! a solid line where nothing above them sets dashes.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::bounds::{has_dynamic_content, property};
+use crate::index::referenced_ids;
+
+/// Properties whose values can be a keyword, which CSS matches
+/// case-insensitively.
+const KEYWORD_PROPERTIES: &[&str] = &[
+    "fill",
+    "stroke",
+    "color",
+    "stop-color",
+    "flood-color",
+    "lighting-color",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-dasharray",
+    "fill-rule",
+    "clip-rule",
+    "visibility",
+    "display",
+    "overflow",
+    "text-anchor",
+    "dominant-baseline",
+    "alignment-baseline",
+    "baseline-shift",
+    "font-style",
+    "font-weight",
+    "font-variant",
+    "font-stretch",
+    "text-decoration",
+    "letter-spacing",
+    "word-spacing",
+    "pointer-events",
+    "shape-rendering",
+    "text-rendering",
+    "image-rendering",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-rendering",
+    "mix-blend-mode",
+    "isolation",
+    "writing-mode",
+    "direction",
+    "unicode-bidi",
+    "vector-effect",
+    "paint-order",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "clip-path",
+    "mask",
+    "filter",
+];
+
+/// Properties an element inherits from its parent unless it sets them.
+pub(crate) const INHERITED: &[&str] = &[
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-rendering",
+    "cursor",
+    "direction",
+    "font",
+    "font-family",
+    "font-size",
+    "font-size-adjust",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "glyph-orientation-horizontal",
+    "glyph-orientation-vertical",
+    "image-rendering",
+    "kerning",
+    "letter-spacing",
+    "marker",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "paint-order",
+    "pointer-events",
+    "shape-rendering",
+    "text-anchor",
+    "text-rendering",
+    "visibility",
+    "word-spacing",
+    "writing-mode",
+];
+
+const ANIMATION: &[&str] = &[
+    "animate",
+    "animateColor",
+    "animateMotion",
+    "animateTransform",
+    "set",
+];
+
+struct Context {
+    /// Whether stylesheets, scripts, or animations can restyle elements or
+    /// react to pointer events
+    dynamic: bool,
+    /// Ids something in the document refers to: a `<use>` can give their
+    /// elements other ancestors
+    referenced: HashSet<String>,
+}
+
+/// Lowercase keywords, turn transparent paint into `none`, and remove
+/// values an element would inherit or default to anyway.
+pub(crate) fn normalize_keywords(doc: &mut Document) {
+    let ctx = Context {
+        dynamic: has_dynamic_content(doc),
+        referenced: referenced_ids(doc),
+    };
+    visit(&mut doc.root, &ctx, false, false);
+}
+
+/// `dashed` says whether an ancestor (possibly) sets a dash array, and
+/// `interactive` whether `elem` is inside an element that reacts to the
+/// pointer (see [`is_interactive`]).
+fn visit(elem: &mut Element, ctx: &Context, dashed: bool, interactive: bool) {
+    let referenced = elem
+        .get_attr("id")
+        .is_some_and(|id| ctx.referenced.contains(id));
+    // What an instance inherits depends on the <use>
+    let dashed = dashed || referenced;
+    // Transparent paint is still hit by the pointer, which links, handlers,
+    // and dynamic content react to
+    let interactive = interactive || is_interactive(elem);
+    let keep_transparent = ctx.dynamic || interactive;
+    let normalize = |prop: &str, value: &str, in_style: bool| -> Action {
+        let value = value.trim();
+        let keyword = keyword(value);
+        let value = keyword.as_deref().unwrap_or(value);
+        // A stylesheet rule may set what a `style` declaration overrides
+        if value == "inherit" && INHERITED.contains(&prop) && !(in_style && ctx.dynamic) {
+            return Action::Remove;
+        }
+        if !KEYWORD_PROPERTIES.contains(&prop) {
+            return Action::Keep;
+        }
+        match (prop, value) {
+            ("fill" | "stroke", "transparent") if !keep_transparent => {
+                Action::Replace("none".into())
+            }
+            ("stroke-dasharray", _) if draws_solid(value) && !dashed && !ctx.dynamic => {
+                Action::Remove
+            }
+            _ => keyword.map_or(Action::Keep, Action::Replace),
+        }
+    };
+
+    // On animation elements, `fill` is a timing attribute, with
+    // case-sensitive values
+    let animation = ANIMATION.contains(&elem.name.local.as_str());
+    elem.attributes.retain_mut(|attr| {
+        if attr.name.prefix.is_some() || animation {
+            return true;
+        }
+        match normalize(&attr.name.local, &attr.value, false) {
+            Action::Keep => true,
+            Action::Replace(value) => {
+                attr.value = value.into();
+                true
+            }
+            Action::Remove => false,
+        }
+    });
+
+    if let Some(style) = elem.get_attr("style") {
+        let mut changed = false;
+        let decls: Vec<String> = style
+            .split(';')
+            .filter_map(|decl| {
+                let Some((prop, value)) = decl.split_once(':') else {
+                    return Some(decl.to_string());
+                };
+                let prop = prop.trim();
+                match normalize(prop, value, true) {
+                    Action::Keep => Some(decl.to_string()),
+                    Action::Replace(value) => {
+                        changed = true;
+                        Some(format!("{prop}:{value}"))
+                    }
+                    Action::Remove => {
+                        changed = true;
+                        None
+                    }
+                }
+            })
+            .collect();
+        if changed {
+            if decls.iter().all(|decl| decl.trim().is_empty()) {
+                elem.remove_attr("style");
+            } else {
+                elem.set_attr("style", decls.join(";"));
+            }
+        }
+    }
+
+    let dashed = match property(elem, "stroke-dasharray") {
+        Some("inherit") | None => dashed,
+        Some(value) => !draws_solid(value),
+    };
+    for child in elem.child_elements_mut() {
+        visit(child, ctx, dashed, interactive);
+    }
+}
+
+/// Whether `elem`, and so its content, reacts to the pointer: a link, an
+/// element with an `on*` handler or a `cursor`, or one whose
+/// `pointer-events` are asked for where it's painted (which `none` paint
+/// isn't).
+fn is_interactive(elem: &Element) -> bool {
+    elem.is("a")
+        || elem
+            .attributes
+            .iter()
+            .any(|a| a.name.prefix.is_none() && a.name.local.starts_with("on"))
+        || property(elem, "cursor").is_some()
+        || property(elem, "pointer-events").is_some_and(|value| {
+            value.eq_ignore_ascii_case("painted") || value.eq_ignore_ascii_case("visiblePainted")
+        })
+}
+
+enum Action {
+    Keep,
+    Replace(String),
+    Remove,
+}
+
+/// The canonical spelling of `value`, if it's a keyword spelled otherwise.
+fn keyword(value: &str) -> Option<String> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphabetic() || c == '-') {
+        return None;
+    }
+    let canonical = if value.eq_ignore_ascii_case("currentcolor") {
+        "currentColor".to_string()
+    } else {
+        value.to_ascii_lowercase()
+    };
+    (canonical != value).then_some(canonical)
+}
+
+/// Whether a `stroke-dasharray` value draws a solid line: `none`, or dashes
+/// that add up to nothing.
+fn draws_solid(value: &str) -> bool {
+    let value = value.trim();
+    if value == "none" {
+        return true;
+    }
+    let dashes: Option<Vec<f64>> = value
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            item.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%')
+                .parse::<f64>()
+                .ok()
+        })
+        .collect();
+    dashes.is_some_and(|dashes| !dashes.is_empty() && dashes.iter().all(|&n| n == 0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn normalized(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        normalize_keywords(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_normalize_keywords() {
+        assert_eq!(
+            normalized(
+                r#"<svg><g fill="None" stroke="Transparent" font-weight="inherit" style="stroke-linecap:Round;fill:inherit"><rect fill="currentcolor" stroke-dasharray="none" opacity="inherit"/><path stroke-dasharray="0 0px" preserveAspectRatio="None"/><path stroke-dasharray="0 1"/></g><a><rect fill="transparent"/></a></svg>"#
+            ),
+            r#"<svg><g fill="none" stroke="none" style="stroke-linecap:round"><rect fill="currentColor" opacity="inherit"/><path preserveAspectRatio="None"/><path stroke-dasharray="0 1"/></g><a><rect fill="transparent"/></a></svg>"#
+        );
+
+        // Handlers, cursors, and painted pointer events keep transparent
+        // paint hit by the pointer, on the element and its content
+        for svg in [
+            r#"<svg><rect fill="transparent" onclick="x()"/></svg>"#,
+            r#"<svg><rect fill="transparent" style="cursor:pointer"/></svg>"#,
+            r#"<svg><g cursor="pointer"><rect fill="transparent"/></g></svg>"#,
+            r#"<svg><g onmouseover="x()"><rect stroke="transparent"/></g></svg>"#,
+            r#"<svg><rect fill="transparent" pointer-events="painted"/></svg>"#,
+        ] {
+            assert_eq!(normalized(svg), svg);
+        }
+        assert_eq!(
+            normalized(r#"<svg><rect fill="transparent" pointer-events="fill"/></svg>"#),
+            r#"<svg><rect fill="none" pointer-events="fill"/></svg>"#
+        );
+
+        // On animations, `fill` isn't paint
+        let svg = r#"<svg><rect><set attributeName="x" fill="Freeze" to="1"/></rect></svg>"#;
+        assert_eq!(normalized(svg), svg);
+    }
+
+    #[test]
+    fn test_keep_inherited_dashes_off() {
+        // Under a dashed parent, `none` turns dashes off
+        let svg = r#"<svg><g stroke-dasharray="4 2"><path stroke-dasharray="none"/><g stroke-dasharray="0"><path stroke-dasharray="none"/></g></g></svg>"#;
+        assert_eq!(
+            normalized(svg),
+            r#"<svg><g stroke-dasharray="4 2"><path stroke-dasharray="none"/><g stroke-dasharray="0"><path/></g></g></svg>"#
+        );
+
+        // A <use> can give a referenced element dashed ancestors, and a
+        // stylesheet can set them
+        for svg in [
+            r##"<svg><path id="p" stroke-dasharray="none"/><use href="#p" stroke-dasharray="2"/></svg>"##,
+            r#"<svg><style>g{stroke-dasharray:2}</style><g><path stroke-dasharray="none" fill="transparent" style="fill:inherit"/></g></svg>"#,
+        ] {
+            assert_eq!(
+                normalized(svg),
+                serialize(&parse_svg(svg).unwrap(), &Options::default())
+            );
+        }
+    }
+}
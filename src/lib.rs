@@ -1,21 +1,108 @@
 //! svag - An SVG minifier
 //!
 //! svag optimizes SVG files while maintaining visual fidelity.
+//!
+//! Output is deterministic: the same input and [`Options`] produce the same
+//! bytes on every platform, run, and thread count, so minified files can be
+//! content-addressed.
 
+mod analyze;
+pub(crate) mod arena;
+mod assets;
 mod ast;
+mod audit;
+mod bounds;
+pub mod build;
+mod canonical;
+pub mod check;
+mod classes;
+mod clip;
+mod codegen;
+mod color;
+mod compress;
+mod conditional;
+mod coords;
+mod crop;
+mod css;
+mod current_color;
+mod custom_properties;
+mod data_uri;
+mod diff;
+mod duplicates;
+mod entities;
 mod error;
+mod external;
+mod extract;
+mod filters;
 mod fonts;
+pub mod geometry;
+mod glob;
+mod hash;
+mod html;
+#[cfg(feature = "images")]
+mod images;
+mod index;
+mod jsx;
+mod keywords;
+mod markers;
+mod merge;
+mod namespaces;
+mod nested_svg;
+mod occlusion;
 mod optimize;
+mod ordering;
 mod parse;
-mod path;
+pub mod path;
+mod pattern_defs;
+mod pipeline;
+mod recolor;
+mod regroup;
+mod resize;
 mod serialize;
+mod sprite;
+mod styles;
+mod stylesheet;
+mod svg_images;
+pub mod transform;
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "visual")]
+pub mod visual;
+mod xml_stylesheet;
 
+pub use analyze::*;
+pub use assets::*;
 pub use ast::*;
+pub use audit::*;
+pub use canonical::*;
+pub use codegen::*;
+pub use color::*;
+pub use current_color::*;
+pub use custom_properties::*;
+pub use diff::*;
 pub use error::*;
+pub use external::*;
+pub use extract::*;
 pub use fonts::*;
+pub use glob::*;
+pub use hash::*;
+pub use html::*;
+#[cfg(feature = "images")]
+pub use images::*;
+pub use index::*;
+pub use jsx::*;
 pub use optimize::*;
 pub use parse::*;
+pub use pipeline::*;
+pub use recolor::*;
+pub use resize::*;
 pub use serialize::*;
+pub use sprite::*;
+pub use stylesheet::*;
+pub use svg_images::*;
+#[cfg(feature = "verify")]
+pub use verify::*;
+pub use xml_stylesheet::*;
 
 /// Minify an SVG string with default settings.
 pub fn minify(svg: &str) -> Result<String, SvagError> {
@@ -24,16 +111,113 @@ pub fn minify(svg: &str) -> Result<String, SvagError> {
 
 /// Minify an SVG string with custom options.
 pub fn minify_with_options(svg: &str, options: &Options) -> Result<String, SvagError> {
-    let mut doc = parse_svg(svg)?;
+    let mut doc = parse_with_options(svg, options)?;
     optimize(&mut doc, options);
     Ok(serialize(&doc, options))
 }
 
+/// Minify an SVG string into `out`, reusing its allocation.
+///
+/// `out` is cleared first, and left empty if parsing fails.
+///
+/// ```
+/// let options = svag::Options::default();
+/// let mut out = String::new();
+/// for icon in [r#"<svg><rect width="10"/></svg>"#, "<svg><g/></svg>"] {
+///     svag::minify_into(icon, &options, &mut out).unwrap();
+///     assert!(out.starts_with("<svg"));
+/// }
+/// assert!(svag::minify_into("<svg><g>", &options, &mut out).is_err());
+/// assert!(out.is_empty());
+/// ```
+pub fn minify_into(svg: &str, options: &Options, out: &mut String) -> Result<(), SvagError> {
+    out.clear();
+    let mut doc = parse_with_options(svg, options)?;
+    optimize(&mut doc, options);
+    serialize::serialize_document(out, &doc, options);
+    Ok(())
+}
+
+/// Parse strictly, or leniently (dropping the warnings) if the options ask
+/// for it.
+fn parse_with_options<'a>(svg: &'a str, options: &Options) -> Result<Document<'a>, SvagError> {
+    if options.lenient {
+        Ok(parse_svg_lenient(svg)?.0)
+    } else {
+        parse_svg(svg)
+    }
+}
+
+/// Which renderers the output has to work in. Rewrites that rely on SVG 2
+/// or newer CSS are only made when the target supports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SvgTarget {
+    /// SVG 1.1 renderers: Batik, print RIPs, older rasterizers
+    Svg11,
+    /// SVG 2 renderers
+    Svg2,
+    /// Current browsers: SVG 2, plus CSS Color 4 syntax such as `#rrggbbaa`
+    #[default]
+    Browsers,
+}
+
+impl SvgTarget {
+    /// Whether SVG 2 syntax (plain `href`) is understood, and SVG 1.1-only
+    /// attributes (`version`, `baseProfile`, `requiredFeatures`) are ignored.
+    pub fn is_svg2(self) -> bool {
+        self != SvgTarget::Svg11
+    }
+
+    /// Whether colors may be written as `#rgba`/`#rrggbbaa`.
+    pub fn supports_hex_alpha(self) -> bool {
+        self == SvgTarget::Browsers
+    }
+
+    /// Whether CSS custom properties (`var(--name)`) are understood.
+    pub fn supports_custom_properties(self) -> bool {
+        self == SvgTarget::Browsers
+    }
+}
+
+/// What the output size is measured as, for decisions where the smallest
+/// raw output isn't the smallest once compressed (and vice versa).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptimizeFor {
+    /// Uncompressed bytes
+    #[default]
+    RawSize,
+    /// Bytes once gzipped, as most servers send SVG
+    GzipSize,
+    /// Bytes once compressed with brotli
+    BrotliSize,
+}
+
 /// Minification options.
 #[derive(Debug, Clone)]
 pub struct Options {
     /// Number of decimal places for coordinates (default: 2)
     pub precision: u8,
+    /// Number of decimal places for the scale, rotation, and skew parts of
+    /// transforms (default: 5)
+    pub transform_precision: u8,
+    /// Number of decimal places for opacities, which show rounding sooner
+    /// than coordinates do (default: 3)
+    pub opacity_precision: u8,
+    /// Renderers the output must work in (default: current browsers)
+    pub target: SvgTarget,
+    /// Whether to break ties in favor of raw or compressed size (attribute
+    /// order, relative or absolute path commands, extracted classes)
+    pub optimize_for: OptimizeFor,
+    /// Recover from malformed input (unclosed tags, stray `&`, HTML entities,
+    /// unquoted or duplicate attributes) instead of failing; see
+    /// [`parse_svg_lenient`]
+    pub lenient: bool,
+    /// Loads stylesheets linked with `<?xml-stylesheet?>` so they can be
+    /// inlined into `<style>` elements
+    pub stylesheet_resolver: Option<StylesheetResolver>,
+    /// Loads SVG files referenced by `<image>` so they can be inlined as
+    /// minified `data:` URIs
+    pub image_resolver: Option<ImageResolver>,
     /// Remove comments
     pub remove_comments: bool,
     /// Remove metadata elements
@@ -44,44 +228,232 @@ pub struct Options {
     pub remove_doctype: bool,
     /// Remove unused namespaces
     pub remove_unused_namespaces: bool,
+    /// Move namespace declarations from inner elements to the root, and
+    /// merge prefixes bound to the same URI
+    pub hoist_namespaces: bool,
+    /// Drop element prefixes that name the default namespace, as in
+    /// `<svg:rect>` in an SVG document
+    pub unprefix_elements: bool,
+    /// Rewrite `xlink:href` to SVG 2 `href` on use, image, gradient,
+    /// pattern, and textPath elements
+    pub modernize_href: bool,
+    /// Remove shapes an identical, opaque copy right above them paints
+    /// over again (copy-paste leftovers)
+    pub remove_duplicate_shapes: bool,
     /// Collapse unnecessary groups
     pub collapse_groups: bool,
     /// Remove hidden elements
     pub remove_hidden: bool,
-    /// Remove empty containers
+    /// Remove empty containers and text elements
     pub remove_empty: bool,
+    /// Remove attributes with empty or whitespace-only values
+    pub remove_empty_attrs: bool,
+    /// Remove attributes matching any of these patterns, such as
+    /// `data-figma-*` or `path[class]`
+    pub remove_attrs: Vec<AttrPattern>,
+    /// Remove elements whose names match any of these globs, with their
+    /// content
+    pub remove_elements: Vec<Glob>,
+    /// If set, remove every element (with its content) whose name matches
+    /// none of these globs. The root is always kept.
+    pub keep_elements: Option<Vec<Glob>>,
+    /// Remove conditional processing attributes (`systemLanguage`,
+    /// `requiredExtensions`, `requiredFeatures`) that always hold, elements
+    /// whose conditions never do, and `<switch>` branches that can't render
+    pub prune_conditions: bool,
+    /// If set, the reader's languages (e.g. `en-US`): `systemLanguage`
+    /// conditions are evaluated for them, so each `<switch>` keeps only the
+    /// child that renders. Applies even without `prune_conditions`.
+    pub system_language: Option<Vec<String>>,
+    /// Remove `xml:lang` from elements without text content
+    pub remove_xml_lang: bool,
+    /// Remove `xml:base` where no relative URL resolves against it
+    pub remove_xml_base: bool,
+    /// Remove `xml:space="default"` where it's already inherited
+    pub remove_xml_space: bool,
     /// Minify colors (#ffffff -> #fff)
     pub minify_colors: bool,
     /// Remove default attribute values
     pub remove_defaults: bool,
+    /// Convert ellipses with equal radii to circles
+    pub convert_ellipses: bool,
     /// Minify path data
     pub minify_paths: bool,
-    /// Minify styles
+    /// Round transforms and drop the functions that become no-ops
+    pub minify_transforms: bool,
+    /// Collapse the whitespace and commas separating items in list-valued
+    /// attributes (`points`, `viewBox`, `class`, ...) to single spaces
+    pub normalize_lists: bool,
+    /// Round opacities to `opacity_precision`, and dash, `letter-spacing`,
+    /// and `baseline-shift` lengths to `precision`, in their shortest form
+    pub minify_numbers: bool,
+    /// Lowercase keywords, turn `transparent` paint into `none` (outside
+    /// links, elements with `on*` handlers, a `cursor`, or painted
+    /// `pointer-events`, and dynamic content, where it can still catch the
+    /// pointer), and remove `inherit` on inherited properties and dash
+    /// arrays that draw a solid line anyway
+    pub normalize_keywords: bool,
+    /// Fold `fill-opacity`, `stroke-opacity`, `stop-opacity`, and
+    /// `flood-opacity` into the hex color they apply to (`#f008`), for
+    /// targets that support it. Alpha is quantized to 8 bits (default: off)
+    pub hex_alpha: bool,
+    /// If set, replace this fill and stroke color with `currentColor`, so
+    /// the icon takes the `color` of the page it's embedded in (default:
+    /// off)
+    pub current_color: Option<CurrentColor>,
+    /// Colors to write as `var(--name, color)`, declared in a `:root` rule
+    /// so CSS can theme them, for targets that support it (see
+    /// [`extract_custom_properties`])
+    pub custom_properties: Vec<CustomProperty>,
+    /// Minify `style` attributes and `<style>` rules: drop overridden
+    /// declarations, merge `font-*` longhands into `font`, and drop the
+    /// unit of zero lengths
     pub minify_styles: bool,
+    /// Sort `<defs>` children, referenced definitions first, then by element
+    /// name and id, so files from the same family compress alike and diff
+    /// cleanly (default: off)
+    pub sort_defs: bool,
     /// Merge adjacent paths with same attributes
     pub merge_paths: bool,
     /// Sort attributes for better gzip
     pub sort_attrs: bool,
+    /// Write `<style>` and `<script>` content as a CDATA section or as
+    /// escaped text, whichever is shorter
+    pub convert_cdata: bool,
+    /// End the output with a newline
+    pub final_newline: bool,
+    /// Text of a comment written before the root element, such as a
+    /// generator or license notice. It's written even when other comments
+    /// are removed.
+    pub banner: Option<String>,
+    /// If set, the accessible name to give the document: a `<title>` as
+    /// the root's first child, added after the passes so metadata removal
+    /// doesn't drop it (see [`Document::set_title`])
+    pub title: Option<String>,
+    /// Deduplicate repeated embedded images and `@font-face` rules, merge
+    /// `@font-face` rules embedding the same font under other family
+    /// names, and (with custom properties in the target) declare
+    /// `url(data:...)` values repeated in styles once on the root
+    pub dedupe_data_uris: bool,
+    /// Remove `clip-path`/`mask` references that don't hide anything, and
+    /// their definitions once unused
+    pub remove_noop_clips: bool,
+    /// Turn inner `<svg>` elements that only offset their content into
+    /// `<g transform="translate(x,y)">`
+    pub flatten_nested_svg: bool,
+    /// Prune `<filter>` primitives and names that don't affect the output,
+    /// round their numbers, and drop filters that change nothing
+    pub optimize_filters: bool,
+    /// Merge identical `<marker>` definitions, and remove marker properties
+    /// that reference no marker or apply to nothing that draws markers
+    pub optimize_markers: bool,
+    /// Round `<pattern>` tiles, drop default units, merge identical
+    /// patterns, and replace ones that paint nothing with `none`
+    pub optimize_patterns: bool,
+    /// Wrap runs of siblings sharing presentation attributes or a transform
+    /// in a `<g>` carrying them, when that's smaller. Off by default: it
+    /// restructures the document.
+    pub regroup_siblings: bool,
+    /// Replace presentation attribute sets repeated on many elements with
+    /// generated classes in a `<style>` element. Off by default: the rules
+    /// compete with CSS from a page embedding the SVG, and some
+    /// rasterizers ignore `<style>`.
+    pub extract_classes: bool,
+    /// If set, shrink the root `viewBox` (and `width` and `height` with it)
+    /// to the painted content plus this margin, in user units, moving the
+    /// content to the origin when that's shorter (default: off)
+    pub autocrop: Option<f64>,
+    /// If set, the size to give the document, after cropping. Without a
+    /// `viewBox`, the content is scaled to match (see [`scale_document`]).
+    pub resize: Option<Size>,
+    /// Remove shapes fully covered by a later opaque sibling. Lossy by
+    /// analysis: relies on bounds and opacity reasoning, so it's opt-in.
+    pub remove_occluded: bool,
+    /// If set, the passes to run, in this order and as many times as
+    /// they're listed, instead of the ones the flags above enable (see
+    /// [`Options::pipeline`]). Passes still take their settings from here.
+    pub passes: Option<Pipeline>,
+    /// Called before and after every pass, to log, stop early, or keep
+    /// intermediate states
+    pub on_pass: Option<PassHook>,
+}
+
+impl Options {
+    /// These options without the banner and final newline, for output
+    /// embedded in another file rather than written as one.
+    pub(crate) fn without_framing(&self) -> Options {
+        Options {
+            final_newline: false,
+            banner: None,
+            ..self.clone()
+        }
+    }
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             precision: 2,
+            transform_precision: 5,
+            opacity_precision: 3,
+            target: SvgTarget::default(),
+            optimize_for: OptimizeFor::default(),
+            lenient: false,
+            stylesheet_resolver: None,
+            image_resolver: None,
             remove_comments: true,
             remove_metadata: true,
             remove_xml_declaration: true,
             remove_doctype: true,
             remove_unused_namespaces: true,
+            hoist_namespaces: true,
+            unprefix_elements: true,
+            modernize_href: true,
+            remove_duplicate_shapes: true,
             collapse_groups: true,
             remove_hidden: true,
             remove_empty: true,
+            remove_empty_attrs: true,
+            remove_attrs: Vec::new(),
+            remove_elements: Vec::new(),
+            keep_elements: None,
+            prune_conditions: true,
+            system_language: None,
+            remove_xml_lang: true,
+            remove_xml_base: true,
+            remove_xml_space: true,
             minify_colors: true,
             remove_defaults: true,
+            convert_ellipses: true,
             minify_paths: true,
+            minify_transforms: true,
+            normalize_lists: true,
+            minify_numbers: true,
+            normalize_keywords: true,
+            hex_alpha: false,
+            current_color: None,
+            custom_properties: Vec::new(),
             minify_styles: true,
+            sort_defs: false,
             merge_paths: false, // conservative default - can break things
             sort_attrs: true,
+            convert_cdata: true,
+            final_newline: false,
+            banner: None,
+            title: None,
+            dedupe_data_uris: true,
+            remove_noop_clips: true,
+            flatten_nested_svg: true,
+            optimize_filters: true,
+            optimize_markers: true,
+            optimize_patterns: true,
+            regroup_siblings: false,
+            extract_classes: false,
+            autocrop: None,
+            resize: None,
+            remove_occluded: false,
+            passes: None,
+            on_pass: None,
         }
     }
 }
@@ -1,17 +1,73 @@
+use std::borrow::Cow;
 use std::fs;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cli::compare::{self, Comparison, run_command};
+use cli::log::{Progress, debug, error, info, trace, warning};
+use cli::read::{Input, gzip};
+use cli::report::{FailureKind, FileError, FileRecord, ReportFormat, summary, write_report};
+use cli::write::write_atomic;
 use ignore::WalkBuilder;
 use rayon::prelude::*;
-use svag::{Options, minify_with_options};
+use svag::{
+    Document, ImageResolver, JsxOptions, Options, PassStats, RustStyle, StylesheetResolver,
+    SvagError, SvgTarget, analyze, build_sprite, check, diff_documents, generate_rust,
+    minify_css_data_uris, minify_html, optimize, optimize_with_stats, parse_svg, parse_svg_lenient,
+    serialize, serialize_jsx,
+};
+
+mod cli;
 
 #[derive(Parser)]
 #[command(name = "svag")]
 #[command(about = "An SVG minifier", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(after_help = "Exit codes:
+  0   success
+  1   invalid arguments, failed checks, or other errors
+  65  an input could not be parsed or minified
+  74  a file could not be read or written")]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    minify: MinifyArgs,
+
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print per-file details (-vv adds per-pass savings)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Minify SVG files (the default when no command is given)
+    Minify(MinifyArgs),
+    /// Print where a file's bytes go: a size breakdown by kind of content
+    /// and the heaviest elements
+    Analyze(AnalyzeArgs),
+    /// Combine icons into one sprite sheet of `<symbol>` elements
+    Sprite(SpriteArgs),
+    /// Print a structural diff of what minification would change, without
+    /// writing anything
+    Diff(DiffArgs),
+    /// Check that minified output parses back to the same document and
+    /// doesn't change when minified again
+    Check(CheckArgs),
+}
+
+#[derive(Args)]
+struct MinifyArgs {
     /// Input file or directory (use - for stdin)
     #[arg(default_value = "-")]
     input: PathBuf,
@@ -20,10 +76,132 @@ struct Cli {
     #[arg(short, long, default_value = "-")]
     output: PathBuf,
 
+    #[command(flatten)]
+    optimize: OptimizeArgs,
+
+    /// Print size comparison
+    #[arg(short, long)]
+    stats: bool,
+
+    /// Show a progress line in directory mode (on a terminal)
+    #[arg(long)]
+    progress: bool,
+
+    /// Benchmark mode: process files but don't write output, print JSON stats
+    #[arg(long)]
+    bench: bool,
+
+    /// Treat input as HTML and minify its inline <svg> elements.
+    /// In directory mode, processes .html/.htm files instead of .svg files.
+    #[arg(long, conflicts_with_all = ["css", "jsx"])]
+    html: bool,
+
+    /// Treat input as CSS and minify its embedded SVG data URIs.
+    /// In directory mode, processes .css files instead of .svg files.
+    #[arg(long, conflicts_with = "jsx")]
+    css: bool,
+
+    /// Output a React component instead of SVG (single file mode only)
+    #[arg(long)]
+    jsx: bool,
+
+    /// Read the files to process from a newline- or NUL-delimited list
+    /// (use - for stdin). Files are minified in-place, as in directory mode.
+    #[arg(long, value_name = "LIST", conflicts_with_all = ["input", "jsx"])]
+    files_from: Option<PathBuf>,
+
+    /// Keep a copy of each overwritten file with this suffix (.bak, or
+    /// another given as --backup=SUFFIX)
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak"
+    )]
+    backup: Option<String>,
+
+    /// Overwrite files in place even when the output isn't smaller
+    #[arg(long)]
+    force: bool,
+
+    /// Number of files to process in parallel (defaults to RAYON_NUM_THREADS,
+    /// or the number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Process files one at a time (same as --jobs 1)
+    #[arg(long, conflicts_with = "jobs")]
+    sequential: bool,
+
+    /// Stop at the first file that fails (same as --max-errors 1)
+    #[arg(long, conflicts_with = "max_errors")]
+    fail_fast: bool,
+
+    /// Stop after this many files have failed
+    #[arg(long, value_name = "N")]
+    max_errors: Option<usize>,
+
+    /// Don't report inputs that can't be parsed as errors or exit with a
+    /// failure status for them; they're written out unchanged
+    #[arg(long)]
+    no_error_on_unoptimized: bool,
+
+    /// Emit a per-file report (sizes, per-pass savings, timing, errors). For
+    /// a summary of a directory run, use --stats
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    report: Option<ReportFormat>,
+
+    /// Where to write the report (use - for stdout)
+    #[arg(long, value_name = "PATH", default_value = "-", requires = "report")]
+    report_output: PathBuf,
+
+    /// Render input and output with resvg and keep the original when they differ
+    #[cfg(feature = "verify")]
+    #[arg(long, conflicts_with_all = ["html", "css", "jsx"])]
+    verify: bool,
+
+    /// Minimum SSIM score for --verify to accept the output
+    #[cfg(feature = "verify")]
+    #[arg(
+        long,
+        value_name = "SCORE",
+        default_value = "0.999",
+        requires = "verify"
+    )]
+    verify_threshold: f64,
+
+    /// Output a Rust module with one constant per icon instead of SVG
+    #[arg(long, value_enum, conflicts_with_all = ["html", "css", "jsx", "bench"])]
+    rust: Option<RustFormat>,
+
+    /// Also pipe each file through COMMAND (run by the shell, reading the
+    /// input on stdin and writing its output to stdout) and print how their
+    /// sizes and times compare, e.g. 'svgo --input - --output -'. Nothing
+    /// is written.
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        conflicts_with_all = ["html", "css", "jsx", "bench", "rust", "report"]
+    )]
+    compare_with: Option<String>,
+}
+
+/// What to optimize and how, shared by the commands that minify.
+#[derive(Args)]
+struct OptimizeArgs {
     /// Precision for coordinates (decimal places)
     #[arg(short, long, default_value = "2")]
     precision: u8,
 
+    /// Precision for the scale, rotation, and skew parts of transforms
+    #[arg(long, default_value = "5")]
+    transform_precision: u8,
+
+    /// Precision for opacities (decimal places)
+    #[arg(long, default_value = "3")]
+    opacity_precision: u8,
+
     /// Keep XML declaration
     #[arg(long)]
     keep_xml_declaration: bool,
@@ -44,189 +222,1299 @@ struct Cli {
     #[arg(long)]
     no_minify_colors: bool,
 
+    /// Renderers the output must work in
+    #[arg(long, value_enum, default_value = "browsers")]
+    target: Target,
+
+    /// Size to minimize where choices trade raw bytes against
+    /// compressibility
+    #[arg(long, value_enum, default_value = "raw")]
+    optimize_for: OptimizeFor,
+
+    /// Keep `xlink:href` instead of rewriting it to `href` (for SVG 1.1
+    /// renderers)
+    #[arg(long)]
+    keep_xlink: bool,
+
+    /// Remove attributes matching PATTERN: a name glob like `data-figma-*`,
+    /// optionally scoped as `path[class]` or `*[fill=none]` (repeatable)
+    #[arg(
+        long = "remove-attr",
+        value_name = "PATTERN",
+        conflicts_with = "no_optimize"
+    )]
+    remove_attrs: Vec<svag::AttrPattern>,
+
+    /// Remove elements whose names match GLOB, with their content
+    /// (repeatable)
+    #[arg(
+        long = "remove-element",
+        value_name = "GLOB",
+        conflicts_with = "no_optimize"
+    )]
+    remove_elements: Vec<svag::Glob>,
+
+    /// Keep only elements whose names match one of these globs, removing
+    /// the rest with their content (the root is always kept)
+    #[arg(
+        long,
+        value_name = "GLOBS",
+        value_delimiter = ',',
+        conflicts_with = "no_optimize"
+    )]
+    keep_elements: Option<Vec<svag::Glob>>,
+
+    /// Keep only the `<switch>` branches that render for these languages
+    /// (comma-separated, e.g. `de,en`)
+    #[arg(
+        long,
+        value_name = "LANGS",
+        value_delimiter = ',',
+        conflicts_with = "no_optimize"
+    )]
+    system_language: Option<Vec<String>>,
+
+    /// Inline stylesheets linked with `<?xml-stylesheet href="…"?>` from
+    /// files next to the input (URLs are left alone)
+    #[arg(long, conflicts_with = "no_optimize")]
+    inline_stylesheets: bool,
+
+    /// Inline SVG files referenced by `<image>` next to the input as
+    /// minified data URIs, for self-contained output (URLs are left alone)
+    #[arg(long, conflicts_with = "no_optimize")]
+    inline_images: bool,
+
+    /// Remove shapes fully hidden behind a later opaque sibling (lossy by
+    /// analysis: trusts svag's bounds and opacity reasoning)
+    #[arg(long, conflicts_with = "no_optimize")]
+    remove_occluded: bool,
+
+    /// Shrink the viewBox, width, and height to the painted content, with
+    /// a margin in user units given as --autocrop=MARGIN (default 0)
+    #[arg(
+        long,
+        value_name = "MARGIN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        conflicts_with = "no_optimize"
+    )]
+    autocrop: Option<f64>,
+
+    /// Width to give the output; without --height, the height follows
+    /// the aspect ratio (content without a viewBox is scaled to match)
+    #[arg(long, value_name = "PX", conflicts_with = "no_optimize")]
+    width: Option<f64>,
+
+    /// Height to give the output; without --width, the width follows the
+    /// aspect ratio
+    #[arg(long, value_name = "PX", conflicts_with = "no_optimize")]
+    height: Option<f64>,
+
+    /// Wrap runs of siblings with shared attributes in a `<g>` when that
+    /// makes the output smaller
+    #[arg(long, conflicts_with = "no_optimize")]
+    regroup_siblings: bool,
+
+    /// Move presentation attributes repeated on many elements into
+    /// generated classes (the rules compete with CSS of a page embedding
+    /// the SVG)
+    #[arg(long, conflicts_with = "no_optimize")]
+    extract_classes: bool,
+
+    /// Sort `<defs>` children (referenced first, then by element name and
+    /// id), for smaller archives and stable diffs across a family of icons
+    #[arg(long, conflicts_with = "no_optimize")]
+    sort_defs: bool,
+
+    /// Fold opacities into the colors they apply to, as `#rrggbbaa`
+    /// (browsers target only; alpha is quantized to 8 bits)
+    #[arg(long, conflicts_with = "no_optimize")]
+    hex_alpha: bool,
+
+    /// Replace the most used fill and stroke color, or the one given as
+    /// --current-color=COLOR, with `currentColor`, so icons take the page's
+    /// text color
+    #[arg(
+        long,
+        value_name = "COLOR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "dominant",
+        conflicts_with = "no_optimize"
+    )]
+    current_color: Option<svag::CurrentColor>,
+
+    /// Write this color as `var(--NAME, COLOR)` and declare it in a `:root`
+    /// rule, for theming from CSS (repeatable; browsers target only)
+    #[arg(
+        long = "custom-property",
+        value_name = "NAME=COLOR",
+        conflicts_with = "no_optimize"
+    )]
+    custom_properties: Vec<svag::CustomProperty>,
+
+    /// Run these passes, in this order, instead of the ones the other
+    /// flags enable: comma-separated names as listed by --stats, which may
+    /// repeat (e.g. remove_hidden,collapse_groups,minify_paths)
+    #[arg(long, value_name = "PASSES", conflicts_with = "no_optimize")]
+    passes: Option<svag::Pipeline>,
+
+    /// End output files with a newline
+    #[arg(long)]
+    final_newline: bool,
+
+    /// Comment to write before the root element (e.g. a license notice),
+    /// kept even though other comments are removed
+    #[arg(long, value_name = "TEXT")]
+    banner: Option<String>,
+
+    /// Accessible name for the output, as a `<title>` first in the root
+    /// (replacing the root's own, even with metadata removed)
+    #[arg(long, value_name = "TEXT")]
+    add_title: Option<String>,
+
+    /// Repair malformed input (unclosed tags, stray `&`, unquoted or
+    /// duplicate attributes) with a warning instead of failing
+    #[arg(long)]
+    lenient: bool,
+
     /// Disable all optimizations (just parse and re-serialize)
     #[arg(long)]
     no_optimize: bool,
+}
 
-    /// Print size comparison
-    #[arg(short, long)]
-    stats: bool,
+impl OptimizeArgs {
+    /// The library options these flags ask for.
+    fn options(&self) -> Options {
+        if self.no_optimize {
+            Options {
+                remove_comments: false,
+                remove_metadata: false,
+                remove_xml_declaration: false,
+                remove_doctype: false,
+                remove_unused_namespaces: false,
+                hoist_namespaces: false,
+                unprefix_elements: false,
+                modernize_href: false,
+                remove_duplicate_shapes: false,
+                collapse_groups: false,
+                remove_hidden: false,
+                remove_empty: false,
+                remove_empty_attrs: false,
+                remove_attrs: Vec::new(),
+                remove_elements: Vec::new(),
+                keep_elements: None,
+                prune_conditions: false,
+                system_language: None,
+                remove_xml_lang: false,
+                remove_xml_base: false,
+                remove_xml_space: false,
+                minify_colors: false,
+                remove_defaults: false,
+                convert_ellipses: false,
+                minify_paths: false,
+                minify_transforms: false,
+                normalize_lists: false,
+                minify_numbers: false,
+                normalize_keywords: false,
+                minify_styles: false,
+                sort_defs: false,
+                merge_paths: false,
+                sort_attrs: false,
+                convert_cdata: false,
+                dedupe_data_uris: false,
+                remove_noop_clips: false,
+                flatten_nested_svg: false,
+                optimize_filters: false,
+                optimize_markers: false,
+                optimize_patterns: false,
+                regroup_siblings: false,
+                extract_classes: false,
+                hex_alpha: false,
+                current_color: None,
+                autocrop: None,
+                resize: None,
+                custom_properties: Vec::new(),
+                remove_occluded: false,
+                passes: None,
+                on_pass: None,
+                precision: self.precision,
+                transform_precision: self.transform_precision,
+                opacity_precision: self.opacity_precision,
+                target: self.target.into(),
+                optimize_for: self.optimize_for.into(),
+                lenient: self.lenient,
+                stylesheet_resolver: None,
+                image_resolver: None,
+                final_newline: self.final_newline,
+                banner: self.banner.clone(),
+                title: self.add_title.clone(),
+            }
+        } else {
+            Options {
+                precision: self.precision,
+                transform_precision: self.transform_precision,
+                opacity_precision: self.opacity_precision,
+                target: self.target.into(),
+                optimize_for: self.optimize_for.into(),
+                lenient: self.lenient,
+                final_newline: self.final_newline,
+                banner: self.banner.clone(),
+                title: self.add_title.clone(),
+                remove_xml_declaration: !self.keep_xml_declaration,
+                remove_doctype: !self.keep_doctype,
+                remove_comments: !self.keep_comments,
+                minify_paths: !self.no_minify_paths,
+                minify_colors: !self.no_minify_colors,
+                modernize_href: !self.keep_xlink,
+                remove_attrs: self.remove_attrs.clone(),
+                remove_elements: self.remove_elements.clone(),
+                keep_elements: self.keep_elements.clone(),
+                system_language: self.system_language.clone(),
+                remove_occluded: self.remove_occluded,
+                regroup_siblings: self.regroup_siblings,
+                extract_classes: self.extract_classes,
+                hex_alpha: self.hex_alpha,
+                current_color: self.current_color,
+                autocrop: self.autocrop,
+                resize: (self.width.is_some() || self.height.is_some()).then_some(svag::Size {
+                    width: self.width,
+                    height: self.height,
+                }),
+                custom_properties: self.custom_properties.clone(),
+                sort_defs: self.sort_defs,
+                passes: self.passes.clone(),
+                ..Options::default()
+            }
+        }
+    }
+}
 
-    /// Benchmark mode: process files but don't write output, print JSON stats
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// Input file (use - for stdin)
+    #[arg(default_value = "-")]
+    input: PathBuf,
+
+    /// Print a JSON object instead of a table
     #[arg(long)]
-    bench: bool,
+    json: bool,
+
+    /// Number of heaviest elements to list
+    #[arg(long, value_name = "N", default_value = "20")]
+    top: usize,
+
+    /// Repair malformed input instead of failing
+    #[arg(long)]
+    lenient: bool,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[derive(Args)]
+struct SpriteArgs {
+    /// Icon files, or directories of them. Each icon becomes a `<symbol>`
+    /// with its file name (without extension) as id.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output file (use - for stdout)
+    #[arg(short, long, default_value = "-")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    optimize: OptimizeArgs,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Input file (use - for stdin)
+    #[arg(default_value = "-")]
+    input: PathBuf,
+
+    #[command(flatten)]
+    optimize: OptimizeArgs,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// Input file or directory (use - for stdin)
+    #[arg(default_value = "-")]
+    input: PathBuf,
+
+    #[command(flatten)]
+    optimize: OptimizeArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Target {
+    /// SVG 1.1 renderers (Batik, print RIPs, older rasterizers)
+    Svg11,
+    /// SVG 2 renderers
+    Svg2,
+    /// Current browsers
+    Browsers,
+}
+
+impl From<Target> for SvgTarget {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Svg11 => SvgTarget::Svg11,
+            Target::Svg2 => SvgTarget::Svg2,
+            Target::Browsers => SvgTarget::Browsers,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OptimizeFor {
+    /// Uncompressed bytes
+    Raw,
+    /// Gzipped bytes
+    Gzip,
+    /// Brotli-compressed bytes
+    Brotli,
+}
+
+impl From<OptimizeFor> for svag::OptimizeFor {
+    fn from(optimize_for: OptimizeFor) -> Self {
+        match optimize_for {
+            OptimizeFor::Raw => svag::OptimizeFor::RawSize,
+            OptimizeFor::Gzip => svag::OptimizeFor::GzipSize,
+            OptimizeFor::Brotli => svag::OptimizeFor::BrotliSize,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum RustFormat {
+    /// `pub const NAME: &str = "<svg ...>";`
+    Str,
+    /// `pub const NAME: Icon = Icon { view_box, attrs, body };`
+    Struct,
+}
+
+/// An input could not be parsed or minified (`EX_DATAERR`).
+const EXIT_PARSE_FAILURE: u8 = 65;
+/// A file could not be read or written (`EX_IOERR`).
+const EXIT_IO_FAILURE: u8 = 74;
+
+/// Entries listed per section of the `--stats` directory summary.
+const SUMMARY_LIMIT: usize = 10;
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    cli::log::init(cli.quiet, cli.verbose);
 
-    // Build options
-    let options = if cli.no_optimize {
-        Options {
-            remove_comments: false,
-            remove_metadata: false,
-            remove_xml_declaration: false,
-            remove_doctype: false,
-            remove_unused_namespaces: false,
-            collapse_groups: false,
-            remove_hidden: false,
-            remove_empty: false,
-            minify_colors: false,
-            remove_defaults: false,
-            minify_paths: false,
-            minify_styles: false,
-            merge_paths: false,
-            sort_attrs: false,
-            precision: cli.precision,
-        }
-    } else {
-        Options {
-            precision: cli.precision,
-            remove_xml_declaration: !cli.keep_xml_declaration,
-            remove_doctype: !cli.keep_doctype,
-            remove_comments: !cli.keep_comments,
-            minify_paths: !cli.no_minify_paths,
-            minify_colors: !cli.no_minify_colors,
-            ..Options::default()
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e);
+            if e.is::<io::Error>() {
+                ExitCode::from(EXIT_IO_FAILURE)
+            } else {
+                ExitCode::FAILURE
+            }
         }
-    };
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    match &cli.command {
+        None => process_minify(&cli.minify),
+        Some(Command::Minify(args)) => process_minify(args),
+        Some(Command::Analyze(args)) => process_analyze(args),
+        Some(Command::Sprite(args)) => process_sprite(args),
+        Some(Command::Diff(args)) => process_diff(args),
+        Some(Command::Check(args)) => process_check(args),
+    }
+}
 
-    // Check if input is a directory
-    if cli.input.is_dir() {
-        process_directory(&cli, &options)?;
+fn process_minify(args: &MinifyArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // Without an explicit count, rayon uses RAYON_NUM_THREADS or the CPU count
+    let jobs = if args.sequential {
+        Some(1)
     } else {
-        process_single_file(&cli, &options)?;
+        args.jobs.map(NonZeroUsize::get)
+    };
+    if let Some(jobs) = jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
     }
 
-    Ok(())
-}
+    let options = args.optimize.options();
 
-fn process_single_file(cli: &Cli, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
-    // Read input
-    let input = if cli.input.as_os_str() == "-" {
-        let mut buf = String::new();
-        io::stdin().read_to_string(&mut buf)?;
-        buf
+    if let Some(format) = args.rust {
+        process_rust(args, &options, format)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(command) = &args.compare_with {
+        return process_compare(args, &options, command);
+    }
+
+    let many = args.files_from.is_some() || args.input.is_dir();
+    let stdout_taken = if many {
+        args.bench
     } else {
-        fs::read_to_string(&cli.input)?
+        args.output.as_os_str() == "-"
     };
+    if args.report.is_some() && args.report_output.as_os_str() == "-" && stdout_taken {
+        return Err("--report needs --report-output when the output goes to stdout".into());
+    }
+
+    // Check if input is a directory or a file list
+    if many {
+        if args.jsx {
+            return Err("--jsx is only supported for single files".into());
+        }
+        process_files(args, &options)
+    } else {
+        process_single_file(args, &options)
+    }
+}
+
+fn process_single_file(
+    args: &MinifyArgs,
+    options: &Options,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let options = &file_options(&args.optimize, options, &args.input);
 
+    // Read input
+    let Input {
+        text: input,
+        gzipped,
+        len: file_len,
+    } = Input::read(&args.input)?;
     let input_len = input.len();
 
     // Minify
-    let output = minify_with_options(&input, options)?;
+    let mut passes = args.report.is_some().then(Vec::new);
+    let result = minify_input(
+        args,
+        &args.input.display().to_string(),
+        &input,
+        options,
+        passes.as_mut(),
+    );
+
+    if let Some(format) = args.report {
+        let mut record = FileRecord::new(args.input.display().to_string());
+        record.original = input_len;
+        match &result {
+            Ok(output) => record.minified = output.len(),
+            Err(e) => {
+                record.minified = input_len;
+                record.error = Some(FileError::new(FailureKind::Parse, e));
+            }
+        }
+        record.passes = passes.unwrap_or_default();
+        record.duration = start.elapsed();
+        emit_report(args, format, &[record])?;
+    }
+
+    let output = match result {
+        Ok(output) if !verified(args, &args.input.display().to_string(), &input, &output) => input,
+        Ok(output) => output,
+        Err(_) if args.no_error_on_unoptimized => input,
+        Err(e) => {
+            error!("{}: {}", args.input.display(), e);
+            return Ok(ExitCode::from(EXIT_PARSE_FAILURE));
+        }
+    };
     let output_len = output.len();
 
-    // Write output
-    if cli.output.as_os_str() == "-" {
+    // Write output, gzipped again when rewriting an .svgz file
+    if args.output.as_os_str() == "-" {
         io::stdout().write_all(output.as_bytes())?;
     } else {
-        fs::write(&cli.output, &output)?;
+        let in_place = args.input.as_os_str() != "-"
+            && fs::canonicalize(&args.input).ok() == fs::canonicalize(&args.output).ok();
+        let bytes = if (in_place && gzipped) || args.output.extension().is_some_and(|e| e == "svgz")
+        {
+            gzip(&output)?
+        } else {
+            output.into_bytes()
+        };
+        if !in_place || args.force || bytes.len() < file_len {
+            write_atomic(&args.output, &bytes, args.backup.as_deref())?;
+        }
     }
 
     // Print stats if requested
-    if cli.stats {
+    if args.stats {
         let saved = input_len.saturating_sub(output_len);
         let percent = if input_len > 0 {
             (saved as f64 / input_len as f64) * 100.0
         } else {
             0.0
         };
-        eprintln!(
+        info!(
             "{} -> {} bytes ({:.1}% smaller)",
             input_len, output_len, percent
         );
     }
 
+    Ok(ExitCode::SUCCESS)
+}
+
+fn process_diff(args: &DiffArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let options = args.optimize.options();
+    let options = &file_options(&args.optimize, &options, &args.input);
+    let input = Input::read(&args.input)?.text;
+
+    let before = match parse_input(&args.input.display().to_string(), &input, options) {
+        Ok(doc) => doc,
+        Err(e) => {
+            error!("{}: {}", args.input.display(), e);
+            return Ok(ExitCode::from(EXIT_PARSE_FAILURE));
+        }
+    };
+    let mut after = before.clone();
+    optimize(&mut after, options);
+
+    let changes = diff_documents(&before, &after);
+    let mut stdout = io::stdout().lock();
+    for change in &changes {
+        writeln!(stdout, "{}", change)?;
+    }
+
+    info!(
+        "{} changes, {} -> {} bytes",
+        changes.len(),
+        input.len(),
+        serialize(&after, options).len()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn process_analyze(args: &AnalyzeArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let path = args.input.display().to_string();
+    let input = Input::read(&args.input)?.text;
+    let options = Options {
+        lenient: args.lenient,
+        ..Options::default()
+    };
+    let doc = match parse_input(&path, &input, &options) {
+        Ok(doc) => doc,
+        Err(e) => {
+            error!("{}: {}", path, e);
+            return Ok(ExitCode::from(EXIT_PARSE_FAILURE));
+        }
+    };
+
+    let analysis = analyze(&doc);
+    let mut stdout = io::stdout().lock();
+    if args.json {
+        writeln!(
+            stdout,
+            "{}",
+            cli::analyze::to_json(&path, &analysis, args.top)
+        )?;
+    } else {
+        write!(
+            stdout,
+            "{}",
+            cli::analyze::to_text(&path, &analysis, args.top)
+        )?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn process_sprite(args: &SpriteArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let options = args.optimize.options();
+
+    let mut files = Vec::new();
+    for input in &args.inputs {
+        if input.is_dir() {
+            files.extend(walk(input, &["svg", "svgz"]));
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    // Every icon is read and parsed before giving up, so one run names all
+    // the broken ones
+    let mut icons: Vec<(String, Document)> = Vec::new();
+    let (mut unreadable, mut unparseable) = (0, 0);
+    for path in &files {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if icons.iter().any(|(other, _)| *other == name) {
+            return Err(format!("two icons are named {:?}", name).into());
+        }
+
+        let options = &file_options(&args.optimize, &options, path);
+        let input = match Input::read(path) {
+            Ok(input) => input.text,
+            Err(e) => {
+                error!("{}: {}", path.display(), e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        let mut doc = match parse_input(&path.display().to_string(), &input, options) {
+            Ok(doc) => doc,
+            Err(e) => {
+                error!("{}: {}", path.display(), e);
+                unparseable += 1;
+                continue;
+            }
+        };
+        optimize(&mut doc, options);
+        icons.push((name, doc.into_owned()));
+    }
+
+    if unreadable + unparseable > 0 {
+        error!(
+            "{} icons could not be read and {} could not be parsed, no sprite written",
+            unreadable, unparseable
+        );
+        return Ok(ExitCode::from(if unreadable > 0 {
+            EXIT_IO_FAILURE
+        } else {
+            EXIT_PARSE_FAILURE
+        }));
+    }
+
+    let count = icons.len();
+    let output = serialize(&build_sprite(icons), &options);
+    if args.output.as_os_str() == "-" {
+        io::stdout().write_all(output.as_bytes())?;
+    } else {
+        write_atomic(&args.output, output.as_bytes(), None)?;
+    }
+    info!("{} icons, {} bytes", count, output.len());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn process_check(args: &CheckArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let options = args.optimize.options();
+    let files = if args.input.is_dir() {
+        walk(&args.input, &["svg", "svgz"])
+    } else {
+        vec![args.input.clone()]
+    };
+
+    let (mut failed, mut unparseable, mut unreadable) = (0, 0, 0);
+    let mut stdout = io::stdout().lock();
+    for path in &files {
+        let name = path.display().to_string();
+        let options = &file_options(&args.optimize, &options, path);
+        let input = match Input::read(path) {
+            Ok(input) => input.text,
+            Err(e) => {
+                error!("{}: {}", name, e);
+                unreadable += 1;
+                continue;
+            }
+        };
+        let mut doc = match parse_input(&name, &input, options) {
+            Ok(doc) => doc,
+            Err(e) => {
+                error!("{}: {}", name, e);
+                unparseable += 1;
+                continue;
+            }
+        };
+        optimize(&mut doc, options);
+
+        let mut violations = check::roundtrip(&doc, options);
+        match check::idempotent(&input, options) {
+            Ok(more) => violations.extend(more),
+            Err(e) => {
+                error!("{}: {}", name, e);
+                unparseable += 1;
+                continue;
+            }
+        }
+        for violation in &violations {
+            writeln!(stdout, "{}: {}", name, violation)?;
+        }
+        if !violations.is_empty() {
+            failed += 1;
+        }
+    }
+
+    info!(
+        "{} files checked, {} failed, {} could not be parsed, {} could not be read",
+        files.len(),
+        failed,
+        unparseable,
+        unreadable
+    );
+    Ok(if unreadable > 0 {
+        ExitCode::from(EXIT_IO_FAILURE)
+    } else if unparseable > 0 {
+        ExitCode::from(EXIT_PARSE_FAILURE)
+    } else if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn process_compare(
+    args: &MinifyArgs,
+    options: &Options,
+    command: &str,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let files = if args.files_from.is_some() || args.input.is_dir() {
+        collect_files(args)?
+    } else {
+        vec![args.input.clone()]
+    };
+
+    let comparisons: Vec<Comparison> = files
+        .par_iter()
+        .map(|path| {
+            let name = path.display().to_string();
+            let input = match Input::read(path) {
+                Ok(input) => input.text,
+                Err(e) => {
+                    let error = e.to_string();
+                    return Comparison {
+                        path: name,
+                        original: 0,
+                        svag: Err(error.clone()),
+                        other: Err(error),
+                    };
+                }
+            };
+            let options = &file_options(&args.optimize, options, path);
+
+            let start = Instant::now();
+            let svag = minify_input(args, &name, &input, options, None)
+                .map(|output| (output.len(), start.elapsed()))
+                .map_err(|e| e.to_string());
+            let other = run_command(command, input.as_bytes())
+                .map(|(output, elapsed)| (output.len(), elapsed))
+                .map_err(|e| e.to_string());
+            for error in [&svag, &other].into_iter().filter_map(|r| r.as_ref().err()) {
+                error!("{}: {}", name, error);
+            }
+            Comparison {
+                path: name,
+                original: input.len(),
+                svag,
+                other,
+            }
+        })
+        .collect();
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", compare::header())?;
+    for comparison in &comparisons {
+        writeln!(stdout, "{}", compare::row(comparison))?;
+    }
+    for line in compare::totals(&comparisons) {
+        writeln!(stdout, "{}", line)?;
+    }
+
+    Ok(if comparisons.iter().any(|c| c.svag.is_err()) {
+        ExitCode::from(EXIT_PARSE_FAILURE)
+    } else if comparisons.iter().any(|c| c.other.is_err()) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn process_rust(
+    args: &MinifyArgs,
+    options: &Options,
+    format: RustFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut files = if args.files_from.is_some() || args.input.is_dir() {
+        collect_files(args)?
+    } else {
+        vec![args.input.clone()]
+    };
+    files.sort();
+
+    let mut icons = Vec::new();
+    for path in &files {
+        let options = &file_options(&args.optimize, options, path);
+        let input = Input::read(path)?.text;
+        match parse_input(&path.display().to_string(), &input, options) {
+            Ok(mut doc) => {
+                optimize(&mut doc, options);
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                icons.push((name, doc.into_owned()));
+            }
+            Err(e) => warning!("skipping {}: {}", path.display(), e),
+        }
+    }
+
+    let icons: Vec<(&str, &svag::Document)> = icons
+        .iter()
+        .map(|(name, doc)| (name.as_str(), doc))
+        .collect();
+    let style = match format {
+        RustFormat::Str => RustStyle::Str,
+        RustFormat::Struct => RustStyle::Struct,
+    };
+    let output = generate_rust(&icons, options, style);
+
+    if args.output.as_os_str() == "-" {
+        io::stdout().write_all(output.as_bytes())?;
+    } else {
+        write_atomic(&args.output, output.as_bytes(), args.backup.as_deref())?;
+    }
+
     Ok(())
 }
 
-fn process_directory(cli: &Cli, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
-    // Collect all SVG files
-    let files: Vec<PathBuf> = WalkBuilder::new(&cli.input)
+/// Collect the files to process from `--files-from` or by walking the input
+/// directory.
+fn collect_files(args: &MinifyArgs) -> io::Result<Vec<PathBuf>> {
+    if let Some(list) = &args.files_from {
+        let mut buf = Vec::new();
+        if list.as_os_str() == "-" {
+            io::stdin().read_to_end(&mut buf)?;
+        } else {
+            buf = fs::read(list)?;
+        }
+        return Ok(parse_file_list(&buf));
+    }
+
+    let extensions: &[&str] = if args.html {
+        &["html", "htm"]
+    } else if args.css {
+        &["css"]
+    } else {
+        &["svg", "svgz"]
+    };
+    Ok(walk(&args.input, extensions))
+}
+
+/// The files below `dir` with one of `extensions`.
+fn walk(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    // Sorted, so reports list files in the same order on every filesystem
+    WalkBuilder::new(dir)
         .git_ignore(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
         .build()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().is_some_and(|ext| ext == "svg"))
+        .filter(|e| {
+            e.path()
+                .extension()
+                .is_some_and(|ext| extensions.iter().any(|x| ext == *x))
+        })
         .map(|e| e.path().to_path_buf())
-        .collect();
+        .collect()
+}
 
-    let file_count = files.len();
+/// Split a file list on NUL bytes if it has any (as from `find -print0` or
+/// `git ls-files -z`), otherwise on newlines.
+fn parse_file_list(buf: &[u8]) -> Vec<PathBuf> {
+    let text = String::from_utf8_lossy(buf);
+    let separator = if buf.contains(&0) { '\0' } else { '\n' };
+    text.split(separator)
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
 
-    if cli.bench {
-        // Benchmark mode: process in parallel, collect stats
-        let total_original = AtomicUsize::new(0);
-        let total_minified = AtomicUsize::new(0);
-        let success_count = AtomicUsize::new(0);
-        let fail_count = AtomicUsize::new(0);
+fn process_files(
+    args: &MinifyArgs,
+    options: &Options,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let files = collect_files(args)?;
 
-        let start = std::time::Instant::now();
+    let file_count = files.len();
+    let start = Instant::now();
 
-        files.par_iter().for_each(|path| {
-            if let Ok(input) = fs::read_to_string(path) {
-                let input_len = input.len();
-                total_original.fetch_add(input_len, Ordering::Relaxed);
+    let max_errors = if args.fail_fast {
+        Some(1)
+    } else {
+        args.max_errors
+    };
+    let error_count = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let progress = Progress::new(file_count, args.progress);
 
-                match minify_with_options(&input, options) {
-                    Ok(output) => {
-                        total_minified.fetch_add(output.len(), Ordering::Relaxed);
-                        success_count.fetch_add(1, Ordering::Relaxed);
-                    }
-                    Err(_) => {
-                        total_minified.fetch_add(input_len, Ordering::Relaxed);
-                        fail_count.fetch_add(1, Ordering::Relaxed);
-                    }
+    // Minify in parallel; in benchmark mode nothing is written. Once the
+    // error budget is used up, files that haven't started are skipped.
+    let records: Vec<FileRecord> = files
+        .par_iter()
+        .filter_map(|path| {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            let record = process_file(args, options, path);
+            log_record(&record);
+            if record.error.is_some() {
+                let errors = error_count.fetch_add(1, Ordering::Relaxed) + 1;
+                if max_errors.is_some_and(|max| errors >= max) {
+                    stop.store(true, Ordering::Relaxed);
                 }
             }
-        });
+            progress.tick(&record.path);
+            Some(record)
+        })
+        .collect();
+    progress.finish();
+
+    let elapsed = start.elapsed();
+
+    if let Some(format) = args.report {
+        emit_report(args, format, &records)?;
+    }
+
+    let failed = records.iter().filter(|r| r.error.is_some()).count();
 
-        let elapsed = start.elapsed();
-        let orig = total_original.load(Ordering::Relaxed);
-        let mini = total_minified.load(Ordering::Relaxed);
-        let succ = success_count.load(Ordering::Relaxed);
-        let fail = fail_count.load(Ordering::Relaxed);
+    if args.bench {
+        let orig: usize = records.iter().map(|r| r.original).sum();
+        let mini: usize = records.iter().map(|r| r.minified).sum();
 
         // Output JSON for easy parsing
         println!(
             r#"{{"files":{},"success":{},"failed":{},"original":{},"minified":{},"saved":{},"time_ms":{:.2}}}"#,
             file_count,
-            succ,
-            fail,
+            file_count - failed,
+            failed,
             orig,
             mini,
             orig.saturating_sub(mini),
             elapsed.as_secs_f64() * 1000.0
         );
+    } else if args.stats {
+        for line in summary(&records, SUMMARY_LIMIT) {
+            info!("{}", line);
+        }
+    }
+
+    if records.len() < file_count {
+        warning!(
+            "stopped early, {} files skipped",
+            file_count - records.len()
+        );
+    }
+
+    let failure = |kind| {
+        records
+            .iter()
+            .any(|r| r.error.as_ref().is_some_and(|e| e.kind == kind))
+    };
+    Ok(if failure(FailureKind::Io) {
+        ExitCode::from(EXIT_IO_FAILURE)
+    } else if failure(FailureKind::Parse) {
+        ExitCode::from(EXIT_PARSE_FAILURE)
     } else {
-        // Regular mode: minify in-place
-        let processed = AtomicUsize::new(0);
-        let failed = AtomicUsize::new(0);
-
-        files.par_iter().for_each(|path| {
-            if let Ok(input) = fs::read_to_string(path) {
-                match minify_with_options(&input, options) {
-                    Ok(output) => {
-                        if fs::write(path, &output).is_ok() {
-                            processed.fetch_add(1, Ordering::Relaxed);
-                        } else {
-                            failed.fetch_add(1, Ordering::Relaxed);
+        ExitCode::SUCCESS
+    })
+}
+
+/// Log the outcome of one file: errors always, sizes with `-v`, per-pass
+/// savings with `-vv`.
+fn log_record(record: &FileRecord) {
+    if let Some(error) = &record.error {
+        error!("{}: {}", record.path, error.message);
+        return;
+    }
+
+    debug!(
+        "{}: {} -> {} bytes ({:.1}ms)",
+        record.path,
+        record.original,
+        record.minified,
+        record.duration.as_secs_f64() * 1000.0
+    );
+    for pass in record.passes.iter().filter(|p| p.saved != 0) {
+        trace!("  {}: {} bytes", pass.name, pass.saved);
+    }
+}
+
+/// Minify one file in place (or only measure it in `--bench` mode).
+fn process_file(args: &MinifyArgs, options: &Options, path: &Path) -> FileRecord {
+    let start = Instant::now();
+    let options = &file_options(&args.optimize, options, path);
+    let mut record = FileRecord::new(path.display().to_string());
+
+    match Input::read(path) {
+        Ok(Input {
+            text: input,
+            gzipped,
+            len: file_len,
+        }) => {
+            record.original = input.len();
+            let want_passes = args.report.is_some() || cli::log::enabled(cli::log::Level::Trace);
+            let mut passes = want_passes.then(Vec::new);
+
+            // .svgz files are written back gzipped, and compared by their
+            // size on disk
+            let encode = |output: String| {
+                if gzipped {
+                    gzip(&output)
+                } else {
+                    Ok(output.into_bytes())
+                }
+            };
+            match minify_input(args, &record.path, &input, options, passes.as_mut()) {
+                Ok(output) if args.bench => record.minified = output.len(),
+                Ok(output) => match encode(output.clone()) {
+                    Ok(bytes)
+                        if (!args.force && bytes.len() >= file_len)
+                            || !verified(args, &record.path, &input, &output) =>
+                    {
+                        // Not worth rewriting, or it would look different;
+                        // the file stays as it was
+                        record.minified = input.len();
+                    }
+                    Ok(bytes) => {
+                        record.minified = output.len();
+                        if let Err(e) = write_atomic(path, &bytes, args.backup.as_deref()) {
+                            record.error = Some(FileError::new(FailureKind::Io, e));
                         }
                     }
-                    Err(_) => {
-                        failed.fetch_add(1, Ordering::Relaxed);
+                    Err(e) => {
+                        record.minified = input.len();
+                        record.error = Some(FileError::new(FailureKind::Io, e));
+                    }
+                },
+                Err(e) => {
+                    // Unparseable files are left untouched
+                    record.minified = input.len();
+                    if !args.no_error_on_unoptimized {
+                        record.error = Some(FileError::new(FailureKind::Parse, e));
                     }
                 }
             }
-        });
+            record.passes = passes.unwrap_or_default();
+        }
+        Err(e) => record.error = Some(FileError::new(FailureKind::Io, e)),
+    }
+
+    record.duration = start.elapsed();
+    record
+}
 
-        if cli.stats {
-            eprintln!(
-                "Processed {} files, {} failed",
-                processed.load(Ordering::Relaxed),
-                failed.load(Ordering::Relaxed)
+/// With `--verify`, check that `output` renders like `input`. Returns `false`
+/// (after logging why) when the original should be kept.
+#[cfg(feature = "verify")]
+fn verified(args: &MinifyArgs, path: &str, input: &str, output: &str) -> bool {
+    if !args.verify {
+        return true;
+    }
+
+    match svag::verify_fidelity(input, output, args.verify_threshold) {
+        Ok(fidelity) if fidelity.passed => true,
+        Ok(fidelity) => {
+            warning!(
+                "{}: kept original, SSIM {:.5} is below {}",
+                path,
+                fidelity.score,
+                args.verify_threshold
             );
+            false
+        }
+        Err(e) => {
+            warning!("{}: kept original, could not verify: {}", path, e);
+            false
         }
     }
+}
 
-    Ok(())
+#[cfg(not(feature = "verify"))]
+fn verified(_args: &MinifyArgs, _path: &str, _input: &str, _output: &str) -> bool {
+    true
+}
+
+fn emit_report(args: &MinifyArgs, format: ReportFormat, records: &[FileRecord]) -> io::Result<()> {
+    if args.report_output.as_os_str() == "-" {
+        write_report(&mut io::stdout().lock(), format, records)
+    } else {
+        write_report(&mut fs::File::create(&args.report_output)?, format, records)
+    }
+}
+
+/// Minify a whole SVG file, or the embedded SVGs of an HTML (`--html`) or
+/// CSS (`--css`) file.
+///
+/// When `passes` is given, per-pass savings are recorded into it (SVG and
+/// JSX output only).
+fn minify_input(
+    args: &MinifyArgs,
+    path: &str,
+    input: &str,
+    options: &Options,
+    passes: Option<&mut Vec<PassStats>>,
+) -> Result<String, SvagError> {
+    if args.html {
+        return Ok(minify_html(input, options));
+    } else if args.css {
+        return Ok(minify_css_data_uris(input, options));
+    }
+
+    let mut doc = parse_input(path, input, options)?;
+    match passes {
+        Some(passes) => *passes = optimize_with_stats(&mut doc, options),
+        None => optimize(&mut doc, options),
+    }
+
+    if args.jsx {
+        let jsx_options = JsxOptions {
+            component_name: component_name(&args.input),
+        };
+        Ok(serialize_jsx(&doc, &jsx_options))
+    } else {
+        Ok(serialize(&doc, options))
+    }
+}
+
+/// The options for one input: with `--inline-stylesheets` or
+/// `--inline-images`, the files it links to are loaded relative to its
+/// directory.
+fn file_options<'a>(
+    optimize: &OptimizeArgs,
+    options: &'a Options,
+    path: &Path,
+) -> Cow<'a, Options> {
+    if !optimize.inline_stylesheets && !optimize.inline_images {
+        return Cow::Borrowed(options);
+    }
+    let dir = match path.parent() {
+        Some(dir) if path.as_os_str() != "-" => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut options = options.clone();
+    if optimize.inline_stylesheets {
+        let dir = dir.clone();
+        options.stylesheet_resolver =
+            Some(StylesheetResolver::new(move |href| read_local(&dir, href)));
+    }
+    if optimize.inline_images {
+        options.image_resolver = Some(ImageResolver::new(move |href| {
+            let is_svg = href.ends_with(".svg") || href.ends_with(".svgz");
+            is_svg.then(|| read_local(&dir, href)).flatten()
+        }));
+    }
+    Cow::Owned(options)
+}
+
+/// Read a file an input links to by relative path, within its directory
+/// (see [`svag::local_path`]). URLs aren't fetched.
+fn read_local(dir: &Path, href: &str) -> Option<String> {
+    let path = svag::local_path(dir, href)?;
+    Input::read(&path).ok().map(|input| input.text)
+}
+
+/// Parse an SVG, logging what was repaired with `--lenient`.
+fn parse_input<'a>(
+    path: &str,
+    input: &'a str,
+    options: &Options,
+) -> Result<Document<'a>, SvagError> {
+    if !options.lenient {
+        return parse_svg(input);
+    }
+    let (doc, warnings) = parse_svg_lenient(input)?;
+    for warning in warnings {
+        warning!("{}: {}", path, warning);
+    }
+    Ok(doc)
+}
+
+/// Derive a PascalCase component name from a file name, e.g.
+/// `arrow-left.svg` becomes `ArrowLeft`.
+fn component_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+
+    let mut name = String::new();
+    for word in stem.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.push_str(chars.as_str());
+        }
+    }
+
+    if name.is_empty() {
+        "SvgComponent".to_string()
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Svg{}", name)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_list() {
+        assert_eq!(
+            parse_file_list(b"a.svg\r\nicons/b.svg\n\n"),
+            vec![PathBuf::from("a.svg"), PathBuf::from("icons/b.svg")]
+        );
+        assert_eq!(
+            parse_file_list(b"with\nnewline.svg\0c.svg\0"),
+            vec![PathBuf::from("with\nnewline.svg"), PathBuf::from("c.svg")]
+        );
+    }
+
+    /// The arguments of a plain `svag ...` invocation.
+    fn minify_args(args: &[&str]) -> MinifyArgs {
+        let cli = Cli::try_parse_from(["svag"].iter().chain(args)).unwrap();
+        assert!(cli.command.is_none());
+        cli.minify
+    }
+
+    #[test]
+    fn test_jobs() {
+        assert_eq!(minify_args(&["a.svg"]).jobs, None);
+        assert_eq!(
+            minify_args(&["--jobs", "1", "a.svg"]).jobs,
+            NonZeroUsize::new(1)
+        );
+        for jobs in ["0", "-1", "many"] {
+            let args = ["svag", "--jobs", jobs, "a.svg"];
+            assert!(Cli::try_parse_from(args).is_err(), "{jobs}");
+        }
+        assert!(Cli::try_parse_from(["svag", "--jobs", "2", "--sequential", "a.svg"]).is_err());
+    }
+
+    #[test]
+    fn test_optional_values_need_equals() {
+        // A file after the flag isn't taken for its value
+        for flag in ["--backup", "--autocrop", "--current-color"] {
+            let args = minify_args(&[flag, "a.svg"]);
+            assert_eq!(args.input, PathBuf::from("a.svg"), "{flag}");
+        }
+
+        let args = minify_args(&["--backup", "a.svg"]);
+        assert_eq!(args.backup.as_deref(), Some(".bak"));
+        let args = minify_args(&["--backup=.orig", "a.svg"]);
+        assert_eq!(args.backup.as_deref(), Some(".orig"));
+
+        let args = minify_args(&["--autocrop", "a.svg"]);
+        assert_eq!(args.optimize.autocrop, Some(0.0));
+        let args = minify_args(&["--autocrop=2", "a.svg"]);
+        assert_eq!(args.optimize.autocrop, Some(2.0));
+
+        let args = minify_args(&["--current-color", "a.svg"]);
+        assert_eq!(
+            args.optimize.current_color,
+            Some(svag::CurrentColor::Dominant)
+        );
+        let args = minify_args(&["--current-color=#000", "a.svg"]);
+        assert_eq!(
+            args.optimize.current_color,
+            Some(svag::CurrentColor::Color(svag::Color::rgb(0, 0, 0)))
+        );
+    }
 }
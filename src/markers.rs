@@ -0,0 +1,221 @@
+//! `<marker>` cleanup.
+//!
+//! Diagramming tools (draw.io, Mermaid) write one arrowhead definition per
+//! edge, and leave marker properties on whatever they styled. Identical
+//! `<marker>` definitions are merged, references to markers that don't
+//! exist are removed, and marker properties are dropped where nothing
+//! below them can draw markers.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Options;
+use crate::ast::*;
+use crate::bounds::{has_scripts_or_animation, property};
+use crate::clip::{remove_property, url_fragment};
+use crate::index::DocumentIndex;
+use crate::optimize::{is_whitespace, stylesheet_ids};
+use crate::serialize::serialize_children;
+
+/// The marker properties.
+const MARKER_PROPERTIES: [&str; 3] = ["marker-start", "marker-mid", "marker-end"];
+
+/// Elements markers are drawn on.
+const MARKABLE: &[&str] = &["path", "line", "polyline", "polygon"];
+
+/// Merge duplicate markers and remove marker properties that draw nothing.
+pub(crate) fn optimize_markers(doc: &mut Document) {
+    // Scripts and animations can refer to markers in ways that aren't
+    // rewritten here
+    if has_scripts_or_animation(doc) {
+        return;
+    }
+
+    dedupe_markers(doc);
+
+    let mut markers = HashSet::new();
+    doc.for_each_element(|elem| {
+        if elem.is("marker")
+            && let Some(id) = elem.get_attr("id")
+        {
+            markers.insert(id.to_string());
+        }
+    });
+    remove_marker_properties(&mut doc.root, &markers, [false; 3]);
+}
+
+/// Point references to identical markers at the first of them, and remove
+/// the others.
+fn dedupe_markers(doc: &mut Document) {
+    if !doc.any_element(|elem| elem.is("marker")) {
+        return;
+    }
+    let index = DocumentIndex::new(doc);
+    let selected = stylesheet_ids(&doc.root);
+    let mut first: HashMap<String, &str> = HashMap::new();
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut duplicates: Vec<Vec<usize>> = Vec::new();
+    for (id, elem) in index.iter() {
+        let Some(marker_id) = elem.get_attr("id").filter(|_| elem.is("marker")) else {
+            continue;
+        };
+        // A stylesheet may select it by id, or the first with the id wins
+        if index.by_id(marker_id) != Some(id) || selected.contains(marker_id) {
+            continue;
+        }
+        match first.get(&definition_key(elem)) {
+            Some(&kept) => {
+                renames.insert(marker_id.to_string(), kept.to_string());
+                duplicates.push(index.path(id));
+            }
+            None => {
+                first.insert(definition_key(elem), marker_id);
+            }
+        }
+    }
+    if renames.is_empty() {
+        return;
+    }
+
+    doc.for_each_element_mut(|elem| retarget(elem, &renames));
+
+    doc.remove_elements_at(duplicates);
+}
+
+/// What makes two definitions (markers, patterns) render the same: their
+/// attributes other than `id`, and their content.
+pub(crate) fn definition_key(def: &Element) -> String {
+    let mut attrs: Vec<String> = def
+        .attributes
+        .iter()
+        .filter(|a| a.name.local != "id" || a.name.prefix.is_some())
+        .map(|a| format!("{}={:?}", a.name.full_name(), a.value))
+        .collect();
+    attrs.sort_unstable();
+    let mut content = def.clone();
+    content.children.retain(|node| !is_whitespace(node));
+    format!(
+        "{}>{}",
+        attrs.join(" "),
+        serialize_children(&content, &Options::default())
+    )
+}
+
+/// Rewrite marker references to renamed markers, in presentation attributes
+/// and the `style` attribute.
+fn retarget(elem: &mut Element, renames: &HashMap<String, String>) {
+    let rename = |value: &str| {
+        let id = url_fragment(value)?;
+        renames.get(id).map(|new| format!("url(#{new})"))
+    };
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_some() {
+            continue;
+        }
+        let name = attr.name.local.as_str();
+        if (MARKER_PROPERTIES.contains(&name) || name == "marker")
+            && let Some(value) = rename(&attr.value)
+        {
+            attr.value = value.into();
+        }
+    }
+
+    let Some(style) = elem.get_attr("style") else {
+        return;
+    };
+    let mut changed = false;
+    let decls: Vec<String> = style
+        .split(';')
+        .map(|decl| {
+            if let Some((prop, value)) = decl.split_once(':')
+                && let prop = prop.trim()
+                && (MARKER_PROPERTIES.contains(&prop) || prop == "marker")
+                && let Some(value) = rename(value)
+            {
+                changed = true;
+                return format!("{prop}:{value}");
+            }
+            decl.to_string()
+        })
+        .collect();
+    if changed {
+        elem.set_attr("style", decls.join(";"));
+    }
+}
+
+/// Remove marker properties that reference no marker, or sit where no
+/// descendant draws markers. `inherited` says whether an ancestor sets each
+/// property. Returns whether `elem` or its descendants can draw markers.
+fn remove_marker_properties(
+    elem: &mut Element,
+    markers: &HashSet<String>,
+    inherited: [bool; 3],
+) -> bool {
+    let mut sets = inherited;
+    for (i, prop) in MARKER_PROPERTIES.iter().enumerate() {
+        let Some(value) = property(elem, prop) else {
+            continue;
+        };
+        let value = value.trim();
+        let dangling = url_fragment(value).is_some_and(|id| !markers.contains(id));
+        if dangling {
+            remove_property(elem, prop);
+            // Without it, the element would inherit its parent's marker
+            if inherited[i] {
+                elem.set_attr(*prop, "none");
+            }
+        } else {
+            sets[i] = value != "none";
+        }
+    }
+
+    // Instances of a <use> inherit from it
+    let mut can_mark = MARKABLE.contains(&elem.name.local.as_str()) || elem.is("use");
+    for child in elem.child_elements_mut() {
+        can_mark |= remove_marker_properties(child, markers, sets);
+    }
+    if !can_mark {
+        for prop in MARKER_PROPERTIES {
+            remove_property(elem, prop);
+        }
+    }
+    can_mark
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_svg, serialize};
+
+    fn optimized(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        optimize_markers(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_dedupe_markers() {
+        assert_eq!(
+            optimized(
+                r##"<svg><defs><marker id="a" orient="auto"><path d="M0 0L5 5"/></marker>
+<marker orient="auto" id="b">
+  <path d="M0 0L5 5"/>
+</marker><marker id="c" orient="auto"><path d="M0 0L5 6"/></marker></defs><path d="M0 0H9" marker-end="url(#b)"/><line style="marker-start:url(&quot;#b&quot;);stroke:red" marker-end="url(#c)"/></svg>"##
+            ),
+            r##"<svg><defs><marker id="a" orient="auto"><path d="M0 0L5 5"/></marker><marker id="c" orient="auto"><path d="M0 0L5 6"/></marker></defs><path d="M0 0H9" marker-end="url(#a)"/><line marker-end="url(#c)" style="marker-start:url(#a);stroke:red"/></svg>"##
+        );
+
+        // Markers a stylesheet selects by id stay apart
+        let svg = r##"<svg><style>#b path{fill:red}</style><marker id="a"><path/></marker><marker id="b"><path/></marker><path marker-end="url(#b)"/></svg>"##;
+        assert_eq!(optimized(svg), svg);
+    }
+
+    #[test]
+    fn test_remove_marker_properties() {
+        assert_eq!(
+            optimized(
+                r##"<svg><marker id="m"/><g marker-end="url(#m)"><path marker-end="url(#gone)"/><rect marker-start="url(#m)"/></g><g marker-mid="url(#m)"><circle r="1"/><text>a</text></g><use href="#p" style="marker-end:url(#m)"/><path marker-start="url(#gone)" marker-mid="url(#m)"/></svg>"##
+            ),
+            r##"<svg><marker id="m"/><g marker-end="url(#m)"><path marker-end="none"/><rect/></g><g><circle r="1"/><text>a</text></g><use href="#p" style="marker-end:url(#m)"/><path marker-mid="url(#m)"/></svg>"##
+        );
+    }
+}
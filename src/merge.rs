@@ -0,0 +1,190 @@
+//! Composing documents as layers of one.
+//!
+//! Map and diagram pipelines draw each layer (base map, roads, labels) as
+//! its own SVG. Stacking them means moving each root's content into a
+//! group, declaring namespaces once, and keeping two layers' `#clip` from
+//! pointing at the same element: ids that an earlier layer already uses
+//! are renamed, along with the references to them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::crop::{set_view_box, viewport};
+use crate::index::rename_references;
+use crate::sprite::SVG_NS;
+
+/// Root attributes that describe the viewport rather than the content,
+/// which the merged root takes from the first layer.
+pub(crate) const VIEWPORT: &[&str] = &[
+    "x",
+    "y",
+    "width",
+    "height",
+    "viewBox",
+    "preserveAspectRatio",
+    "version",
+    "baseProfile",
+    "zoomAndPan",
+    "contentScriptType",
+    "contentStyleType",
+];
+
+/// Decimals kept in the `viewBox` written for layers without one.
+const PRECISION: u8 = 6;
+
+impl<'a> Document<'a> {
+    /// Stack `layers`, first at the bottom, into one document. Each root's
+    /// content becomes a `<g>` carrying its presentation attributes and
+    /// `id`; the merged root takes its size and `viewBox` from the first
+    /// layer that has one.
+    ///
+    /// Layers are expected to share that viewport. One that doesn't (a
+    /// different `viewBox`, or `width` and `height` without one) is kept as
+    /// a nested `<svg>` instead, which scales it to the merged viewport the
+    /// way it would have filled its own.
+    ///
+    /// Ids already used by an earlier layer get a `-2`, `-3`, ... suffix,
+    /// and references to them within the layer follow; ids mentioned in
+    /// stylesheets and scripts aren't renamed.
+    ///
+    /// ```
+    /// let base = svag::parse_svg(
+    ///     r##"<svg viewBox="0 0 10 10"><clipPath id="c"><rect width="5" height="5"/></clipPath><rect width="10" height="10" clip-path="url(#c)"/></svg>"##,
+    /// )
+    /// .unwrap();
+    /// let roads = svag::parse_svg(
+    ///     r##"<svg viewBox="0 0 10 10" stroke="red"><clipPath id="c"><circle r="5"/></clipPath><path d="M0 0l10 10" clip-path="url(#c)"/></svg>"##,
+    /// )
+    /// .unwrap();
+    /// let merged = svag::Document::merge_layers(&[&base, &roads]);
+    /// assert_eq!(
+    ///     svag::serialize(&merged, &svag::Options::default()),
+    ///     r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><g><clipPath id="c"><rect height="5" width="5"/></clipPath><rect clip-path="url(#c)" height="10" width="10"/></g><g stroke="red"><clipPath id="c-2"><circle r="5"/></clipPath><path clip-path="url(#c-2)" d="M0 0l10 10"/></g></svg>"##
+    /// );
+    /// ```
+    pub fn merge_layers(layers: &[&Document<'a>]) -> Document<'a> {
+        let mut root = Element::new("svg");
+        root.set_attr("xmlns", SVG_NS);
+
+        let first = layers.iter().find(|doc| viewport(&doc.root).is_some());
+        let base = first.and_then(|doc| viewport(&doc.root));
+        if let Some(doc) = first {
+            for attr in &doc.root.attributes {
+                if attr.name.prefix.is_none() && VIEWPORT.contains(&attr.name.local.as_str()) {
+                    root.attributes.push(attr.clone());
+                }
+            }
+        }
+
+        let mut taken = HashSet::new();
+        for doc in layers {
+            let mut svg = doc.root.clone();
+            rename_colliding_ids(&mut svg, &mut taken);
+
+            let own = viewport(&svg);
+            let nested = own.is_some() && own != base;
+            let mut layer = Element::new(if nested { "svg" } else { "g" });
+            if nested
+                && svg.get_attr("viewBox").is_none()
+                && let Some(own) = &own
+            {
+                set_view_box(&mut layer, own, PRECISION);
+            }
+            for attr in svg.attributes {
+                if attr.name.is_xmlns() {
+                    // Declared once on the root, unless the prefix is taken
+                    let declared = root.attributes.iter().find(|a| a.name == attr.name);
+                    match declared {
+                        Some(existing) if existing.value != attr.value => {
+                            layer.attributes.push(attr)
+                        }
+                        Some(_) => {}
+                        None => root.attributes.push(attr),
+                    }
+                } else if attr.name.prefix.is_some()
+                    || !VIEWPORT.contains(&attr.name.local.as_str())
+                    || (nested
+                        && matches!(attr.name.local.as_str(), "viewBox" | "preserveAspectRatio"))
+                {
+                    layer.attributes.push(attr);
+                }
+            }
+            layer.children = svg.children;
+            root.append_child(layer);
+        }
+
+        Document {
+            xml_declaration: None,
+            doctype: None,
+            prolog: Vec::new(),
+            root,
+        }
+    }
+}
+
+/// Rename the ids in `svg` that are in `taken`, and the references to
+/// them, then add its ids to `taken`.
+fn rename_colliding_ids(svg: &mut Element, taken: &mut HashSet<String>) {
+    fn collect(elem: &Element, ids: &mut Vec<String>) {
+        if let Some(id) = elem.get_attr("id") {
+            ids.push(id.to_string());
+        }
+        for child in elem.child_elements() {
+            collect(child, ids);
+        }
+    }
+    fn rename(elem: &mut Element, renames: &HashMap<String, String>) {
+        if let Some(new) = elem.get_attr("id").and_then(|id| renames.get(id)) {
+            let new = new.clone();
+            elem.set_attr("id", new);
+        }
+        rename_references(elem, renames);
+        for child in elem.child_elements_mut() {
+            rename(child, renames);
+        }
+    }
+
+    let mut ids = Vec::new();
+    collect(svg, &mut ids);
+    let own: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    let mut renames = HashMap::new();
+    for id in &ids {
+        if !taken.contains(id) || renames.contains_key(id) {
+            continue;
+        }
+        let new = (2..)
+            .map(|n| format!("{id}-{n}"))
+            .find(|new| !taken.contains(new) && !own.contains(new.as_str()))
+            .expect("some suffix is free");
+        taken.insert(new.clone());
+        renames.insert(id.clone(), new);
+    }
+    if !renames.is_empty() {
+        rename(svg, &renames);
+    }
+    taken.extend(ids.into_iter().filter(|id| !renames.contains_key(id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Document, Options, parse_svg, serialize};
+
+    #[test]
+    fn test_merge_layers() {
+        let layers: Vec<Document> = [
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="20" height="20" id="base"><path id="p" d="M0 0h20"/><use xlink:href="#p"/></svg>"##,
+            r##"<svg width="20" height="20" id="base"><g id="p"/><g id="p-2"/><use href="#p"/></svg>"##,
+            r#"<svg viewBox="0 0 10 10" preserveAspectRatio="none"><circle r="5"/></svg>"#,
+            "<svg><text>No viewport</text></svg>",
+        ]
+        .iter()
+        .map(|svg| parse_svg(svg).unwrap())
+        .collect();
+        let layers: Vec<&Document> = layers.iter().collect();
+
+        assert_eq!(
+            serialize(&Document::merge_layers(&layers), &Options::default()),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" height="20" width="20"><g id="base"><path d="M0 0h20" id="p"/><use xlink:href="#p"/></g><g id="base-2"><g id="p-3"/><g id="p-2"/><use href="#p-3"/></g><svg preserveAspectRatio="none" viewBox="0 0 10 10"><circle r="5"/></svg><g><text>No viewport</text></g></svg>"##
+        );
+    }
+}
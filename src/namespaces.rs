@@ -0,0 +1,272 @@
+//! Namespace normalization: move declarations to the root.
+//!
+//! SVGs pasted or composed from several sources often carry `xmlns:*`
+//! declarations on inner elements, sometimes with a different prefix for
+//! the same URI in each piece. A prefix bound to a single URI throughout
+//! the document means the same thing everywhere, so it can be declared once
+//! on the root. Prefixes sharing a URI are renamed to one of them.
+//! Prefixes bound to different URIs in different places stay where they
+//! are, as do default namespace changes (XHTML in `<foreignObject>`), but
+//! declarations repeating what's already in scope are dropped.
+//!
+//! Separately, elements like `<svg:rect>` whose prefix names the same URI
+//! as the default namespace in scope lose the prefix.
+
+use crate::ast::*;
+
+/// Prefixes the XML spec reserves, which are never declared or renamed.
+const RESERVED: &[&str] = &["xml", "xmlns"];
+
+/// Hoist namespace declarations to the root and merge duplicate prefixes.
+pub(crate) fn hoist_namespaces(root: &mut Element) {
+    // Every (prefix, URI) binding, in document order
+    let mut bindings: Vec<(String, String)> = Vec::new();
+    collect_bindings(root, &mut bindings);
+
+    let hoistable = |prefix: &str| {
+        !RESERVED.contains(&prefix) && bindings.iter().filter(|(p, _)| p == prefix).count() == 1
+    };
+
+    // One prefix per URI: the root's if it has one, else the first seen
+    let mut canonical: Vec<(&str, &str)> = Vec::new();
+    let root_bindings: Vec<(String, String)> = root
+        .attributes
+        .iter()
+        .filter(|a| a.name.prefix.as_deref() == Some("xmlns"))
+        .map(|a| (a.name.local.clone(), a.value.to_string()))
+        .collect();
+    for (prefix, uri) in root_bindings.iter().chain(&bindings) {
+        if hoistable(prefix) && !canonical.iter().any(|(_, u)| u == uri) {
+            canonical.push((prefix, uri));
+        }
+    }
+
+    let renames: Vec<(String, String)> = bindings
+        .iter()
+        .filter(|(prefix, _)| hoistable(prefix))
+        .filter_map(|(prefix, uri)| {
+            let (to, _) = canonical.iter().find(|(_, u)| u == uri)?;
+            (prefix != to).then(|| (prefix.clone(), to.to_string()))
+        })
+        .collect();
+    let declarations: Vec<(String, String)> = canonical
+        .iter()
+        .map(|(prefix, uri)| (prefix.to_string(), uri.to_string()))
+        .collect();
+
+    rewrite(root, &renames, &hoistable, true);
+    for (prefix, uri) in declarations {
+        let name = QName::with_prefix("xmlns", prefix);
+        if !root.attributes.iter().any(|a| a.name == name) {
+            let xmlns_end = root
+                .attributes
+                .iter()
+                .rposition(|a| a.name.is_xmlns())
+                .map_or(0, |i| i + 1);
+            root.attributes.insert(
+                xmlns_end,
+                Attribute {
+                    name,
+                    value: uri.into(),
+                },
+            );
+        }
+    }
+
+    drop_redundant(root, &mut Vec::new());
+}
+
+fn collect_bindings(elem: &Element, bindings: &mut Vec<(String, String)>) {
+    for attr in &elem.attributes {
+        if attr.name.prefix.as_deref() == Some("xmlns") {
+            let binding = (attr.name.local.clone(), attr.value.to_string());
+            if !bindings.contains(&binding) {
+                bindings.push(binding);
+            }
+        }
+    }
+    for child in elem.child_elements() {
+        collect_bindings(child, bindings);
+    }
+}
+
+/// Rename prefixes and remove the declarations of hoisted ones (all but
+/// the root's canonical ones).
+fn rewrite(
+    elem: &mut Element,
+    renames: &[(String, String)],
+    hoistable: &dyn Fn(&str) -> bool,
+    is_root: bool,
+) {
+    let rename = |name: &mut QName| {
+        if let Some(prefix) = &mut name.prefix
+            && let Some((_, to)) = renames.iter().find(|(from, _)| from == prefix)
+        {
+            *prefix = to.clone();
+        }
+    };
+
+    elem.attributes.retain(|a| {
+        let hoisted = a.name.prefix.as_deref() == Some("xmlns") && hoistable(&a.name.local);
+        let renamed = renames.iter().any(|(from, _)| *from == a.name.local);
+        !hoisted || (is_root && !renamed)
+    });
+    rename(&mut elem.name);
+    for attr in &mut elem.attributes {
+        if !attr.name.is_xmlns() {
+            rename(&mut attr.name);
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        rewrite(child, renames, hoistable, false);
+    }
+}
+
+/// Namespace bindings in scope, innermost last. `None` is the default
+/// namespace.
+type Scope = Vec<(Option<String>, String)>;
+
+/// The URI `prefix` is bound to in `scope`.
+fn lookup<'a>(scope: &'a [(Option<String>, String)], prefix: Option<&str>) -> Option<&'a str> {
+    scope
+        .iter()
+        .rev()
+        .find(|(p, _)| p.as_deref() == prefix)
+        .map(|(_, uri)| uri.as_str())
+}
+
+/// The prefix (or `None` for the default namespace) `attr` declares, if
+/// it's a namespace declaration.
+fn declared_prefix(attr: &Attribute) -> Option<Option<String>> {
+    match (attr.name.prefix.as_deref(), attr.name.local.as_str()) {
+        (None, "xmlns") => Some(None),
+        (Some("xmlns"), prefix) => Some(Some(prefix.to_string())),
+        _ => None,
+    }
+}
+
+/// Drop declarations that bind a prefix (or the default namespace) to what
+/// it's already bound to in that scope.
+fn drop_redundant(elem: &mut Element, scope: &mut Scope) {
+    let outer = scope.len();
+    elem.attributes.retain(|a| {
+        let Some(prefix) = declared_prefix(a) else {
+            return true;
+        };
+        let redundant = lookup(&scope[..outer], prefix.as_deref()) == Some(a.value.as_ref());
+        if !redundant {
+            scope.push((prefix, a.value.to_string()));
+        }
+        !redundant
+    });
+
+    for child in elem.child_elements_mut() {
+        drop_redundant(child, scope);
+    }
+    scope.truncate(outer);
+}
+
+/// Remove element prefixes that name the default namespace in scope, as in
+/// `<svg:rect>` under `xmlns="http://www.w3.org/2000/svg"`. Attributes keep
+/// theirs: a prefixed attribute is in a namespace, an unprefixed one isn't.
+pub(crate) fn unprefix_elements(root: &mut Element) {
+    fn visit(elem: &mut Element, scope: &mut Scope) {
+        let outer = scope.len();
+        for attr in &elem.attributes {
+            if let Some(prefix) = declared_prefix(attr) {
+                scope.push((prefix, attr.value.to_string()));
+            }
+        }
+
+        if let Some(prefix) = elem.name.prefix.as_deref()
+            && let Some(uri) = lookup(scope, Some(prefix))
+            && lookup(scope, None) == Some(uri)
+        {
+            elem.name.prefix = None;
+        }
+
+        for child in elem.child_elements_mut() {
+            visit(child, scope);
+        }
+        scope.truncate(outer);
+    }
+
+    visit(root, &mut Vec::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn run(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        hoist_namespaces(&mut doc.root);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_hoists_and_merges() {
+        let out = run(
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><g xmlns:xlink="http://www.w3.org/1999/xlink"><use xlink:href="#a"/></g><g xmlns:xl="http://www.w3.org/1999/xlink"><use xl:href="#b"/></g></svg>"##,
+        );
+        assert_eq!(
+            out,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><g><use xlink:href="#a"/></g><g><use xlink:href="#b"/></g></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_prefers_root_prefix() {
+        let out = run(r#"<svg xmlns:b="urn:x"><g xmlns:a="urn:x"><a:thing a:attr="1"/></g></svg>"#);
+        assert_eq!(
+            out,
+            r#"<svg xmlns:b="urn:x"><g><b:thing b:attr="1"/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keeps_conflicts_and_default_changes() {
+        // "a" means different things in each group, and the XHTML default
+        // only applies inside foreignObject
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><g xmlns:a="urn:1"><a:x/></g><g xmlns:a="urn:2"><a:x/></g><foreignObject><div xmlns="http://www.w3.org/1999/xhtml"/></foreignObject></svg>"#;
+        assert_eq!(run(svg), svg);
+
+        // Redeclaring what's in scope is dropped
+        let out = run(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g xmlns="http://www.w3.org/2000/svg"><rect/></g></svg>"#,
+        );
+        assert_eq!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g><rect/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_unprefix_elements() {
+        let unprefix = |svg: &str| {
+            let mut doc = parse_svg(svg).unwrap();
+            unprefix_elements(&mut doc.root);
+            serialize(&doc, &Options::default())
+        };
+
+        let out = unprefix(
+            r#"<svg:svg xmlns="http://www.w3.org/2000/svg" xmlns:svg="http://www.w3.org/2000/svg"><svg:rect svg:fill="red"/><g><svg:circle/></g></svg:svg>"#,
+        );
+        assert_eq!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:svg="http://www.w3.org/2000/svg"><rect svg:fill="red"/><g><circle/></g></svg>"#
+        );
+
+        // No SVG default namespace, or a different one in scope
+        let cases = [
+            r#"<svg:svg xmlns:svg="http://www.w3.org/2000/svg"><svg:rect/></svg:svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:svg="http://www.w3.org/2000/svg"><foreignObject><div xmlns="http://www.w3.org/1999/xhtml"><svg:svg/></div></foreignObject></svg>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:s="urn:other"><s:rect/></svg>"#,
+        ];
+        for svg in cases {
+            assert_eq!(unprefix(svg), svg);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Flatten nested `<svg>` elements that only position their content.
+//!
+//! Compositions pasted together from several files often keep each one as
+//! an inner `<svg x="…" y="…">`. Without a `viewBox`, such an element only
+//! moves its content's origin to `(x, y)` and clips it to its `width` and
+//! `height`; as `<g transform="translate(x,y)">` it renders the same, as
+//! long as the clip doesn't hide anything and no content is sized relative
+//! to its viewport. Elements referenced by `<use>` are left alone, since a
+//! `<use>` can resize an `<svg>` but not a `<g>`.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::bounds::{
+    Inherited, coord, fraction, has_dynamic_content, length, painted_bounds, paints_nothing,
+    property,
+};
+use crate::index::DocumentIndex;
+
+/// Parents where a `<g>` renders its content like an `<svg>` would.
+const PARENTS: &[&str] = &[
+    "svg", "g", "a", "switch", "defs", "symbol", "mask", "pattern", "marker",
+];
+
+/// Attributes that only mean something on an `<svg>`.
+const SVG_ONLY: &[&str] = &[
+    "x",
+    "y",
+    "width",
+    "height",
+    "preserveAspectRatio",
+    "overflow",
+    "version",
+    "baseProfile",
+    "zoomAndPan",
+    "contentScriptType",
+    "contentStyleType",
+];
+
+struct Context {
+    /// Ids something in the document refers to
+    referenced: HashSet<String>,
+    /// Ids of elements that establish a viewport when used
+    viewports: HashSet<String>,
+    /// Whether any element is sized in `userSpaceOnUse`, whose percentages
+    /// resolve against the viewport of what references it
+    user_space: bool,
+    /// The root's default namespace, which a flattened `<svg>` needn't
+    /// repeat
+    root_ns: Option<String>,
+}
+
+/// Turn inner `<svg>` elements that act as a translation into `<g>`.
+pub(crate) fn flatten_nested_svg(doc: &mut Document) {
+    fn has_svg(elem: &Element) -> bool {
+        elem.is("svg") || elem.child_elements().any(has_svg)
+    }
+    if !doc.root.child_elements().any(has_svg) || has_dynamic_content(doc) {
+        return;
+    }
+
+    let index = DocumentIndex::new(doc);
+    let ctx = Context {
+        referenced: index
+            .iter()
+            .flat_map(|(id, _)| index.references(id))
+            .map(|id| id.to_string())
+            .collect(),
+        viewports: index
+            .iter()
+            .filter(|(_, e)| e.is("svg") || e.is("symbol"))
+            .filter_map(|(_, e)| e.get_attr("id"))
+            .map(str::to_string)
+            .collect(),
+        user_space: index
+            .iter()
+            .any(|(_, e)| e.attributes.iter().any(|a| a.value == "userSpaceOnUse")),
+        root_ns: doc.root.get_attr("xmlns").map(str::to_string),
+    };
+
+    let inherited = Inherited::default().child(&doc.root);
+    visit(&mut doc.root, &inherited, &ctx);
+}
+
+/// Flatten inside `elem` bottom-up, so an outer `<svg>` sees what its inner
+/// ones became. `inherited` includes `elem`'s own properties.
+fn visit(elem: &mut Element, inherited: &Inherited, ctx: &Context) {
+    for child in elem.child_elements_mut() {
+        let child_inherited = inherited.child(child);
+        visit(child, &child_inherited, ctx);
+    }
+
+    if !PARENTS.contains(&elem.name.local.as_str()) {
+        return;
+    }
+    for child in elem.child_elements_mut() {
+        if child.is("svg") && can_flatten(child, inherited, ctx) {
+            flatten(child, ctx);
+        }
+    }
+}
+
+fn can_flatten(svg: &Element, inherited: &Inherited, ctx: &Context) -> bool {
+    let plain = svg.attributes.iter().all(|attr| {
+        attr.name.prefix.is_some()
+            || match attr.name.local.as_str() {
+                "viewBox" | "transform" | "style" | "clip" => false,
+                "id" => !ctx.referenced.contains(attr.value.as_ref()),
+                _ => true,
+            }
+    });
+    if !plain || coord(svg, "x").is_none() || coord(svg, "y").is_none() {
+        return false;
+    }
+
+    // A zero or negative size disables rendering
+    let size = |name| {
+        svg.get_attr(name)
+            .map_or(Some(None), |v| Some(Some(positive(v)?)))
+    };
+    let (Some(width), Some(height)) = (size("width"), size("height")) else {
+        return false;
+    };
+
+    let clips = !matches!(property(svg, "overflow"), Some("visible" | "auto"));
+    if clips {
+        let (Some(Size::Length(width)), Some(Size::Length(height))) = (width, height) else {
+            return false;
+        };
+        if content_fits(svg, &inherited.child(svg), width, height) != Some(true) {
+            return false;
+        }
+    }
+
+    !depends_on_viewport(svg, ctx)
+}
+
+enum Size {
+    Length(f64),
+    Percentage,
+}
+
+fn positive(value: &str) -> Option<Size> {
+    if let Some(length) = length(value) {
+        return (length > 0.0).then_some(Size::Length(length));
+    }
+    (fraction(value)? > 0.0).then_some(Size::Percentage)
+}
+
+/// Whether everything `svg` paints lies inside its `width` × `height`
+/// viewport, so clipping to it hides nothing. `None` means unknown.
+fn content_fits(svg: &Element, inherited: &Inherited, width: f64, height: f64) -> Option<bool> {
+    for child in svg.child_elements() {
+        if paints_nothing(child) {
+            continue;
+        }
+        let bounds = painted_bounds(child, &inherited.child(child))?;
+        if bounds.min_x < 0.0 || bounds.min_y < 0.0 || bounds.max_x > width || bounds.max_y > height
+        {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+/// Whether anything inside `elem` is sized relative to the viewport it's
+/// in: percentages, inner viewports that default to `100%`, and references
+/// to `userSpaceOnUse` content.
+fn depends_on_viewport(elem: &Element, ctx: &Context) -> bool {
+    elem.child_elements().any(|child| {
+        child.is("svg")
+            || (child.is("use") && uses_viewport(child, ctx))
+            || child.attributes.iter().any(|a| {
+                a.value.contains('%')
+                    || a.value == "userSpaceOnUse"
+                    || (ctx.user_space && (a.value.contains("url(") || a.value.starts_with('#')))
+            })
+            || depends_on_viewport(child, ctx)
+    })
+}
+
+/// Whether a `<use>` instantiates a `<symbol>` or `<svg>`, sized `100%`
+/// unless the `<use>` sets a size.
+fn uses_viewport(elem: &Element, ctx: &Context) -> bool {
+    let target = elem
+        .get_attr("href")
+        .or_else(|| elem.get_attr("xlink:href"))
+        .and_then(|href| href.strip_prefix('#'));
+    target.is_some_and(|id| ctx.viewports.contains(id))
+        && (elem.get_attr("width").is_none() || elem.get_attr("height").is_none())
+}
+
+fn flatten(svg: &mut Element, ctx: &Context) {
+    let (x, y) = (
+        coord(svg, "x").unwrap_or(0.0),
+        coord(svg, "y").unwrap_or(0.0),
+    );
+    svg.name = QName::new("g");
+    svg.attributes.retain(|attr| {
+        attr.name.prefix.is_some()
+            || !(SVG_ONLY.contains(&attr.name.local.as_str())
+                || (attr.name.local == "xmlns" && ctx.root_ns.as_deref() == Some(&attr.value)))
+    });
+    if x != 0.0 || y != 0.0 {
+        let translate = if y == 0.0 {
+            format!("translate({x})")
+        } else {
+            format!("translate({x},{y})")
+        };
+        svg.set_attr("transform", translate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn flattened(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        flatten_nested_svg(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_flatten_nested_svg() {
+        // Positioned content that fits its viewport
+        assert_eq!(
+            flattened(
+                r#"<svg xmlns="http://www.w3.org/2000/svg"><svg xmlns="http://www.w3.org/2000/svg" version="1.1" x="10" y="5" width="20" height="20" fill="red"><rect width="20" height="20"/></svg><svg width="4" height="4"><circle cx="2" cy="2" r="2"/></svg></svg>"#
+            ),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g fill="red" transform="translate(10,5)"><rect height="20" width="20"/></g><g><circle cx="2" cy="2" r="2"/></g></svg>"#
+        );
+
+        // Without clipping, the size doesn't matter; nested ones flatten
+        // from the inside out
+        assert_eq!(
+            flattened(
+                r#"<svg><svg x="3" overflow="visible"><svg y="1" overflow="visible"><path d="M-5 0h99"/></svg></svg></svg>"#
+            ),
+            r#"<svg><g transform="translate(3)"><g transform="translate(0,1)"><path d="M-5 0h99"/></g></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keep_nested_svg() {
+        for svg in [
+            // Scales its content
+            r#"<svg><svg viewBox="0 0 10 10" width="20" height="20"><rect width="10" height="10"/></svg></svg>"#,
+            // Clips content that overflows
+            r#"<svg><svg width="5" height="5"><rect width="10" height="10"/></svg></svg>"#,
+            // Clips content whose bounds aren't known
+            r#"<svg><svg width="5" height="5"><text>A</text></svg></svg>"#,
+            // Doesn't render
+            r#"<svg><svg width="0" overflow="visible"><rect width="1" height="1"/></svg></svg>"#,
+            // Percentages resolve against it
+            r#"<svg><svg width="10" height="10"><rect width="50%" height="5"/></svg></svg>"#,
+            // Resized by a <use>
+            r##"<svg><svg id="a" width="10" height="10"><rect width="1" height="1"/></svg><use href="#a" width="5"/></svg>"##,
+            // Styled by a stylesheet
+            r#"<svg><style>svg svg{fill:red}</style><svg width="10" height="10"><rect width="1" height="1"/></svg></svg>"#,
+        ] {
+            assert_eq!(
+                flattened(svg),
+                serialize(&parse_svg(svg).unwrap(), &Options::default()),
+                "{svg}"
+            );
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Occlusion culling: drop shapes hidden behind a later opaque sibling.
+//!
+//! This is lossy by analysis: it's only as right as the opacity and bounds
+//! reasoning below, so it's opt-in ([`crate::Options::remove_occluded`]).
+//! The analysis is deliberately narrow. A shape is removed only when a
+//! later sibling in the same `<g>`/`<svg>`/`<a>` is known to paint an
+//! opaque, axis-aligned rectangle that contains the shape's bounds,
+//! including a generous stroke allowance. Documents with `<style>`,
+//! scripts, or animation are left alone, since any of them can change
+//! what's on top at render time.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::bounds::{
+    Inherited, box_bounds, fraction, has_dynamic_content, is_opaque_color, is_shape,
+    painted_bounds, property,
+};
+use crate::geometry::Rect;
+use crate::index::referenced_ids;
+
+/// Remove elements fully covered by a later opaque sibling.
+pub(crate) fn remove_occluded(doc: &mut Document) {
+    if has_dynamic_content(doc) {
+        return;
+    }
+
+    let referenced = referenced_ids(doc);
+
+    cull(&mut doc.root, &Inherited::default(), &referenced);
+}
+
+fn cull(elem: &mut Element, inherited: &Inherited, referenced: &HashSet<String>) {
+    let inherited = inherited.child(elem);
+
+    // Walk back to front, collecting the areas painted over so far
+    let mut covers: Vec<Rect> = Vec::new();
+    let mut remove = vec![false; elem.children.len()];
+    for (i, child) in elem.children.iter().enumerate().rev() {
+        let Node::Element(child) = child else {
+            continue;
+        };
+        let child_inherited = inherited.child(child);
+
+        if let Some(bounds) = removable_bounds(child, &child_inherited, referenced)
+            && covers.iter().any(|cover| cover.contains(&bounds))
+        {
+            remove[i] = true;
+            continue;
+        }
+        if let Some(cover) = opaque_cover(child, &child_inherited) {
+            covers.push(cover);
+        }
+    }
+
+    let mut remove = remove.into_iter();
+    elem.children.retain(|_| !remove.next().unwrap_or(false));
+
+    // Referenced groups also render through <use>, where they inherit
+    // different properties, so only plain rendering containers are entered
+    for child in elem.child_elements_mut() {
+        let is_referenced = child
+            .get_attr("id")
+            .is_some_and(|id| referenced.contains(id));
+        if matches!(child.name.local.as_str(), "g" | "svg" | "a") && !is_referenced {
+            cull(child, &inherited, referenced);
+        }
+    }
+}
+
+/// The rectangle `elem` paints fully opaquely, if it is provably one.
+fn opaque_cover(elem: &Element, inherited: &Inherited) -> Option<Rect> {
+    if !is_plain_paint(elem) || inherited.visibility != "visible" {
+        return None;
+    }
+
+    match elem.name.local.as_str() {
+        "rect" => {
+            let rounded = ["rx", "ry"]
+                .iter()
+                .any(|r| property(elem, r).is_some_and(|v| v != "0" && v != "auto"));
+            if rounded
+                || !is_opaque_color(&inherited.fill)
+                || fraction(&inherited.fill_opacity)? < 1.0
+            {
+                return None;
+            }
+            box_bounds(elem)
+        }
+        "image" => {
+            // JPEG has no alpha channel, and "slice"/"none" fill the whole box
+            let href = elem.get_attr("href")?.trim().to_ascii_lowercase();
+            let jpeg = href.starts_with("data:image/jpeg")
+                || href.starts_with("data:image/jpg")
+                || (!href.starts_with("data:")
+                    && (href.ends_with(".jpg") || href.ends_with(".jpeg")));
+            let fills_box = property(elem, "preserveAspectRatio")
+                .is_some_and(|par| par.trim() == "none" || par.contains("slice"));
+            if !jpeg || !fills_box {
+                return None;
+            }
+            box_bounds(elem)
+        }
+        _ => None,
+    }
+}
+
+/// Bounds of everything `elem` can paint, if it's a shape that may be
+/// dropped when covered.
+fn removable_bounds(
+    elem: &Element,
+    inherited: &Inherited,
+    referenced: &HashSet<String>,
+) -> Option<Rect> {
+    if !is_shape(elem)
+        || elem
+            .get_attr("id")
+            .is_some_and(|id| referenced.contains(id))
+    {
+        return None;
+    }
+    painted_bounds(elem, inherited)
+}
+
+/// No effect that could make the element paint partially or elsewhere.
+fn is_plain_paint(elem: &Element) -> bool {
+    let opacity_ok =
+        property(elem, "opacity").is_none_or(|o| fraction(o).is_some_and(|o| o >= 1.0));
+    let blend_ok = property(elem, "mix-blend-mode").is_none_or(|m| m == "normal");
+    let display_ok = property(elem, "display") != Some("none");
+    let effects = ["transform", "clip-path", "mask", "filter"]
+        .iter()
+        .any(|p| property(elem, p).is_some_and(|v| v != "none"));
+    opacity_ok && blend_ok && display_ok && !effects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn cull_svg(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        remove_occluded(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_removes_covered_shapes() {
+        let out = cull_svg(
+            r##"<svg><rect width="100" height="100" fill="#fff"/><circle cx="50" cy="50" r="10" stroke="red" stroke-width="2"/><path d="M10 10h20v20z"/><rect x="-1" y="-1" width="102" height="102" fill="navy"/><circle cx="200" cy="0" r="5"/></svg>"##,
+        );
+        assert_eq!(
+            out,
+            r#"<svg><rect fill="navy" height="102" width="102" x="-1" y="-1"/><circle cx="200" cy="0" r="5"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keeps_partially_or_translucently_covered() {
+        let cases = [
+            // Cover is translucent, or inherits a translucent fill
+            r#"<svg><rect width="9" height="9"/><rect width="10" height="10" opacity=".5"/></svg>"#,
+            r#"<svg><rect width="9" height="9"/><g fill-opacity=".5"><rect width="10" height="10"/></g></svg>"#,
+            r#"<svg><rect width="9" height="9"/><rect width="10" height="10" style="fill:rgba(0,0,0,.5)"/></svg>"#,
+            r#"<svg><rect width="9" height="9"/><rect width="10" height="10" fill="none"/></svg>"#,
+            // Rounded corners, transforms, and thick strokes escape the cover
+            r#"<svg><rect width="10" height="10"/><rect width="10" height="10" rx="2"/></svg>"#,
+            r#"<svg><rect width="9" height="9"/><rect width="10" height="10" transform="scale(.1)"/></svg>"#,
+            r#"<svg><rect x="2" y="2" width="6" height="6" stroke="red" stroke-width="2"/><rect width="10" height="10"/></svg>"#,
+            // Referenced elements, and documents with stylesheets
+            r##"<svg><rect id="r" width="9" height="9"/><rect width="10" height="10"/><use href="#r" x="20"/></svg>"##,
+            r#"<svg><style>rect{fill:none}</style><rect width="9" height="9"/><rect width="10" height="10"/></svg>"#,
+            // PNG may be transparent; "meet" may letterbox
+            r#"<svg><rect width="9" height="9"/><image href="a.png" width="10" height="10" preserveAspectRatio="none"/></svg>"#,
+            r#"<svg><rect width="9" height="9"/><image href="a.jpg" width="10" height="10"/></svg>"#,
+        ];
+        for svg in cases {
+            let culled = cull_svg(svg);
+            let kept = parse_svg(&culled).unwrap();
+            let before = parse_svg(svg).unwrap();
+            assert_eq!(
+                kept.root.children.len(),
+                before.root.children.len(),
+                "{svg}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_skips_referenced_groups() {
+        let svg = r##"<svg><g id="g"><rect width="9" height="9"/><rect width="10" height="10"/></g><use href="#g" fill="none"/></svg>"##;
+        assert_eq!(cull_svg(svg).matches("<rect").count(), 2);
+    }
+
+    #[test]
+    fn test_image_cover_in_nested_group() {
+        let out = cull_svg(
+            r#"<svg><g><rect width="50" height="50" fill="red"/><image href="bg.JPG" width="50" height="50" preserveAspectRatio="xMidYMid slice"/></g></svg>"#,
+        );
+        assert!(!out.contains("<rect"));
+    }
+}
@@ -1,55 +1,234 @@
 //! SVG optimization passes.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::Options;
 use crate::ast::*;
-use crate::path::{parse_path, serialize_path};
-
-/// Apply all enabled optimizations to the document.
+use crate::bounds::{has_dynamic_content, property};
+use crate::classes::extract_classes;
+use crate::clip::remove_noop_clips;
+use crate::color::COLOR_PROPERTIES;
+use crate::compress::{self, compressed_len};
+use crate::conditional::evaluate_conditions;
+use crate::crop::autocrop;
+use crate::css::{CssUrl, css_urls, format_css_url, hash_names};
+use crate::current_color::{CurrentColor, use_current_color};
+use crate::custom_properties::extract_custom_properties;
+use crate::data_uri::parse_data_uri;
+use crate::duplicates::remove_duplicate_shapes;
+use crate::filters::optimize_filters;
+use crate::fonts::{font_face_rules, parse_value_unquoted, split_top_level};
+use crate::hash::fnv1a;
+use crate::index::referenced_ids;
+use crate::keywords::{INHERITED, normalize_keywords};
+use crate::markers::optimize_markers;
+use crate::namespaces::{hoist_namespaces, unprefix_elements};
+use crate::nested_svg::flatten_nested_svg;
+use crate::occlusion::remove_occluded;
+use crate::ordering::{normalize_stops, sort_defs};
+use crate::path::{format_number, parse_path, serialize_path};
+use crate::pattern_defs::optimize_patterns;
+use crate::pipeline::{Pass, PassContext, PassStage, Pipeline};
+use crate::regroup::{MOVABLE, regroup_siblings};
+use crate::resize::resize;
+use crate::styles::{map_declarations, map_stylesheet, minify_declarations, minify_stylesheet};
+use crate::svg_images::inline_svg_images;
+use crate::transform::{minify_transform, parse_transform};
+use crate::xml_stylesheet::inline_stylesheets;
+use crate::{AttrPattern, Glob, OptimizeFor, Options, SvgTarget, serialize};
+
+/// Apply all enabled optimizations to the document: run the pipeline
+/// [`Options::pipeline`] builds.
 pub fn optimize(doc: &mut Document, options: &Options) {
-    if options.remove_metadata {
-        remove_metadata(&mut doc.root);
-    }
-
-    if options.remove_unused_namespaces {
-        remove_unused_namespaces(&mut doc.root);
-    }
-
-    if options.remove_comments {
-        remove_comments(&mut doc.root);
-    }
-
-    if options.remove_hidden {
-        remove_hidden(&mut doc.root);
-    }
+    options.pipeline().run(doc, options);
+}
 
-    if options.remove_empty {
-        remove_empty(&mut doc.root);
-    }
+/// Bytes saved by a single optimization pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassStats {
+    /// The pass name (see [`Pass::name`])
+    pub name: String,
+    /// Serialized size before minus size after (negative if the pass grew
+    /// the output)
+    pub saved: isize,
+}
 
-    if options.collapse_groups {
-        collapse_groups(&mut doc.root);
-    }
+/// Like [`optimize`], but measure how much each enabled pass saved.
+///
+/// This re-serializes the document after every pass, so it is noticeably
+/// slower than [`optimize`].
+pub fn optimize_with_stats(doc: &mut Document, options: &Options) -> Vec<PassStats> {
+    let mut size = serialize(doc, options).len();
+    let mut stats = Vec::new();
+    run_passes(doc, &options.pipeline(), options, &mut |pass, doc| {
+        let new_size = serialize(doc, options).len();
+        stats.push(PassStats {
+            name: pass.name().to_string(),
+            saved: size as isize - new_size as isize,
+        });
+        size = new_size;
+    });
+    stats
+}
 
-    if options.minify_paths {
-        minify_paths(&mut doc.root, options.precision);
+/// Run the passes of `pipeline` in order, calling `after_pass` after each
+/// one, and [`Options::on_pass`] around each one until it stops them.
+pub(crate) fn run_passes(
+    doc: &mut Document,
+    pipeline: &Pipeline,
+    options: &Options,
+    after_pass: &mut dyn FnMut(&Pass, &Document),
+) {
+    let ctx = PassContext { options, pipeline };
+    let hook = options.on_pass.as_ref();
+    // Before a pass, the size is what the last one left
+    let mut size = hook.map_or(0, |_| serialize(doc, options).len());
+    for pass in &pipeline.0 {
+        if let Some(hook) = hook
+            && hook.call(pass, PassStage::Before, doc, size)
+        {
+            return;
+        }
+        run_pass(pass, doc, &ctx);
+        after_pass(pass, doc);
+        if let Some(hook) = hook {
+            size = serialize(doc, options).len();
+            if hook.call(pass, PassStage::After, doc, size) {
+                return;
+            }
+        }
     }
+}
 
-    if options.minify_colors {
-        minify_colors(&mut doc.root);
+fn run_pass(pass: &Pass, doc: &mut Document, ctx: &PassContext) {
+    let PassContext { options, pipeline } = *ctx;
+    match pass {
+        Pass::InlineStylesheets => {
+            if let Some(resolver) = &options.stylesheet_resolver {
+                inline_stylesheets(doc, resolver);
+            }
+        }
+        Pass::InlineSvgImages => {
+            if let Some(resolver) = &options.image_resolver {
+                inline_svg_images(&mut doc.root, resolver, options);
+            }
+        }
+        Pass::RemoveMetadata => remove_metadata(&mut doc.root),
+        Pass::RemoveElements => remove_elements(
+            &mut doc.root,
+            &options.remove_elements,
+            options.keep_elements.as_deref(),
+        ),
+        Pass::EvaluateConditions => {
+            evaluate_conditions(doc, options.system_language.as_deref(), options.target)
+        }
+        Pass::RemoveAttrs => remove_attrs(&mut doc.root, &options.remove_attrs),
+        Pass::ModernizeHref => {
+            if options.target.is_svg2() {
+                modernize_href(&mut doc.root);
+            }
+        }
+        Pass::HoistNamespaces => hoist_namespaces(&mut doc.root),
+        Pass::UnprefixElements => unprefix_elements(&mut doc.root),
+        Pass::RemoveUnusedNamespaces => remove_unused_namespaces(&mut doc.root),
+        Pass::RemoveComments => remove_comments(&mut doc.root),
+        Pass::RemoveEmptyAttrs => remove_empty_attrs(&mut doc.root),
+        Pass::RemoveXmlLang => remove_xml_lang(&mut doc.root),
+        Pass::RemoveXmlBase => remove_xml_base(&mut doc.root),
+        Pass::RemoveXmlSpace => remove_xml_space(&mut doc.root, false),
+        Pass::MinifyNumbers => {
+            minify_numbers(&mut doc.root, options.precision, options.opacity_precision);
+            // Offsets are fractions of the gradient, as precise as opacities
+            normalize_stops(doc, options.opacity_precision);
+        }
+        Pass::NormalizeKeywords => normalize_keywords(doc),
+        Pass::RemoveHidden => remove_hidden(&mut doc.root),
+        Pass::RemoveEmpty => remove_empty(&mut doc.root),
+        Pass::RemoveNoopClips => remove_noop_clips(doc),
+        Pass::FlattenNestedSvg => flatten_nested_svg(doc),
+        Pass::OptimizeFilters => {
+            optimize_filters(doc, options.precision, options.transform_precision)
+        }
+        Pass::OptimizeMarkers => optimize_markers(doc),
+        Pass::OptimizePatterns => {
+            optimize_patterns(doc, options.precision, options.transform_precision)
+        }
+        Pass::RemoveDuplicateShapes => remove_duplicate_shapes(doc),
+        Pass::CollapseGroups => collapse_groups(doc),
+        Pass::RemoveOccluded => remove_occluded(doc),
+        Pass::ConvertEllipses => convert_ellipses(doc, options.precision),
+        Pass::Autocrop => autocrop(doc, options.autocrop.unwrap_or(0.0), options),
+        Pass::Resize => {
+            if let Some(size) = options.resize {
+                // Documents without a size or viewBox are left as they are
+                let _ = resize(doc, size, options.precision);
+            }
+        }
+        Pass::MinifyPaths => minify_paths(doc, options.precision, options.optimize_for),
+        Pass::MinifyTransforms => minify_transforms(
+            &mut doc.root,
+            options.precision,
+            options.transform_precision,
+        ),
+        Pass::NormalizeLists => normalize_lists(&mut doc.root),
+        Pass::CurrentColor => {
+            use_current_color(doc, options.current_color.unwrap_or(CurrentColor::Dominant))
+        }
+        Pass::CustomProperties => {
+            if options.target.supports_custom_properties() {
+                extract_custom_properties(doc, &options.custom_properties);
+            }
+        }
+        Pass::MinifyColors => minify_colors(&mut doc.root, options.target),
+        Pass::HexAlpha => {
+            if options.target.supports_hex_alpha() {
+                merge_color_alpha(doc, options.target);
+            }
+        }
+        Pass::RemoveDefaults => {
+            let mut inherited = Inheritance::new(&doc.root);
+            remove_default_attrs(&mut doc.root, options.target, &mut inherited);
+            simplify_aspect_ratios(&mut doc.root, true);
+        }
+        Pass::MinifyStyles => {
+            let mut inherited = Inheritance::new(&doc.root);
+            minify_styles(&mut doc.root, &mut inherited);
+        }
+        Pass::RegroupSiblings => if_smaller(doc, options, |doc| {
+            regroup_siblings(doc);
+            // A group whose children all moved into a new one is left with
+            // a single child, which would otherwise collapse on a second run
+            if pipeline.contains(Pass::CollapseGroups) {
+                collapse_groups(doc);
+            }
+        }),
+        Pass::ExtractClasses => if_smaller(doc, options, extract_classes),
+        Pass::SortDefs => sort_defs(doc),
+        Pass::DedupeDataUris => dedupe_data_uris(&mut doc.root, options.target),
+        Pass::CleanupWhitespace => cleanup_whitespace(&mut doc.root),
+        Pass::Title => {
+            if let Some(title) = &options.title {
+                doc.set_title(title.as_str());
+            }
+        }
+        Pass::Custom(custom) => custom.0.run(doc, ctx),
     }
+}
 
-    if options.remove_defaults {
-        remove_default_attrs(&mut doc.root);
+/// Run a pass that decides by raw size whether it pays off. When optimizing
+/// for compressed size, its result is kept only if that compresses smaller
+/// too.
+fn if_smaller(doc: &mut Document, options: &Options, pass: impl FnOnce(&mut Document)) {
+    if options.optimize_for == OptimizeFor::RawSize {
+        pass(doc);
+        return;
     }
 
-    if options.minify_styles {
-        minify_styles(&mut doc.root);
+    let mut candidate = doc.clone();
+    pass(&mut candidate);
+    let size = |doc: &Document| compressed_len(&serialize(doc, options), options.optimize_for);
+    if size(&candidate) < size(doc) {
+        *doc = candidate;
     }
-
-    // Clean up whitespace-only text nodes
-    cleanup_whitespace(&mut doc.root);
 }
 
 /// Remove metadata, title, desc, and other non-rendering elements.
@@ -89,24 +268,76 @@ fn is_id_referenced(_id: &str) -> bool {
     true
 }
 
-/// Remove unused namespace declarations.
-fn remove_unused_namespaces(elem: &mut Element) {
+/// Remove unused namespace declarations, on the root and inner elements.
+pub(crate) fn remove_unused_namespaces(root: &mut Element) {
+    fn visit(elem: &mut Element, used_prefixes: &HashSet<Option<String>>) {
+        elem.attributes.retain(|attr| {
+            if attr.name.local == "xmlns" && attr.name.prefix.is_none() {
+                // Default namespace - always keep
+                true
+            } else if attr.name.prefix.as_deref() == Some("xmlns") {
+                // xmlns:prefix - keep if prefix is used
+                used_prefixes.contains(&Some(attr.name.local.clone()))
+            } else {
+                true
+            }
+        });
+        for child in elem.child_elements_mut() {
+            visit(child, used_prefixes);
+        }
+    }
+
     // Collect all prefixes actually used in the document
     let mut used_prefixes: HashSet<Option<String>> = HashSet::new();
-    collect_used_prefixes(elem, &mut used_prefixes);
+    collect_used_prefixes(root, &mut used_prefixes);
+    visit(root, &used_prefixes);
+}
 
-    // Remove unused xmlns declarations
-    elem.attributes.retain(|attr| {
-        if attr.name.local == "xmlns" && attr.name.prefix.is_none() {
-            // Default namespace - always keep
-            true
-        } else if attr.name.prefix.as_deref() == Some("xmlns") {
-            // xmlns:prefix - keep if prefix is used
-            used_prefixes.contains(&Some(attr.name.local.clone()))
-        } else {
-            true
+/// Elements whose `xlink:href` all current browsers accept as plain `href`.
+const HREF_ELEMENTS: &[&str] = &[
+    "use",
+    "image",
+    "linearGradient",
+    "radialGradient",
+    "pattern",
+    "textPath",
+];
+
+/// Rewrite `xlink:href` to SVG 2 `href`, then drop the `xmlns:xlink`
+/// declarations if nothing else uses the prefix.
+fn modernize_href(root: &mut Element) {
+    fn visit(elem: &mut Element) {
+        if HREF_ELEMENTS.contains(&elem.name.local.as_str())
+            && let Some(i) = elem
+                .attributes
+                .iter()
+                .position(|a| a.name.prefix.as_deref() == Some("xlink") && a.name.local == "href")
+        {
+            // A plain href already wins over xlink:href
+            let xlink = elem.attributes.remove(i);
+            if elem.get_attr("href").is_none() {
+                elem.set_attr("href", xlink.value);
+            }
         }
-    });
+        for child in elem.child_elements_mut() {
+            visit(child);
+        }
+    }
+
+    fn drop_xlink_ns(elem: &mut Element) {
+        elem.attributes
+            .retain(|a| !(a.name.prefix.as_deref() == Some("xmlns") && a.name.local == "xlink"));
+        for child in elem.child_elements_mut() {
+            drop_xlink_ns(child);
+        }
+    }
+
+    visit(root);
+    let mut used = HashSet::new();
+    collect_used_prefixes(root, &mut used);
+    if !used.contains(&Some("xlink".to_string())) {
+        drop_xlink_ns(root);
+    }
 }
 
 fn collect_used_prefixes(elem: &Element, used: &mut HashSet<Option<String>>) {
@@ -136,6 +367,183 @@ fn remove_comments(elem: &mut Element) {
     }
 }
 
+/// Remove attributes whose value is empty or whitespace-only (`class=""`,
+/// `style=" "`, `transform=""`), which are either no-ops or invalid and
+/// ignored.
+fn remove_empty_attrs(elem: &mut Element) {
+    elem.attributes.retain(|attr| {
+        // An empty `xmlns` undeclares the default namespace, empty
+        // conditional attributes evaluate to false, and an empty `href`
+        // points at the document itself
+        let meaningful = attr.name.is_xmlns()
+            || matches!(
+                attr.name.local.as_str(),
+                "requiredFeatures" | "requiredExtensions" | "systemLanguage" | "href"
+            )
+            || attr.name.local.starts_with("data-");
+        meaningful || !attr.value.trim().is_empty()
+    });
+
+    for child in elem.child_elements_mut() {
+        remove_empty_attrs(child);
+    }
+}
+
+/// Remove elements named in `deny`, or missing from `allow` when that's
+/// set, along with their content. References to removed elements are left
+/// dangling, which renderers treat like references to missing ids.
+fn remove_elements(elem: &mut Element, deny: &[Glob], allow: Option<&[Glob]>) {
+    elem.children.retain(|node| {
+        let Node::Element(child) = node else {
+            return true;
+        };
+        let name = child.name.full_name();
+        !deny.iter().any(|glob| glob.matches(&name))
+            && allow.is_none_or(|allow| allow.iter().any(|glob| glob.matches(&name)))
+    });
+
+    for child in elem.child_elements_mut() {
+        remove_elements(child, deny, allow);
+    }
+}
+
+/// Remove attributes matching any of `patterns`. Namespace declarations
+/// are left to `remove_unused_namespaces`, since removing one still in use
+/// would make the output malformed.
+fn remove_attrs(elem: &mut Element, patterns: &[AttrPattern]) {
+    let name = elem.name.clone();
+    elem.attributes
+        .retain(|attr| attr.name.is_xmlns() || !patterns.iter().any(|p| p.matches(&name, attr)));
+
+    for child in elem.child_elements_mut() {
+        remove_attrs(child, patterns);
+    }
+}
+
+fn is_xml_attr(name: &QName, local: &str) -> bool {
+    name.prefix.as_deref() == Some("xml") && name.local == local
+}
+
+/// Remove `xml:lang` from elements without any text below them, where
+/// there's nothing for a language to apply to.
+fn remove_xml_lang(root: &mut Element) {
+    fn has_text(elem: &Element) -> bool {
+        elem.children.iter().any(|node| match node {
+            Node::Text(text) | Node::CData(text) => !text.trim().is_empty(),
+            Node::Element(e) => has_text(e),
+            _ => false,
+        })
+    }
+
+    fn visit(elem: &mut Element) {
+        if !has_text(elem) {
+            elem.attributes.retain(|a| !is_xml_attr(&a.name, "lang"));
+        }
+        for child in elem.child_elements_mut() {
+            visit(child);
+        }
+    }
+
+    // `:lang()` selectors match elements by it, text or not
+    if !stylesheets_contain(root, ":lang(") {
+        visit(root);
+    }
+}
+
+/// Remove `xml:base` where no relative URL below it would resolve against
+/// it.
+fn remove_xml_base(elem: &mut Element) {
+    fn is_relative(url: &str) -> bool {
+        let url = url.trim().trim_matches(['"', '\'']);
+        let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        });
+        !url.is_empty() && !url.starts_with('#') && !has_scheme
+    }
+
+    fn has_relative_urls(elem: &Element) -> bool {
+        let in_css = |css: &str| {
+            css.split("url(")
+                .skip(1)
+                .any(|rest| is_relative(rest.split(')').next().unwrap_or_default()))
+        };
+        elem.attributes
+            .iter()
+            .any(|a| (a.name.local == "href" && is_relative(&a.value)) || in_css(&a.value))
+            || elem.children.iter().any(|node| match node {
+                Node::Element(e) => has_relative_urls(e),
+                Node::Text(css) | Node::CData(css) => elem.is("style") && in_css(css),
+                _ => false,
+            })
+    }
+
+    if elem.attributes.iter().any(|a| is_xml_attr(&a.name, "base")) && !has_relative_urls(elem) {
+        elem.attributes.retain(|a| !is_xml_attr(&a.name, "base"));
+    }
+    for child in elem.child_elements_mut() {
+        remove_xml_base(child);
+    }
+}
+
+/// Remove `xml:space="default"` where that's already the inherited value.
+fn remove_xml_space(elem: &mut Element, inherited_preserve: bool) {
+    let mut preserve = inherited_preserve;
+    elem.attributes.retain(|a| {
+        if !is_xml_attr(&a.name, "space") {
+            return true;
+        }
+        match a.value.trim() {
+            "default" => {
+                preserve = false;
+                inherited_preserve
+            }
+            "preserve" => {
+                preserve = true;
+                true
+            }
+            _ => true,
+        }
+    });
+    for child in elem.child_elements_mut() {
+        remove_xml_space(child, preserve);
+    }
+}
+
+/// Whether any `<style>` element's content contains `needle`.
+pub(crate) fn stylesheets_contain(root: &Element, needle: &str) -> bool {
+    root.is("style")
+        && root.children.iter().any(|node| match node {
+            Node::Text(css) | Node::CData(css) => css.contains(needle),
+            _ => false,
+        })
+        || root
+            .child_elements()
+            .any(|child| stylesheets_contain(child, needle))
+}
+
+/// The ids stylesheets may select with `#id`, gathered in one walk for
+/// passes that check many of them.
+pub(crate) fn stylesheet_ids(root: &Element) -> HashSet<String> {
+    fn visit(elem: &Element, ids: &mut HashSet<String>) {
+        if elem.is("style") {
+            for node in &elem.children {
+                if let Node::Text(css) | Node::CData(css) = node {
+                    ids.extend(hash_names(css));
+                }
+            }
+        }
+        for child in elem.child_elements() {
+            visit(child, ids);
+        }
+    }
+    let mut ids = HashSet::new();
+    visit(root, &mut ids);
+    ids
+}
+
 /// Remove hidden elements (display:none, visibility:hidden, opacity:0).
 fn remove_hidden(elem: &mut Element) {
     elem.children.retain(|node| {
@@ -196,6 +604,8 @@ fn remove_empty(elem: &mut Element) {
             if container_elements.contains(&e.name.local.as_str()) {
                 // Keep if it has children or important attributes
                 !e.children.is_empty() || e.get_attr("id").is_some()
+            } else if e.is("text") || e.is("tspan") {
+                has_text_content(e) || e.get_attr("id").is_some()
             } else {
                 true
             }
@@ -205,104 +615,441 @@ fn remove_empty(elem: &mut Element) {
     });
 }
 
+/// Whether a text element has any character data to render.
+fn has_text_content(elem: &Element) -> bool {
+    elem.children.iter().any(|node| match node {
+        Node::Text(text) | Node::CData(text) => !text.trim().is_empty(),
+        Node::Element(e) => !e.is("title") && !e.is("desc") && has_text_content(e),
+        _ => false,
+    })
+}
+
 /// Collapse groups that serve no purpose.
-fn collapse_groups(elem: &mut Element) {
+fn collapse_groups(doc: &mut Document) {
+    // Stylesheet rules and animations can target the group itself, so its
+    // attributes only move to the child when there are none
+    let move_attrs = !has_dynamic_content(doc);
+    collapse(&mut doc.root, move_attrs);
+}
+
+fn collapse(elem: &mut Element, move_attrs: bool) {
     // First recurse
     for child in elem.child_elements_mut() {
-        collapse_groups(child);
+        collapse(child, move_attrs);
     }
 
-    // Collect indices of groups we can collapse
-    let mut new_children = Vec::new();
-
-    for child in std::mem::take(&mut elem.children) {
-        if let Node::Element(e) = &child {
-            if can_collapse_group(e) {
-                // Collapse: add the group's children directly
-                if let Node::Element(mut e) = child {
-                    new_children.extend(std::mem::take(&mut e.children));
+    for child in elem.take_children() {
+        match child {
+            // Collapse: add the group's children directly
+            Node::Element(mut e) if can_collapse_group(&e, move_attrs) => {
+                let mut children = e.take_children();
+                children.retain(|node| !is_whitespace(node));
+                // Whatever the group passed down by inheritance, the child
+                // now sets itself
+                if let Some(Node::Element(only)) = children.first_mut() {
+                    only.attributes.append(&mut e.attributes);
                 }
-            } else {
-                new_children.push(child);
+                elem.children.extend(children);
             }
-        } else {
-            new_children.push(child);
+            child => elem.append_child(child),
         }
     }
-
-    elem.children = new_children;
 }
 
-fn can_collapse_group(elem: &Element) -> bool {
+fn can_collapse_group(elem: &Element, move_attrs: bool) -> bool {
     // Only collapse <g> elements
     if elem.name.local != "g" {
         return false;
     }
 
-    // Don't collapse if it has an id (might be referenced)
-    if elem.get_attr("id").is_some() {
+    // Don't collapse if it has multiple children (preserve structure).
+    // Whitespace between elements is dropped later anyway, and counting it
+    // would let a second run collapse what the first one didn't.
+    let mut children = elem.children.iter().filter(|node| !is_whitespace(node));
+    let (Some(only), None) = (children.next(), children.next()) else {
+        return false;
+    };
+    if elem.attributes.is_empty() {
+        return true;
+    }
+    if !move_attrs {
         return false;
     }
 
-    // Don't collapse if it has any meaningful attributes
-    let dominated_attrs = ["class", "style", "transform", "fill", "stroke", "opacity"];
-    for attr in &elem.attributes {
-        if dominated_attrs.contains(&attr.name.local.as_str()) {
-            return false;
+    // Otherwise its attributes must mean the same on the child: properties
+    // that inherit, which the child doesn't set itself. A referenced child
+    // would carry them into its other instances too.
+    let Node::Element(child) = only else {
+        return false;
+    };
+    child.get_attr("id").is_none()
+        && child.get_attr("style").is_none()
+        && elem.attributes.iter().all(|a| {
+            a.name.prefix.is_none()
+                && a.name.local != "transform"
+                && MOVABLE.contains(&a.name.local.as_str())
+                && child.get_attr(&a.name.local).is_none()
+        })
+}
+
+/// Whitespace-only text, which [`cleanup_whitespace`] drops at the end.
+pub(crate) fn is_whitespace(node: &Node) -> bool {
+    matches!(node, Node::Text(text) if text.trim().is_empty())
+}
+
+/// Turn `<ellipse>` elements with equal radii into the shorter `<circle>`.
+fn convert_ellipses(doc: &mut Document, precision: u8) {
+    fn visit(elem: &mut Element, precision: u8) {
+        if elem.is("ellipse")
+            && let Some(r) = circle_radius(elem, precision)
+        {
+            elem.name.local = "circle".into();
+            elem.remove_attr("rx");
+            elem.remove_attr("ry");
+            elem.set_attr("r", r);
+        }
+        for child in elem.child_elements_mut() {
+            visit(child, precision);
         }
     }
 
-    // Don't collapse if it has multiple children (preserve structure)
-    elem.children.len() == 1
+    // Renaming would change which type selectors match
+    let styled =
+        stylesheets_contain(&doc.root, "ellipse") || stylesheets_contain(&doc.root, "circle");
+    if !styled {
+        visit(&mut doc.root, precision);
+    }
 }
 
-/// Minify path data.
-fn minify_paths(elem: &mut Element, precision: u8) {
-    if elem.name.local == "path"
-        && let Some(d) = elem.get_attr("d").map(|s| s.to_string())
-        && let Ok(path) = parse_path(&d)
+/// The radius of an ellipse that is really a circle, once both radii are
+/// rounded to `precision` like the rest of the geometry.
+///
+/// Both radii must be plain lengths: a percentage `r` resolves against the
+/// viewport's normalized diagonal, where `rx` and `ry` use its width and
+/// height, and `auto` has no `r` equivalent.
+fn circle_radius(elem: &Element, precision: u8) -> Option<String> {
+    if elem
+        .get_attr("style")
+        .is_some_and(|style| style.contains("rx") || style.contains("ry"))
     {
-        let minified = serialize_path(&path, precision);
-        elem.set_attr("d", minified);
+        return None;
+    }
+    let (rx, ry) = (elem.get_attr("rx")?.trim(), elem.get_attr("ry")?.trim());
+    if !is_plain_length(rx) || !is_plain_length(ry) {
+        return None;
+    }
+    if rx == ry {
+        return Some(rx.to_string());
+    }
+    let rx = format_number(rx.parse().ok()?, precision);
+    let ry = format_number(ry.parse().ok()?, precision);
+    (rx == ry).then_some(rx)
+}
+
+/// A number with an optional unit, but not a percentage or keyword.
+fn is_plain_length(value: &str) -> bool {
+    value
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse::<f64>()
+        .is_ok_and(f64::is_finite)
+}
+
+/// Minify path data, writing each path in whichever of its source,
+/// absolute, or relative form is smallest.
+///
+/// For raw size that's decided path by path. Compressors do better when
+/// paths are written alike, so for compressed sizes one choice is made for
+/// the whole document.
+fn minify_paths(doc: &mut Document, precision: u8, optimize_for: OptimizeFor) {
+    let mut forms: Vec<[String; 3]> = Vec::new();
+    doc.for_each_element(|elem| {
+        if elem.is("path")
+            && let Some(d) = elem.get_attr("d")
+            && let Ok(path) = parse_path(d)
+        {
+            // Every form describes the rounded source, so a second run,
+            // which reads that back, makes the same choice
+            let path = path.rounded(precision);
+            let absolute = path.to_absolute();
+            forms.push([
+                serialize_path(&path, precision),
+                serialize_path(&absolute, precision),
+                serialize_path(&absolute.to_relative(precision), precision),
+            ]);
+        }
+    });
+
+    let shortest = |f: &[String; 3]| {
+        f.iter()
+            .enumerate()
+            .min_by_key(|(i, d)| (d.len(), *i))
+            .map_or(0, |(i, _)| i)
+    };
+    let choices: Vec<usize> = forms.iter().map(shortest).collect();
+    let choices = if optimize_for == OptimizeFor::RawSize {
+        choices
+    } else {
+        let uniform = (0..3).map(|form| vec![form; forms.len()]);
+        let joined = |choices: &Vec<usize>| {
+            let ds: Vec<&str> = forms
+                .iter()
+                .zip(choices)
+                .map(|(f, &c)| f[c].as_str())
+                .collect();
+            ds.join("\"/><path d=\"")
+        };
+        let candidates = std::iter::once(choices)
+            .chain(uniform)
+            .map(|c| (joined(&c), c));
+        compress::smallest(candidates, optimize_for, |(text, _)| text)
+            .map(|(_, c)| c)
+            .unwrap_or_default()
+    };
+
+    let mut forms = forms.into_iter().zip(choices);
+    doc.for_each_element_mut(|elem| {
+        if elem.is("path")
+            && elem.get_attr("d").is_some_and(|d| parse_path(d).is_ok())
+            && let Some((form, choice)) = forms.next()
+        {
+            let mut form = form;
+            elem.set_attr("d", std::mem::take(&mut form[choice]));
+        }
+    });
+}
+
+/// Merge and round transforms, dropping the attribute when nothing but
+/// identity functions are left.
+fn minify_transforms(elem: &mut Element, precision: u8, transform_precision: u8) {
+    for name in ["transform", "gradientTransform", "patternTransform"] {
+        if let Some(value) = elem.get_attr(name)
+            && let Ok(transform) = parse_transform(value)
+        {
+            let minified = minify_transform(&transform, precision, transform_precision);
+            if minified.is_empty() {
+                elem.remove_attr(name);
+            } else {
+                elem.set_attr(name, minified);
+            }
+        }
     }
 
     for child in elem.child_elements_mut() {
-        minify_paths(child, precision);
+        minify_transforms(child, precision, transform_precision);
     }
 }
 
-/// Minify color values.
-fn minify_colors(elem: &mut Element) {
-    let color_attrs = [
-        "fill",
-        "stroke",
-        "stop-color",
-        "flood-color",
-        "lighting-color",
-        "color",
+/// Collapse the separators in list-valued attributes (`points`, `viewBox`,
+/// `stroke-dasharray`, transforms, `class`) to single spaces, for values the
+/// other passes leave as written.
+fn normalize_lists(elem: &mut Element) {
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_some() {
+            continue;
+        }
+        let normalized = match attr.name.local.as_str() {
+            "points" | "viewBox" | "stroke-dasharray" | "transform" | "gradientTransform"
+            | "patternTransform" => normalize_separators(&attr.value),
+            "class" => Some(
+                attr.value
+                    .split_ascii_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        };
+        if let Some(normalized) = normalized
+            && !normalized.is_empty()
+        {
+            attr.value = normalized.into();
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        normalize_lists(child);
+    }
+}
+
+/// Rewrite each run of whitespace and commas between list items as a
+/// single space, and drop the ones around parentheses. Returns `None` for
+/// lists with empty items (`1,,2`, a leading or trailing comma), which are
+/// invalid and left as they are.
+fn normalize_separators(value: &str) -> Option<String> {
+    let value = value.trim_ascii();
+    if value.starts_with(',') || value.ends_with(',') {
+        return None;
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut commas = 0;
+    let mut in_separator = false;
+    for c in value.chars() {
+        if c == ',' || c.is_ascii_whitespace() {
+            commas += usize::from(c == ',');
+            in_separator = true;
+            continue;
+        }
+        if in_separator {
+            let around_paren = out.ends_with('(') || c == '(' || c == ')';
+            match (around_paren, commas) {
+                (false, 0 | 1) => out.push(' '),
+                (true, 0) => {}
+                _ => return None,
+            }
+            in_separator = false;
+            commas = 0;
+        }
+        out.push(c);
+    }
+    Some(out)
+}
+
+/// Opacity-like properties, which take a number from 0 to 1.
+const OPACITIES: [&str; 5] = [
+    "opacity",
+    "fill-opacity",
+    "stroke-opacity",
+    "stop-opacity",
+    "flood-opacity",
+];
+
+/// Properties taking a length, or a list of them.
+const LENGTHS: [&str; 4] = [
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "baseline-shift",
+    "letter-spacing",
+];
+
+/// Round numeric property values, in presentation attributes and the
+/// `style` attribute: lengths to `precision`, opacities to
+/// `opacity_precision`.
+fn minify_numbers(elem: &mut Element, precision: u8, opacity_precision: u8) {
+    let minify = |prop: &str, value: &str| {
+        if OPACITIES.contains(&prop) {
+            minify_opacity(value, opacity_precision)
+        } else if prop == "stroke-dasharray" && value.contains('-') {
+            // A negative dash makes the list invalid, which rounding
+            // mustn't fix
+            None
+        } else if LENGTHS.contains(&prop) {
+            minify_lengths(value, precision)
+        } else {
+            None
+        }
+    };
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_none()
+            && let Some(value) = minify(&attr.name.local, &attr.value)
+        {
+            attr.value = value.into();
+        }
+    }
+
+    if let Some(style) = elem.get_attr("style") {
+        let mut changed = false;
+        let decls: Vec<String> = style
+            .split(';')
+            .map(|decl| {
+                if let Some((prop, value)) = decl.split_once(':')
+                    && let Some(value) = minify(prop.trim(), value)
+                {
+                    changed = true;
+                    return format!("{}:{}", prop.trim(), value);
+                }
+                decl.to_string()
+            })
+            .collect();
+        if changed {
+            elem.set_attr("style", decls.join(";"));
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        minify_numbers(child, precision, opacity_precision);
+    }
+}
+
+/// The rounded form of an opacity, if it's a number.
+fn minify_opacity(value: &str, precision: u8) -> Option<String> {
+    // Percentages are the same fraction, and values out of range are
+    // clamped
+    let value = value.trim();
+    let n = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    n.is_finite()
+        .then(|| format_number(n.clamp(0.0, 1.0), precision))
+}
+
+/// The rounded form of a length, or a list of them, if every item is one.
+/// Units are kept; keywords (`none`, `normal`, `sub`, …) are left alone.
+fn minify_lengths(value: &str, precision: u8) -> Option<String> {
+    const UNITS: &[&str] = &[
+        "px", "em", "ex", "rem", "ch", "pt", "pc", "cm", "mm", "q", "in", "%",
     ];
+    let items: Vec<String> = value
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let number_len = item
+                .rfind(|c: char| c.is_ascii_digit() || c == '.')
+                .map_or(0, |i| i + 1);
+            let (number, unit) = item.split_at(number_len);
+            let unit_ok = unit.is_empty() || UNITS.contains(&unit.to_ascii_lowercase().as_str());
+            let n = number.parse::<f64>().ok().filter(|n| n.is_finite())?;
+            // A font-relative unit is tens of user units, so it keeps two
+            // more decimals
+            let precision = match unit.to_ascii_lowercase().as_str() {
+                "em" | "ex" | "rem" | "ch" => precision.saturating_add(2),
+                _ => precision,
+            };
+            unit_ok.then(|| format!("{}{unit}", format_number(n, precision)))
+        })
+        .collect::<Option<_>>()?;
+    (!items.is_empty()).then(|| items.join(" "))
+}
 
+/// Minify color values.
+fn minify_colors(elem: &mut Element, target: SvgTarget) {
     for attr in &mut elem.attributes {
-        if color_attrs.contains(&attr.name.local.as_str()) {
-            attr.value = minify_color(&attr.value);
+        if COLOR_PROPERTIES.contains(&attr.name.local.as_str()) {
+            attr.value = minify_color(&attr.value, target).into();
         }
     }
 
     // Also check style attribute
     if let Some(style) = elem.get_attr("style").map(|s| s.to_string()) {
-        let new_style = minify_style_colors(&style);
+        let new_style = minify_style_colors(&style, target);
         elem.set_attr("style", new_style);
     }
 
     for child in elem.child_elements_mut() {
-        minify_colors(child);
+        minify_colors(child, target);
     }
 }
 
-fn minify_color(color: &str) -> String {
+pub(crate) fn minify_color(color: &str, target: SvgTarget) -> String {
     let color = color.trim();
+
+    // Custom property names are case-sensitive; only the fallback is a
+    // color to minify
+    if let Some((name, fallback)) = var_fallback(color) {
+        return format!("var({name},{})", minify_color(fallback, target));
+    }
+
+    // Wide-gamut and relative colors (`color(display-p3 …)`, `lab()`,
+    // `oklch()`, …) match none of the forms below, and pass through as-is
     let lower = color.to_lowercase();
 
+    if target.supports_hex_alpha()
+        && let Some(hex) = rgba_to_hex(&lower)
+        && hex.len() < color.len()
+    {
+        return hex;
+    }
+
     // Check for named color shortcuts first
     match lower.as_str() {
         "white" | "#ffffff" | "#fff" => return "#fff".into(),
@@ -314,6 +1061,13 @@ fn minify_color(color: &str) -> String {
         _ => {}
     }
 
+    // #RRGGBBAA -> #RGBA if possible, and opaque ones without alpha
+    if matches!(color.len(), 5 | 9)
+        && let Some(channels) = parse_hex_color(&lower)
+    {
+        return hex_color(channels, target);
+    }
+
     // #RRGGBB -> #RGB if possible
     if color.len() == 7 && color.starts_with('#') {
         let hex = &lower[1..];
@@ -333,7 +1087,181 @@ fn minify_color(color: &str) -> String {
     color.to_string()
 }
 
-fn minify_style_colors(style: &str) -> String {
+/// The custom property name and fallback of `var(--name, fallback)`.
+fn var_fallback(value: &str) -> Option<(&str, &str)> {
+    let args = value
+        .get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("var("))
+        .and_then(|_| value[4..].strip_suffix(')'))?;
+    let (name, fallback) = args.split_once(',')?;
+    let (name, fallback) = (name.trim(), fallback.trim());
+    (name.starts_with("--") && !fallback.is_empty()).then_some((name, fallback))
+}
+
+/// `rgba(r, g, b, a)` with integer channels as `#rrggbbaa`, or `#rgba`
+/// when every channel pairs up. Alpha is quantized to 8 bits, as renderers
+/// store it anyway.
+fn rgba_to_hex(color: &str) -> Option<String> {
+    let args = color.strip_prefix("rgba(")?.strip_suffix(')')?;
+    let parts: Vec<&str> = args.split(',').map(str::trim).collect();
+    let [r, g, b, a] = parts[..] else {
+        return None;
+    };
+    let mut channels = [0u8; 4];
+    for (channel, value) in channels.iter_mut().zip([r, g, b]) {
+        *channel = value.parse().ok()?;
+    }
+    let alpha = match a.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().ok()? / 100.0,
+        None => a.parse::<f64>().ok()?,
+    };
+    if !(0.0..=1.0).contains(&alpha) {
+        return None;
+    }
+    channels[3] = (alpha * 255.0).round() as u8;
+    Some(hex_color(channels, SvgTarget::Browsers))
+}
+
+/// The channels of a `#rgb`, `#rgba`, `#rrggbb`, or `#rrggbbaa` color, or
+/// of a name [`minify_color`] writes.
+fn parse_hex_color(color: &str) -> Option<[u8; 4]> {
+    match color {
+        "red" => return Some([255, 0, 0, 255]),
+        "blue" => return Some([0, 0, 255, 255]),
+        _ => {}
+    }
+    let hex = color.strip_prefix('#')?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let mut channels = [255u8; 4];
+    match hex.len() {
+        3 | 4 => {
+            for (i, channel) in channels.iter_mut().take(hex.len()).enumerate() {
+                *channel = digit(i)? * 0x11;
+            }
+        }
+        6 | 8 => {
+            for (i, channel) in channels.iter_mut().take(hex.len() / 2).enumerate() {
+                *channel = pair(i * 2)?;
+            }
+        }
+        _ => return None,
+    }
+    Some(channels)
+}
+
+/// The shortest hex form of `channels`: `#rgba` when every channel pairs
+/// up, `#rrggbbaa` otherwise, and without alpha when opaque.
+fn hex_color(channels: [u8; 4], target: SvgTarget) -> String {
+    if channels[3] == 255 {
+        let [r, g, b, _] = channels;
+        return minify_color(&format!("#{r:02x}{g:02x}{b:02x}"), target);
+    }
+    if channels.iter().all(|c| c >> 4 == c & 0xf) {
+        channels.iter().fold(String::from("#"), |mut out, c| {
+            out.push(char::from_digit(u32::from(c & 0xf), 16).unwrap());
+            out
+        })
+    } else {
+        channels.iter().fold(String::from("#"), |mut out, c| {
+            out.push_str(&format!("{c:02x}"));
+            out
+        })
+    }
+}
+
+/// Color properties and the opacity that applies to each.
+const COLOR_OPACITIES: [(&str, &str); 4] = [
+    ("fill", "fill-opacity"),
+    ("stroke", "stroke-opacity"),
+    ("stop-color", "stop-opacity"),
+    ("flood-color", "flood-opacity"),
+];
+
+/// Fold opacities into the hex colors they apply to, as `#rrggbbaa`, where
+/// that's shorter.
+fn merge_color_alpha(doc: &mut Document, target: SvgTarget) {
+    let has_opacity = doc.any_element(|elem| {
+        COLOR_OPACITIES
+            .iter()
+            .any(|(_, opacity)| elem.get_attr(opacity).is_some())
+    });
+    // Stylesheets and animations can set either half on their own
+    if !has_opacity || has_dynamic_content(doc) {
+        return;
+    }
+    let referenced = referenced_ids(doc);
+
+    /// `inherited` says whether an ancestor (possibly) sets `fill-opacity`
+    /// and `stroke-opacity`, which an element's own would override.
+    fn visit(
+        elem: &mut Element,
+        referenced: &HashSet<String>,
+        target: SvgTarget,
+        inherited: [bool; 2],
+    ) {
+        // A <use> can give a referenced element other ancestors
+        let inherited = if elem
+            .get_attr("id")
+            .is_some_and(|id| referenced.contains(id))
+        {
+            [true; 2]
+        } else {
+            inherited
+        };
+        // Children inherit the opacity, but not what it's folded into
+        let leaf = elem.child_elements().next().is_none() && !elem.is("use");
+        for (i, (color_prop, opacity_prop)) in COLOR_OPACITIES.into_iter().enumerate() {
+            let is_inherited = i < 2;
+            if is_inherited && (!leaf || inherited[i]) {
+                continue;
+            }
+            // Both halves must be attributes, which `style` would override
+            let (Some(color), Some(opacity)) =
+                (elem.get_attr(color_prop), elem.get_attr(opacity_prop))
+            else {
+                continue;
+            };
+            if property(elem, color_prop) != Some(color.trim())
+                || property(elem, opacity_prop) != Some(opacity.trim())
+            {
+                continue;
+            }
+            let Some(mut channels) = parse_hex_color(&color.trim().to_lowercase()) else {
+                continue;
+            };
+            let Some(factor) = (match opacity.trim().strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok().map(|n| n / 100.0),
+                None => opacity.trim().parse::<f64>().ok(),
+            }) else {
+                continue;
+            };
+            let alpha = f64::from(channels[3]) / 255.0 * factor.clamp(0.0, 1.0);
+            channels[3] = (alpha * 255.0).round() as u8;
+            let merged = hex_color(channels, target);
+            // The opacity attribute goes, with its name, `=""`, and a space
+            let current = color.len() + opacity_prop.len() + opacity.len() + 4;
+            if merged.len() < current {
+                elem.set_attr(color_prop, merged);
+                elem.remove_attr(opacity_prop);
+            }
+        }
+
+        let mut inherited = inherited;
+        for (i, (_, opacity_prop)) in COLOR_OPACITIES[..2].iter().enumerate() {
+            inherited[i] |= property(elem, opacity_prop).is_some_and(|v| v != "1");
+        }
+        for child in elem.child_elements_mut() {
+            visit(child, referenced, target, inherited);
+        }
+    }
+    visit(&mut doc.root, &referenced, target, [false; 2]);
+}
+
+fn minify_style_colors(style: &str, target: SvgTarget) -> String {
     let mut result = String::new();
     for decl in style.split(';') {
         let decl = decl.trim();
@@ -350,17 +1278,8 @@ fn minify_style_colors(style: &str) -> String {
             let value = value.trim();
             result.push_str(prop);
             result.push(':');
-            if [
-                "fill",
-                "stroke",
-                "color",
-                "stop-color",
-                "flood-color",
-                "lighting-color",
-            ]
-            .contains(&prop)
-            {
-                result.push_str(&minify_color(value));
+            if COLOR_PROPERTIES.contains(&prop) {
+                result.push_str(&minify_color(value, target));
             } else {
                 result.push_str(value);
             }
@@ -372,27 +1291,181 @@ fn minify_style_colors(style: &str) -> String {
 }
 
 /// Remove default attribute values.
-fn remove_default_attrs(elem: &mut Element) {
-    elem.attributes
-        .retain(|attr| !is_default_value(&elem.name.local, &attr.name.local, &attr.value));
+fn remove_default_attrs(elem: &mut Element, target: SvgTarget, inherited: &mut Inheritance) {
+    let len = inherited.enter(elem);
+    elem.attributes.retain(|attr| {
+        let obsolete = target.is_svg2() && is_svg11_only(&attr.name);
+        let name = attr.name.local.as_str();
+        !obsolete
+            && (inherited.contains(name) || !is_default_value(&elem.name.local, name, &attr.value))
+    });
 
+    inherited.push_set(elem);
     for child in elem.child_elements_mut() {
-        remove_default_attrs(child);
+        remove_default_attrs(child, target, inherited);
     }
+    inherited.leave(len);
 }
 
-fn is_default_value(element: &str, attr: &str, value: &str) -> bool {
-    // Common defaults
-    match (element, attr, value) {
-        // SVG element defaults
-        (_, "version", "1.1") => true,
+/// The inherited properties set above an element, as the walk of a tree
+/// goes. A default value for one of those overrides what the element would
+/// inherit, so it has to stay.
+struct Inheritance {
+    set: Vec<&'static str>,
+    /// Ids `<use>` elements reference, which can give an element other
+    /// ancestors
+    used: HashSet<String>,
+}
+
+impl Inheritance {
+    fn new(root: &Element) -> Self {
+        fn collect_used(elem: &Element, used: &mut HashSet<String>) {
+            if elem.is("use")
+                && let Some(id) = elem
+                    .get_attr("href")
+                    .or_else(|| elem.get_attr("xlink:href"))
+                    .and_then(|href| href.strip_prefix('#'))
+            {
+                used.insert(id.to_string());
+            }
+            for child in elem.child_elements() {
+                collect_used(child, used);
+            }
+        }
+        let mut used = HashSet::new();
+        collect_used(root, &mut used);
+        // A stylesheet rule may set a property on any element
+        let set = INHERITED
+            .iter()
+            .copied()
+            .filter(|prop| stylesheets_contain(root, prop))
+            .collect();
+        Inheritance { set, used }
+    }
+
+    /// Enter `elem`, returning the length to [`leave`](Self::leave) it with.
+    fn enter(&mut self, elem: &Element) -> usize {
+        let len = self.set.len();
+        if elem.get_attr("id").is_some_and(|id| self.used.contains(id)) {
+            for prop in INHERITED {
+                if !self.set.contains(prop) {
+                    self.set.push(prop);
+                }
+            }
+        }
+        len
+    }
+
+    /// Add the inherited properties `elem` sets, as presentation attributes
+    /// or `style` declarations, for its descendants.
+    fn push_set(&mut self, elem: &Element) {
+        let declared = elem
+            .get_attr("style")
+            .into_iter()
+            .flat_map(|style| style.split(';'))
+            .filter_map(|decl| decl.split_once(':').map(|(prop, _)| prop.trim()));
+        let attrs = elem.attributes.iter().map(|attr| attr.name.local.as_str());
+        for name in attrs.chain(declared) {
+            if let Some(prop) = INHERITED.iter().find(|prop| **prop == name)
+                && !self.set.contains(prop)
+            {
+                self.set.push(prop);
+            }
+        }
+    }
+
+    fn leave(&mut self, len: usize) {
+        self.set.truncate(len);
+    }
+
+    fn contains(&self, prop: &str) -> bool {
+        self.set.contains(&prop)
+    }
+}
+
+/// Elements `preserveAspectRatio` fits a `viewBox` into; without one, it
+/// does nothing there.
+const VIEW_BOX_ELEMENTS: [&str; 5] = ["svg", "symbol", "marker", "pattern", "view"];
+
+/// The `preserveAspectRatio` alignments.
+const ALIGNMENTS: [&str; 10] = [
+    "none", "xMinYMin", "xMidYMin", "xMaxYMin", "xMinYMid", "xMidYMid", "xMaxYMid", "xMinYMax",
+    "xMidYMax", "xMaxYMax",
+];
+
+/// Write `preserveAspectRatio` in its shortest form, and remove it where
+/// it's the default or has no `viewBox` to act on.
+fn simplify_aspect_ratios(elem: &mut Element, is_root: bool) {
+    if let Some(value) = elem.get_attr("preserveAspectRatio")
+        && let Some(simplified) = simplify_aspect_ratio(value)
+    {
+        // A pattern inherits the attribute from the one it links to, unless
+        // it sets it, and a view of the document can give the root a
+        // viewBox
+        let inherits = elem.is("pattern")
+            && (elem.get_attr("href").is_some() || elem.get_attr("xlink:href").is_some());
+        let no_view_box = VIEW_BOX_ELEMENTS.contains(&elem.name.local.as_str())
+            && elem.get_attr("viewBox").is_none()
+            && !is_root;
+        if !inherits && (simplified == "xMidYMid" || no_view_box) {
+            elem.remove_attr("preserveAspectRatio");
+        } else {
+            elem.set_attr("preserveAspectRatio", simplified);
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        simplify_aspect_ratios(child, false);
+    }
+}
+
+/// `[defer] <align> [meet | slice]` with single spaces and without the
+/// implied `meet`, or `None` if it isn't valid.
+fn simplify_aspect_ratio(value: &str) -> Option<String> {
+    let mut tokens = value.split_ascii_whitespace().peekable();
+    let defer = tokens.next_if_eq(&"defer").is_some();
+    let align = tokens.next().filter(|align| ALIGNMENTS.contains(align))?;
+    let slice = match tokens.next() {
+        None | Some("meet") => false,
+        Some("slice") => true,
+        Some(_) => return None,
+    };
+    if tokens.next().is_some() {
+        return None;
+    }
+    let mut out = String::new();
+    if defer {
+        out.push_str("defer ");
+    }
+    out.push_str(align);
+    if slice {
+        out.push_str(" slice");
+    }
+    Some(out)
+}
+
+/// Attributes SVG 2 dropped, which its renderers ignore whatever the value.
+fn is_svg11_only(name: &QName) -> bool {
+    name.prefix.is_none()
+        && matches!(
+            name.local.as_str(),
+            "version" | "baseProfile" | "requiredFeatures"
+        )
+}
+
+fn is_default_value(element: &str, attr: &str, value: &str) -> bool {
+    // Common defaults
+    match (element, attr, value) {
+        // SVG element defaults
+        (_, "version", "1.1") => true,
         (_, "baseProfile", "full") => true,
-        (_, "preserveAspectRatio", "xMidYMid meet") => true,
 
         // Presentation attribute defaults
         (_, "fill-opacity", "1") => true,
         (_, "stroke-opacity", "1") => true,
         (_, "opacity", "1") => true,
+        (_, "stop-opacity", "1") => true,
+        (_, "flood-opacity", "1") => true,
         (_, "stroke-width", "1") => true,
         (_, "stroke-linecap", "butt") => true,
         (_, "stroke-linejoin", "miter") => true,
@@ -419,10 +1492,17 @@ fn is_default_value(element: &str, attr: &str, value: &str) -> bool {
     }
 }
 
-/// Minify inline styles.
-fn minify_styles(elem: &mut Element) {
+/// Minify inline styles and `<style>` rules.
+fn minify_styles(elem: &mut Element, inherited: &mut Inheritance) {
+    let len = inherited.enter(elem);
     if let Some(style) = elem.get_attr("style").map(|s| s.to_string()) {
-        let minified = minify_style(&style);
+        // A default value overrides the element's own presentation attribute
+        // as well as what it inherits
+        let minified = minify_declarations(&style, |prop, value| {
+            inherited.contains(prop)
+                || elem.get_attr(prop).is_some()
+                || !is_default_style_value(prop, value)
+        });
         if minified.is_empty() {
             elem.remove_attr("style");
         } else {
@@ -430,34 +1510,19 @@ fn minify_styles(elem: &mut Element) {
         }
     }
 
-    for child in elem.child_elements_mut() {
-        minify_styles(child);
-    }
-}
-
-fn minify_style(style: &str) -> String {
-    let mut parts = Vec::new();
-
-    for decl in style.split(';') {
-        let decl = decl.trim();
-        if decl.is_empty() {
-            continue;
-        }
-
-        if let Some((prop, value)) = decl.split_once(':') {
-            let prop = prop.trim();
-            let value = value.trim();
-
-            // Skip default values
-            if is_default_style_value(prop, value) {
-                continue;
+    if elem.is("style") && elem.get_attr("type").is_none_or(|t| t == "text/css") {
+        for child in &mut elem.children {
+            if let Node::Text(css) | Node::CData(css) = child {
+                *css = minify_stylesheet(css).into();
             }
-
-            parts.push(format!("{}:{}", prop, value));
         }
     }
 
-    parts.join(";")
+    inherited.push_set(elem);
+    for child in elem.child_elements_mut() {
+        minify_styles(child, inherited);
+    }
+    inherited.leave(len);
 }
 
 fn is_default_style_value(prop: &str, value: &str) -> bool {
@@ -466,6 +1531,8 @@ fn is_default_style_value(prop: &str, value: &str) -> bool {
         ("fill-opacity", "1")
             | ("stroke-opacity", "1")
             | ("opacity", "1")
+            | ("stop-opacity", "1")
+            | ("flood-opacity", "1")
             | ("stroke-width", "1")
             | ("font-style", "normal")
             | ("font-weight", "normal")
@@ -473,15 +1540,487 @@ fn is_default_style_value(prop: &str, value: &str) -> bool {
     )
 }
 
-/// Clean up whitespace-only text nodes.
-fn cleanup_whitespace(elem: &mut Element) {
-    elem.children.retain(|node| {
-        if let Node::Text(text) = node {
-            !text.trim().is_empty()
-        } else {
-            true
+/// Data URIs shorter than this aren't worth a `<defs>` entry.
+const MIN_DEDUPE_LEN: usize = 128;
+
+/// Deduplicate repeated base64 payloads: identical embedded images become a
+/// single `<image>` in `<defs>` referenced by `<use>`, repeated
+/// `@font-face` rules are dropped and ones embedding the same font under
+/// other family names merged, and, for targets with custom properties,
+/// `url(data:...)` values repeated in styles are declared once.
+fn dedupe_data_uris(root: &mut Element, target: SvgTarget) {
+    dedupe_font_faces(root);
+    merge_font_families(root);
+    dedupe_images(root);
+    if target.supports_custom_properties() {
+        dedupe_css_urls(root);
+    }
+}
+
+/// Whether `url` embeds a payload worth deduplicating.
+fn is_large_data_uri(url: &str) -> bool {
+    url.len() >= MIN_DEDUPE_LEN && parse_data_uri(url).is_some()
+}
+
+fn dedupe_font_faces(root: &mut Element) {
+    fn visit(elem: &mut Element, seen: &mut HashSet<String>) {
+        if elem.is("style") {
+            for child in &mut elem.children {
+                let (Node::Text(css) | Node::CData(css)) = child else {
+                    continue;
+                };
+
+                let mut duplicates = Vec::new();
+                for (rule, _) in font_face_rules(css) {
+                    let normalized: String = css[rule.clone()]
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if !seen.insert(normalized) {
+                        duplicates.push(rule);
+                    }
+                }
+
+                for rule in duplicates.into_iter().rev() {
+                    css.to_mut().replace_range(rule, "");
+                }
+            }
+        }
+
+        for child in elem.child_elements_mut() {
+            visit(child, seen);
+        }
+    }
+
+    visit(root, &mut HashSet::new());
+}
+
+/// Merge `@font-face` rules that embed the same font (and are otherwise
+/// equal) under different family names: later ones are dropped, and their
+/// family renamed to the first one's wherever `font-family` names it.
+///
+/// Only families with a single rule are merged, so no other face of the
+/// family they join can match instead, and not ones a `font` shorthand
+/// names, which isn't rewritten.
+fn merge_font_families(root: &mut Element) {
+    struct Face {
+        /// Unquoted, lowercased
+        family: String,
+        /// As written in the rule
+        spelled: String,
+        /// The other descriptors, sorted
+        descriptors: Vec<(String, String)>,
+    }
+
+    fn collect(elem: &Element, faces: &mut Vec<Face>) {
+        if elem.is("style") {
+            for child in &elem.children {
+                let (Node::Text(css) | Node::CData(css)) = child else {
+                    continue;
+                };
+                for (_, block) in font_face_rules(css) {
+                    let block = &css[block];
+                    let mut face = Face {
+                        family: String::new(),
+                        spelled: String::new(),
+                        descriptors: Vec::new(),
+                    };
+                    for decl in split_top_level(block, ';') {
+                        let Some((name, value)) = block[decl].split_once(':') else {
+                            continue;
+                        };
+                        let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+                        if name == "font-family" {
+                            face.family = parse_value_unquoted(value).to_ascii_lowercase();
+                            face.spelled = value.to_string();
+                        } else {
+                            face.descriptors.push((name, value.to_string()));
+                        }
+                    }
+                    face.descriptors.sort();
+                    faces.push(face);
+                }
+            }
+        }
+        for child in elem.child_elements() {
+            collect(child, faces);
+        }
+    }
+
+    fn drop_rules(elem: &mut Element, dropped: &HashSet<usize>, next: &mut usize) {
+        if elem.is("style") {
+            for child in &mut elem.children {
+                let (Node::Text(css) | Node::CData(css)) = child else {
+                    continue;
+                };
+                let mut rules = Vec::new();
+                for (rule, _) in font_face_rules(css) {
+                    if dropped.contains(next) {
+                        rules.push(rule);
+                    }
+                    *next += 1;
+                }
+                for rule in rules.into_iter().rev() {
+                    css.to_mut().replace_range(rule, "");
+                }
+            }
+        }
+        for child in elem.child_elements_mut() {
+            drop_rules(child, dropped, next);
+        }
+    }
+
+    fn rename_attrs(elem: &mut Element, renames: &HashMap<String, String>) {
+        for attr in &mut elem.attributes {
+            if attr.name.prefix.is_none()
+                && attr.name.local == "font-family"
+                && let Some(value) = rename_families(&attr.value, renames)
+            {
+                attr.value = value.into();
+            }
+        }
+        for child in elem.child_elements_mut() {
+            rename_attrs(child, renames);
+        }
+    }
+
+    fn shorthands(elem: &Element, out: &mut Vec<String>) {
+        if let Some(font) = elem.get_attr("font") {
+            out.push(font.to_ascii_lowercase());
+        }
+        for child in elem.child_elements() {
+            shorthands(child, out);
         }
+    }
+
+    let mut faces = Vec::new();
+    collect(root, &mut faces);
+    let mut rules_per_family: HashMap<&str, usize> = HashMap::new();
+    for face in &faces {
+        *rules_per_family.entry(&face.family).or_default() += 1;
+    }
+
+    let mut first: HashMap<&[(String, String)], &Face> = HashMap::new();
+    let mut merges = Vec::new();
+    for (i, face) in faces.iter().enumerate() {
+        let embeds = face.descriptors.iter().any(|(name, value)| {
+            name == "src" && css_urls(value).iter().any(|u| is_large_data_uri(&u.url))
+        });
+        if !embeds || face.family.is_empty() || rules_per_family[face.family.as_str()] > 1 {
+            continue;
+        }
+        match first.get(face.descriptors.as_slice()) {
+            Some(kept) if kept.family != face.family => merges.push((i, face, *kept)),
+            Some(_) => {}
+            None => {
+                first.insert(&face.descriptors, face);
+            }
+        }
+    }
+
+    let mut fonts = Vec::new();
+    shorthands(root, &mut fonts);
+    map_all_declarations(root, &mut |prop, value| {
+        if prop == "font" {
+            fonts.push(value.to_ascii_lowercase());
+        }
+        None
+    });
+    merges.retain(|(_, face, _)| !fonts.iter().any(|font| font.contains(&face.family)));
+    if merges.is_empty() {
+        return;
+    }
+
+    let dropped: HashSet<usize> = merges.iter().map(|&(i, _, _)| i).collect();
+    let renames: HashMap<String, String> = merges
+        .iter()
+        .map(|(_, face, kept)| (face.family.clone(), kept.spelled.clone()))
+        .collect();
+    drop_rules(root, &dropped, &mut 0);
+    rename_attrs(root, &renames);
+    map_all_declarations(root, &mut |prop, value| {
+        (prop == "font-family")
+            .then(|| rename_families(value, &renames))
+            .flatten()
     });
+}
+
+/// `families` (a `font-family` list) with the families `renames` has
+/// (unquoted and lowercased) replaced, or `None` if there are none.
+fn rename_families(families: &str, renames: &HashMap<String, String>) -> Option<String> {
+    let mut changed = false;
+    let list: Vec<&str> = split_top_level(families, ',')
+        .into_iter()
+        .map(|family| {
+            let family = families[family].trim();
+            match renames.get(&parse_value_unquoted(family).to_ascii_lowercase()) {
+                Some(new) => {
+                    changed = true;
+                    new.as_str()
+                }
+                None => family,
+            }
+        })
+        .collect();
+    changed.then(|| list.join(","))
+}
+
+/// Declare `url(data:...)` values repeated in stylesheets and `style`
+/// attributes once, as custom properties on the root, and use them with
+/// `var()`. Properties are named after a hash of the URI, so inline SVGs on
+/// one page that declare the same name agree on its value. `@font-face`
+/// sources, where `var()` isn't allowed, are left alone.
+fn dedupe_css_urls(root: &mut Element) {
+    let dedupable = |prop: &str, url: &CssUrl| {
+        !prop.starts_with("--") && prop != "src" && is_large_data_uri(&url.url)
+    };
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut declared = HashSet::new();
+    map_all_declarations(root, &mut |prop, value| {
+        if prop.starts_with("--") {
+            declared.insert(prop.to_string());
+        }
+        for url in css_urls(value) {
+            if dedupable(prop, &url) {
+                *counts.entry(url.url).or_default() += 1;
+            }
+        }
+        None
+    });
+
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut declarations = Vec::new();
+    for (url, n) in counts {
+        let name = format!("--d{:08x}", fnv1a(url.as_bytes()) as u32);
+        if n < 2 || !declared.insert(name.clone()) {
+            continue;
+        }
+        declarations.push(format!("{name}:{}", format_css_url(&url)));
+        names.insert(url, name);
+    }
+    if names.is_empty() {
+        return;
+    }
+
+    map_all_declarations(root, &mut |prop, value| {
+        let mut out = value.to_string();
+        let mut changed = false;
+        for url in css_urls(value).into_iter().rev() {
+            if dedupable(prop, &url)
+                && let Some(name) = names.get(&url.url)
+            {
+                out.replace_range(url.token, &format!("var({name})"));
+                changed = true;
+            }
+        }
+        changed.then_some(out)
+    });
+    let style = match root.get_attr("style").map(str::trim) {
+        Some(style) if !style.is_empty() => format!("{};{style}", declarations.join(";")),
+        _ => declarations.join(";"),
+    };
+    root.set_attr("style", style);
+}
+
+/// Rewrite the declarations of every `<style>` element and `style`
+/// attribute with `f`, as [`map_declarations`] does; ones `f` leaves alone
+/// are left exactly as written.
+fn map_all_declarations(elem: &mut Element, f: &mut dyn FnMut(&str, &str) -> Option<String>) {
+    let mut map = |css: &str, stylesheet: bool| -> Option<String> {
+        let mut changed = false;
+        let mut g = |prop: &str, value: &str| {
+            let new = f(prop, value);
+            changed |= new.is_some();
+            new
+        };
+        let out = if stylesheet {
+            map_stylesheet(css, &mut |block| map_declarations(block, &mut g))
+        } else {
+            map_declarations(css, &mut g)
+        };
+        changed.then_some(out)
+    };
+
+    if elem.is("style") {
+        for child in &mut elem.children {
+            if let Node::Text(css) | Node::CData(css) = child
+                && let Some(new) = map(css, true)
+            {
+                *css = new.into();
+            }
+        }
+    }
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_none()
+            && attr.name.local == "style"
+            && let Some(new) = map(&attr.value, false)
+        {
+            attr.value = new.into();
+        }
+    }
+    for child in elem.child_elements_mut() {
+        map_all_declarations(child, f);
+    }
+}
+
+/// Attributes that define the image itself and move to the shared
+/// definition; everything else (position, transform, styling) stays on the
+/// `<use>`.
+const IMAGE_DEF_ATTRS: [&str; 4] = ["href", "width", "height", "preserveAspectRatio"];
+
+fn dedupe_images(root: &mut Element) {
+    fn image_key(elem: &Element) -> Option<Vec<(QName, String)>> {
+        if !elem.is("image") || !elem.children.is_empty() {
+            return None;
+        }
+        let href = elem.get_attr("href")?;
+        if parse_data_uri(href).is_none() || href.len() < MIN_DEDUPE_LEN {
+            return None;
+        }
+        Some(
+            elem.attributes
+                .iter()
+                .filter(|a| IMAGE_DEF_ATTRS.contains(&a.name.local.as_str()))
+                .map(|a| (a.name.clone(), a.value.to_string()))
+                .collect(),
+        )
+    }
+
+    fn count(elem: &Element, counts: &mut Vec<(Vec<(QName, String)>, usize)>) {
+        for child in elem.child_elements() {
+            if let Some(key) = image_key(child) {
+                match counts.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((key, 1)),
+                }
+            }
+            count(child, counts);
+        }
+    }
+
+    fn replace(elem: &mut Element, ids: &HashMap<Vec<(QName, String)>, String>) {
+        for child in elem.child_elements_mut() {
+            if let Some(id) = image_key(child).and_then(|key| ids.get(&key)) {
+                let href_name = child
+                    .attributes
+                    .iter()
+                    .find(|a| a.name.local == "href")
+                    .map(|a| a.name.clone())
+                    .unwrap_or_else(|| QName::new("href"));
+                child.name = QName::new("use");
+                child
+                    .attributes
+                    .retain(|a| !IMAGE_DEF_ATTRS.contains(&a.name.local.as_str()));
+                child.attributes.push(Attribute {
+                    name: href_name,
+                    value: format!("#{}", id).into(),
+                });
+            } else {
+                replace(child, ids);
+            }
+        }
+    }
+
+    // Turning `<image>`s into `<use>`s would change which type selectors,
+    // and `*-of-type` pseudo-classes, match
+    if ["image", "use", "-of-type"]
+        .iter()
+        .any(|needle| stylesheets_contain(root, needle))
+    {
+        return;
+    }
+
+    let mut counts = Vec::new();
+    count(root, &mut counts);
+    counts.retain(|(_, n)| *n > 1);
+    if counts.is_empty() {
+        return;
+    }
+
+    let mut id_gen = IdGenerator::new(root);
+    let mut ids = HashMap::new();
+    let mut defs = Vec::new();
+    for (key, _) in counts {
+        let id = id_gen.next_id();
+        let mut image = Element::new("image");
+        image.set_attr("id", id.clone());
+        image
+            .attributes
+            .extend(key.iter().map(|(name, value)| Attribute {
+                name: name.clone(),
+                value: value.clone().into(),
+            }));
+        defs.push(Node::Element(image));
+        ids.insert(key, id);
+    }
+
+    replace(root, &ids);
+
+    if let Some(existing) = root.child_elements_mut().find(|e| e.is("defs")) {
+        existing.children.extend(defs);
+    } else {
+        let mut new_defs = Element::new("defs");
+        new_defs.children = defs;
+        root.children.insert(0, Node::Element(new_defs));
+    }
+}
+
+/// Generates short ids (`a`, `b`, ..., `aa`, ...) that don't collide with
+/// ids already in the document.
+pub(crate) struct IdGenerator {
+    existing: HashSet<String>,
+    next: usize,
+}
+
+impl IdGenerator {
+    fn new(root: &Element) -> Self {
+        fn collect(elem: &Element, ids: &mut HashSet<String>) {
+            if let Some(id) = elem.get_attr("id") {
+                ids.insert(id.to_string());
+            }
+            for child in elem.child_elements() {
+                collect(child, ids);
+            }
+        }
+
+        let mut existing = HashSet::new();
+        collect(root, &mut existing);
+        Self::avoiding(existing)
+    }
+
+    /// A generator for any kind of name, skipping `existing` ones.
+    pub(crate) fn avoiding(existing: HashSet<String>) -> Self {
+        Self { existing, next: 0 }
+    }
+
+    pub(crate) fn next_id(&mut self) -> String {
+        loop {
+            let mut n = self.next;
+            self.next += 1;
+
+            let mut id = Vec::new();
+            loop {
+                id.push(b'a' + (n % 26) as u8);
+                n /= 26;
+                if n == 0 {
+                    break;
+                }
+                n -= 1;
+            }
+            id.reverse();
+
+            let id = String::from_utf8(id).unwrap();
+            if self.existing.insert(id.clone()) {
+                return id;
+            }
+        }
+    }
+}
+
+/// Clean up whitespace-only text nodes.
+fn cleanup_whitespace(elem: &mut Element) {
+    elem.children.retain(|node| !is_whitespace(node));
 
     for child in elem.child_elements_mut() {
         cleanup_whitespace(child);
@@ -492,12 +2031,487 @@ fn cleanup_whitespace(elem: &mut Element) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_optimize_with_stats() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg"><title>Icon</title><rect fill="#ff0000"/></svg>"##;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        let options = Options::default();
+        let stats = optimize_with_stats(&mut doc, &options);
+
+        let saved = |name| stats.iter().find(|s| s.name == name).unwrap().saved;
+        assert_eq!(
+            saved("remove_metadata"),
+            "<title>Icon</title>".len() as isize
+        );
+        assert_eq!(
+            saved("minify_colors"),
+            "#ff0000".len() as isize - "red".len() as isize
+        );
+        assert_eq!(saved("remove_comments"), 0);
+        assert_eq!(stats.last().unwrap().name, "cleanup_whitespace");
+    }
+
     #[test]
     fn test_minify_color() {
-        assert_eq!(minify_color("#ffffff"), "#fff");
-        assert_eq!(minify_color("#ff0000"), "red");
-        assert_eq!(minify_color("#aabbcc"), "#abc");
-        assert_eq!(minify_color("#abcdef"), "#abcdef"); // can't shorten
+        let target = SvgTarget::default();
+        assert_eq!(minify_color("#ffffff", target), "#fff");
+        assert_eq!(minify_color("#ff0000", target), "red");
+        assert_eq!(minify_color("#aabbcc", target), "#abc");
+        assert_eq!(minify_color("#abcdef", target), "#abcdef"); // can't shorten
+    }
+
+    #[test]
+    fn test_simplify_aspect_ratios() {
+        let svg = r##"<svg viewBox="0 0 1 1" preserveAspectRatio=" xMidYMid   meet "><symbol viewBox="0 0 1 1" preserveAspectRatio="xMinYMin meet"/><symbol preserveAspectRatio="xMinYMin"/><marker viewBox="0 0 1 1" preserveAspectRatio="defer  xMidYMid"/><image preserveAspectRatio="none  meet"/><image preserveAspectRatio="xMidYMid bogus"/><pattern id="a" preserveAspectRatio="none"/><pattern href="#a" preserveAspectRatio="xMidYMid meet"/></svg>"##;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        simplify_aspect_ratios(&mut doc.root, true);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r##"<svg viewBox="0 0 1 1"><symbol preserveAspectRatio="xMinYMin" viewBox="0 0 1 1"/><symbol/><marker preserveAspectRatio="defer xMidYMid" viewBox="0 0 1 1"/><image preserveAspectRatio="none"/><image preserveAspectRatio="xMidYMid bogus"/><pattern id="a"/><pattern href="#a" preserveAspectRatio="xMidYMid"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_hex_alpha() {
+        let target = SvgTarget::Browsers;
+        assert_eq!(minify_color("#FF000088", target), "#f008");
+        assert_eq!(minify_color("#ff000080", target), "#ff000080");
+        assert_eq!(minify_color("#ff0000ff", target), "red");
+        assert_eq!(minify_color("#abcf", target), "#abc");
+
+        let merged = |svg: &str| {
+            let mut doc = crate::parse_svg(svg).unwrap();
+            merge_color_alpha(&mut doc, target);
+            crate::serialize(&doc, &Options::default())
+        };
+        assert_eq!(
+            merged(
+                r##"<svg><rect fill="#f00" fill-opacity=".5"/><rect stroke="#fff" stroke-opacity="53.3%" fill="#0008" fill-opacity=".5"/><stop stop-color="#00f" stop-opacity="0"/></svg>"##
+            ),
+            r##"<svg><rect fill="#ff000080"/><rect fill="#0004" stroke="#fff8"/><stop stop-color="#00f0"/></svg>"##
+        );
+
+        // The opacity would reach other elements, or meet an inherited one
+        for svg in [
+            r##"<svg><g fill="red" fill-opacity=".5"><rect/></g></svg>"##,
+            r##"<svg><g fill-opacity=".5"><rect fill="red" fill-opacity=".5"/></g></svg>"##,
+            r##"<svg><path fill="red" fill-opacity=".5" id="p"/><use href="#p"/></svg>"##,
+            r##"<svg><rect fill="red" fill-opacity=".5" style="fill:blue"/></svg>"##,
+            r##"<svg><rect fill="url(#g)" fill-opacity=".5"/></svg>"##,
+        ] {
+            assert_eq!(merged(svg), svg);
+        }
+    }
+
+    #[test]
+    fn test_modern_colors() {
+        let target = SvgTarget::default();
+        for color in [
+            "color(display-p3 1 0.5 0)",
+            "color(display-p3 1 0 0 / 0.5)",
+            "lab(52.2345% 40.1645 59.9971)",
+            "lch(52% 72 56 / 50%)",
+            "oklab(0.63 0.22 0.13)",
+            "oklch(63% 0.26 29)",
+            "var(--Brand)",
+            "var(--c,)",
+        ] {
+            assert_eq!(minify_color(color, target), color);
+        }
+        assert_eq!(
+            minify_color("var(--Brand, #FFFFFF)", target),
+            "var(--Brand,#fff)"
+        );
+        assert_eq!(
+            minify_color("var(--a, var(--b, #ff0000))", target),
+            "var(--a,var(--b,red))"
+        );
+
+        // Through the whole pipeline, in attributes and styles alike
+        let svg = r##"<svg><path d="M0 0h1" fill="oklch(63% 0.26 29)" stroke="color(display-p3 1 0 0)" style="stop-color:lab(52% 40 60);color:var(--fg, #000000)"/></svg>"##;
+        assert_eq!(
+            crate::minify(svg).unwrap(),
+            r##"<svg><path d="M0 0h1" fill="oklch(63% 0.26 29)" stroke="color(display-p3 1 0 0)" style="stop-color:lab(52% 40 60);color:var(--fg,#000)"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_rgba_by_target() {
+        assert_eq!(
+            minify_color("rgba(255, 0, 0, 0.5)", SvgTarget::Browsers),
+            "#ff000080"
+        );
+        assert_eq!(
+            minify_color("rgba(0,0,0,20%)", SvgTarget::Browsers),
+            "#0003"
+        );
+        assert_eq!(
+            minify_color("rgba(0,0,0,.2)", SvgTarget::Svg2),
+            "rgba(0,0,0,.2)"
+        );
+        assert_eq!(
+            minify_color("rgba(300,0,0,.2)", SvgTarget::Browsers),
+            "rgba(300,0,0,.2)"
+        );
+    }
+
+    #[test]
+    fn test_svg11_target() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.2" baseProfile="tiny"><switch><use xlink:href="#a" requiredFeatures="http://www.w3.org/TR/SVG11/feature#Shape"/></switch></svg>"##;
+        let minify = |target| {
+            let mut doc = crate::parse_svg(svg).unwrap();
+            optimize(
+                &mut doc,
+                &Options {
+                    target,
+                    ..Options::default()
+                },
+            );
+            crate::serialize(&doc, &Options::default())
+        };
+
+        let legacy = minify(SvgTarget::Svg11);
+        assert!(legacy.contains("xlink:href") && legacy.contains("requiredFeatures"));
+        assert!(legacy.contains(r#"version="1.2""#));
+
+        let modern = minify(SvgTarget::Svg2);
+        assert_eq!(
+            modern,
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><use href="#a"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_dedupe_images() {
+        let payload = "A".repeat(200);
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><image id="a" href="data:image/png;base64,{p}" width="10" height="10"/><g><image x="5" href="data:image/png;base64,{p}" width="10" height="10"/></g></svg>"#,
+            p = payload
+        );
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(out.matches(&payload).count(), 1);
+        assert!(out.contains(r##"<use href="#b" id="a"/>"##));
+        assert!(out.contains(r##"<use href="#b" x="5"/>"##));
+
+        // Type selectors would stop (or start) matching
+        for css in [
+            "image{opacity:.5}",
+            "use{fill:red}",
+            "g>:first-of-type{opacity:0}",
+        ] {
+            let styled = svg.replace("<g>", &format!("<style>{css}</style><g>"));
+            let mut doc = crate::parse_svg(&styled).unwrap();
+            dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+            let out = crate::serialize(&doc, &Options::default());
+            assert_eq!(out.matches(&payload).count(), 2, "{css}");
+        }
+    }
+
+    #[test]
+    fn test_dedupe_font_faces() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><style>@font-face { font-family: 'A'; src: url('a.woff2'); }</style><style>@font-face {  font-family: 'A'; src: url('a.woff2'); } text { fill: red }</style></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(out.matches("@font-face").count(), 1);
+        assert!(out.contains("text { fill: red }"));
+    }
+
+    #[test]
+    fn test_dedupe_css_urls() {
+        let uri = format!("data:image/png;base64,{}", "A".repeat(200));
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" style="color:red"><style>.a{{cursor:url({uri}),auto}} .b{{cursor:url("{uri}") 2 2,auto}}</style><g style="cursor:url('{uri}'),auto"/><text style="cursor:url(data:,short),auto"/></svg>"#
+        );
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+        let out = crate::serialize(&doc, &Options::default());
+        let name = format!("--d{:08x}", fnv1a(uri.as_bytes()) as u32);
+        assert_eq!(out.matches(&uri).count(), 1);
+        assert!(out.contains(&format!(r#"style="{name}:url({uri});color:red""#)));
+        assert!(out.contains(&format!(".a{{cursor:var({name}),auto}}")));
+        assert!(out.contains(&format!(".b{{cursor:var({name}) 2 2,auto}}")));
+        assert!(out.contains(&format!(r#"<g style="cursor:var({name}),auto"/>"#)));
+        assert!(out.contains("url(data:,short)"));
+
+        // Running again changes nothing
+        let mut again = crate::parse_svg(&out).unwrap();
+        dedupe_data_uris(&mut again.root, SvgTarget::Browsers);
+        assert_eq!(crate::serialize(&again, &Options::default()), out);
+
+        // Older renderers don't know var()
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Svg11);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(out.matches(&uri).count(), 3);
+    }
+
+    #[test]
+    fn test_merge_font_families() {
+        let font = format!("data:font/woff2;base64,{}", "A".repeat(200));
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><style>@font-face{{font-family:"Brand Sans";src:url({font})}}@font-face{{font-family:Brand-UI;src:url({font})}}@font-face{{font-family:Other;font-weight:700;src:url({font})}} .t{{font-family:Brand-UI,sans-serif}}</style><text font-family="'brand-ui'">a</text><text style="font-family:Brand-UI">b</text></svg>"#
+        );
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(out.matches(&font).count(), 2);
+        assert!(!out.contains("Brand-UI") && !out.contains("brand-ui"));
+        assert!(out.contains(r#".t{font-family:"Brand Sans",sans-serif}"#));
+        assert!(out.contains(r#"<text font-family='"Brand Sans"'>a</text>"#));
+        assert!(out.contains(r#"<text style='font-family:"Brand Sans"'>b</text>"#));
+        // A different weight isn't the same face
+        assert!(out.contains("font-family:Other"));
+
+        // Families named in `font` shorthands stay
+        let svg = svg.replace(
+            r#"style="font-family:Brand-UI""#,
+            r#"style="font:12px Brand-UI""#,
+        );
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        dedupe_data_uris(&mut doc.root, SvgTarget::Browsers);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(out.matches(&font).count(), 3);
+    }
+
+    #[test]
+    fn test_remove_empty_attrs() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><g class="" style=" " transform=""><rect width="1" height="1" fill="" data-x="" systemLanguage=""/></g><a href=""><circle r="1"/></a></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        remove_empty_attrs(&mut doc.root);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g><rect data-x="" height="1" systemLanguage="" width="1"/></g><a href=""><circle r="1"/></a></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_remove_elements() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><switch><foreignObject><div xmlns="http://www.w3.org/1999/xhtml"/></foreignObject><rect/></switch><g><video/><audio/><circle r="1"/></g></svg>"#;
+        let run = |deny: &[&str], allow: Option<&[&str]>| {
+            let globs = |names: &[&str]| -> Vec<Glob> {
+                names.iter().map(|n| n.parse().unwrap()).collect()
+            };
+            let mut doc = crate::parse_svg(svg).unwrap();
+            remove_elements(&mut doc.root, &globs(deny), allow.map(globs).as_deref());
+            crate::serialize(&doc, &Options::default())
+        };
+
+        assert_eq!(
+            run(&["foreignObject", "audio", "video"], None),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><switch><rect/></switch><g><circle r="1"/></g></svg>"#
+        );
+        // Content of elements outside the allowlist goes with them
+        assert_eq!(
+            run(&[], Some(&["g", "rect", "circle"])),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g><circle r="1"/></g></svg>"#
+        );
+        assert_eq!(
+            run(&["circle"], Some(&["*"])),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><switch><foreignObject><div xmlns="http://www.w3.org/1999/xhtml"/></foreignObject><rect/></switch><g><video/><audio/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_remove_attrs() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:figma="urn:figma" data-figma-id="1"><g class="layer" figma:node="2"><path class="icon" d="M0 0" fill="none"/><rect class="bg" fill="red"/></g></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        let patterns: Vec<AttrPattern> = ["data-figma-*", "figma:*", "path[class]", "[fill=none]"]
+            .iter()
+            .map(|p| p.parse().unwrap())
+            .collect();
+        remove_attrs(&mut doc.root, &patterns);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:figma="urn:figma"><g class="layer"><path d="M0 0"/><rect class="bg" fill="red"/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_remove_xml_attrs() {
+        let svg = r#"<svg xml:lang="en" xml:space="default" xml:base="https://example.com/"><g xml:lang="en" xml:base="a/"><use href="b.svg#c"/><rect/></g><g xml:base="a/"><use href="https://example.com/b.svg"/><rect fill="url(#g)"/></g><text xml:lang="fr" xml:space="preserve"><tspan xml:space="default">Bonjour</tspan></text></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        remove_xml_lang(&mut doc.root);
+        remove_xml_base(&mut doc.root);
+        remove_xml_space(&mut doc.root, false);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg xml:base="https://example.com/" xml:lang="en"><g xml:base="a/"><use href="b.svg#c"/><rect/></g><g><use href="https://example.com/b.svg"/><rect fill="url(#g)"/></g><text xml:lang="fr" xml:space="preserve"><tspan xml:space="default">Bonjour</tspan></text></svg>"#
+        );
+
+        // Stylesheets can select by language
+        let svg = r#"<svg><style>:lang(en){fill:red}</style><rect xml:lang="en"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        remove_xml_lang(&mut doc.root);
+        assert_eq!(crate::serialize(&doc, &Options::default()), svg);
+    }
+
+    #[test]
+    fn test_remove_empty_text() {
+        let svg = r#"<svg><text x="1"> <tspan/> </text><text><title>t</title></text><text>a<tspan dx="2"></tspan><tspan>b</tspan></text><text id="t"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        remove_empty(&mut doc.root);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r#"<svg><text>a<tspan>b</tspan></text><text id="t"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_modernize_href() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><linearGradient id="a" xlink:href="#b"/><use xlink:href="#a" href="#c"/><image xlink:href="a.png"/></svg>"##;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        modernize_href(&mut doc.root);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r##"<svg xmlns="http://www.w3.org/2000/svg"><linearGradient href="#b" id="a"/><use href="#c"/><image href="a.png"/></svg>"##
+        );
+
+        // Other xlink attributes keep the declaration alive
+        let svg = r##"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><a xlink:title="t"><use xlink:href="#a"/></a></svg>"##;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        modernize_href(&mut doc.root);
+        let out = crate::serialize(&doc, &Options::default());
+        assert!(out.contains("xmlns:xlink") && out.contains(r##"<use href="#a"/>"##));
+    }
+
+    #[test]
+    fn test_collapse_groups() {
+        let svg = r#"<svg><g fill="red">
+            <rect/>
+        </g><g id="a"><rect/></g><g opacity=".5"><rect/></g><g fill="red"><rect fill="blue"/></g><g><rect/><rect/></g></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        collapse_groups(&mut doc);
+        cleanup_whitespace(&mut doc.root);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg><rect fill="red"/><g id="a"><rect/></g><g opacity=".5"><rect/></g><g fill="red"><rect fill="blue"/></g><g><rect/><rect/></g></svg>"#
+        );
+
+        // A stylesheet may target the group
+        let svg = r#"<svg><style>g{fill:blue}</style><g fill="red"><rect/></g></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        collapse_groups(&mut doc);
+        assert_eq!(crate::serialize(&doc, &Options::default()), svg);
+    }
+
+    #[test]
+    fn test_convert_ellipses() {
+        let svg = r#"<svg><ellipse cx="5" cy="5" rx="5" ry="5"/><ellipse rx="2.001" ry="1.998" fill="red"/><ellipse rx="2" ry="3"/><ellipse rx="auto" ry="3"/><ellipse rx="auto" ry="auto"/><ellipse rx="10%" ry="10%"/><ellipse rx="1em" ry="1em"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        convert_ellipses(&mut doc, 2);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r#"<svg><circle cx="5" cy="5" r="5"/><circle fill="red" r="2"/><ellipse rx="2" ry="3"/><ellipse rx="auto" ry="3"/><ellipse rx="auto" ry="auto"/><ellipse rx="10%" ry="10%"/><circle r="1em"/></svg>"#
+        );
+
+        // Type selectors would start or stop matching
+        let svg = r#"<svg><style>circle{fill:red}</style><ellipse rx="5" ry="5"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        convert_ellipses(&mut doc, 2);
+        assert!(crate::serialize(&doc, &Options::default()).contains("<ellipse"));
+    }
+
+    #[test]
+    fn test_minify_transforms() {
+        let svg = r#"<svg><g transform="matrix(1.0000001,0,0,0.9999999,12.000001,0)"/><g transform="rotate(0)"/><linearGradient gradientTransform="rotate(45deg)"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        minify_transforms(&mut doc.root, 2, 5);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r#"<svg><g transform="translate(12)"/><g/><linearGradient gradientTransform="rotate(45deg)"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_normalize_lists() {
+        let svg = r#"<svg viewBox=" 0,0  24 24 "><polyline points="  10 , 20   30,40 "/><g class=" a  b " stroke-dasharray="5 ,, 5" transform="translate( 10 , 20 )  scale (2)"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        normalize_lists(&mut doc.root);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg viewBox="0 0 24 24"><polyline points="10 20 30 40"/><g class="a b" stroke-dasharray="5 ,, 5" transform="translate(10 20) scale(2)"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_minify_numbers() {
+        let svg = r#"<svg><g opacity="0.50000001" fill-opacity="0.12345" style="stroke-opacity: 40%;fill:red"><stop stop-opacity="1.0" offset="0.123456"/><rect opacity="2" stroke-opacity="none"/></g></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        minify_numbers(&mut doc.root, 2, 3);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg><g fill-opacity=".123" opacity=".5" style="stroke-opacity:.4;fill:red"><stop offset="0.123456" stop-opacity="1"/><rect opacity="1" stroke-opacity="none"/></g></svg>"#
+        );
+
+        let svg = r#"<svg><path stroke-dasharray="4.00001, 2.555" stroke-dashoffset="-1.004" style="letter-spacing:.06251em;stroke-dasharray:none"/><text baseline-shift="33.333%" letter-spacing="normal"/><path stroke-dasharray="-0.001 1.001"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        minify_numbers(&mut doc.root, 2, 3);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg><path stroke-dasharray="4 2.56" stroke-dashoffset="-1" style="letter-spacing:.0625em;stroke-dasharray:none"/><text baseline-shift="33.33%" letter-spacing="normal"/><path stroke-dasharray="-0.001 1.001"/></svg>"#
+        );
+
+        // Opaque values then go with the defaults
+        let svg = r#"<svg><g><stop stop-opacity="1.0"/></g></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        optimize(&mut doc, &Options::default());
+        assert!(!crate::serialize(&doc, &Options::default()).contains("stop-opacity"));
+    }
+
+    #[test]
+    fn test_minify_paths_form() {
+        let svg = r#"<svg><path d="M100 100L101 101L102 103"/><path d="m5 5 10 10"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        minify_paths(&mut doc, 2, OptimizeFor::RawSize);
+        let out = crate::serialize(&doc, &Options::default());
+        assert_eq!(
+            out,
+            r#"<svg><path d="m100 100 1 1 1 2"/><path d="m5 5 10 10"/></svg>"#
+        );
+
+        // Compressed sizes pick one form for the whole document
+        let paths = r#"<path d="M10 10L20 20"/><path d="m10 10 10 10"/>"#.repeat(20);
+        let svg = format!("<svg>{paths}</svg>");
+        let mut doc = crate::parse_svg(&svg).unwrap();
+        minify_paths(&mut doc, 2, OptimizeFor::GzipSize);
+        let mut ds = Vec::new();
+        doc.for_each_element(|e| ds.extend(e.get_attr("d").map(str::to_string)));
+        assert!(ds.windows(2).all(|w| w[0] == w[1]), "{ds:?}");
+    }
+
+    #[test]
+    fn test_optimize_for_compressed_size() {
+        let rects: String = (0..50)
+            .map(|x| {
+                format!(r##"<rect x="{x}" width="1" height="1" fill="#123456" stroke="#abcdef"/>"##)
+            })
+            .collect();
+        let svg = format!("<svg>{rects}</svg>");
+        let run = |optimize_for| {
+            let options = Options {
+                extract_classes: true,
+                optimize_for,
+                ..Options::default()
+            };
+            let mut doc = crate::parse_svg(&svg).unwrap();
+            optimize(&mut doc, &options);
+            crate::serialize(&doc, &options)
+        };
+        let gzip = |s: &str| compressed_len(s, OptimizeFor::GzipSize);
+
+        let raw = run(OptimizeFor::RawSize);
+        let compressed = run(OptimizeFor::GzipSize);
+        assert!(raw.contains("<style>"));
+        assert!(raw.len() <= compressed.len());
+        assert!(gzip(&compressed) <= gzip(&raw));
     }
 
     #[test]
@@ -506,4 +2520,54 @@ mod tests {
         assert!(is_default_value("rect", "opacity", "1"));
         assert!(!is_default_value("rect", "opacity", "0.5"));
     }
+
+    #[test]
+    fn test_inherited_defaults_kept() {
+        // A default value overrides an ancestor's, once rounded too
+        let svg = r#"<svg><g style="fill-opacity:0.5"><path style="fill:red;fill-opacity:1.0000000" d="M10 10h80v80z"/></g></svg>"#;
+        let minified = crate::minify(svg).unwrap();
+        assert!(minified.contains("fill-opacity:1"), "{minified}");
+
+        for (svg, expected) in [
+            (
+                r#"<svg><g fill-opacity=".5"><rect fill-opacity="1" stroke-width="1"/></g></svg>"#,
+                r#"<svg><g fill-opacity=".5"><rect fill-opacity="1"/></g></svg>"#,
+            ),
+            (
+                r##"<svg><rect id="r" fill-opacity="1"/><use href="#r" fill-opacity=".5"/></svg>"##,
+                r##"<svg><rect fill-opacity="1" id="r"/><use fill-opacity=".5" href="#r"/></svg>"##,
+            ),
+            (
+                r#"<svg><g fill-opacity=".5"/><rect fill-opacity="1"/></svg>"#,
+                r#"<svg><g fill-opacity=".5"/><rect/></svg>"#,
+            ),
+        ] {
+            let mut doc = crate::parse_svg(svg).unwrap();
+            let mut inherited = Inheritance::new(&doc.root);
+            remove_default_attrs(&mut doc.root, SvgTarget::default(), &mut inherited);
+            assert_eq!(crate::serialize(&doc, &Options::default()), expected);
+        }
+
+        let svg = r#"<svg><rect fill-opacity=".5" style="fill-opacity:1;stroke-width:1"/></svg>"#;
+        let mut doc = crate::parse_svg(svg).unwrap();
+        let mut inherited = Inheritance::new(&doc.root);
+        minify_styles(&mut doc.root, &mut inherited);
+        assert_eq!(
+            crate::serialize(&doc, &Options::default()),
+            r#"<svg><rect fill-opacity=".5" style="fill-opacity:1"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_title_option() {
+        let options = Options {
+            title: Some("Logo".to_string()),
+            ..Options::default()
+        };
+        let svg = "<svg><title>Untitled-1</title><metadata/><rect/></svg>";
+        assert_eq!(
+            crate::minify_with_options(svg, &options).unwrap(),
+            "<svg><title>Logo</title><rect/></svg>"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+//! Deterministic order of definitions and gradient stops.
+//!
+//! Editors write `<defs>` in whatever order things were created, so two
+//! icons from the same family can hold the same gradients and clip paths in
+//! different orders. Sorting them gives gzip longer repeats across files
+//! and keeps diffs between exports small. Stops can't be reordered without
+//! changing the gradient, but their offsets can be written as the
+//! renderer reads them: clamped to `0..1` and never below an earlier stop's.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::bounds::{fraction, has_dynamic_content, has_scripts_or_animation};
+use crate::index::referenced_ids;
+use crate::optimize::is_whitespace;
+use crate::path::format_number;
+
+/// Sort the children of every `<defs>`: referenced definitions first, then
+/// by element name and id.
+pub(crate) fn sort_defs(doc: &mut Document) {
+    // Stylesheets can select definitions by position, and scripts by
+    // anything
+    if has_dynamic_content(doc) || !doc.any_element(|elem| elem.is("defs")) {
+        return;
+    }
+
+    let referenced = referenced_ids(doc);
+    // Of two elements with the same id, references go to the first, which
+    // sorting could change
+    let mut id_counts: HashMap<String, usize> = HashMap::new();
+    doc.for_each_element(|elem| {
+        if let Some(id) = elem.get_attr("id") {
+            *id_counts.entry(id.to_string()).or_default() += 1;
+        }
+    });
+
+    doc.for_each_element_mut(|elem| {
+        if elem.is("defs") && can_sort(elem, &id_counts) {
+            sort(elem, &referenced);
+        }
+    });
+}
+
+/// Whether `defs` holds only definitions whose order nothing depends on.
+fn can_sort(defs: &Element, id_counts: &HashMap<String, usize>) -> bool {
+    fn unique_ids(elem: &Element, id_counts: &HashMap<String, usize>) -> bool {
+        elem.get_attr("id").is_none_or(|id| id_counts[id] == 1)
+            && elem.child_elements().all(|c| unique_ids(c, id_counts))
+    }
+    defs.children.iter().all(|node| match node {
+        Node::Element(child) => !child.is("style") && !child.is("script"),
+        node => is_whitespace(node),
+    }) && defs.child_elements().all(|c| unique_ids(c, id_counts))
+}
+
+fn sort(defs: &mut Element, referenced: &HashSet<String>) {
+    let mut children: Vec<Element> = defs
+        .take_children()
+        .into_iter()
+        .filter_map(|node| match node {
+            Node::Element(child) => Some(child),
+            _ => None,
+        })
+        .collect();
+    // Stable, so elements with equal keys keep their order
+    children.sort_by_cached_key(|child| {
+        let id = child.get_attr("id").unwrap_or("").to_string();
+        (!referenced.contains(&id), child.name.full_name(), id)
+    });
+    defs.children = children.into_iter().map(Node::Element).collect();
+}
+
+/// Write gradient stop offsets as the renderer uses them, rounded to
+/// `precision`: clamped to `0..1`, and raised to the largest offset before
+/// them.
+pub(crate) fn normalize_stops(doc: &mut Document, precision: u8) {
+    // Animated offsets change at render time
+    if has_scripts_or_animation(doc) {
+        return;
+    }
+    doc.for_each_element_mut(|elem| {
+        if !elem.is("linearGradient") && !elem.is("radialGradient") {
+            return;
+        }
+        let mut previous = 0.0_f64;
+        for stop in elem.child_elements_mut().filter(|c| c.is("stop")) {
+            let Some(offset) = stop.get_attr("offset") else {
+                // A missing offset is 0, which is raised the same way
+                continue;
+            };
+            let Some(n) = fraction(offset).filter(|n| n.is_finite()) else {
+                continue;
+            };
+            let formatted = format_number(n.clamp(0.0, 1.0), precision);
+            let n = formatted.parse::<f64>().unwrap_or(0.0).max(previous);
+            previous = n;
+            let formatted = format_number(n, precision);
+            if formatted != offset {
+                stop.set_attr("offset", formatted);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    #[test]
+    fn test_sort_defs() {
+        let svg = r##"<svg><defs>
+            <radialGradient id="r"/>
+            <clipPath id="unused"/>
+            <linearGradient id="b"/>
+            <linearGradient id="a"/>
+            <path d="M0 0"/>
+        </defs><rect fill="url(#b)"/><rect fill="url(#r)"/><rect fill="url(#a)"/></svg>"##;
+        let mut doc = parse_svg(svg).unwrap();
+        sort_defs(&mut doc);
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r##"<svg><defs><linearGradient id="a"/><linearGradient id="b"/><radialGradient id="r"/><clipPath id="unused"/><path d="M0 0"/></defs><rect fill="url(#b)"/><rect fill="url(#r)"/><rect fill="url(#a)"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_keep_defs_order() {
+        for svg in [
+            r#"<svg><defs><style>:first-child{fill:red}</style><g id="b"/><g id="a"/></defs></svg>"#,
+            r#"<svg><defs><g id="b"/><!--a--><g id="a"/></defs></svg>"#,
+            r##"<svg><defs><g id="x"><rect/></g><clipPath id="x"/></defs><use href="#x"/></svg>"##,
+        ] {
+            let mut doc = parse_svg(svg).unwrap();
+            sort_defs(&mut doc);
+            assert_eq!(
+                serialize(&doc, &Options::default()),
+                serialize(&parse_svg(svg).unwrap(), &Options::default())
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalize_stops() {
+        let svg = r#"<svg><linearGradient><stop offset="20%"/><stop/><stop offset="0.1"/><stop offset="150%"/><stop offset="bogus"/></linearGradient><stop offset="-1"/></svg>"#;
+        let mut doc = parse_svg(svg).unwrap();
+        normalize_stops(&mut doc, 3);
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r#"<svg><linearGradient><stop offset=".2"/><stop/><stop offset=".2"/><stop offset="1"/><stop offset="bogus"/></linearGradient><stop offset="-1"/></svg>"#
+        );
+    }
+}
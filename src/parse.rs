@@ -1,149 +1,429 @@
 //! SVG parsing from XML.
 
+use std::borrow::Cow;
+use std::fmt;
+
 use quick_xml::Reader;
-use quick_xml::events::{BytesStart, Event};
+use quick_xml::escape::unescape;
+use quick_xml::events::{BytesDecl, BytesStart, Event};
 
+use crate::arena::{ArenaDocument, NodeId};
 use crate::ast::*;
+use crate::entities::html_entity;
 use crate::error::SvagError;
 
 /// Parse an SVG string into a Document.
-pub fn parse_svg(svg: &str) -> Result<Document, SvagError> {
-    let mut reader = Reader::from_str(svg);
-
-    let mut xml_declaration = None;
-    let mut doctype = None;
-    let mut root = None;
-
-    loop {
-        match reader.read_event()? {
-            Event::Decl(decl) => {
-                xml_declaration = Some(XmlDeclaration {
-                    version: String::from_utf8_lossy(decl.version()?.as_ref()).into_owned(),
-                    encoding: decl
-                        .encoding()
-                        .transpose()
-                        .ok()
-                        .flatten()
-                        .map(|e| String::from_utf8_lossy(e.as_ref()).into_owned()),
-                    standalone: decl.standalone().transpose().ok().flatten().map(|s| {
-                        let s = String::from_utf8_lossy(s.as_ref());
-                        s == "yes"
-                    }),
-                });
-            }
-            Event::DocType(dt) => {
-                doctype = Some(String::from_utf8_lossy(&dt).into_owned());
-            }
-            Event::Start(start) => {
-                root = Some(parse_element(&mut reader, &start)?);
-                break;
-            }
-            Event::Empty(start) => {
-                root = Some(parse_empty_element(&start)?);
-                break;
-            }
-            Event::Comment(_) | Event::Text(_) | Event::PI(_) => {
-                // Skip top-level comments/whitespace/PIs before root
-            }
-            Event::Eof => break,
-            _ => {}
-        }
-    }
+///
+/// Attribute values and text borrow from `svg` unless they had to be
+/// unescaped or had their line endings normalized.
+pub fn parse_svg(svg: &str) -> Result<Document<'_>, SvagError> {
+    Ok(parse_arena(svg, false)?.0.to_document())
+}
 
-    let root = root.ok_or_else(|| SvagError::InvalidSvg("No root element found".into()))?;
+/// Parse an SVG string, recovering from common breakage instead of failing:
+/// elements left open at the end of the input, mismatched or stray end
+/// tags, unescaped `&`, HTML named entities like `&nbsp;` (resolved to
+/// their characters), unquoted or valueless attributes, and duplicate
+/// attributes (the last one wins, as in browsers). Input that can't be
+/// read past some point (e.g. a truncated tag) ends the document there.
+///
+/// Returns the best-effort document with a warning for each repair. Input
+/// without any root element is still an error.
+///
+/// ```
+/// let (doc, warnings) = svag::parse_svg_lenient(r#"<svg><text x=1>Q&A</svg>"#).unwrap();
+/// assert_eq!(doc.root.child_elements().next().unwrap().get_attr("x"), Some("1"));
+/// assert_eq!(warnings.len(), 2);
+/// ```
+pub fn parse_svg_lenient(svg: &str) -> Result<(Document<'_>, Vec<ParseWarning>), SvagError> {
+    let (arena, warnings) = parse_arena(svg, true)?;
+    Ok((arena.to_document(), warnings))
+}
 
-    Ok(Document {
-        xml_declaration,
-        doctype,
-        root,
-    })
+/// Parse into an arena, which the [`Document`] is then materialized from.
+pub(crate) fn parse_arena(
+    svg: &str,
+    lenient: bool,
+) -> Result<(ArenaDocument<'_>, Vec<ParseWarning>), SvagError> {
+    Parser::new(svg, lenient).parse()
 }
 
-fn parse_element(reader: &mut Reader<&[u8]>, start: &BytesStart) -> Result<Element, SvagError> {
-    let mut element = parse_element_start(start)?;
+/// Something [`parse_svg_lenient`] repaired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    /// Byte offset in the input where it was noticed
+    pub position: usize,
+    pub message: String,
+}
 
-    loop {
-        match reader.read_event()? {
-            Event::Start(start) => {
-                element
-                    .children
-                    .push(Node::Element(parse_element(reader, &start)?));
-            }
-            Event::Empty(start) => {
-                element
-                    .children
-                    .push(Node::Element(parse_empty_element(&start)?));
-            }
-            Event::End(_) => {
-                break;
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: {}", self.position, self.message)
+    }
+}
+
+struct Parser<'a> {
+    reader: Reader<&'a [u8]>,
+    /// What `reader` reads, for borrowing values from
+    input: &'a [u8],
+    /// The document being built
+    doc: ArenaDocument<'a>,
+    lenient: bool,
+    warnings: Vec<ParseWarning>,
+    /// Names of the elements being parsed, outermost first
+    open: Vec<String>,
+    /// An end tag that closes an element further out than the current one
+    pending_end: Option<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(svg: &'a str, lenient: bool) -> Self {
+        let input = strip_bom(svg);
+        let mut reader = Reader::from_str(input);
+        if lenient {
+            // End tags are matched against the open elements here instead
+            reader.config_mut().check_end_names = false;
+            reader.config_mut().allow_unmatched_ends = true;
+        }
+        Parser {
+            reader,
+            input: input.as_bytes(),
+            doc: ArenaDocument::empty(input),
+            lenient,
+            warnings: Vec::new(),
+            open: Vec::new(),
+            pending_end: None,
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(ParseWarning {
+            position: self.reader.buffer_position() as usize,
+            message,
+        });
+    }
+
+    /// The next event. In lenient mode, an error ends the input.
+    fn next_event(&mut self) -> Result<Event<'a>, SvagError> {
+        match self.reader.read_event() {
+            Ok(event) => Ok(event),
+            Err(e) if self.lenient => {
+                self.warn(format!("stopped parsing: {e}"));
+                Ok(Event::Eof)
             }
-            Event::Text(text) => {
-                let text = text.unescape()?;
-                if !text.trim().is_empty() || !element.children.is_empty() {
-                    element.children.push(Node::Text(text.into_owned()));
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn parse(mut self) -> Result<(ArenaDocument<'a>, Vec<ParseWarning>), SvagError> {
+        loop {
+            match self.next_event()? {
+                Event::Decl(decl) => {
+                    self.doc.xml_declaration = Some(parse_xml_declaration(&decl)?);
+                }
+                Event::DocType(dt) => {
+                    self.doc.doctype = Some(String::from_utf8_lossy(&dt).into_owned());
+                }
+                Event::Start(start) => {
+                    self.parse_element(None, &start)?;
+                    break;
+                }
+                Event::Empty(start) => {
+                    self.parse_element_start(None, &start)?;
+                    break;
+                }
+                Event::PI(pi) => {
+                    if let Some(pi) = prolog_node(&pi) {
+                        self.doc.prolog.push(pi);
+                    }
                 }
+                Event::Comment(_) | Event::Text(_) => {
+                    // Skip top-level comments/whitespace before root
+                }
+                Event::Eof => break,
+                _ => {}
             }
-            Event::Comment(comment) => {
-                element.children.push(Node::Comment(
-                    String::from_utf8_lossy(&comment).into_owned(),
-                ));
+        }
+
+        if self.doc.is_empty() {
+            return Err(SvagError::InvalidSvg("No root element found".into()));
+        }
+        Ok((self.doc, self.warnings))
+    }
+
+    fn parse_element(
+        &mut self,
+        parent: Option<NodeId>,
+        start: &BytesStart,
+    ) -> Result<NodeId, SvagError> {
+        let element = self.parse_element_start(parent, start)?;
+        let name = self.doc.element_name(element).to_string();
+        self.open.push(name.clone());
+
+        loop {
+            match self.next_event()? {
+                Event::Start(start) => {
+                    self.parse_element(Some(element), &start)?;
+                    if let Some(end) = &self.pending_end {
+                        // The end tag closes this element too, or one
+                        // further out
+                        if *end == name {
+                            self.pending_end = None;
+                        }
+                        break;
+                    }
+                }
+                Event::Empty(start) => {
+                    self.parse_element_start(Some(element), &start)?;
+                }
+                Event::End(end) => {
+                    let end = String::from_utf8_lossy(end.name().as_ref()).into_owned();
+                    if !self.lenient || end == name {
+                        break;
+                    }
+                    if self.open.contains(&end) {
+                        self.warn(format!("</{end}> closes unclosed <{name}>"));
+                        self.pending_end = Some(end);
+                        break;
+                    }
+                    self.warn(format!("ignored stray </{end}>"));
+                }
+                Event::Text(text) => {
+                    let text = match self.unescape(&text) {
+                        Ok(text) => text,
+                        Err(_) if self.lenient => self.unescape_lenient(&text).into(),
+                        Err(e) => return Err(e),
+                    };
+                    if !text.trim().is_empty() || self.doc.has_children(element) {
+                        self.doc.push_text(element, text);
+                    }
+                }
+                Event::Comment(comment) => {
+                    let comment = self.text(&comment);
+                    self.doc.push_comment(element, comment);
+                }
+                Event::CData(cdata) => {
+                    let cdata = self.text(&cdata);
+                    self.doc.push_cdata(element, cdata);
+                }
+                Event::PI(pi) => {
+                    let (target, content) = split_processing_instruction(&pi);
+                    self.doc
+                        .push_processing_instruction(element, &target, content.as_deref());
+                }
+                Event::Eof if self.lenient => {
+                    self.warn(format!("closed <{name}> at end of file"));
+                    break;
+                }
+                Event::Eof => {
+                    return Err(SvagError::InvalidSvg("Unexpected end of file".into()));
+                }
+                _ => {}
             }
-            Event::CData(cdata) => {
-                element
-                    .children
-                    .push(Node::CData(String::from_utf8_lossy(&cdata).into_owned()));
+        }
+
+        self.open.pop();
+        Ok(element)
+    }
+
+    fn parse_element_start(
+        &mut self,
+        parent: Option<NodeId>,
+        start: &BytesStart,
+    ) -> Result<NodeId, SvagError> {
+        let name_bytes = start.name();
+        let name = std::str::from_utf8(name_bytes.as_ref())?;
+        let element = self.doc.push_element(parent, name);
+
+        if !self.lenient {
+            for attr in start.attributes() {
+                let attr =
+                    attr.map_err(|e| SvagError::InvalidSvg(format!("Invalid attribute: {}", e)))?;
+                let key = std::str::from_utf8(attr.key.as_ref())?;
+                let value = self.unescape(&attr.value)?;
+                self.doc.push_attr(element, key, value);
             }
-            Event::PI(pi) => {
-                let content = String::from_utf8_lossy(&pi).into_owned();
-                let (target, rest) = content
-                    .split_once(char::is_whitespace)
-                    .map(|(t, r)| (t.to_string(), Some(r.to_string())))
-                    .unwrap_or_else(|| (content, None));
-                element.children.push(Node::ProcessingInstruction {
-                    target,
-                    content: rest,
-                });
+            return Ok(element);
+        }
+
+        // HTML rules allow unquoted and valueless attributes
+        let mut attrs = start.html_attributes();
+        attrs.with_checks(false);
+        for attr in attrs {
+            let attr = match attr {
+                Ok(attr) => attr,
+                Err(e) => {
+                    self.warn(format!("dropped invalid attribute on <{name}>: {e}"));
+                    continue;
+                }
+            };
+            let key = String::from_utf8_lossy(attr.key.as_ref());
+            let value = match self.unescape(&attr.value) {
+                Ok(value) => value,
+                Err(_) => self.unescape_lenient(&attr.value).into(),
+            };
+            if self.doc.push_attr(element, &key, value) {
+                self.warn(format!("duplicate {key} on <{name}>, kept the last"));
             }
-            Event::Eof => {
-                return Err(SvagError::InvalidSvg("Unexpected end of file".into()));
+        }
+
+        Ok(element)
+    }
+
+    /// `raw` as the slice of the input it was read from. Events hold
+    /// slices of the input, but their accessors only lend them out for as
+    /// long as the event lives.
+    fn in_input(&self, raw: &[u8]) -> Option<&'a [u8]> {
+        let input = self.input.as_ptr_range();
+        let start = (raw.as_ptr() as usize).checked_sub(input.start as usize)?;
+        self.input.get(start..start + raw.len())
+    }
+
+    /// Unescape an attribute value or text, borrowing it from the input if
+    /// it is unchanged.
+    fn unescape(&self, raw: &[u8]) -> Result<Cow<'a, str>, SvagError> {
+        match self.in_input(raw) {
+            Some(raw) => unescape_normalized(raw),
+            None => unescape_normalized(raw).map(|s| Cow::Owned(s.into_owned())),
+        }
+    }
+
+    /// A comment or CDATA section with normalized line endings, borrowed
+    /// from the input if it is unchanged.
+    fn text(&self, raw: &[u8]) -> Cow<'a, str> {
+        let borrowed = |raw: &'a [u8]| match String::from_utf8_lossy(raw) {
+            Cow::Borrowed(text) => normalize_line_endings(text),
+            Cow::Owned(text) => Cow::Owned(normalize_line_endings(&text).into_owned()),
+        };
+        match self.in_input(raw) {
+            Some(raw) => borrowed(raw),
+            None => Cow::Owned(normalize_line_endings(&String::from_utf8_lossy(raw)).into_owned()),
+        }
+    }
+
+    /// Unescape text with HTML named entities or a stray `&`, keeping what
+    /// can't be resolved as written.
+    fn unescape_lenient(&mut self, raw: &[u8]) -> String {
+        let raw = String::from_utf8_lossy(raw);
+        let raw = normalize_line_endings(&raw);
+        let mut out = String::with_capacity(raw.len());
+        let mut rest = raw.as_ref();
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            rest = &rest[amp..];
+            let name = rest[1..]
+                .find(';')
+                .filter(|&end| end <= MAX_ENTITY_LEN)
+                .map(|end| &rest[1..end + 1]);
+            if let Some(name) = name
+                && let Some(c) = resolve_entity(name)
+            {
+                out.push(c);
+            } else if let Some(name) = name
+                && let Some(text) = html_entity(name)
+            {
+                self.warn(format!("resolved HTML entity &{name};"));
+                out.push_str(text);
+            } else {
+                let context: String = rest.chars().take(12).collect();
+                self.warn(format!("kept unescaped & in {context:?}"));
+                out.push('&');
+                rest = &rest[1..];
+                continue;
             }
-            _ => {}
+            rest = &rest[name.map_or(0, str::len) + 2..];
         }
+        out.push_str(rest);
+        out
     }
+}
 
-    Ok(element)
+/// The input without a leading byte order mark.
+pub(crate) fn strip_bom(svg: &str) -> &str {
+    svg.strip_prefix('\u{feff}').unwrap_or(svg)
 }
 
-fn parse_empty_element(start: &BytesStart) -> Result<Element, SvagError> {
-    parse_element_start(start)
+/// Unescape raw text or an attribute value, normalizing its line endings
+/// first. Character references like `&#13;` are resolved after, so they
+/// keep the `\r` they ask for.
+pub(crate) fn unescape_normalized(raw: &[u8]) -> Result<Cow<'_, str>, SvagError> {
+    let raw = std::str::from_utf8(raw)?;
+    let unescaped = match normalize_line_endings(raw) {
+        Cow::Borrowed(raw) => unescape(raw),
+        Cow::Owned(raw) => unescape(&raw).map(|s| Cow::Owned(s.into_owned())),
+    };
+    unescaped.map_err(|e| quick_xml::Error::from(e).into())
 }
 
-fn parse_element_start(start: &BytesStart) -> Result<Element, SvagError> {
-    let name_bytes = start.name();
-    let name = std::str::from_utf8(name_bytes.as_ref())?;
+/// Replace `\r\n` and lone `\r` line endings with `\n`, as XML parsers do
+/// before anything else sees the text.
+pub(crate) fn normalize_line_endings(s: &str) -> Cow<'_, str> {
+    if s.contains('\r') {
+        Cow::Owned(s.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Longest entity name or character reference looked for after a `&`.
+const MAX_ENTITY_LEN: usize = 32;
 
-    let mut element = Element {
-        name: QName::parse(name),
-        attributes: Vec::new(),
-        children: Vec::new(),
+/// The character an XML entity or character reference (without the `&`
+/// and `;`) stands for.
+fn resolve_entity(name: &str) -> Option<char> {
+    let code = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(decimal) = name.strip_prefix('#') {
+        decimal.parse().ok()?
+    } else {
+        return match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => None,
+        };
     };
+    char::from_u32(code)
+}
 
-    for attr in start.attributes() {
-        let attr = attr.map_err(|e| SvagError::InvalidSvg(format!("Invalid attribute: {}", e)))?;
-        let key = std::str::from_utf8(attr.key.as_ref())?;
-        let value = attr.unescape_value()?;
-        element.attributes.push(Attribute {
-            name: QName::parse(key),
-            value: value.into_owned(),
-        });
-    }
+fn parse_xml_declaration(decl: &BytesDecl) -> Result<XmlDeclaration, SvagError> {
+    Ok(XmlDeclaration {
+        version: String::from_utf8_lossy(decl.version()?.as_ref()).into_owned(),
+        encoding: decl
+            .encoding()
+            .transpose()
+            .ok()
+            .flatten()
+            .map(|e| String::from_utf8_lossy(e.as_ref()).into_owned()),
+        standalone: decl.standalone().transpose().ok().flatten().map(|s| {
+            let s = String::from_utf8_lossy(s.as_ref());
+            s == "yes"
+        }),
+    })
+}
+
+/// The node for a processing instruction before the root, if it's one
+/// that's kept: only stylesheets affect rendering.
+pub(crate) fn prolog_node(pi: &[u8]) -> Option<Node<'static>> {
+    let (target, content) = split_processing_instruction(pi);
+    (target == "xml-stylesheet").then_some(Node::ProcessingInstruction { target, content })
+}
 
-    Ok(element)
+/// Split a processing instruction body into its target and content.
+fn split_processing_instruction(pi: &[u8]) -> (String, Option<String>) {
+    let content = String::from_utf8_lossy(pi).into_owned();
+    content
+        .split_once(char::is_whitespace)
+        .map(|(t, r)| (t.to_string(), Some(r.to_string())))
+        .unwrap_or_else(|| (content, None))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Options, serialize};
 
     #[test]
     fn test_parse_simple_svg() {
@@ -187,4 +467,126 @@ mod tests {
         assert!(ns.contains_key(&None)); // default namespace
         assert!(ns.contains_key(&Some("xlink")));
     }
+
+    #[test]
+    fn test_parse_borrows_from_input() {
+        let svg = "\u{feff}<svg a=\"1\" b=\"&amp;\" c=\"x\r\ny\"><!--n--><![CDATA[d]]>t&lt;</svg>";
+        for doc in [parse_svg(svg).unwrap(), parse_svg_lenient(svg).unwrap().0] {
+            let borrowed = |value: &Cow<'_, str>| matches!(value, Cow::Borrowed(_));
+            let values: Vec<_> = doc.root.attributes.iter().map(|a| &a.value).collect();
+            assert!(matches!(values[..], [a, b, c] if borrowed(a) && !borrowed(b) && !borrowed(c)));
+            assert_eq!(values[2], "x\ny");
+            assert!(matches!(
+                &doc.root.children[..],
+                [Node::Comment(Cow::Borrowed("n")), Node::CData(Cow::Borrowed("d")), Node::Text(Cow::Owned(t))] if t == "t<"
+            ));
+        }
+
+        let owned = parse_svg(&String::from(svg)).unwrap().into_owned();
+        assert_eq!(owned.root.get_attr("a"), Some("1"));
+        assert!(
+            owned
+                .root
+                .attributes
+                .iter()
+                .all(|a| matches!(a.value, Cow::Owned(_)))
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient_duplicate_attributes() {
+        let svg = r##"<svg><use fill="red" xlink:href="#a" href="#b" fill="green" xlink:href="#c" fill="blue"/></svg>"##;
+        assert!(parse_svg(svg).is_err());
+
+        // The last of each wins, in its own position; other prefixes of the
+        // same local name are other attributes
+        let (doc, warnings) = parse_svg_lenient(svg).unwrap();
+        let elem = doc.root.child_elements().next().unwrap();
+        let attrs: Vec<(String, &str)> = elem
+            .attributes
+            .iter()
+            .map(|a| (a.name.full_name(), a.value.as_ref()))
+            .collect();
+        assert_eq!(
+            attrs,
+            [
+                ("href".to_string(), "#b"),
+                ("xlink:href".to_string(), "#c"),
+                ("fill".to_string(), "blue"),
+            ]
+        );
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "duplicate fill on <use>, kept the last",
+                "duplicate xlink:href on <use>, kept the last",
+                "duplicate fill on <use>, kept the last",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><g><rect width=10 height="1" height="2" hidden></g><text>Fish &amp; chips & peas &#x41;</text><circle></p>"#;
+        assert!(parse_svg(svg).is_err());
+
+        let (doc, warnings) = parse_svg_lenient(svg).unwrap();
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g><rect height="2" hidden="" width="10"/></g><text>Fish &amp; chips &amp; peas A</text><circle/></svg>"#
+        );
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "duplicate height on <rect>, kept the last",
+                "</g> closes unclosed <rect>",
+                "kept unescaped & in \"& peas &#x41\"",
+                "ignored stray </p>",
+                "closed <circle> at end of file",
+                "closed <svg> at end of file",
+            ]
+        );
+
+        // Well-formed input parses the same either way, without warnings
+        let svg = r#"<svg><g fill="red"><rect/></g><text>a &lt; b</text></svg>"#;
+        let (doc, warnings) = parse_svg_lenient(svg).unwrap();
+        let options = Options::default();
+        assert_eq!(
+            serialize(&doc, &options),
+            serialize(&parse_svg(svg).unwrap(), &options)
+        );
+        assert!(warnings.is_empty());
+
+        // HTML entities resolve to their characters
+        let (doc, warnings) = parse_svg_lenient(
+            r#"<svg><text title="&copy;&nbsp;ACME">A&mdash;B&nbsp;</text></svg>"#,
+        )
+        .unwrap();
+        let text = doc.root.child_elements().next().unwrap();
+        assert_eq!(text.get_attr("title"), Some("\u{a9}\u{a0}ACME"));
+        assert!(matches!(&text.children[..], [Node::Text(t)] if t == "A\u{2014}B\u{a0}"));
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(warnings[0].message, "resolved HTML entity &copy;");
+
+        // Truncated input ends where it breaks
+        let (doc, warnings) = parse_svg_lenient(r#"<svg><rect/><circle r="#).unwrap();
+        assert_eq!(doc.root.child_elements().count(), 1);
+        assert!(warnings[0].message.starts_with("stopped parsing"));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let svg = "\u{feff}<?xml version=\"1.0\"?>\r\n<svg>\r\n<text title=\"a\r\nb\rc\">x\r\ny&#13;z</text><style><![CDATA[a\r\nb]]></style><!--\r--></svg>";
+        let expected =
+            "<svg><text title=\"a\nb\nc\">x\ny&#13;z</text><style>a\nb</style><!--\n--></svg>";
+        let options = Options {
+            remove_comments: false,
+            ..Options::default()
+        };
+        assert_eq!(serialize(&parse_svg(svg).unwrap(), &options), expected);
+        let (doc, _) = parse_svg_lenient(svg).unwrap();
+        assert_eq!(serialize(&doc, &options), expected);
+    }
 }
@@ -6,12 +6,14 @@ use crate::error::SvagError;
 
 /// A parsed SVG path.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Path {
     pub commands: Vec<Command>,
 }
 
 /// A path command.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Command {
     /// M/m - Move to
     MoveTo { rel: bool, x: f64, y: f64 },
@@ -70,28 +72,257 @@ pub fn parse_path(d: &str) -> Result<Path, SvagError> {
     parser.parse()
 }
 
+impl Path {
+    /// The same path with every command in absolute form.
+    pub fn to_absolute(&self) -> Path {
+        self.convert(false, None)
+    }
+
+    /// The same path with every command in relative form. Offsets are taken
+    /// from the current point as a reader will see it once the output is
+    /// rounded to `precision`, so rounding doesn't drift along the path.
+    pub fn to_relative(&self, precision: u8) -> Path {
+        self.convert(true, Some(precision))
+    }
+
+    /// The same path with every number rounded to `precision`, as
+    /// [`serialize_path`] would write it.
+    pub fn rounded(&self, precision: u8) -> Path {
+        let r = |n: f64| round(n, precision);
+        let commands = self
+            .commands
+            .iter()
+            .map(|cmd| match *cmd {
+                Command::MoveTo { rel, x, y } => Command::MoveTo {
+                    rel,
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::LineTo { rel, x, y } => Command::LineTo {
+                    rel,
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::HorizontalTo { rel, x } => Command::HorizontalTo { rel, x: r(x) },
+                Command::VerticalTo { rel, y } => Command::VerticalTo { rel, y: r(y) },
+                Command::CurveTo {
+                    rel,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => Command::CurveTo {
+                    rel,
+                    x1: r(x1),
+                    y1: r(y1),
+                    x2: r(x2),
+                    y2: r(y2),
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::SmoothCurveTo { rel, x2, y2, x, y } => Command::SmoothCurveTo {
+                    rel,
+                    x2: r(x2),
+                    y2: r(y2),
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::QuadTo { rel, x1, y1, x, y } => Command::QuadTo {
+                    rel,
+                    x1: r(x1),
+                    y1: r(y1),
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::SmoothQuadTo { rel, x, y } => Command::SmoothQuadTo {
+                    rel,
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::Arc {
+                    rel,
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                } => Command::Arc {
+                    rel,
+                    rx: r(rx),
+                    ry: r(ry),
+                    x_axis_rotation: r(x_axis_rotation),
+                    large_arc,
+                    sweep,
+                    x: r(x),
+                    y: r(y),
+                },
+                Command::ClosePath => Command::ClosePath,
+            })
+            .collect();
+        Path { commands }
+    }
+
+    fn convert(&self, rel: bool, precision: Option<u8>) -> Path {
+        let round = |n: f64| precision.map_or(n, |p| round(n, p));
+
+        // The current point and subpath start in the source, and the current
+        // point a reader of the output ends up with
+        let mut cur = (0.0, 0.0);
+        let mut start = (0.0, 0.0);
+        let mut seen = (0.0, 0.0);
+        let mut commands = Vec::with_capacity(self.commands.len());
+        for cmd in &self.commands {
+            let base = cur;
+            let abs = move |r: bool, x: f64, y: f64| {
+                if r { (base.0 + x, base.1 + y) } else { (x, y) }
+            };
+            let out = move |(x, y): (f64, f64)| {
+                if rel {
+                    (x - seen.0, y - seen.1)
+                } else {
+                    (x, y)
+                }
+            };
+
+            let (converted, end) = match *cmd {
+                Command::MoveTo { rel: r, x, y } => {
+                    let p = abs(r, x, y);
+                    start = p;
+                    let (x, y) = out(p);
+                    (Command::MoveTo { rel, x, y }, p)
+                }
+                Command::LineTo { rel: r, x, y } => {
+                    let p = abs(r, x, y);
+                    let (x, y) = out(p);
+                    (Command::LineTo { rel, x, y }, p)
+                }
+                Command::HorizontalTo { rel: r, x } => {
+                    let p = (if r { base.0 + x } else { x }, base.1);
+                    let (x, _) = out(p);
+                    (Command::HorizontalTo { rel, x }, p)
+                }
+                Command::VerticalTo { rel: r, y } => {
+                    let p = (base.0, if r { base.1 + y } else { y });
+                    let (_, y) = out(p);
+                    (Command::VerticalTo { rel, y }, p)
+                }
+                Command::CurveTo {
+                    rel: r,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let p = abs(r, x, y);
+                    let ((x1, y1), (x2, y2)) = (out(abs(r, x1, y1)), out(abs(r, x2, y2)));
+                    let (x, y) = out(p);
+                    let converted = Command::CurveTo {
+                        rel,
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        x,
+                        y,
+                    };
+                    (converted, p)
+                }
+                Command::SmoothCurveTo {
+                    rel: r,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let p = abs(r, x, y);
+                    let (x2, y2) = out(abs(r, x2, y2));
+                    let (x, y) = out(p);
+                    (Command::SmoothCurveTo { rel, x2, y2, x, y }, p)
+                }
+                Command::QuadTo {
+                    rel: r,
+                    x1,
+                    y1,
+                    x,
+                    y,
+                } => {
+                    let p = abs(r, x, y);
+                    let (x1, y1) = out(abs(r, x1, y1));
+                    let (x, y) = out(p);
+                    (Command::QuadTo { rel, x1, y1, x, y }, p)
+                }
+                Command::SmoothQuadTo { rel: r, x, y } => {
+                    let p = abs(r, x, y);
+                    let (x, y) = out(p);
+                    (Command::SmoothQuadTo { rel, x, y }, p)
+                }
+                Command::Arc {
+                    rel: r,
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                } => {
+                    let p = abs(r, x, y);
+                    let (x, y) = out(p);
+                    let converted = Command::Arc {
+                        rel,
+                        rx,
+                        ry,
+                        x_axis_rotation,
+                        large_arc,
+                        sweep,
+                        x,
+                        y,
+                    };
+                    (converted, p)
+                }
+                Command::ClosePath => (Command::ClosePath, start),
+            };
+            commands.push(converted);
+            cur = end;
+            seen = (round(end.0), round(end.1));
+        }
+
+        Path { commands }
+    }
+}
+
 /// Serialize path data with the given precision.
 pub fn serialize_path(path: &Path, precision: u8) -> String {
     let mut out = String::new();
     let mut prev_cmd: Option<char> = None;
 
     for cmd in &path.commands {
-        let (c, new_cmd) = match cmd {
+        let c = match *cmd {
             Command::MoveTo { rel, x, y } => {
-                let c = if *rel { 'm' } else { 'M' };
-                (format_cmd(c, prev_cmd, &[*x, *y], precision), c)
+                let c = if rel { 'm' } else { 'M' };
+                write_cmd(&mut out, c, prev_cmd, &[x, y], precision);
+                c
             }
             Command::LineTo { rel, x, y } => {
-                let c = if *rel { 'l' } else { 'L' };
-                (format_cmd(c, prev_cmd, &[*x, *y], precision), c)
+                let c = if rel { 'l' } else { 'L' };
+                write_cmd(&mut out, c, prev_cmd, &[x, y], precision);
+                c
             }
             Command::HorizontalTo { rel, x } => {
-                let c = if *rel { 'h' } else { 'H' };
-                (format_cmd(c, prev_cmd, &[*x], precision), c)
+                let c = if rel { 'h' } else { 'H' };
+                write_cmd(&mut out, c, prev_cmd, &[x], precision);
+                c
             }
             Command::VerticalTo { rel, y } => {
-                let c = if *rel { 'v' } else { 'V' };
-                (format_cmd(c, prev_cmd, &[*y], precision), c)
+                let c = if rel { 'v' } else { 'V' };
+                write_cmd(&mut out, c, prev_cmd, &[y], precision);
+                c
             }
             Command::CurveTo {
                 rel,
@@ -102,23 +333,24 @@ pub fn serialize_path(path: &Path, precision: u8) -> String {
                 x,
                 y,
             } => {
-                let c = if *rel { 'c' } else { 'C' };
-                (
-                    format_cmd(c, prev_cmd, &[*x1, *y1, *x2, *y2, *x, *y], precision),
-                    c,
-                )
+                let c = if rel { 'c' } else { 'C' };
+                write_cmd(&mut out, c, prev_cmd, &[x1, y1, x2, y2, x, y], precision);
+                c
             }
             Command::SmoothCurveTo { rel, x2, y2, x, y } => {
-                let c = if *rel { 's' } else { 'S' };
-                (format_cmd(c, prev_cmd, &[*x2, *y2, *x, *y], precision), c)
+                let c = if rel { 's' } else { 'S' };
+                write_cmd(&mut out, c, prev_cmd, &[x2, y2, x, y], precision);
+                c
             }
             Command::QuadTo { rel, x1, y1, x, y } => {
-                let c = if *rel { 'q' } else { 'Q' };
-                (format_cmd(c, prev_cmd, &[*x1, *y1, *x, *y], precision), c)
+                let c = if rel { 'q' } else { 'Q' };
+                write_cmd(&mut out, c, prev_cmd, &[x1, y1, x, y], precision);
+                c
             }
             Command::SmoothQuadTo { rel, x, y } => {
-                let c = if *rel { 't' } else { 'T' };
-                (format_cmd(c, prev_cmd, &[*x, *y], precision), c)
+                let c = if rel { 't' } else { 'T' };
+                write_cmd(&mut out, c, prev_cmd, &[x, y], precision);
+                c
             }
             Command::Arc {
                 rel,
@@ -130,169 +362,163 @@ pub fn serialize_path(path: &Path, precision: u8) -> String {
                 x,
                 y,
             } => {
-                let c = if *rel { 'a' } else { 'A' };
-                let arc_str = format_arc(
-                    c,
-                    prev_cmd,
-                    *rx,
-                    *ry,
-                    *x_axis_rotation,
-                    *large_arc,
-                    *sweep,
-                    *x,
-                    *y,
-                    precision,
-                );
-                (arc_str, c)
+                let c = if rel { 'a' } else { 'A' };
+                // Flags are always exactly 0 or 1, so they survive any precision
+                let flags = [f64::from(u8::from(large_arc)), f64::from(u8::from(sweep))];
+                let args = [rx, ry, x_axis_rotation, flags[0], flags[1], x, y];
+                write_cmd(&mut out, c, prev_cmd, &args, precision);
+                c
             }
             Command::ClosePath => {
-                let c = 'z';
-                (format_cmd(c, prev_cmd, &[], precision), c)
+                write_cmd(&mut out, 'z', prev_cmd, &[], precision);
+                'z'
             }
         };
-        // Check if we need a separator between previous output and new command
-        if !out.is_empty() && !c.is_empty() {
-            let last = out.chars().last().unwrap();
-            let first = c.chars().next().unwrap();
-            if (last.is_ascii_digit() || last == '.') && (first.is_ascii_digit() || first == '.') {
-                out.push(' ');
-            }
-        }
-        out.push_str(&c);
-        prev_cmd = Some(new_cmd);
+        prev_cmd = Some(c);
     }
 
     out
 }
 
-fn format_cmd(cmd: char, prev_cmd: Option<char>, args: &[f64], precision: u8) -> String {
-    let mut out = String::new();
-
+/// Append one command, omitting the command letter when it repeats.
+fn write_cmd(out: &mut String, cmd: char, prev_cmd: Option<char>, args: &[f64], precision: u8) {
     // Omit command letter if it's the same as previous (except for M which becomes L)
     let needs_cmd = match prev_cmd {
+        // Always write z/Z
+        _ if args.is_empty() => true,
         None => true,
-        Some(prev) => {
-            // After M, coordinates are treated as L; after m, as l
-            if (prev == 'M' && cmd == 'L') || (prev == 'm' && cmd == 'l') {
-                false
-            } else {
-                prev != cmd
-            }
-        }
+        // After M, coordinates are treated as L; after m, as l
+        Some(prev) => !((prev == 'M' && cmd == 'L') || (prev == 'm' && cmd == 'l')) && prev != cmd,
     };
-
-    if needs_cmd && !args.is_empty() {
-        out.push(cmd);
-    } else if args.is_empty() {
-        // Always write z/Z
+    if needs_cmd {
         out.push(cmd);
     }
 
-    for arg in args.iter() {
-        let formatted = format_number(*arg, precision);
-        // Add separator if needed
-        let needs_sep = if out.is_empty() {
-            false
-        } else {
-            let last = out.chars().last().unwrap();
-            let first = formatted.chars().next().unwrap();
-            // Need space if both are digits or if previous is digit and current starts with .
-            (last.is_ascii_digit() || last == '.') && (first.is_ascii_digit() || first == '.')
-        };
-        if needs_sep {
-            out.push(' ');
+    for &arg in args {
+        let start = out.len();
+        write_number(out, arg, precision);
+        // Separate from a preceding number unless the sign or the command
+        // letter already does
+        let continues_number = |b: u8| b.is_ascii_digit() || b == b'.';
+        if start > 0
+            && continues_number(out.as_bytes()[start - 1])
+            && continues_number(out.as_bytes()[start])
+        {
+            out.insert(start, ' ');
         }
-        out.push_str(&formatted);
     }
-
-    out
 }
 
-#[allow(clippy::too_many_arguments)]
-fn format_arc(
-    cmd: char,
-    prev_cmd: Option<char>,
-    rx: f64,
-    ry: f64,
-    x_rot: f64,
-    large_arc: bool,
-    sweep: bool,
-    x: f64,
-    y: f64,
-    precision: u8,
-) -> String {
+/// Format a number with the given precision, removing unnecessary zeros.
+pub fn format_number(n: f64, precision: u8) -> String {
     let mut out = String::new();
-
-    if prev_cmd != Some(cmd) {
-        out.push(cmd);
-    }
-
-    let parts = [
-        format_number(rx, precision),
-        format_number(ry, precision),
-        format_number(x_rot, precision),
-        if large_arc { "1".into() } else { "0".into() },
-        if sweep { "1".into() } else { "0".into() },
-        format_number(x, precision),
-        format_number(y, precision),
-    ];
-
-    for (i, part) in parts.iter().enumerate() {
-        if i > 0 {
-            let last = out.chars().last().unwrap();
-            let first = part.chars().next().unwrap();
-            if last.is_ascii_digit() && (first.is_ascii_digit() || first == '.') {
-                out.push(' ');
-            }
-        }
-        out.push_str(part);
-    }
-
+    write_number(&mut out, n, precision);
     out
 }
 
-/// Format a number with the given precision, removing unnecessary zeros.
-pub fn format_number(n: f64, precision: u8) -> String {
-    if n == 0.0 {
-        return "0".into();
-    }
-
-    // Round to precision
-    let factor = 10f64.powi(precision as i32);
-    let rounded = (n * factor).round() / factor;
+/// Append a number rounded to `precision` decimals in its shortest form:
+/// no trailing zeros, no leading zero before the decimal point.
+pub(crate) fn write_number(out: &mut String, n: f64, precision: u8) {
+    let rounded = round(n, precision);
 
-    // Check if it's an integer
-    if rounded.fract() == 0.0 {
-        return format!("{}", rounded as i64);
+    if rounded == 0.0 {
+        out.push('0');
+        return;
     }
 
-    // Format with precision then trim trailing zeros
-    let mut s = format!("{:.prec$}", rounded, prec = precision as usize);
-
-    // Trim trailing zeros after decimal point
-    if s.contains('.') {
-        s = s.trim_end_matches('0').to_string();
-        s = s.trim_end_matches('.').to_string();
+    // Rounding leaves the closest double to a short decimal, which is exactly
+    // what ryu's shortest round-trip representation prints.
+    let mut buf = ryu::Buffer::new();
+    let s = if rounded.is_finite() {
+        buf.format_finite(rounded)
+    } else {
+        ""
+    };
+    if s.is_empty() || s.contains('e') {
+        // Exponent notation for very large or small values; spell it out
+        write_number_fixed(out, rounded, precision);
+        return;
     }
 
+    let s = s.strip_suffix(".0").unwrap_or(s);
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", s),
+    };
+    out.push_str(sign);
     // Remove leading zero before decimal: 0.5 -> .5
-    if s.starts_with("0.") {
-        s = s[1..].to_string();
-    } else if s.starts_with("-0.") {
-        s = format!("-{}", &s[2..]);
-    }
+    out.push_str(digits.strip_prefix('0').unwrap_or(digits));
+}
+
+/// Round `n` to `precision` decimals, the same way on every platform.
+///
+/// Trig functions come from the platform's libm and may differ in the last
+/// bits between, say, macOS and Linux. Those bits mustn't decide which way
+/// a value rounds, so anything within a few thousand ulps of a half is
+/// rounded as if it were exactly the half (away from zero). The window is
+/// capped for huge values, where a few thousand ulps would reach past the
+/// half and round already-rounded numbers up again.
+pub(crate) fn round(n: f64, precision: u8) -> f64 {
+    // Powers of ten are exact doubles up to 1e22
+    const POWERS: [f64; 23] = [
+        1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+        1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+    ];
+    let factor = POWERS
+        .get(usize::from(precision))
+        .copied()
+        .unwrap_or_else(|| 10f64.powi(i32::from(precision)));
+
+    let scaled = n * factor;
+    let half = scaled.floor() + 0.5;
+    let tolerance = (scaled.abs().max(1.0) * 1e-12).min(0.01);
+    let rounded = if (scaled - half).abs() <= tolerance {
+        half.round()
+    } else {
+        scaled.round()
+    };
+    rounded / factor
+}
 
-    s
+fn write_number_fixed(out: &mut String, rounded: f64, precision: u8) {
+    let s = format!("{:.prec$}", rounded, prec = precision as usize);
+    let s = if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &s
+    };
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", s),
+    };
+    out.push_str(sign);
+    out.push_str(digits.strip_prefix("0.").map_or(digits, |_| &digits[1..]));
 }
 
+/// Path data is ASCII, so the parser works on bytes and only goes back to
+/// `str` for error messages and the rare number [`parse_number`] can't
+/// handle on its own.
+///
+/// [`parse_number`]: PathParser::parse_number
 struct PathParser<'a> {
     input: &'a str,
+    bytes: &'a [u8],
     pos: usize,
 }
 
+/// Powers of ten that are exact in an `f64`.
+const EXACT_POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
 impl<'a> PathParser<'a> {
     fn new(input: &'a str) -> Self {
-        Self { input, pos: 0 }
+        Self {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+        }
     }
 
     fn parse(&mut self) -> Result<Path, SvagError> {
@@ -302,14 +528,10 @@ impl<'a> PathParser<'a> {
         self.skip_whitespace();
 
         while !self.is_eof() {
-            let cmd = if self
-                .peek()
-                .map(|c| c.is_ascii_alphabetic())
-                .unwrap_or(false)
-            {
-                let c = self.next().unwrap();
-                last_cmd = Some(c);
-                c
+            let cmd = if let Some(c) = self.peek().filter(u8::is_ascii_alphabetic) {
+                self.pos += 1;
+                last_cmd = Some(char::from(c));
+                char::from(c)
             } else {
                 // Implicit command - repeat last command
                 // After M, implicit command is L; after m, it's l
@@ -437,38 +659,81 @@ impl<'a> PathParser<'a> {
         let start = self.pos;
 
         // Optional sign
-        if self.peek() == Some('-') || self.peek() == Some('+') {
-            self.next();
+        let negative = self.peek() == Some(b'-');
+        if matches!(self.peek(), Some(b'-' | b'+')) {
+            self.pos += 1;
         }
 
+        // Accumulate significant digits while they fit; `exact` goes false
+        // once they don't, and the standard parser takes over.
+        let mut mantissa: u64 = 0;
+        let mut exact = true;
+        let mut has_digits = false;
+        let mut exponent: i32 = 0;
+        let mut push_digit = |d: u8| match mantissa
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(u64::from(d)))
+        {
+            Some(m) => mantissa = m,
+            None => exact = false,
+        };
+
         // Integer part
-        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-            self.next();
+        while let Some(d) = self.peek_digit() {
+            self.pos += 1;
+            has_digits = true;
+            push_digit(d);
         }
 
         // Decimal part
-        if self.peek() == Some('.') {
-            self.next();
-            while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-                self.next();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            while let Some(d) = self.peek_digit() {
+                self.pos += 1;
+                has_digits = true;
+                push_digit(d);
+                exponent -= 1;
             }
         }
 
         // Exponent
-        if self.peek() == Some('e') || self.peek() == Some('E') {
-            self.next();
-            if self.peek() == Some('-') || self.peek() == Some('+') {
-                self.next();
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            self.pos += 1;
+            let exp_negative = self.peek() == Some(b'-');
+            if matches!(self.peek(), Some(b'-' | b'+')) {
+                self.pos += 1;
             }
-            while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
-                self.next();
+            let mut exp: i32 = 0;
+            let mut has_exp_digits = false;
+            while let Some(d) = self.peek_digit() {
+                self.pos += 1;
+                has_exp_digits = true;
+                exp = exp.saturating_mul(10).saturating_add(i32::from(d));
             }
+            // "1e" is not a number
+            has_digits &= has_exp_digits;
+            exponent = exponent.saturating_add(if exp_negative { -exp } else { exp });
         }
 
         let s = &self.input[start..self.pos];
         if s.is_empty() {
             return Err(SvagError::InvalidPath("Expected number".into()));
         }
+        if !has_digits {
+            return Err(SvagError::InvalidPath(format!("Invalid number: {}", s)));
+        }
+
+        // Clinger's fast path: an integer below 2^53 scaled by an exact power
+        // of ten is a single correctly rounded operation.
+        if exact && mantissa <= 1 << 53 && (-22..=22).contains(&exponent) {
+            let power = EXACT_POWERS_OF_TEN[exponent.unsigned_abs() as usize];
+            let value = if exponent < 0 {
+                mantissa as f64 / power
+            } else {
+                mantissa as f64 * power
+            };
+            return Ok(if negative { -value } else { value });
+        }
 
         s.parse()
             .map_err(|_| SvagError::InvalidPath(format!("Invalid number: {}", s)))
@@ -476,43 +741,47 @@ impl<'a> PathParser<'a> {
 
     fn parse_flag(&mut self) -> Result<bool, SvagError> {
         self.skip_whitespace_and_comma();
-        match self.next() {
-            Some('0') => Ok(false),
-            Some('1') => Ok(true),
-            Some(c) => Err(SvagError::InvalidPath(format!(
-                "Expected flag (0 or 1), got: {}",
-                c
-            ))),
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            Some(_) => {
+                let c = self.input[self.pos..].chars().next().unwrap_or_default();
+                Err(SvagError::InvalidPath(format!(
+                    "Expected flag (0 or 1), got: {}",
+                    c
+                )))
+            }
             None => Err(SvagError::InvalidPath("Expected flag".into())),
         }
     }
 
     fn skip_whitespace(&mut self) {
-        while self
-            .peek()
-            .map(|c| c.is_ascii_whitespace())
-            .unwrap_or(false)
-        {
-            self.next();
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
         }
     }
 
     fn skip_whitespace_and_comma(&mut self) {
         self.skip_whitespace();
-        if self.peek() == Some(',') {
-            self.next();
+        if self.peek() == Some(b',') {
+            self.pos += 1;
         }
         self.skip_whitespace();
     }
 
-    fn peek(&self) -> Option<char> {
-        self.input[self.pos..].chars().next()
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
     }
 
-    fn next(&mut self) -> Option<char> {
-        let c = self.peek()?;
-        self.pos += c.len_utf8();
-        Some(c)
+    /// The value of the next byte if it is a decimal digit.
+    fn peek_digit(&self) -> Option<u8> {
+        self.peek().filter(u8::is_ascii_digit).map(|b| b - b'0')
     }
 
     fn is_eof(&self) -> bool {
@@ -562,6 +831,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_numbers() {
+        let numbers = |d: &str| -> Vec<f64> {
+            parse_path(d)
+                .unwrap()
+                .commands
+                .iter()
+                .map(|c| match c {
+                    Command::HorizontalTo { x, .. } => *x,
+                    other => panic!("unexpected {other:?}"),
+                })
+                .collect()
+        };
+        assert_eq!(
+            numbers("H1.5.5-.25+3e2,1E-2\t\n-0 0.1"),
+            [1.5, 0.5, -0.25, 300.0, 0.01, -0.0, 0.1]
+        );
+        // Beyond the fast path: long mantissas and large exponents
+        assert_eq!(
+            numbers("H123456789012345678901 1e300 .30000000000000004"),
+            [123456789012345678901.0, 1e300, 0.30000000000000004]
+        );
+
+        assert!(parse_path("M1e 2").is_err());
+        assert!(parse_path("M- 2").is_err());
+        assert!(parse_path("M1 2 L.").is_err());
+        assert!(parse_path("M1 2 A1 1 0 2 0 3 3").is_err());
+        assert!(parse_path("M1 2 L3 é").is_err());
+    }
+
     #[test]
     fn test_format_number() {
         assert_eq!(format_number(0.0, 2), "0");
@@ -572,6 +871,24 @@ mod tests {
         assert_eq!(format_number(-0.5, 2), "-.5");
         assert_eq!(format_number(1.234, 2), "1.23");
         assert_eq!(format_number(1.235, 2), "1.24"); // rounding
+        assert_eq!(format_number(-0.001, 2), "0");
+        assert_eq!(format_number(1234.5, 0), "1235");
+        assert_eq!(format_number(0.000001, 6), ".000001");
+        assert_eq!(format_number(1e20, 2), "100000000000000000000");
+    }
+
+    #[test]
+    fn test_round_is_stable() {
+        // cos(60°) may come out on either side of .5 depending on the libm
+        assert_eq!(round(0.49999999999999994, 0), 1.0);
+        assert_eq!(round(0.5000000000000001, 0), 1.0);
+        assert_eq!(round(-2.4999999999999996, 0), -3.0);
+        assert_eq!(round(1.23449999, 3), 1.234);
+        assert_eq!(round(1.2345, 3), 1.235);
+        assert_eq!(round(0.49, 0), 0.0);
+        // Already-rounded large values stay put
+        assert_eq!(format_number(242612.7829626, 7), "242612.7829626");
+        assert_eq!(format_number(128326190733.1, 2), "128326190733.1");
     }
 
     #[test]
@@ -589,4 +906,48 @@ mod tests {
         // .5 .5 need space between (both start with .), -.5 doesn't need space before -
         assert_eq!(out, "M.5 .5-.5-.5");
     }
+
+    #[test]
+    fn test_absolute_relative() {
+        let path = parse_path("M10 10l5 0c1 1 2 2 3 3s1 1 2 2zm1 1H20v5a5 5 0 0 1-5 5").unwrap();
+        assert_eq!(
+            serialize_path(&path.to_absolute(), 2),
+            "M10 10 15 10C16 11 17 12 18 13S19 14 20 15zM11 11H20V16A5 5 0 0 1 15 21"
+        );
+        assert_eq!(
+            serialize_path(&path.to_relative(2), 2),
+            "m10 10 5 0c1 1 2 2 3 3s1 1 2 2zm1 1h9v5a5 5 0 0 1-5 5"
+        );
+    }
+
+    #[test]
+    fn test_relative_does_not_drift() {
+        // Rounding each offset independently would place the last point at 0
+        let path = parse_path("M0 0L.4 0 .8 0 1.2 0 1.6 0").unwrap();
+        let out = serialize_path(&path.to_relative(0), 0);
+        let end = parse_path(&out).unwrap().to_absolute();
+        assert!(matches!(
+            end.commands.last(),
+            Some(Command::LineTo { x, .. }) if *x == 2.0
+        ));
+    }
+
+    #[test]
+    fn test_rounded_forms_agree() {
+        // Offsets rounded in the source drift from the absolute coordinates;
+        // every form is derived from what the rounded source describes
+        let path = parse_path("m.004 0l.004 0 .004 0 .004 0").unwrap();
+        assert_eq!(
+            serialize_path(&path.to_absolute(), 2),
+            "M0 0 .01 0 .01 0 .02 0"
+        );
+        let path = path.rounded(2);
+        let absolute = path.to_absolute();
+        assert_eq!(serialize_path(&path, 2), "m0 0 0 0 0 0 0 0");
+        assert_eq!(serialize_path(&absolute, 2), "M0 0 0 0 0 0 0 0");
+        assert_eq!(
+            serialize_path(&absolute.to_relative(2), 2),
+            "m0 0 0 0 0 0 0 0"
+        );
+    }
 }
@@ -0,0 +1,246 @@
+//! `<pattern>` cleanup.
+//!
+//! Wallpaper-style backgrounds from design tools define a tile per use,
+//! with long tile coordinates and spelled-out defaults. Tile coordinates
+//! are rounded, default units dropped, identical patterns merged, and
+//! patterns that paint nothing (no content, or a zero-sized tile) replaced
+//! by `none` where they're used.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+use crate::bounds::{has_scripts_or_animation, paints_nothing};
+use crate::css::css_urls;
+use crate::index::{DocumentIndex, rename_references};
+use crate::markers::definition_key;
+use crate::optimize::stylesheet_ids;
+use crate::path::format_number;
+
+/// The tile's position and size.
+const TILE: [&str; 4] = ["x", "y", "width", "height"];
+
+/// Round tile coordinates, merge duplicate patterns, and drop empty ones.
+pub(crate) fn optimize_patterns(doc: &mut Document, precision: u8, transform_precision: u8) {
+    let mut has_patterns = false;
+    doc.for_each_element_mut(|elem| {
+        if elem.is("pattern") {
+            simplify_attrs(elem, precision, transform_precision);
+            has_patterns = true;
+        }
+    });
+
+    // Scripts and animations can refer to patterns in ways that aren't
+    // rewritten here
+    if !has_patterns || has_scripts_or_animation(doc) {
+        return;
+    }
+    // A stylesheet may select a pattern by id
+    let selected = stylesheet_ids(&doc.root);
+    dedupe_patterns(doc, &selected);
+    remove_empty_patterns(doc, &selected);
+}
+
+fn simplify_attrs(pattern: &mut Element, precision: u8, transform_precision: u8) {
+    // A pattern linked with `href` inherits the attributes it doesn't set,
+    // so defaults there aren't redundant
+    let inherits = pattern.get_attr("href").is_some() || pattern.get_attr("xlink:href").is_some();
+    if !inherits {
+        if pattern.get_attr("patternUnits") == Some("objectBoundingBox") {
+            pattern.remove_attr("patternUnits");
+        }
+        if pattern.get_attr("patternContentUnits") == Some("userSpaceOnUse") {
+            pattern.remove_attr("patternContentUnits");
+        }
+    }
+
+    // In bounding box units, the tile is a fraction of the element's size,
+    // so it gets the finer precision
+    let bbox_units = pattern.get_attr("patternUnits") != Some("userSpaceOnUse");
+    let precision = if bbox_units {
+        transform_precision
+    } else {
+        precision
+    };
+    for attr in &mut pattern.attributes {
+        if attr.name.prefix.is_none()
+            && TILE.contains(&attr.name.local.as_str())
+            && let Ok(n) = attr.value.trim().parse::<f64>()
+            && n.is_finite()
+        {
+            attr.value = format_number(n, precision).into();
+        }
+    }
+}
+
+/// Point references to identical patterns at the first of them, and remove
+/// the others.
+fn dedupe_patterns(doc: &mut Document, selected: &HashSet<String>) {
+    let index = DocumentIndex::new(doc);
+    let mut first: HashMap<String, &str> = HashMap::new();
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut duplicates: Vec<Vec<usize>> = Vec::new();
+    for (id, elem) in index.iter() {
+        let Some(pattern_id) = elem.get_attr("id").filter(|_| elem.is("pattern")) else {
+            continue;
+        };
+        // The first with the id wins
+        if index.by_id(pattern_id) != Some(id) || selected.contains(pattern_id) {
+            continue;
+        }
+        let key = definition_key(elem);
+        match first.get(&key) {
+            Some(&kept) => {
+                renames.insert(pattern_id.to_string(), kept.to_string());
+                duplicates.push(index.path(id));
+            }
+            None => {
+                first.insert(key, pattern_id);
+            }
+        }
+    }
+    if renames.is_empty() {
+        return;
+    }
+
+    doc.for_each_element_mut(|elem| rename_references(elem, &renames));
+    doc.remove_elements_at(duplicates);
+}
+
+/// Replace `fill`/`stroke` references to patterns that paint nothing with
+/// `none`, and remove the patterns.
+fn remove_empty_patterns(doc: &mut Document, selected: &HashSet<String>) {
+    let index = DocumentIndex::new(doc);
+    let mut empty: Vec<(String, Vec<usize>)> = Vec::new();
+    for (id, elem) in index.iter() {
+        let Some(pattern_id) = elem.get_attr("id").filter(|_| elem.is("pattern")) else {
+            continue;
+        };
+        // Other references (another pattern's `href`, a stylesheet) would
+        // see it go
+        let only_painted = index.referrers(pattern_id).iter().all(|&referrer| {
+            index
+                .get(referrer)
+                .attributes
+                .iter()
+                .all(|a| match a.name.local.as_str() {
+                    "href" => a.value.trim().strip_prefix('#') != Some(pattern_id),
+                    "fill" | "stroke" => true,
+                    "style" => a.value.split(';').all(|decl| {
+                        decl.split_once(':').is_none_or(|(prop, value)| {
+                            is_paint(prop.trim()) || !references(value, pattern_id)
+                        })
+                    }),
+                    _ => !references(&a.value, pattern_id),
+                })
+        });
+        if index.by_id(pattern_id) == Some(id)
+            && paints_nothing_at_all(elem)
+            && only_painted
+            && !selected.contains(pattern_id)
+        {
+            empty.push((pattern_id.to_string(), index.path(id)));
+        }
+    }
+    if empty.is_empty() {
+        return;
+    }
+
+    doc.for_each_element_mut(|elem| {
+        for (id, _) in &empty {
+            unpaint(elem, id);
+        }
+    });
+    doc.remove_elements_at(empty.into_iter().map(|(_, path)| path).collect());
+}
+
+/// Whether a pattern without `href` renders nothing: it has no content, or
+/// its tile has no area (a missing `width` or `height` is zero).
+fn paints_nothing_at_all(pattern: &Element) -> bool {
+    if pattern.get_attr("href").is_some() || pattern.get_attr("xlink:href").is_some() {
+        return false;
+    }
+    let no_area = ["width", "height"].iter().any(|name| {
+        pattern
+            .get_attr(name)
+            .is_none_or(|v| v.trim().parse::<f64>().is_ok_and(|n| n <= 0.0))
+    });
+    no_area || pattern.child_elements().all(paints_nothing)
+}
+
+fn is_paint(name: &str) -> bool {
+    matches!(name, "fill" | "stroke")
+}
+
+/// Whether `value` has a `url(#id)` token.
+fn references(value: &str, id: &str) -> bool {
+    css_urls(value)
+        .iter()
+        .any(|url| url.url.strip_prefix('#') == Some(id))
+}
+
+/// Replace paint referencing pattern `id`, fallback included, with `none`.
+fn unpaint(elem: &mut Element, id: &str) {
+    let paints = |value: &str| {
+        css_urls(value)
+            .first()
+            .is_some_and(|url| url.token.start == 0 && url.url.strip_prefix('#') == Some(id))
+    };
+    for attr in &mut elem.attributes {
+        if attr.name.prefix.is_none() && is_paint(&attr.name.local) && paints(attr.value.trim()) {
+            attr.value = "none".to_string().into();
+        }
+    }
+
+    let Some(style) = elem.get_attr("style") else {
+        return;
+    };
+    let mut changed = false;
+    let decls: Vec<String> = style
+        .split(';')
+        .map(|decl| {
+            if let Some((prop, value)) = decl.split_once(':')
+                && is_paint(prop.trim())
+                && paints(value.trim())
+            {
+                changed = true;
+                return format!("{}:none", prop.trim());
+            }
+            decl.to_string()
+        })
+        .collect();
+    if changed {
+        elem.set_attr("style", decls.join(";"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn optimized(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        optimize_patterns(&mut doc, 2, 5);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_optimize_patterns() {
+        assert_eq!(
+            optimized(
+                r##"<svg><pattern id="a" patternUnits="userSpaceOnUse" width="10.00001" height="4.256"><path d="M0 0h1"/></pattern><pattern id="b" width=".1234567" height=".5" patternUnits="objectBoundingBox"><path d="M0 0h1"/></pattern><pattern id="c" height=".5" width=".1234567"><path d="M0 0h1"/></pattern><rect fill="url(#a)"/><rect fill="url(#b)" style="stroke:url(#c)"/></svg>"##
+            ),
+            r##"<svg><pattern height="4.26" id="a" patternUnits="userSpaceOnUse" width="10"><path d="M0 0h1"/></pattern><pattern height=".5" id="b" width=".12346"><path d="M0 0h1"/></pattern><rect fill="url(#a)"/><rect fill="url(#b)" style="stroke:url(#b)"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_remove_empty_patterns() {
+        assert_eq!(
+            optimized(
+                r##"<svg><pattern id="a" width="1" height="1"><title>Tile</title></pattern><pattern id="b"><rect width="1" height="1"/></pattern><pattern id="c" width="1" height="1"/><pattern id="d" href="#c"/><rect fill="url(#a) red" style="stroke:url(#b)"/><rect fill="url(#d)"/></svg>"##
+            ),
+            r##"<svg><pattern height="1" id="c" width="1"/><pattern href="#c" id="d"/><rect fill="none" style="stroke:none"/><rect fill="url(#d)"/></svg>"##
+        );
+    }
+}
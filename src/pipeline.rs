@@ -0,0 +1,669 @@
+//! Optimization passes as values.
+//!
+//! [`optimize`](crate::optimize) runs a [`Pipeline`]: a list of [`Pass`]es
+//! in the order they run. [`Options::pipeline`] builds the standard one
+//! from the option flags, unless [`Options::passes`] gives another; the
+//! settings passes share (precision, target, colors to replace, ...) stay
+//! in [`Options`].
+//!
+//! Order matters: ellipses become circles before paths are minified, and
+//! unused ids and definitions only show up once hidden content is gone.
+//! A pass may run more than once, as when collapsing groups again after
+//! removing what emptied them.
+//!
+//! Passes of your own implement [`OptimizePass`] and run among the
+//! built-in ones as [`Pass::Custom`]. To watch the passes run instead,
+//! set [`Options::on_pass`].
+
+use std::cell::Cell;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::Options;
+use crate::ast::Document;
+use crate::error::SvagError;
+use crate::optimize::run_passes;
+
+/// One optimization pass. Passes read their settings from [`Options`],
+/// where most also have a field of the same name that enables them in the
+/// standard pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// Inline stylesheets linked with `<?xml-stylesheet?>`, through
+    /// [`Options::stylesheet_resolver`]
+    InlineStylesheets,
+    /// Inline SVG files referenced by `<image>`, through
+    /// [`Options::image_resolver`]
+    InlineSvgImages,
+    RemoveMetadata,
+    /// Remove the elements [`Options::remove_elements`] and
+    /// [`Options::keep_elements`] select
+    RemoveElements,
+    /// Evaluate conditional processing attributes (see
+    /// [`Options::prune_conditions`])
+    EvaluateConditions,
+    RemoveAttrs,
+    ModernizeHref,
+    HoistNamespaces,
+    UnprefixElements,
+    RemoveUnusedNamespaces,
+    RemoveComments,
+    RemoveEmptyAttrs,
+    RemoveXmlLang,
+    RemoveXmlBase,
+    RemoveXmlSpace,
+    MinifyNumbers,
+    NormalizeKeywords,
+    RemoveHidden,
+    RemoveEmpty,
+    RemoveNoopClips,
+    FlattenNestedSvg,
+    OptimizeFilters,
+    OptimizeMarkers,
+    OptimizePatterns,
+    RemoveDuplicateShapes,
+    CollapseGroups,
+    RemoveOccluded,
+    ConvertEllipses,
+    /// Crop to the content, with [`Options::autocrop`] as the margin (or
+    /// none)
+    Autocrop,
+    Resize,
+    MinifyPaths,
+    MinifyTransforms,
+    NormalizeLists,
+    /// Replace a color with `currentColor`: [`Options::current_color`],
+    /// or the dominant one
+    CurrentColor,
+    CustomProperties,
+    MinifyColors,
+    HexAlpha,
+    RemoveDefaults,
+    MinifyStyles,
+    RegroupSiblings,
+    ExtractClasses,
+    SortDefs,
+    DedupeDataUris,
+    /// Drop whitespace-only text nodes
+    CleanupWhitespace,
+    /// Add the `<title>` in [`Options::title`]
+    Title,
+    /// A pass of your own (see [`Pass::custom`]), which the standard
+    /// pipeline never includes
+    Custom(CustomPass),
+}
+
+/// Every pass with its name, in the standard order.
+const PASSES: &[(Pass, &str)] = &[
+    (Pass::InlineStylesheets, "inline_stylesheets"),
+    (Pass::InlineSvgImages, "inline_svg_images"),
+    (Pass::RemoveMetadata, "remove_metadata"),
+    (Pass::RemoveElements, "remove_elements"),
+    (Pass::EvaluateConditions, "evaluate_conditions"),
+    (Pass::RemoveAttrs, "remove_attrs"),
+    (Pass::ModernizeHref, "modernize_href"),
+    (Pass::HoistNamespaces, "hoist_namespaces"),
+    (Pass::UnprefixElements, "unprefix_elements"),
+    (Pass::RemoveUnusedNamespaces, "remove_unused_namespaces"),
+    (Pass::RemoveComments, "remove_comments"),
+    (Pass::RemoveEmptyAttrs, "remove_empty_attrs"),
+    (Pass::RemoveXmlLang, "remove_xml_lang"),
+    (Pass::RemoveXmlBase, "remove_xml_base"),
+    (Pass::RemoveXmlSpace, "remove_xml_space"),
+    // Values are rounded and normalized first, so the passes below see
+    // what they end up as
+    (Pass::MinifyNumbers, "minify_numbers"),
+    (Pass::NormalizeKeywords, "normalize_keywords"),
+    (Pass::RemoveHidden, "remove_hidden"),
+    (Pass::RemoveEmpty, "remove_empty"),
+    (Pass::RemoveNoopClips, "remove_noop_clips"),
+    (Pass::FlattenNestedSvg, "flatten_nested_svg"),
+    (Pass::OptimizeFilters, "optimize_filters"),
+    (Pass::OptimizeMarkers, "optimize_markers"),
+    (Pass::OptimizePatterns, "optimize_patterns"),
+    (Pass::RemoveDuplicateShapes, "remove_duplicate_shapes"),
+    (Pass::CollapseGroups, "collapse_groups"),
+    (Pass::RemoveOccluded, "remove_occluded"),
+    (Pass::ConvertEllipses, "convert_ellipses"),
+    (Pass::Autocrop, "autocrop"),
+    (Pass::Resize, "resize"),
+    (Pass::MinifyPaths, "minify_paths"),
+    (Pass::MinifyTransforms, "minify_transforms"),
+    (Pass::NormalizeLists, "normalize_lists"),
+    (Pass::CurrentColor, "current_color"),
+    (Pass::CustomProperties, "custom_properties"),
+    (Pass::MinifyColors, "minify_colors"),
+    (Pass::HexAlpha, "hex_alpha"),
+    (Pass::RemoveDefaults, "remove_defaults"),
+    (Pass::MinifyStyles, "minify_styles"),
+    (Pass::RegroupSiblings, "regroup_siblings"),
+    (Pass::ExtractClasses, "extract_classes"),
+    (Pass::SortDefs, "sort_defs"),
+    (Pass::DedupeDataUris, "dedupe_data_uris"),
+    (Pass::CleanupWhitespace, "cleanup_whitespace"),
+    (Pass::Title, "title"),
+];
+
+impl Pass {
+    /// Every pass, in the order the standard pipeline runs them.
+    pub fn all() -> impl Iterator<Item = Pass> {
+        PASSES.iter().map(|(pass, _)| pass.clone())
+    }
+
+    /// Wrap a pass of your own.
+    pub fn custom(pass: impl OptimizePass + 'static) -> Self {
+        Pass::Custom(CustomPass(Arc::new(pass)))
+    }
+
+    /// The pass name, as in its [`Options`] field and in
+    /// [`PassStats`](crate::PassStats).
+    pub fn name(&self) -> &str {
+        match self {
+            Pass::Custom(custom) => custom.0.name(),
+            _ => PASSES
+                .iter()
+                .find(|(pass, _)| pass == self)
+                .map(|&(_, name)| name)
+                .expect("every built-in pass is listed"),
+        }
+    }
+
+    /// Whether `options` enable this pass in the standard pipeline.
+    fn is_enabled(&self, options: &Options) -> bool {
+        match self {
+            Pass::InlineStylesheets => options.stylesheet_resolver.is_some(),
+            Pass::InlineSvgImages => options.image_resolver.is_some(),
+            Pass::RemoveMetadata => options.remove_metadata,
+            Pass::RemoveElements => {
+                !options.remove_elements.is_empty() || options.keep_elements.is_some()
+            }
+            Pass::EvaluateConditions => {
+                options.prune_conditions || options.system_language.is_some()
+            }
+            Pass::RemoveAttrs => !options.remove_attrs.is_empty(),
+            Pass::ModernizeHref => options.modernize_href,
+            Pass::HoistNamespaces => options.hoist_namespaces,
+            Pass::UnprefixElements => options.unprefix_elements,
+            Pass::RemoveUnusedNamespaces => options.remove_unused_namespaces,
+            Pass::RemoveComments => options.remove_comments,
+            Pass::RemoveEmptyAttrs => options.remove_empty_attrs,
+            Pass::RemoveXmlLang => options.remove_xml_lang,
+            Pass::RemoveXmlBase => options.remove_xml_base,
+            Pass::RemoveXmlSpace => options.remove_xml_space,
+            Pass::MinifyNumbers => options.minify_numbers,
+            Pass::NormalizeKeywords => options.normalize_keywords,
+            Pass::RemoveHidden => options.remove_hidden,
+            Pass::RemoveEmpty => options.remove_empty,
+            Pass::RemoveNoopClips => options.remove_noop_clips,
+            Pass::FlattenNestedSvg => options.flatten_nested_svg,
+            Pass::OptimizeFilters => options.optimize_filters,
+            Pass::OptimizeMarkers => options.optimize_markers,
+            Pass::OptimizePatterns => options.optimize_patterns,
+            Pass::RemoveDuplicateShapes => options.remove_duplicate_shapes,
+            Pass::CollapseGroups => options.collapse_groups,
+            Pass::RemoveOccluded => options.remove_occluded,
+            Pass::ConvertEllipses => options.convert_ellipses,
+            Pass::Autocrop => options.autocrop.is_some(),
+            Pass::Resize => options.resize.is_some(),
+            Pass::MinifyPaths => options.minify_paths,
+            Pass::MinifyTransforms => options.minify_transforms,
+            Pass::NormalizeLists => options.normalize_lists,
+            Pass::CurrentColor => options.current_color.is_some(),
+            Pass::CustomProperties => !options.custom_properties.is_empty(),
+            Pass::MinifyColors => options.minify_colors,
+            Pass::HexAlpha => options.hex_alpha,
+            Pass::RemoveDefaults => options.remove_defaults,
+            Pass::MinifyStyles => options.minify_styles,
+            Pass::RegroupSiblings => options.regroup_siblings,
+            Pass::ExtractClasses => options.extract_classes,
+            Pass::SortDefs => options.sort_defs,
+            Pass::DedupeDataUris => options.dedupe_data_uris,
+            Pass::CleanupWhitespace => true,
+            Pass::Title => options.title.is_some(),
+            Pass::Custom(_) => false,
+        }
+    }
+}
+
+impl FromStr for Pass {
+    type Err = SvagError;
+
+    /// Parsed from its [name](Pass::name); dashes may stand for
+    /// underscores.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().replace('-', "_");
+        PASSES
+            .iter()
+            .find(|&&(_, n)| n == name)
+            .map(|(pass, _)| pass.clone())
+            .ok_or_else(|| SvagError::UnknownPass(s.to_string()))
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An optimization pass of your own, for what only your documents need:
+/// stripping in-house attributes, say, or adding a stylesheet your content
+/// security policy allows. Wrapped with [`Pass::custom`], it runs wherever
+/// it's placed in a [`Pipeline`].
+///
+/// ```
+/// use svag::{Document, Options, OptimizePass, Pass, PassContext};
+///
+/// /// Removes `data-track-*` attributes.
+/// struct StripTracking;
+///
+/// impl OptimizePass for StripTracking {
+///     fn name(&self) -> &str {
+///         "strip_tracking"
+///     }
+///
+///     fn run(&self, doc: &mut Document, _ctx: &PassContext) {
+///         fn strip(elem: &mut svag::Element) {
+///             elem.attributes
+///                 .retain(|a| !a.name.local.starts_with("data-track-"));
+///             for child in elem.child_elements_mut() {
+///                 strip(child);
+///             }
+///         }
+///         strip(&mut doc.root);
+///     }
+/// }
+///
+/// // Before empty groups are collapsed, so groups it empties go too
+/// let mut pipeline = Options::default().pipeline();
+/// pipeline.insert_before(Pass::RemoveEmptyAttrs, Pass::custom(StripTracking));
+/// let options = Options {
+///     passes: Some(pipeline),
+///     ..Options::default()
+/// };
+/// assert_eq!(
+///     svag::minify_with_options(
+///         r#"<svg><g data-track-id="hero"><rect width="1" data-track-click="cta"/></g></svg>"#,
+///         &options,
+///     )
+///     .unwrap(),
+///     r#"<svg><rect width="1"/></svg>"#
+/// );
+/// ```
+pub trait OptimizePass: Send + Sync {
+    /// The name it goes by in [`PassStats`](crate::PassStats) and when
+    /// its pipeline is displayed.
+    fn name(&self) -> &str;
+
+    fn run(&self, doc: &mut Document, ctx: &PassContext);
+}
+
+/// What a pass runs with, besides the document.
+#[derive(Debug, Clone, Copy)]
+pub struct PassContext<'a> {
+    /// The settings: precision, target, ...
+    pub options: &'a Options,
+    /// The passes being run, this one among them
+    pub pipeline: &'a Pipeline,
+}
+
+/// An [`OptimizePass`] in a [`Pass`]. Copies are equal to each other and
+/// to nothing else.
+#[derive(Clone)]
+pub struct CustomPass(pub(crate) Arc<dyn OptimizePass>);
+
+impl PartialEq for CustomPass {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CustomPass {}
+
+impl Hash for CustomPass {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+impl fmt::Debug for CustomPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomPass").field(&self.0.name()).finish()
+    }
+}
+
+/// Passes to run, in order. Written as comma-separated pass names:
+/// `remove_comments,collapse_groups,minify_paths,collapse_groups`.
+///
+/// ```
+/// use svag::{Options, Pass, Pipeline};
+///
+/// let options = Options::default();
+/// let mut doc = svag::parse_svg(r##"<svg><!-- x --><g><rect fill="#ff0000"/></g></svg>"##).unwrap();
+/// Pipeline(vec![Pass::RemoveComments, Pass::CollapseGroups]).run(&mut doc, &options);
+/// assert_eq!(
+///     svag::serialize(&doc, &options),
+///     r##"<svg><rect fill="#ff0000"/></svg>"##
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pipeline(pub Vec<Pass>);
+
+impl Pipeline {
+    /// The passes [`Options::default`] enables.
+    pub fn standard() -> Self {
+        Options::default().pipeline()
+    }
+
+    pub fn contains(&self, pass: Pass) -> bool {
+        self.0.contains(&pass)
+    }
+
+    /// Insert `pass` just before the first run of `before`, or at the end
+    /// if it doesn't run.
+    pub fn insert_before(&mut self, before: Pass, pass: Pass) {
+        let at = self.0.iter().position(|p| *p == before);
+        self.0.insert(at.unwrap_or(self.0.len()), pass);
+    }
+
+    /// Insert `pass` just after the last run of `after`, or at the end if
+    /// it doesn't run.
+    pub fn insert_after(&mut self, after: Pass, pass: Pass) {
+        let at = self.0.iter().rposition(|p| *p == after);
+        self.0.insert(at.map_or(self.0.len(), |at| at + 1), pass);
+    }
+
+    /// Run the passes on `doc`, with their settings from `options`.
+    pub fn run(&self, doc: &mut Document, options: &Options) {
+        run_passes(doc, self, options, &mut |_, _| {});
+    }
+}
+
+/// Whether a [`PassEvent`] comes before or after its pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassStage {
+    Before,
+    After,
+}
+
+/// A pass about to run, or that just ran, as seen by [`Options::on_pass`].
+#[derive(Debug)]
+pub struct PassEvent<'a> {
+    pub pass: &'a Pass,
+    pub stage: PassStage,
+    /// The document at this point
+    pub doc: &'a Document<'a>,
+    /// Its serialized size
+    pub size: usize,
+    stopped: Cell<bool>,
+}
+
+impl PassEvent<'_> {
+    /// Skip the passes still to run (this one too, before it runs),
+    /// leaving the document as it is now.
+    pub fn stop(&self) {
+        self.stopped.set(true);
+    }
+}
+
+/// A callback for [`Options::on_pass`]. Sizes are measured only when one
+/// is set, by serializing the document between passes, which makes
+/// optimizing noticeably slower.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use svag::{Options, PassHook, PassStage};
+///
+/// // Stop at the first pass that makes the output larger
+/// let grew = Arc::new(Mutex::new(None));
+/// let before = Arc::new(Mutex::new(0));
+/// let options = Options {
+///     on_pass: Some(PassHook::new({
+///         let grew = grew.clone();
+///         move |event| match event.stage {
+///             PassStage::Before => *before.lock().unwrap() = event.size,
+///             PassStage::After => {
+///                 if event.size > *before.lock().unwrap() {
+///                     *grew.lock().unwrap() = Some(event.pass.name().to_string());
+///                     event.stop();
+///                 }
+///             }
+///         }
+///     })),
+///     title: Some("Logo".into()),
+///     ..Options::default()
+/// };
+/// svag::minify_with_options("<svg><g/></svg>", &options).unwrap();
+/// assert_eq!(grew.lock().unwrap().as_deref(), Some("title"));
+/// ```
+#[derive(Clone)]
+pub struct PassHook(Arc<HookFn>);
+
+type HookFn = dyn Fn(&PassEvent) + Send + Sync;
+
+impl PassHook {
+    pub fn new(hook: impl Fn(&PassEvent) + Send + Sync + 'static) -> Self {
+        PassHook(Arc::new(hook))
+    }
+
+    /// Call the hook, and return whether it asked to stop.
+    pub(crate) fn call(&self, pass: &Pass, stage: PassStage, doc: &Document, size: usize) -> bool {
+        let event = PassEvent {
+            pass,
+            stage,
+            doc,
+            size,
+            stopped: Cell::new(false),
+        };
+        (self.0)(&event);
+        event.stopped.get()
+    }
+}
+
+impl fmt::Debug for PassHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PassHook")
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = SvagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Pipeline)
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pass) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{pass}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Options {
+    /// The passes to run: [`Options::passes`] if set, or else the ones
+    /// these options enable, in the standard order.
+    ///
+    /// ```
+    /// use svag::{Options, Pass};
+    ///
+    /// let options = Options {
+    ///     collapse_groups: false,
+    ///     ..Options::default()
+    /// };
+    /// let pipeline = options.pipeline();
+    /// assert!(pipeline.contains(Pass::MinifyPaths));
+    /// assert!(!pipeline.contains(Pass::CollapseGroups));
+    /// ```
+    pub fn pipeline(&self) -> Pipeline {
+        if let Some(passes) = &self.passes {
+            return passes.clone();
+        }
+        Pipeline(Pass::all().filter(|pass| pass.is_enabled(self)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimize, optimize_with_stats, parse_svg, serialize};
+
+    #[test]
+    fn test_pipeline() {
+        let svg = r#"<svg><!-- x --><g><ellipse rx="1" ry="1"/></g></svg>"#;
+        let options = Options::default();
+        let run = |pipeline: Pipeline| {
+            let mut doc = parse_svg(svg).unwrap();
+            pipeline.run(&mut doc, &options);
+            serialize(&doc, &options)
+        };
+
+        let mut doc = parse_svg(svg).unwrap();
+        optimize(&mut doc, &options);
+        assert_eq!(run(Pipeline::standard()), serialize(&doc, &options));
+        assert_eq!(
+            run(Pipeline::default()),
+            r#"<svg><g><ellipse rx="1" ry="1"/></g></svg>"#
+        );
+        assert_eq!(
+            run(Pipeline(vec![Pass::ConvertEllipses])),
+            r#"<svg><g><circle r="1"/></g></svg>"#
+        );
+
+        // Given passes replace the flags, in their order
+        let options = Options {
+            passes: Some("convert-ellipses, collapse_groups".parse().unwrap()),
+            collapse_groups: false,
+            ..Options::default()
+        };
+        let mut doc = parse_svg(svg).unwrap();
+        optimize(&mut doc, &options);
+        assert_eq!(serialize(&doc, &options), r#"<svg><circle r="1"/></svg>"#);
+    }
+
+    #[test]
+    fn test_custom_pass() {
+        /// Writes the precision it runs with, and whether it runs before
+        /// collapse_groups, on the root.
+        struct Mark;
+
+        impl OptimizePass for Mark {
+            fn name(&self) -> &str {
+                "mark"
+            }
+
+            fn run(&self, doc: &mut Document, ctx: &PassContext) {
+                let before = ctx.pipeline.0.iter().position(|p| p.name() == "mark")
+                    < ctx
+                        .pipeline
+                        .0
+                        .iter()
+                        .position(|p| *p == Pass::CollapseGroups);
+                doc.root
+                    .set_attr("data-mark", format!("{}-{before}", ctx.options.precision));
+            }
+        }
+
+        let mark = Pass::custom(Mark);
+        assert_eq!(mark, mark.clone());
+        assert_ne!(mark, Pass::custom(Mark));
+        assert_eq!(format!("{mark:?}"), r#"Custom(CustomPass("mark"))"#);
+
+        let mut pipeline = Pipeline(vec![Pass::RemoveEmpty, Pass::CollapseGroups]);
+        pipeline.insert_before(Pass::CollapseGroups, mark.clone());
+        pipeline.insert_after(Pass::CollapseGroups, Pass::RemoveEmpty);
+        assert_eq!(
+            pipeline.to_string(),
+            "remove_empty,mark,collapse_groups,remove_empty"
+        );
+        pipeline.insert_after(Pass::Title, mark);
+        assert_eq!(pipeline.0.len(), 5);
+
+        let options = Options {
+            precision: 2,
+            passes: Some(pipeline),
+            ..Options::default()
+        };
+        let mut doc = parse_svg("<svg><g><g/></g></svg>").unwrap();
+        let stats = optimize_with_stats(&mut doc, &options);
+        assert_eq!(serialize(&doc, &options), r#"<svg data-mark="2-true"/>"#);
+        let names: Vec<&str> = stats.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "remove_empty",
+                "mark",
+                "collapse_groups",
+                "remove_empty",
+                "mark"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_on_pass() {
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let options = Options {
+            passes: Some("remove_empty,title".parse().unwrap()),
+            title: Some("x".into()),
+            on_pass: Some(PassHook::new({
+                let events = events.clone();
+                move |event| {
+                    let name = event.pass.name().to_string();
+                    events.lock().unwrap().push((name, event.stage, event.size));
+                    if event.pass == &Pass::Title {
+                        event.stop();
+                    }
+                }
+            })),
+            ..Options::default()
+        };
+        let mut doc = parse_svg("<svg><g/></svg>").unwrap();
+        optimize(&mut doc, &options);
+        assert_eq!(serialize(&doc, &options), "<svg/>");
+
+        use PassStage::*;
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ("remove_empty".to_string(), Before, 15),
+                ("remove_empty".to_string(), After, 6),
+                ("title".to_string(), Before, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        for pass in Pass::all() {
+            assert_eq!(pass.name().parse::<Pass>().unwrap(), pass);
+        }
+
+        let pipeline: Pipeline = "remove_hidden,collapse_groups,remove-empty,collapse_groups"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            pipeline.to_string(),
+            "remove_hidden,collapse_groups,remove_empty,collapse_groups"
+        );
+        assert_eq!("".parse::<Pipeline>().unwrap(), Pipeline::default());
+        assert!(matches!(
+            "remove_hidden,shrink".parse::<Pipeline>(),
+            Err(SvagError::UnknownPass(name)) if name == "shrink"
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+//! Palette mapping.
+//!
+//! Dark-mode and brand variants of an icon set are the same drawings with
+//! a few colors swapped. Colors are matched after parsing, so a mapping
+//! from `#000` also catches `black` and `rgb(0 0 0)`, and within a small
+//! tolerance, so one from `#336699` catches `hsl(210, 50%, 40%)` too.
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::color::{COLOR_PROPERTIES, Color, css_color, rewrite_colors};
+
+/// How far apart (in any channel, out of 255) colors can be and still
+/// match in [`recolor`]: enough for rounding in `hsl()` and percentage
+/// `rgb()`.
+pub const DEFAULT_RECOLOR_TOLERANCE: u8 = 1;
+
+/// Rewrite fills, strokes, stop, flood, and lighting colors, and `color`,
+/// in attributes, `style` attributes, and `<style>` rules, according to
+/// `mapping`. Returns how many values were rewritten.
+///
+/// Colors match a key within [`DEFAULT_RECOLOR_TOLERANCE`]; see
+/// [`recolor_with_tolerance`].
+///
+/// ```
+/// use std::collections::HashMap;
+/// use svag::Color;
+///
+/// let mut doc = svag::parse_svg(
+///     r#"<svg><rect fill="white" stroke="rgb(0 0 0 / 50%)"/><path style="fill:#000"/></svg>"#,
+/// )
+/// .unwrap();
+/// let dark = HashMap::from([
+///     (Color::rgb(255, 255, 255), Color::rgb(0x12, 0x12, 0x12)),
+///     (Color::rgb(0, 0, 0), Color::rgb(0xee, 0xee, 0xee)),
+/// ]);
+/// assert_eq!(svag::recolor(&mut doc, &dark), 3);
+/// assert_eq!(
+///     svag::serialize(&doc, &svag::Options::default()),
+///     r##"<svg><rect fill="#121212" stroke="rgba(238,238,238,.502)"/><path style="fill:#eee"/></svg>"##
+/// );
+/// ```
+pub fn recolor(doc: &mut Document, mapping: &HashMap<Color, Color>) -> usize {
+    recolor_with_tolerance(doc, mapping, DEFAULT_RECOLOR_TOLERANCE)
+}
+
+/// Like [`recolor`], with colors matching a key when no channel differs
+/// by more than `tolerance`. The closest key wins.
+///
+/// An opaque key also matches translucent versions of its color, which
+/// keep their alpha: mapping black to white turns `rgba(0,0,0,.5)` into
+/// `rgba(255,255,255,.5)`.
+pub fn recolor_with_tolerance(
+    doc: &mut Document,
+    mapping: &HashMap<Color, Color>,
+    tolerance: u8,
+) -> usize {
+    // Sorted, so ties between keys go the same way every run
+    let mut keys: Vec<(&Color, &Color)> = mapping.iter().collect();
+    keys.sort_by_key(|(from, _)| (from.r, from.g, from.b, from.a));
+
+    let mut count = 0;
+    rewrite_colors(&mut doc.root, COLOR_PROPERTIES, &mut |_, found| {
+        let (_, to) = keys
+            .iter()
+            .filter_map(|&(from, to)| {
+                let compared = if from.is_opaque() {
+                    Color { a: 255, ..found }
+                } else {
+                    found
+                };
+                let distance = from.distance(compared);
+                (distance <= tolerance).then_some((distance, to))
+            })
+            .min_by_key(|(distance, _)| *distance)?;
+        let to = if to.is_opaque() && !found.is_opaque() {
+            Color { a: found.a, ..*to }
+        } else {
+            *to
+        };
+        count += 1;
+        Some(css_color(to))
+    });
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, serialize};
+
+    #[test]
+    fn test_recolor() {
+        let mut doc = crate::parse_svg(
+            r##"<svg><style>.a{stroke:hsl(210,50%,40%)}</style><linearGradient><stop stop-color="#336699"/><stop stop-color="#34669a"/><stop stop-color="#3a6699"/></linearGradient><path fill="currentColor" stroke="none"/></svg>"##,
+        )
+        .unwrap();
+        let mapping = HashMap::from([(Color::rgb(0x33, 0x66, 0x99), Color::rgb(255, 0, 0))]);
+        assert_eq!(recolor(&mut doc, &mapping), 3);
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r##"<svg><style>.a{stroke:red}</style><linearGradient><stop stop-color="red"/><stop stop-color="red"/><stop stop-color="#3a6699"/></linearGradient><path fill="currentColor" stroke="none"/></svg>"##
+        );
+    }
+
+    #[test]
+    fn test_exact_and_closest() {
+        let mut doc = crate::parse_svg(r##"<svg><path fill="#0a0a0a"/></svg>"##).unwrap();
+        let mapping = HashMap::from([
+            (Color::rgb(0, 0, 0), Color::rgb(255, 255, 255)),
+            (Color::rgb(12, 12, 12), Color::rgb(0, 0, 255)),
+        ]);
+        assert_eq!(recolor_with_tolerance(&mut doc, &mapping, 0), 0);
+        assert_eq!(recolor_with_tolerance(&mut doc, &mapping, 16), 1);
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r#"<svg><path fill="blue"/></svg>"#
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Size-driven regrouping: the inverse of group collapsing.
+//!
+//! Runs of siblings that repeat the same inherited presentation attributes
+//! (or the same `transform`) are wrapped in a new `<g>` carrying them once,
+//! when that makes the output smaller. Only properties that inherit, plus
+//! `transform`, which composes, can move to a parent without changing the
+//! rendering. Documents with stylesheets or scripts are left alone, since
+//! structural selectors would match differently. Elements referenced by
+//! `<use>` or similar are skipped, because their instances don't inherit
+//! from the new group.
+
+use std::collections::HashSet;
+
+use crate::ast::*;
+use crate::bounds::has_dynamic_content;
+use crate::index::referenced_ids;
+use crate::optimize::is_whitespace;
+
+/// Attributes that mean the same on a wrapping `<g>` as on each child.
+pub(crate) const MOVABLE: &[&str] = &[
+    "transform",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "clip-rule",
+    "color",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "text-anchor",
+    "paint-order",
+    "shape-rendering",
+];
+
+/// Parents where a new `<g>` child renders like its children would.
+const PARENTS: &[&str] = &["svg", "g", "a", "symbol", "mask", "pattern", "marker"];
+
+/// Children that may be wrapped.
+const GROUPABLE: &[&str] = &[
+    "path", "rect", "circle", "ellipse", "line", "polyline", "polygon", "g", "use", "image", "text",
+];
+
+/// Wrap runs of siblings with shared attributes in a `<g>` when it saves
+/// bytes.
+pub(crate) fn regroup_siblings(doc: &mut Document) {
+    if has_dynamic_content(doc) {
+        return;
+    }
+
+    let referenced = referenced_ids(doc);
+
+    regroup(&mut doc.root, &referenced);
+}
+
+fn regroup(elem: &mut Element, referenced: &HashSet<String>) {
+    for child in elem.child_elements_mut() {
+        regroup(child, referenced);
+    }
+    if elem.name.prefix.is_none() && PARENTS.contains(&elem.name.local.as_str()) {
+        regroup_children(elem, referenced);
+    }
+}
+
+fn regroup_children(elem: &mut Element, referenced: &HashSet<String>) {
+    let mut children = elem.take_children();
+    let mut out: Vec<Node> = Vec::with_capacity(children.len());
+    let mut i = 0;
+    while i < children.len() {
+        let Some((end, shared)) = best_run(&children[i..], referenced) else {
+            out.push(std::mem::replace(&mut children[i], Node::Text("".into())));
+            i += 1;
+            continue;
+        };
+
+        let mut group = Element::new("g");
+        for attr in &shared {
+            group.attributes.push(attr.clone());
+        }
+        for node in &mut children[i..i + end] {
+            let mut node = std::mem::replace(node, Node::Text("".into()));
+            if let Node::Element(child) = &mut node {
+                child
+                    .attributes
+                    .retain(|a| !shared.iter().any(|s| s.name == a.name));
+            }
+            group.append_child(node);
+        }
+        // Members sharing more than the run did may form a run of their own
+        regroup_children(&mut group, referenced);
+        out.push(group.into());
+        i += end;
+    }
+    elem.children = out;
+}
+
+/// The most profitable run starting at `nodes[0]`: its length in nodes and
+/// the attributes its members share.
+fn best_run<'a>(
+    nodes: &[Node<'a>],
+    referenced: &HashSet<String>,
+) -> Option<(usize, Vec<Attribute<'a>>)> {
+    let first = groupable(nodes.first()?, referenced)?;
+    let mut shared: Vec<Attribute> = first
+        .attributes
+        .iter()
+        .filter(|a| a.name.prefix.is_none() && MOVABLE.contains(&a.name.local.as_str()))
+        .cloned()
+        .collect();
+
+    let mut best: Option<(usize, Vec<Attribute>)> = None;
+    let mut best_saving = 0;
+    let mut count = 1;
+    for (len, node) in nodes.iter().enumerate().skip(1).map(|(i, n)| (i + 1, n)) {
+        // Whitespace is dropped at the end; it mustn't split runs until then
+        if is_whitespace(node) {
+            continue;
+        }
+        let Some(elem) = groupable(node, referenced) else {
+            break;
+        };
+        shared.retain(|s| elem.get_attr(&s.name.local) == Some(s.value.as_ref()));
+        if shared.is_empty() {
+            break;
+        }
+
+        count += 1;
+        let saving = saving(&shared, count);
+        if saving > best_saving {
+            best_saving = saving;
+            best = Some((len, shared.clone()));
+        }
+    }
+    best
+}
+
+/// Bytes saved by writing `shared` once on a new `<g>` instead of on each
+/// of `count` children.
+fn saving(shared: &[Attribute], count: usize) -> isize {
+    // ` name="value"`
+    let attrs: usize = shared
+        .iter()
+        .map(|a| a.name.local.len() + a.value.len() + 4)
+        .sum();
+    let wrapper = "<g>".len() + "</g>".len() + attrs;
+    (attrs * count) as isize - wrapper as isize
+}
+
+fn groupable<'a, 'b>(node: &'a Node<'b>, referenced: &HashSet<String>) -> Option<&'a Element<'b>> {
+    let Node::Element(elem) = node else {
+        return None;
+    };
+    let is_referenced = elem
+        .get_attr("id")
+        .is_some_and(|id| referenced.contains(id));
+    (elem.name.prefix.is_none() && GROUPABLE.contains(&elem.name.local.as_str()) && !is_referenced)
+        .then_some(elem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    fn run(svg: &str) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        regroup_siblings(&mut doc);
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_wraps_shared_attributes() {
+        let out = run(
+            r#"<svg><rect/><path d="M0 0" fill="red" stroke="blue"/><path d="M1 1" fill="red" stroke="blue"/><circle r="1" fill="red" stroke="blue" opacity=".5"/><rect fill="red"/></svg>"#,
+        );
+        assert_eq!(
+            out,
+            r#"<svg><rect/><g fill="red" stroke="blue"><path d="M0 0"/><path d="M1 1"/><circle opacity=".5" r="1"/></g><rect fill="red"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_picks_most_profitable_run() {
+        // Two elements sharing both attributes beat three sharing one
+        let out = run(
+            r#"<svg><rect fill="red" stroke="blue"/><rect fill="red" stroke="blue"/><rect fill="red"/></svg>"#,
+        );
+        assert_eq!(
+            out,
+            r#"<svg><g fill="red" stroke="blue"><rect/><rect/></g><rect fill="red"/></svg>"#
+        );
+
+        let out = run(
+            r#"<svg><rect transform="translate(10 20)"/><rect transform="translate(10 20)"/><rect transform="translate(10 20)"/></svg>"#,
+        );
+        assert_eq!(
+            out,
+            r#"<svg><g transform="translate(10 20)"><rect/><rect/><rect/></g></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_leaves_unsafe_runs() {
+        let cases = [
+            // Opacity doesn't inherit: a group would composite differently
+            r#"<svg><rect opacity=".5"/><rect opacity=".5"/><rect opacity=".5"/><rect opacity=".5"/></svg>"#,
+            // Referenced elements render elsewhere without the group
+            r##"<svg><rect id="a" stroke-width="2"/><rect id="b" stroke-width="2"/><use href="#a"/><use href="#b"/></svg>"##,
+            // A <g> isn't allowed inside <clipPath>, and stylesheets may use
+            // structural selectors
+            r#"<svg><clipPath><rect stroke-width="2"/><rect stroke-width="2"/><rect stroke-width="2"/><rect stroke-width="2"/></clipPath></svg>"#,
+            r#"<svg><style>rect:first-child{fill:red}</style><rect stroke-width="2"/><rect stroke-width="2"/><rect stroke-width="2"/><rect stroke-width="2"/></svg>"#,
+        ];
+        for svg in cases {
+            assert!(!run(svg).contains("<g"), "{svg}");
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Resizing documents.
+//!
+//! Icon sets are usually normalized to one size, such as 24×24. With a
+//! `viewBox`, that's just a new `width` and `height`. Without one, the
+//! content is drawn in the document's own pixels, so its coordinates are
+//! scaled to the new size.
+
+use crate::ast::*;
+use crate::bounds::{has_dynamic_content, length};
+use crate::coords::{AxisMap, map_content};
+use crate::crop::{set_view_box, view_box};
+use crate::error::SvagError;
+use crate::geometry::Rect;
+use crate::path::format_number;
+
+/// A target size, in user units (CSS pixels). A side that's left out
+/// follows the other one, keeping the document's aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Size {
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+}
+
+impl Size {
+    pub fn new(width: f64, height: f64) -> Self {
+        Size {
+            width: Some(width),
+            height: Some(height),
+        }
+    }
+}
+
+/// Decimals kept in the numbers [`scale_document`] writes.
+const PRECISION: u8 = 3;
+
+/// Set the root's `width` and `height` to `target`. Without a `viewBox`,
+/// the content's coordinates are scaled to match, or, where that can't be
+/// done in place (text, transforms, references, `<style>`), a `viewBox` of
+/// the old size is added, stretching like the scaled content would.
+///
+/// Fails when the document has neither a `viewBox` nor a numeric `width`
+/// and `height` to scale from.
+///
+/// ```
+/// let mut doc = svag::parse_svg(
+///     r#"<svg width="48" height="48"><path d="M8 8h32v32H8z" stroke="red"/></svg>"#,
+/// )
+/// .unwrap();
+/// svag::scale_document(&mut doc, svag::Size::new(24.0, 24.0)).unwrap();
+/// assert_eq!(
+///     svag::serialize(&doc, &svag::Options::default()),
+///     r#"<svg height="24" stroke-width=".5" width="24"><path d="M4 4H20V20H4z" stroke="red"/></svg>"#
+/// );
+/// ```
+pub fn scale_document(doc: &mut Document, target: Size) -> Result<(), SvagError> {
+    resize(doc, target, PRECISION)
+}
+
+pub(crate) fn resize(doc: &mut Document, target: Size, precision: u8) -> Result<(), SvagError> {
+    let root_size = |name: &str| doc.root.get_attr(name).and_then(length);
+    let size = match (root_size("width"), root_size("height")) {
+        (Some(width), Some(height)) if width > 0.0 && height > 0.0 => Some((width, height)),
+        _ => None,
+    };
+    let view_box = view_box(&doc.root);
+    let Some((old_width, old_height)) = size.or(view_box.map(|b| (b.width(), b.height()))) else {
+        return Err(SvagError::InvalidSvg(
+            "no viewBox, width, or height to resize from".into(),
+        ));
+    };
+
+    let aspect = old_width / old_height;
+    let (width, height) = match (target.width, target.height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, width / aspect),
+        (None, Some(height)) => (height * aspect, height),
+        (None, None) => return Ok(()),
+    };
+    if !(width > 0.0 && height > 0.0 && width.is_finite() && height.is_finite()) {
+        return Err(SvagError::InvalidSvg(format!(
+            "can't resize to {width}×{height}"
+        )));
+    }
+
+    if view_box.is_none() {
+        let Some((old_width, old_height)) = size else {
+            return Err(SvagError::InvalidSvg(
+                "no viewBox, width, or height to resize from".into(),
+            ));
+        };
+        let map = AxisMap::scale(width / old_width, height / old_height);
+        if has_dynamic_content(doc) || !map_content(&mut doc.root, map, precision) {
+            let old = Rect {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: old_width,
+                max_y: old_height,
+            };
+            set_view_box(&mut doc.root, &old, precision);
+            if (map.sx - map.sy).abs() > 1e-9 {
+                doc.root.set_attr("preserveAspectRatio", "none");
+            }
+        }
+    }
+
+    doc.root.set_attr("width", format_number(width, precision));
+    doc.root
+        .set_attr("height", format_number(height, precision));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, minify_with_options, parse_svg, serialize};
+
+    fn resized(svg: &str, target: Size) -> String {
+        let mut doc = parse_svg(svg).unwrap();
+        scale_document(&mut doc, target).unwrap();
+        serialize(&doc, &Options::default())
+    }
+
+    #[test]
+    fn test_scale_document() {
+        // With a viewBox, only the size changes; a missing side keeps the
+        // aspect ratio
+        assert_eq!(
+            resized(
+                r#"<svg viewBox="0 0 100 50"><rect width="10" height="10"/></svg>"#,
+                Size {
+                    width: Some(24.0),
+                    height: None
+                }
+            ),
+            r#"<svg height="12" viewBox="0 0 100 50" width="24"><rect height="10" width="10"/></svg>"#
+        );
+
+        // Shapes scale along each axis
+        assert_eq!(
+            resized(
+                r#"<svg width="10" height="20"><g fill="red"><ellipse cx="5" cy="5" rx="5" ry="2"/><polygon points="0,0 10,20 0,20"/></g></svg>"#,
+                Size::new(20.0, 20.0)
+            ),
+            r#"<svg height="20" width="20"><g fill="red"><ellipse cx="10" cy="5" rx="10" ry="2"/><polygon points="0 0 20 20 0 20"/></g></svg>"#
+        );
+
+        // Text can't be scaled in place
+        assert_eq!(
+            resized(
+                r#"<svg width="10" height="20"><text>Hi</text></svg>"#,
+                Size::new(20.0, 20.0)
+            ),
+            r#"<svg height="20" preserveAspectRatio="none" viewBox="0 0 10 20" width="20"><text>Hi</text></svg>"#
+        );
+
+        let mut doc = parse_svg("<svg><rect/></svg>").unwrap();
+        assert!(scale_document(&mut doc, Size::new(24.0, 24.0)).is_err());
+    }
+
+    #[test]
+    fn test_resize_option() {
+        let options = Options {
+            resize: Some(Size::new(24.0, 24.0)),
+            ..Options::default()
+        };
+        let svg = r#"<svg width="48" height="48"><circle cx="24" cy="24" r="20"/></svg>"#;
+        assert_eq!(
+            minify_with_options(svg, &options).unwrap(),
+            r#"<svg height="24" width="24"><circle cx="12" cy="12" r="10"/></svg>"#
+        );
+    }
+}
@@ -1,11 +1,89 @@
 //! SVG serialization to minified XML.
 
-use crate::Options;
+use std::io::{self, BufWriter, Write};
+
 use crate::ast::*;
+use crate::compress;
+use crate::{OptimizeFor, Options};
 
 /// Serialize a Document to a minified SVG string.
 pub fn serialize(doc: &Document, options: &Options) -> String {
     let mut out = String::new();
+    serialize_document(&mut out, doc, options);
+    out
+}
+
+/// Serialize a Document into an `io::Write` sink, such as a file, socket,
+/// or compression encoder, without building the whole string first.
+///
+/// Output is buffered internally, so `w` doesn't need to be.
+pub fn serialize_to<W: Write>(doc: &Document, options: &Options, w: W) -> io::Result<()> {
+    let mut out = IoSink {
+        writer: BufWriter::new(w),
+        error: None,
+    };
+    serialize_document(&mut out, doc, options);
+    match out.error {
+        Some(e) => Err(e),
+        None => out.writer.flush(),
+    }
+}
+
+/// Destination for serialized markup.
+pub(crate) trait Sink {
+    fn push_str(&mut self, s: &str);
+
+    fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+}
+
+impl Sink for String {
+    fn push_str(&mut self, s: &str) {
+        String::push_str(self, s);
+    }
+
+    fn push(&mut self, c: char) {
+        String::push(self, c);
+    }
+}
+
+/// Adapts an `io::Write` to [`Sink`], keeping the first error and skipping
+/// all writes after it.
+struct IoSink<W: Write> {
+    writer: BufWriter<W>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Sink for IoSink<W> {
+    fn push_str(&mut self, s: &str) {
+        if self.error.is_none()
+            && let Err(e) = self.writer.write_all(s.as_bytes())
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+pub(crate) fn serialize_document(out: &mut impl Sink, doc: &Document, options: &Options) {
+    // Sorting doesn't change the raw size, but the source order sometimes
+    // compresses better
+    if options.sort_attrs && options.optimize_for != OptimizeFor::RawSize {
+        let source_order = Options {
+            sort_attrs: false,
+            optimize_for: OptimizeFor::RawSize,
+            ..options.clone()
+        };
+        let sorted = Options {
+            optimize_for: OptimizeFor::RawSize,
+            ..options.clone()
+        };
+        let candidates = [&sorted, &source_order].map(|options| serialize(doc, options));
+        if let Some(best) = compress::smallest(candidates, options.optimize_for, String::as_str) {
+            out.push_str(&best);
+        }
+        return;
+    }
 
     // XML declaration
     if !options.remove_xml_declaration
@@ -27,6 +105,16 @@ pub fn serialize(doc: &Document, options: &Options) -> String {
         out.push_str("?>");
     }
 
+    if let Some(banner) = &options.banner {
+        out.push_str("<!--");
+        push_comment_text(out, banner);
+        out.push_str("-->");
+    }
+
+    for node in &doc.prolog {
+        serialize_node(out, node, options);
+    }
+
     // DOCTYPE
     if !options.remove_doctype
         && let Some(ref dt) = doc.doctype
@@ -37,12 +125,23 @@ pub fn serialize(doc: &Document, options: &Options) -> String {
     }
 
     // Root element
-    serialize_element(&mut out, &doc.root, options);
+    serialize_element(out, &doc.root, options);
 
+    if options.final_newline {
+        out.push('\n');
+    }
+}
+
+/// Serialize the children of an element (its inner markup).
+pub(crate) fn serialize_children(elem: &Element, options: &Options) -> String {
+    let mut out = String::new();
+    for child in &elem.children {
+        serialize_node(&mut out, child, options);
+    }
     out
 }
 
-fn serialize_element(out: &mut String, elem: &Element, options: &Options) {
+fn serialize_element(out: &mut impl Sink, elem: &Element, options: &Options) {
     out.push('<');
     out.push_str(&elem.name.full_name());
 
@@ -64,9 +163,8 @@ fn serialize_element(out: &mut String, elem: &Element, options: &Options) {
     for attr in attrs {
         out.push(' ');
         out.push_str(&attr.name.full_name());
-        out.push_str("=\"");
-        push_escaped_attr(out, &attr.value);
-        out.push('"');
+        out.push('=');
+        push_quoted_attr(out, &attr.value);
     }
 
     // Children or self-closing
@@ -75,8 +173,30 @@ fn serialize_element(out: &mut String, elem: &Element, options: &Options) {
     } else {
         out.push('>');
 
-        for child in &elem.children {
-            serialize_node(out, child, options);
+        let raw_text =
+            elem.name.prefix.is_none() && matches!(elem.name.local.as_str(), "style" | "script");
+        if raw_text && options.convert_cdata {
+            // Adjacent text and CDATA read as one string; it's trimmed and
+            // written whichever way is shorter as a whole
+            let is_text = |node: &Node| matches!(node, Node::Text(_) | Node::CData(_));
+            for run in elem.children.chunk_by(|a, b| is_text(a) && is_text(b)) {
+                if !is_text(&run[0]) {
+                    serialize_node(out, &run[0], options);
+                    continue;
+                }
+                let text: String = run
+                    .iter()
+                    .map(|node| match node {
+                        Node::Text(text) | Node::CData(text) => text.as_ref(),
+                        _ => "",
+                    })
+                    .collect();
+                push_shortest_text(out, text.trim());
+            }
+        } else {
+            for child in &elem.children {
+                serialize_node(out, child, options);
+            }
         }
 
         out.push_str("</");
@@ -85,7 +205,7 @@ fn serialize_element(out: &mut String, elem: &Element, options: &Options) {
     }
 }
 
-fn serialize_node(out: &mut String, node: &Node, options: &Options) {
+fn serialize_node(out: &mut impl Sink, node: &Node, options: &Options) {
     match node {
         Node::Element(elem) => serialize_element(out, elem, options),
         Node::Text(text) => {
@@ -119,27 +239,109 @@ fn serialize_node(out: &mut String, node: &Node, options: &Options) {
     }
 }
 
-fn push_escaped_attr(out: &mut String, s: &str) {
+/// Escape an attribute value for use inside double quotes.
+pub(crate) fn escape_attr(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    push_escaped_attr(&mut out, s, '"');
+    out
+}
+
+/// Push an attribute value in whichever quotes need fewer escapes, double
+/// quotes on a tie.
+fn push_quoted_attr(out: &mut impl Sink, s: &str) {
+    let quote = if s.matches('"').count() > s.matches('\'').count() {
+        '\''
+    } else {
+        '"'
+    };
+    out.push(quote);
+    push_escaped_attr(out, s, quote);
+    out.push(quote);
+}
+
+// The shortest reference for each character that may need one. Named
+// entities win ties; quotes are shorter as numeric references (`&#34;`
+// against `&quot;`).
+const AMP: &str = "&amp;";
+const LT: &str = "&lt;";
+const GT: &str = "&gt;";
+const QUOT: &str = "&#34;";
+const APOS: &str = "&#39;";
+// A literal carriage return would read back as a line feed
+const CR: &str = "&#13;";
+
+/// Escape what's not allowed in an attribute value quoted with `quote`:
+/// `&`, `<`, the quote itself, and `\r`.
+fn push_escaped_attr(out: &mut impl Sink, s: &str, quote: char) {
+    push_escaped(out, s, |c, _| match c {
+        '&' => Some(AMP),
+        '<' => Some(LT),
+        '"' if quote == '"' => Some(QUOT),
+        '\'' if quote == '\'' => Some(APOS),
+        '\r' => Some(CR),
+        _ => None,
+    });
+}
+
+/// Escape what's not allowed in text: `&`, `<`, the `>` of `]]>`, and
+/// `\r`.
+fn push_escaped_text(out: &mut impl Sink, s: &str) {
+    push_escaped(out, s, |c, before| match c {
+        '&' => Some(AMP),
+        '<' => Some(LT),
+        '>' if before.ends_with("]]") => Some(GT),
+        '\r' => Some(CR),
+        _ => None,
+    });
+}
+
+/// Push text as comment content. Comments can't contain `--` or end in
+/// `-`, so those get a space.
+fn push_comment_text(out: &mut impl Sink, s: &str) {
+    let mut prev = None;
     for c in s.chars() {
-        match c {
-            '"' => out.push_str("&quot;"),
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            _ => out.push(c),
+        if c == '-' && prev == Some('-') {
+            out.push(' ');
         }
+        out.push(c);
+        prev = Some(c);
+    }
+    if prev == Some('-') {
+        out.push(' ');
     }
 }
 
-fn push_escaped_text(out: &mut String, s: &str) {
-    for c in s.chars() {
-        match c {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            _ => out.push(c),
+/// Push text as a CDATA section when that's shorter than escaping it,
+/// which depends on how many `<` and `&` it has. Stylesheets and scripts
+/// read the same either way. A `]]>` in the text ends one section and
+/// starts another. A `\r` can only be written escaped.
+fn push_shortest_text(out: &mut impl Sink, s: &str) {
+    const SECTION: usize = "<![CDATA[]]>".len();
+
+    let mut escaped = String::with_capacity(s.len());
+    push_escaped_text(&mut escaped, s);
+    let sections = 1 + s.matches("]]>").count();
+    if escaped.len() > s.len() + SECTION * sections && !s.contains('\r') {
+        out.push_str("<![CDATA[");
+        out.push_str(&s.replace("]]>", "]]]]><![CDATA[>"));
+        out.push_str("]]>");
+    } else {
+        out.push_str(&escaped);
+    }
+}
+
+/// Push `s`, replacing characters `escape` maps, in as few sink calls as
+/// possible. `escape` also gets the text before the character.
+fn push_escaped(out: &mut impl Sink, s: &str, escape: impl Fn(char, &str) -> Option<&'static str>) {
+    let mut plain = 0;
+    for (i, c) in s.char_indices() {
+        if let Some(entity) = escape(c, &s[..i]) {
+            out.push_str(&s[plain..i]);
+            out.push_str(entity);
+            plain = i + c.len_utf8();
         }
     }
+    out.push_str(&s[plain..]);
 }
 
 #[cfg(test)]
@@ -176,4 +378,119 @@ mod tests {
         let out = serialize(&doc, &options);
         assert!(!out.contains("<!--"));
     }
+
+    #[test]
+    fn test_shortest_escaping() {
+        let cases = [
+            // `>` only needs escaping after `]]`
+            (
+                "<svg><text>a &gt; b]]&gt; &amp; &lt;</text></svg>",
+                "<svg><text>a > b]]&gt; &amp; &lt;</text></svg>",
+            ),
+            // Quotes that need fewer escapes, and numeric references for
+            // the rest
+            (
+                r#"<svg><g data-a='say "hi"' data-b="it's" data-c="&quot;'&quot;'" data-d="a>b"/></svg>"#,
+                r#"<svg><g data-a='say "hi"' data-b="it's" data-c="&#34;'&#34;'" data-d="a>b"/></svg>"#,
+            ),
+        ];
+        for (svg, expected) in cases {
+            let doc = parse_svg(svg).unwrap();
+            assert_eq!(serialize(&doc, &Options::default()), expected);
+            // And it reads back the same
+            let reparsed = parse_svg(expected).unwrap();
+            assert_eq!(serialize(&reparsed, &Options::default()), expected);
+        }
+    }
+
+    #[test]
+    fn test_convert_cdata() {
+        let options = Options::default();
+        let cases = [
+            (
+                "<svg><style><![CDATA[\n  rect{fill:red}\n]]></style></svg>",
+                "<svg><style>rect{fill:red}</style></svg>",
+            ),
+            (
+                "<svg><script>if(a&amp;&amp;b&lt;c&amp;&amp;d&lt;e){f()}</script></svg>",
+                "<svg><script><![CDATA[if(a&&b<c&&d<e){f()}]]></script></svg>",
+            ),
+            // Cheaper escaped, or can't be wrapped
+            (
+                "<svg><script><![CDATA[a<b]]></script></svg>",
+                "<svg><script>a&lt;b</script></svg>",
+            ),
+            (
+                "<svg><script>x[y[0]]&gt;&amp;&amp;</script></svg>",
+                "<svg><script>x[y[0]]&gt;&amp;&amp;</script></svg>",
+            ),
+            // Split around `]]>` when dense enough to pay for two sections
+            (
+                "<svg><script>x[y[0]]&gt;&amp;&amp;&amp;&amp;&lt;&lt;&lt;&lt;&lt;&lt;&lt;</script></svg>",
+                "<svg><script><![CDATA[x[y[0]]]]><![CDATA[>&&&&<<<<<<<]]></script></svg>",
+            ),
+            // Mixed text and CDATA is one string, decided as a whole
+            (
+                "<svg><style> .a <![CDATA[.b>c]]>{fill:red} </style></svg>",
+                "<svg><style>.a .b>c{fill:red}</style></svg>",
+            ),
+            (
+                "<svg><script>f(a)<![CDATA[&&b<c&&d<e]]><!--x-->g()</script></svg>",
+                "<svg><script><![CDATA[f(a)&&b<c&&d<e]]>g()</script></svg>",
+            ),
+            // Only style and script content
+            (
+                "<svg><text><![CDATA[a]]></text></svg>",
+                "<svg><text><![CDATA[a]]></text></svg>",
+            ),
+        ];
+        for (svg, expected) in cases {
+            let doc = parse_svg(svg).unwrap();
+            assert_eq!(serialize(&doc, &options), expected);
+        }
+
+        let kept = Options {
+            convert_cdata: false,
+            ..Options::default()
+        };
+        let svg = "<svg><style><![CDATA[rect{fill:red}]]></style></svg>";
+        assert_eq!(serialize(&parse_svg(svg).unwrap(), &kept), svg);
+    }
+
+    #[test]
+    fn test_framing() {
+        let doc =
+            parse_svg(r#"<?xml version="1.0"?><!-- old --><svg><!-- c --><g/></svg>"#).unwrap();
+        let options = Options {
+            remove_xml_declaration: false,
+            final_newline: true,
+            banner: Some(" Generated by svag -- do not edit -".to_string()),
+            ..Options::default()
+        };
+        let out = serialize(&doc, &options);
+        assert_eq!(
+            out,
+            "<?xml version=\"1.0\"?><!-- Generated by svag - - do not edit - --><svg><g/></svg>\n"
+        );
+        // The banner replaces itself when minified again
+        assert_eq!(serialize(&parse_svg(&out).unwrap(), &options), out);
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        let svg = r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"><text a="&quot;x&quot;">R&amp;D &lt;3</text></svg>"#;
+        let doc = parse_svg(svg).unwrap();
+        let options = Options {
+            remove_xml_declaration: false,
+            ..Options::default()
+        };
+
+        let mut buf = Vec::new();
+        serialize_to(&doc, &options, &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), serialize(&doc, &options));
+
+        let mut full = [0u8; 8];
+        let err = serialize_to(&doc, &options, &mut full[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    }
 }
@@ -0,0 +1,155 @@
+//! Sprite sheets: many icons in one file, as `<symbol>` elements.
+//!
+//! Each icon becomes a `<symbol>` named after it, which pages show with
+//! `<use href="sprite.svg#name"/>`. The root's presentation attributes move
+//! to the symbol and its size becomes the symbol's `viewBox`. Ids inside an
+//! icon are prefixed with the icon's name, so two icons can both have a
+//! `#clip` without one using the other's. Stylesheets are copied as they
+//! are: their rules apply to the whole sheet, and ids they mention aren't
+//! renamed.
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+use crate::bounds::length;
+use crate::index::rename_references;
+use crate::path::format_number;
+
+pub(crate) const SVG_NS: &str = "http://www.w3.org/2000/svg";
+
+/// Root attributes that only mean something on an `<svg>`, or that the
+/// symbol gets in another form.
+const SVG_ONLY: &[&str] = &[
+    "id",
+    "x",
+    "y",
+    "width",
+    "height",
+    "viewBox",
+    "version",
+    "baseProfile",
+    "zoomAndPan",
+    "contentScriptType",
+    "contentStyleType",
+];
+
+/// Combine `icons`, in order, into a sprite sheet with one `<symbol id="name">`
+/// per icon. Names should be unique and valid ids.
+///
+/// ```
+/// let arrow = svag::parse_svg(r#"<svg width="24" height="24" fill="none"><path d="M0 0h24"/></svg>"#).unwrap();
+/// let sprite = svag::build_sprite(vec![("arrow".to_string(), arrow)]);
+/// assert_eq!(
+///     svag::serialize(&sprite, &svag::Options::default()),
+///     r#"<svg xmlns="http://www.w3.org/2000/svg"><symbol fill="none" id="arrow" viewBox="0 0 24 24"><path d="M0 0h24"/></symbol></svg>"#
+/// );
+/// ```
+pub fn build_sprite<'a>(icons: Vec<(String, Document<'a>)>) -> Document<'a> {
+    let mut root = Element::new("svg");
+    root.set_attr("xmlns", SVG_NS);
+
+    for (name, doc) in icons {
+        let mut svg = doc.root;
+        prefix_ids(&mut svg, &name);
+
+        let mut symbol = Element::new("symbol");
+        symbol.set_attr("id", name);
+        if let Some(view_box) = view_box(&svg) {
+            symbol.set_attr("viewBox", view_box);
+        }
+        for attr in svg.attributes {
+            if attr.name.is_xmlns() {
+                // Declared once on the root, unless the prefix is taken
+                let declared = root.attributes.iter().find(|a| a.name == attr.name);
+                match declared {
+                    Some(existing) if existing.value != attr.value => symbol.attributes.push(attr),
+                    Some(_) => {}
+                    None => root.attributes.push(attr),
+                }
+            } else if attr.name.prefix.is_some() || !SVG_ONLY.contains(&attr.name.local.as_str()) {
+                symbol.attributes.push(attr);
+            }
+        }
+        symbol.children = svg.children;
+        root.append_child(symbol);
+    }
+
+    Document {
+        xml_declaration: None,
+        doctype: None,
+        prolog: Vec::new(),
+        root,
+    }
+}
+
+/// The root's `viewBox`, or one spanning its `width` and `height`.
+fn view_box(svg: &Element) -> Option<String> {
+    if let Some(view_box) = svg.get_attr("viewBox") {
+        return Some(view_box.to_string());
+    }
+    let width = length(svg.get_attr("width")?)?;
+    let height = length(svg.get_attr("height")?)?;
+    Some(format!(
+        "0 0 {} {}",
+        format_number(width, 6),
+        format_number(height, 6)
+    ))
+}
+
+/// Prefix every id below `svg` with `name-`, and the references to them.
+fn prefix_ids(svg: &mut Element, name: &str) {
+    fn collect(elem: &Element, name: &str, renames: &mut HashMap<String, String>) {
+        if let Some(id) = elem.get_attr("id") {
+            renames.insert(id.to_string(), format!("{}-{}", name, id));
+        }
+        for child in elem.child_elements() {
+            collect(child, name, renames);
+        }
+    }
+    fn rename(elem: &mut Element, renames: &HashMap<String, String>) {
+        if let Some(new) = elem.get_attr("id").and_then(|id| renames.get(id)) {
+            let new = new.clone();
+            elem.set_attr("id", new);
+        }
+        rename_references(elem, renames);
+        for child in elem.child_elements_mut() {
+            rename(child, renames);
+        }
+    }
+
+    let mut renames = HashMap::new();
+    for child in svg.child_elements() {
+        collect(child, name, &mut renames);
+    }
+    if !renames.is_empty() {
+        rename(svg, &renames);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    #[test]
+    fn test_build_sprite() {
+        let icons = [
+            (
+                "a",
+                r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 16 16" id="root"><clipPath id="c"><rect width="8" height="8"/></clipPath><use xlink:href="#p" clip-path="url(#c)"/><path id="p" d="M0 0h9"/></svg>"##,
+            ),
+            (
+                "b",
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="10px" height="20" stroke="red"><g id="c"/><use href="#c"/></svg>"##,
+            ),
+        ]
+        .into_iter()
+        .map(|(name, svg)| (name.to_string(), parse_svg(svg).unwrap()))
+        .collect();
+
+        assert_eq!(
+            serialize(&build_sprite(icons), &Options::default()),
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><symbol id="a" viewBox="0 0 16 16"><clipPath id="a-c"><rect height="8" width="8"/></clipPath><use clip-path="url(#a-c)" xlink:href="#a-p"/><path d="M0 0h9" id="a-p"/></symbol><symbol id="b" stroke="red" viewBox="0 0 10 20"><g id="b-c"/><use href="#b-c"/></symbol></svg>"##
+        );
+    }
+}
@@ -0,0 +1,641 @@
+//! Minification of CSS declarations, in `style` attributes and in the rules
+//! of `<style>` elements.
+//!
+//! Declarations a later one overrides are dropped, `font-*` longhands are
+//! merged into the `font` shorthand when it says the same thing, zero
+//! lengths lose their unit, and separators and whitespace are cut down.
+//! Selectors, at-rule preludes, and everything outside declaration blocks
+//! are left as they are.
+
+/// Units a zero length can drop. Not `%`, angles, or times, where a bare
+/// `0` is invalid in places.
+const LENGTH_UNITS: &[&str] = &[
+    "px", "em", "ex", "pt", "pc", "cm", "mm", "in", "q", "rem", "ch", "vw", "vh", "vmin", "vmax",
+];
+
+/// Functions SVG 1.1 renderers understand. A duplicate declaration using
+/// any other (`var()`, `color()`, ...) may be a progressive enhancement
+/// over the one before it, which is kept as the fallback.
+const BASIC_FUNCTIONS: &[&str] = &["url", "rgb", "rgba", "hsl", "hsla"];
+
+/// At-rules whose blocks hold rules rather than declarations.
+const GROUPING_RULES: &[&str] = &[
+    "@media",
+    "@supports",
+    "@document",
+    "@-moz-document",
+    "@layer",
+    "@container",
+    "@scope",
+    "@starting-style",
+    "@keyframes",
+    "@-webkit-keyframes",
+    "@-moz-keyframes",
+];
+
+/// The longhands the `font` shorthand sets, in its order.
+const FONT_LONGHANDS: &[&str] = &[
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "font-stretch",
+    "font-size",
+    "line-height",
+    "font-family",
+];
+
+/// `font-stretch` values the `font` shorthand accepts.
+const FONT_STRETCHES: &[&str] = &[
+    "normal",
+    "ultra-condensed",
+    "extra-condensed",
+    "condensed",
+    "semi-condensed",
+    "semi-expanded",
+    "expanded",
+    "extra-expanded",
+    "ultra-expanded",
+];
+
+const CSS_WIDE_KEYWORDS: &[&str] = &["inherit", "initial", "unset", "revert", "revert-layer"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Declaration {
+    name: String,
+    value: String,
+    important: bool,
+}
+
+impl Declaration {
+    fn parse(decl: &str) -> Option<Declaration> {
+        let (name, value) = decl.split_once(':')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut value = value.trim();
+        let mut important = false;
+        if let Some(bang) = value.rfind('!')
+            && value[bang + 1..].trim().eq_ignore_ascii_case("important")
+        {
+            value = value[..bang].trim_end();
+            important = true;
+        }
+        Some(Declaration {
+            name: name.to_string(),
+            value: normalize_value(name, value),
+            important,
+        })
+    }
+
+    /// The property, in the case it's matched in.
+    fn property(&self) -> String {
+        if self.name.starts_with("--") {
+            self.name.clone()
+        } else {
+            self.name.to_ascii_lowercase()
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str(&self.name);
+        out.push(':');
+        out.push_str(&self.value);
+        if self.important {
+            out.push_str("!important");
+        }
+    }
+}
+
+/// Minify a declaration block (the content of a `style` attribute, or of a
+/// rule's braces), dropping the declarations `keep` rejects.
+pub(crate) fn minify_declarations(block: &str, keep: impl Fn(&str, &str) -> bool) -> String {
+    let block = strip_comments(block);
+    // Unparseable parts are kept as they are, in place
+    let mut items: Vec<Result<Declaration, &str>> = split_top_level(&block, ';')
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| Declaration::parse(part).ok_or(part))
+        .filter(|item| match item {
+            Ok(decl) => keep(&decl.property(), &decl.value),
+            Err(_) => true,
+        })
+        .collect();
+
+    remove_overridden(&mut items);
+    merge_font(&mut items);
+
+    let mut out = String::with_capacity(block.len());
+    for item in &items {
+        if !out.is_empty() {
+            out.push(';');
+        }
+        match item {
+            Ok(decl) => decl.write(&mut out),
+            Err(raw) => out.push_str(raw),
+        }
+    }
+    out
+}
+
+/// Minify the declaration blocks of every rule in a stylesheet.
+pub(crate) fn minify_stylesheet(css: &str) -> String {
+    map_stylesheet(css, &mut |block| minify_declarations(block, |_, _| true))
+}
+
+/// Rewrite the declaration blocks of every rule in a stylesheet with `f`,
+/// leaving selectors, at-rule preludes, and nested rules as they are.
+pub(crate) fn map_stylesheet(css: &str, f: &mut dyn FnMut(&str) -> String) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(open) = find_top_level(rest, &['{', ';']) {
+        let (prelude, after) = rest.split_at(open);
+        out.push_str(prelude);
+        if let Some(after) = after.strip_prefix(';') {
+            // An at-rule statement, like `@import`
+            out.push(';');
+            rest = after;
+            continue;
+        }
+        let Some(close) = matching_brace(after) else {
+            break;
+        };
+        let body = &after[1..close];
+        let at_rule = prelude.split_whitespace().next().unwrap_or("");
+        let body = if GROUPING_RULES
+            .iter()
+            .any(|rule| rule.eq_ignore_ascii_case(at_rule))
+        {
+            map_stylesheet(body, f)
+        } else if find_top_level(body, &['{']).is_some() {
+            // Nested rules; left alone
+            body.to_string()
+        } else {
+            f(body)
+        };
+        out.push('{');
+        out.push_str(&body);
+        out.push('}');
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrite the values of a declaration block's declarations with `f`,
+/// given the property (lowercased, except custom properties) and the value
+/// without `!important`. Declarations `f` returns `None` for, and anything
+/// unparseable, are kept as they are.
+pub(crate) fn map_declarations(
+    block: &str,
+    mut f: impl FnMut(&str, &str) -> Option<String>,
+) -> String {
+    split_top_level(block, ';')
+        .into_iter()
+        .map(|part| {
+            let Some(decl) = Declaration::parse(part) else {
+                return part.to_string();
+            };
+            let Some(value) = f(&decl.property(), &decl.value) else {
+                return part.to_string();
+            };
+            let mut out = String::new();
+            Declaration { value, ..decl }.write(&mut out);
+            out
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Remove declarations that another one in the same block overrides: an
+/// earlier one of the same property and importance, or a normal one after
+/// an important one.
+fn remove_overridden(items: &mut Vec<Result<Declaration, &str>>) {
+    let overridden: Vec<bool> = (0..items.len())
+        .map(|i| {
+            let Ok(decl) = &items[i] else {
+                return false;
+            };
+            let property = decl.property();
+            let later = items[i + 1..].iter().filter_map(|item| item.as_ref().ok());
+            let earlier = items[..i].iter().filter_map(|item| item.as_ref().ok());
+            let same = |other: &&Declaration| other.property() == property;
+            later
+                .filter(same)
+                .any(|later| later.important >= decl.important && is_basic(&later.value))
+                || earlier
+                    .filter(same)
+                    .any(|earlier| earlier.important && !decl.important)
+        })
+        .collect();
+    let mut i = 0;
+    items.retain(|_| {
+        let keep = !overridden[i];
+        i += 1;
+        keep
+    });
+}
+
+/// Whether `value` uses no function beyond what every renderer knows, so
+/// that it can't be a progressive enhancement.
+fn is_basic(value: &str) -> bool {
+    let mut start = None;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => {
+                let name = &value[start.unwrap_or(i)..i];
+                if !BASIC_FUNCTIONS.iter().any(|f| f.eq_ignore_ascii_case(name)) {
+                    return false;
+                }
+                start = None;
+            }
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' => {
+                start.get_or_insert(i);
+            }
+            _ => start = None,
+        }
+    }
+    true
+}
+
+/// Replace `font-*` longhands with the `font` shorthand where it sets the
+/// same values and is shorter.
+fn merge_font(items: &mut Vec<Result<Declaration, &str>>) {
+    let decls: Vec<&Declaration> = items.iter().filter_map(|item| item.as_ref().ok()).collect();
+    let value = |name: &str| {
+        decls
+            .iter()
+            .find(|d| d.property() == name)
+            .map(|d| d.value.as_str())
+    };
+    let (Some(size), Some(family)) = (value("font-size"), value("font-family")) else {
+        return;
+    };
+
+    // The shorthand resets every other font property, so none may be set
+    // alongside; and it can't express importance or keywords for single
+    // longhands. A line height is never folded in: resvg drops text whose
+    // `font` has one
+    let longhands: Vec<&&Declaration> = decls
+        .iter()
+        .filter(|d| FONT_LONGHANDS.contains(&d.property().as_str()))
+        .collect();
+    let mergeable = decls.iter().all(|d| {
+        let property = d.property();
+        !property.starts_with("font") || FONT_LONGHANDS.contains(&property.as_str())
+    }) && longhands.iter().all(|d| {
+        !d.important
+            && !CSS_WIDE_KEYWORDS
+                .iter()
+                .any(|k| d.value.eq_ignore_ascii_case(k))
+            && (d.property() == "font-family" || is_single_token(&d.value))
+            && !d.value.contains("var(")
+    }) && value("font-variant").is_none_or(|v| v == "normal" || v == "small-caps")
+        && value("font-stretch").is_none_or(|v| FONT_STRETCHES.contains(&v))
+        && value("line-height").is_none()
+        && is_length_or_percentage(size);
+    if !mergeable {
+        return;
+    }
+
+    let mut parts: Vec<&str> = ["font-style", "font-variant", "font-weight", "font-stretch"]
+        .iter()
+        .filter_map(|name| value(name))
+        .filter(|v| *v != "normal")
+        .collect();
+    parts.push(size);
+    parts.push(family);
+    let font = Declaration {
+        name: "font".to_string(),
+        value: parts.join(" "),
+        important: false,
+    };
+
+    let longhands_len: usize = longhands
+        .iter()
+        .map(|d| d.name.len() + d.value.len() + 2)
+        .sum();
+    if font.name.len() + font.value.len() + 2 >= longhands_len {
+        return;
+    }
+    let first = items
+        .iter()
+        .position(|item| {
+            item.as_ref()
+                .is_ok_and(|d| FONT_LONGHANDS.contains(&d.property().as_str()))
+        })
+        .expect("font-size is a longhand");
+    items[first] = Ok(font);
+    let mut i = 0;
+    items.retain(|item| {
+        let keep = i <= first
+            || !item
+                .as_ref()
+                .is_ok_and(|d| FONT_LONGHANDS.contains(&d.property().as_str()));
+        i += 1;
+        keep
+    });
+}
+
+/// Whether `value` is a number with a length unit or `%`. A unitless size
+/// is accepted as a presentation attribute but makes `font` invalid.
+fn is_length_or_percentage(value: &str) -> bool {
+    let number = value.strip_suffix('%').or_else(|| {
+        LENGTH_UNITS.iter().find_map(|unit| {
+            let split = value.len().checked_sub(unit.len())?;
+            let (number, suffix) = value.split_at_checked(split)?;
+            suffix.eq_ignore_ascii_case(unit).then_some(number)
+        })
+    });
+    number.is_some_and(|n| n.parse::<f64>().is_ok_and(f64::is_finite))
+}
+
+fn is_single_token(value: &str) -> bool {
+    !value.contains([' ', ',', '/', '('])
+}
+
+/// Collapse whitespace outside strings and `url()`s, drop it around
+/// commas, and drop the unit of zero lengths.
+fn normalize_value(name: &str, value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let end = string_end(value, i);
+                out.push_str(&value[i..end]);
+                while chars.peek().is_some_and(|(j, _)| *j < end) {
+                    chars.next();
+                }
+            }
+            '(' if out.to_ascii_lowercase().ends_with("url") => {
+                let end = value[i..].find(')').map_or(value.len(), |e| i + e + 1);
+                out.push_str(&value[i..end]);
+                while chars.peek().is_some_and(|(j, _)| *j < end) {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                    chars.next();
+                }
+                let next_is_comma = chars.peek().is_some_and(|(_, c)| *c == ',');
+                if !out.is_empty() && !out.ends_with(',') && !next_is_comma {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    // Math functions and custom properties need units on their zeros
+    let lower = out.to_ascii_lowercase();
+    if name.starts_with("--")
+        || ["calc(", "min(", "max(", "clamp("]
+            .iter()
+            .any(|f| lower.contains(f))
+    {
+        return out;
+    }
+    strip_zero_units(&out)
+}
+
+fn strip_zero_units(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while !rest.is_empty() {
+        // Strings and URLs were already copied verbatim by the caller;
+        // tokens are split on the separators around numbers
+        let end = rest
+            .find([' ', ',', '(', ')', '/', '"', '\''])
+            .unwrap_or(rest.len());
+        let token = &rest[..end];
+        if is_zero_length(token) {
+            out.push('0');
+        } else {
+            out.push_str(token);
+        }
+        if end == rest.len() {
+            break;
+        }
+        let c = rest[end..].chars().next().unwrap();
+        if c == '"' || c == '\'' {
+            let close = string_end(rest, end);
+            out.push_str(&rest[end..close]);
+            rest = &rest[close..];
+        } else if c == '(' && token.eq_ignore_ascii_case("url") {
+            let close = rest[end..].find(')').map_or(rest.len(), |e| end + e + 1);
+            out.push_str(&rest[end..close]);
+            rest = &rest[close..];
+        } else {
+            out.push(c);
+            rest = &rest[end + 1..];
+        }
+    }
+    out
+}
+
+fn is_zero_length(token: &str) -> bool {
+    let digits = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &token[digits.len()..];
+    !unit.is_empty()
+        && LENGTH_UNITS.iter().any(|u| u.eq_ignore_ascii_case(unit))
+        && !digits.is_empty()
+        && digits.chars().any(|c| c == '0')
+        && digits.chars().all(|c| c == '0' || c == '.')
+        && digits.matches('.').count() <= 1
+}
+
+/// The end of the string starting with the quote at `start`, past the
+/// closing quote.
+fn string_end(s: &str, start: usize) -> usize {
+    let quote = s[start..].chars().next().unwrap();
+    let mut escaped = false;
+    for (i, c) in s[start + 1..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return start + 1 + i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Remove `/* ... */` comments outside strings.
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut i = 0;
+    while i < css.len() {
+        let rest = &css[i..];
+        if let Some(comment) = rest.strip_prefix("/*") {
+            i += comment.find("*/").map_or(rest.len(), |end| end + 4);
+        } else if rest.starts_with(['"', '\'']) {
+            let end = string_end(css, i);
+            out.push_str(&css[i..end]);
+            i = end;
+        } else {
+            let c = rest.chars().next().unwrap();
+            out.push(c);
+            i += c.len_utf8();
+        }
+    }
+    out
+}
+
+/// Split on `sep` outside strings, parentheses, and escapes.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut rest = s;
+    while let Some(end) = find_top_level(rest, &[sep]) {
+        let at = s.len() - rest.len() + end;
+        parts.push(&s[start..at]);
+        start = at + sep.len_utf8();
+        rest = &s[start..];
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// The first of `targets` outside strings, comments, parentheses, and
+/// escapes.
+fn find_top_level(s: &str, targets: &[char]) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        let c = rest.chars().next().unwrap();
+        match c {
+            '\\' => {
+                i += c.len_utf8();
+                i += rest[1..].chars().next().map_or(0, char::len_utf8);
+                continue;
+            }
+            '"' | '\'' => {
+                i = string_end(s, i);
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                i += rest[2..].find("*/").map_or(rest.len(), |end| end + 4);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 && targets.contains(&c) => return Some(i),
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+/// The index of the `}` closing the block `s` starts with.
+fn matching_brace(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut offset = 0;
+    while let Some(i) = find_top_level(&s[offset..], &['{', '}']) {
+        let at = offset + i;
+        if s[at..].starts_with('{') {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return Some(at);
+            }
+        }
+        offset = at + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minified(block: &str) -> String {
+        minify_declarations(block, |_, _| true)
+    }
+
+    #[test]
+    fn test_minify_declarations() {
+        assert_eq!(
+            minified(" fill : red ; /* x */ stroke-width:0px;;fill:blue;margin:0.0em 1px 0% ; "),
+            "stroke-width:0;fill:blue;margin:0 1px 0%"
+        );
+        assert_eq!(
+            minified(r#"font-family: "A  B" , serif;background:url(data:image/png;base64,AA== ) "#),
+            r#"font-family:"A  B",serif;background:url(data:image/png;base64,AA== )"#
+        );
+        // Importance wins over order
+        assert_eq!(
+            minified("fill:red!important;fill:blue;stroke:red;stroke:blue ! important"),
+            "fill:red!important;stroke:blue!important"
+        );
+        // Fallbacks for newer syntax stay
+        assert_eq!(
+            minified("fill:red;fill:var(--c);fill:color(display-p3 1 0 0)"),
+            "fill:red;fill:var(--c);fill:color(display-p3 1 0 0)"
+        );
+        // Zeros keep units where they'd be invalid without
+        assert_eq!(
+            minified("--gap:0px;width:calc(0px + 1em);transition-delay:0s"),
+            "--gap:0px;width:calc(0px + 1em);transition-delay:0s"
+        );
+    }
+
+    #[test]
+    fn test_merge_font() {
+        assert_eq!(
+            minified(
+                "font-style:italic;font-weight:bold;fill:red;font-size:12px;font-family:Arial, sans-serif"
+            ),
+            "font:italic bold 12px Arial,sans-serif;fill:red"
+        );
+        assert_eq!(
+            minified("font-size:150%;font-family:Arial"),
+            "font:150% Arial"
+        );
+        for block in [
+            // No family
+            "font-style:italic;font-size:12px",
+            // Would reset the kerning
+            "font-size:12px;font-family:A;font-kerning:none",
+            "font-size:12px!important;font-family:A",
+            "font-size:inherit;font-family:A",
+            "font-size:12px;font-family:A;font-variant:all-small-caps",
+            "font-size:12px;font-family:A;font-stretch:50%",
+            // resvg drops text with a line height in the shorthand
+            "font-size:12px;font-family:A;line-height:1.2",
+            // Invalid in the shorthand without a unit
+            "font-size:18.000000;font-family:A",
+            "font-size:large;font-family:A",
+        ] {
+            assert_eq!(minified(block), block);
+        }
+    }
+
+    #[test]
+    fn test_minify_stylesheet() {
+        let css = r#"
+            @import url(a.css);
+            .a { fill : red; fill: blue; }
+            @media (min-width: 10px) { .b{stroke-width:0px;} }
+            @font-face { font-family: "X"; src: url(x.woff) }
+            .c::after { content: "a;b}" }
+            .d { & .e { fill: red } }
+        "#;
+        assert_eq!(
+            minify_stylesheet(css),
+            r#"
+            @import url(a.css);
+            .a {fill:blue}
+            @media (min-width: 10px) { .b{stroke-width:0} }
+            @font-face {font-family:"X";src:url(x.woff)}
+            .c::after {content:"a;b}"}
+            .d { & .e { fill: red } }
+        "#
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! CSS mode: minify SVG `data:` URIs embedded in stylesheets.
+//!
+//! Icon-heavy stylesheets embed SVGs as `url("data:image/svg+xml,...")`.
+//! Each one is decoded, minified and re-encoded with whichever of
+//! percent-encoding or base64 is shorter; the rest of the CSS is untouched.
+
+use base64::Engine;
+
+use crate::css::css_urls;
+use crate::data_uri::parse_data_uri;
+use crate::{Options, minify_with_options};
+
+/// Minify every SVG `data:` URI inside a CSS stylesheet.
+///
+/// URIs that aren't valid UTF-8 SVG, or that fail to minify, are left as they
+/// were.
+pub fn minify_css_data_uris(css: &str, options: &Options) -> String {
+    let options = &options.without_framing();
+    let mut out = css.to_string();
+
+    for url in css_urls(css).into_iter().rev() {
+        // Escaped URLs would need CSS unescaping first; leave them alone
+        if url.url.contains('\\') {
+            continue;
+        }
+        let Some(data) = parse_data_uri(&url.url) else {
+            continue;
+        };
+        if !data.mime.eq_ignore_ascii_case("image/svg+xml") {
+            continue;
+        }
+        let Some(svg) = data
+            .decode()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+        else {
+            continue;
+        };
+        let Ok(minified) = minify_with_options(&svg, options) else {
+            continue;
+        };
+
+        let encoded = encode_svg_data_uri(&minified);
+        if encoded.len() < url.url.len() {
+            out.replace_range(url.token, &format!("url(\"{}\")", encoded));
+        }
+    }
+
+    out
+}
+
+/// Encode an SVG as the shortest `data:` URI that is safe inside a
+/// double-quoted CSS `url()`.
+pub(crate) fn encode_svg_data_uri(svg: &str) -> String {
+    // Attribute quotes are swapped to single quotes so they don't need
+    // escaping, unless that would change text content or attribute values
+    let svg = if svg.contains('\'') || quotes_in_text(svg) {
+        svg.to_string()
+    } else {
+        svg.replace('"', "'")
+    };
+
+    let mut percent = String::from("data:image/svg+xml,");
+    for &b in svg.as_bytes() {
+        match b {
+            b'%' | b'#' | b'<' | b'>' | b'"' | b'\\' | 0..=0x1f | 0x7f.. => {
+                percent.push_str(&format!("%{:02x}", b));
+            }
+            _ => percent.push(b as char),
+        }
+    }
+
+    let base64 = format!(
+        "data:image/svg+xml;base64,{}",
+        base64::prelude::BASE64_STANDARD.encode(svg.as_bytes())
+    );
+
+    if base64.len() < percent.len() {
+        base64
+    } else {
+        percent
+    }
+}
+
+/// Whether a `"` appears outside of a tag.
+fn quotes_in_text(svg: &str) -> bool {
+    let mut in_tag = false;
+    for c in svg.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            '"' if !in_tag => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_css_data_uris() {
+        let css = r#".icon { background: url("data:image/svg+xml,%3Csvg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 16 16%22%3E%3C!-- icon --%3E%3Cpath fill=%22%23ff0000%22 d=%22M 0.000 0.000 L 16.000 16.000%22/%3E%3C/svg%3E") no-repeat; }
+.photo { background: url(data:image/png;base64,aGk=); }"#;
+        let result = minify_css_data_uris(css, &Options::default());
+
+        assert!(result.starts_with(".icon { background: url(\"data:image/svg+xml,%3csvg "));
+        assert!(result.contains("fill='red'"));
+        assert!(!result.contains("icon --"));
+        assert!(result.contains("\") no-repeat; }"));
+        assert!(result.ends_with(".photo { background: url(data:image/png;base64,aGk=); }"));
+    }
+
+    #[test]
+    fn test_encode_svg_data_uri() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg"><path fill="#fff"/></svg>"##;
+        assert_eq!(
+            encode_svg_data_uri(svg),
+            "data:image/svg+xml,%3csvg xmlns='http://www.w3.org/2000/svg'%3e%3cpath fill='%23fff'/%3e%3c/svg%3e"
+        );
+
+        // A literal single quote forces double quotes to be escaped instead
+        let svg = r#"<svg><text font-family="'A'">x</text></svg>"#;
+        assert!(encode_svg_data_uri(svg).contains("%22'A'%22"));
+
+        // Quotes in text content must survive as-is
+        let svg = r#"<svg><text x="1">"hi"</text></svg>"#;
+        assert!(quotes_in_text(svg));
+        let uri = encode_svg_data_uri(svg);
+        assert_eq!(
+            parse_data_uri(&uri).unwrap().decode().unwrap(),
+            svg.as_bytes()
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Inline external SVG images, for self-contained files.
+//!
+//! `<image href="icon.svg">` targets are loaded through a resolver,
+//! minified, and written back as `data:` URIs. An image keeps rendering the
+//! same that way: it's still an isolated document, with its own viewport,
+//! stylesheets, and ids. Pasting it in as a nested `<svg>` would let the
+//! two documents' CSS and ids collide.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::*;
+use crate::stylesheet::encode_svg_data_uri;
+use crate::{Options, minify_with_options};
+
+/// Loads the SVG an `<image>` refers to, given its `href`. Returning `None`
+/// leaves the reference as it is, so the resolver decides what may be read
+/// (e.g. only local files next to the document).
+///
+/// ```
+/// let options = svag::Options {
+///     image_resolver: Some(svag::ImageResolver::new(|href| {
+///         (href == "dot.svg").then(|| r#"<svg xmlns="http://www.w3.org/2000/svg"><circle r="1"/></svg>"#.to_string())
+///     })),
+///     ..svag::Options::default()
+/// };
+/// let svg = r#"<svg><image href="dot.svg" width="2" height="2"/></svg>"#;
+/// let out = svag::minify_with_options(svg, &options).unwrap();
+/// assert!(out.contains(r#"href="data:image/svg+xml,%3csvg"#));
+/// ```
+#[derive(Clone)]
+pub struct ImageResolver(Arc<ResolveFn>);
+
+type ResolveFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl ImageResolver {
+    pub fn new(resolve: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        ImageResolver(Arc::new(resolve))
+    }
+
+    pub fn resolve(&self, href: &str) -> Option<String> {
+        (self.0)(href)
+    }
+}
+
+impl fmt::Debug for ImageResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ImageResolver")
+    }
+}
+
+/// Replace `<image>` references `resolver` can load with minified `data:`
+/// URIs. Images inside the loaded SVGs are left as they are.
+pub(crate) fn inline_svg_images(root: &mut Element, resolver: &ImageResolver, options: &Options) {
+    let nested = Options {
+        image_resolver: None,
+        stylesheet_resolver: None,
+        ..options.without_framing()
+    };
+    let mut cache: Vec<(String, Option<String>)> = Vec::new();
+    visit(root, &mut |href| {
+        if let Some((_, uri)) = cache.iter().find(|(h, _)| h == href) {
+            return uri.clone();
+        }
+        let uri = resolver
+            .resolve(href)
+            .and_then(|svg| minify_with_options(&svg, &nested).ok())
+            .map(|svg| encode_svg_data_uri(&svg));
+        cache.push((href.to_string(), uri.clone()));
+        uri
+    });
+}
+
+fn visit(elem: &mut Element, inline: &mut dyn FnMut(&str) -> Option<String>) {
+    if elem.name.prefix.is_none() && elem.name.local == "image" {
+        for attr in &mut elem.attributes {
+            if attr.name.local == "href"
+                && matches!(attr.name.prefix.as_deref(), None | Some("xlink"))
+                && !attr.value.trim_start().starts_with("data:")
+                && let Some(uri) = inline(attr.value.trim())
+            {
+                attr.value = uri.into();
+            }
+        }
+    }
+
+    for child in elem.child_elements_mut() {
+        visit(child, inline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_svg, serialize};
+
+    #[test]
+    fn test_inline_svg_images() {
+        let resolver = ImageResolver::new(|href| {
+            match href {
+            "a.svg" => Some(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\">\n  <rect width=\"10\" height=\"10\"/>\n  <image href=\"b.svg\"/>\n</svg>\n"
+                    .to_string(),
+            ),
+            "broken.svg" => Some("<svg>".to_string()),
+            _ => None,
+        }
+        });
+        let svg = r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><image href="a.svg"/><image xlink:href="a.svg"/><image href="broken.svg"/><image href="photo.png"/><use href="a.svg"/></svg>"#;
+        let mut doc = parse_svg(svg).unwrap();
+        let options = Options::default();
+        inline_svg_images(&mut doc.root, &resolver, &options);
+
+        let uri = "data:image/svg+xml,%3csvg xmlns='http://www.w3.org/2000/svg'%3e%3crect height='10' width='10'/%3e%3cimage href='b.svg'/%3e%3c/svg%3e";
+        assert_eq!(
+            serialize(&doc, &options),
+            format!(
+                r#"<svg xmlns:xlink="http://www.w3.org/1999/xlink"><image href="{uri}"/><image xlink:href="{uri}"/><image href="broken.svg"/><image href="photo.png"/><use href="a.svg"/></svg>"#
+            )
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//! SVG transform list parsing and minification.
+//!
+//! Transform syntax: <https://www.w3.org/TR/SVG11/coords.html#TransformAttribute>
+
+use crate::error::SvagError;
+use crate::path::{round, write_number};
+
+/// A parsed transform list, applied right to left to the element's
+/// coordinates (the first function is the outermost).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub functions: Vec<TransformFn>,
+}
+
+/// A single transform function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformFn {
+    /// `matrix(a b c d e f)`
+    Matrix([f64; 6]),
+    /// `translate(tx [ty])`
+    Translate { tx: f64, ty: f64 },
+    /// `scale(sx [sy])`
+    Scale { sx: f64, sy: f64 },
+    /// `rotate(angle [cx cy])`, in degrees
+    Rotate { angle: f64, cx: f64, cy: f64 },
+    /// `skewX(angle)`, in degrees
+    SkewX(f64),
+    /// `skewY(angle)`, in degrees
+    SkewY(f64),
+}
+
+/// Parse a `transform` (or `gradientTransform`/`patternTransform`) value.
+///
+/// Only the SVG 1.1 syntax is accepted: CSS-style units such as
+/// `rotate(45deg)` are an error, so callers leave those values alone.
+pub fn parse_transform(s: &str) -> Result<Transform, SvagError> {
+    let mut parser = TransformParser { input: s, pos: 0 };
+    parser.parse()
+}
+
+/// The shortest serialization of a transform list: adjacent functions of
+/// the same kind are merged, and the whole list is written as a single
+/// `matrix()` when that comes out shorter.
+///
+/// The result minifies to itself. Rounding moves the matrix a little, and
+/// from there a shorter form can match where it didn't before, so the
+/// output is minified again until nothing changes.
+pub fn minify_transform(transform: &Transform, precision: u8, transform_precision: u8) -> String {
+    // One extra round settles every case seen so far; the cap bounds the
+    // rounding error that could build up otherwise
+    const MAX_ROUNDS: usize = 4;
+
+    let mut best = minify_once(transform, precision, transform_precision);
+    for _ in 0..MAX_ROUNDS {
+        let Ok(reparsed) = parse_transform(&best) else {
+            break;
+        };
+        let next = minify_once(&reparsed, precision, transform_precision);
+        if next == best {
+            break;
+        }
+        best = next;
+    }
+    best
+}
+
+fn minify_once(transform: &Transform, precision: u8, transform_precision: u8) -> String {
+    let merged = serialize_transform(&transform.merged(), precision, transform_precision);
+    if transform.functions.len() < 2 {
+        return merged;
+    }
+    let composed = Transform {
+        functions: vec![TransformFn::Matrix(transform.to_matrix())],
+    };
+    let composed = serialize_transform(&composed, precision, transform_precision);
+    if composed.len() < merged.len() {
+        composed
+    } else {
+        merged
+    }
+}
+
+impl Transform {
+    /// The whole list as one `[a, b, c, d, e, f]` matrix.
+    pub fn to_matrix(&self) -> [f64; 6] {
+        self.functions
+            .iter()
+            .fold(IDENTITY, |m, function| multiply(&m, &function.to_matrix()))
+    }
+
+    /// Merge adjacent translations, scales, matrices, and rotations about
+    /// the same center. Other pairs don't combine into a single function
+    /// of the same kind.
+    pub fn merged(&self) -> Transform {
+        let mut functions: Vec<TransformFn> = Vec::with_capacity(self.functions.len());
+        for &function in &self.functions {
+            let merged = match (functions.last(), function) {
+                (
+                    Some(&TransformFn::Translate { tx: x1, ty: y1 }),
+                    TransformFn::Translate { tx: x2, ty: y2 },
+                ) => TransformFn::Translate {
+                    tx: x1 + x2,
+                    ty: y1 + y2,
+                },
+                (
+                    Some(&TransformFn::Scale { sx: x1, sy: y1 }),
+                    TransformFn::Scale { sx: x2, sy: y2 },
+                ) => TransformFn::Scale {
+                    sx: x1 * x2,
+                    sy: y1 * y2,
+                },
+                (
+                    Some(&TransformFn::Rotate { angle: a1, cx, cy }),
+                    TransformFn::Rotate {
+                        angle: a2,
+                        cx: cx2,
+                        cy: cy2,
+                    },
+                ) if (cx, cy) == (cx2, cy2) => TransformFn::Rotate {
+                    angle: a1 + a2,
+                    cx,
+                    cy,
+                },
+                (Some(TransformFn::Matrix(m1)), TransformFn::Matrix(m2)) => {
+                    TransformFn::Matrix(multiply(m1, &m2))
+                }
+                _ => {
+                    functions.push(function);
+                    continue;
+                }
+            };
+            *functions.last_mut().unwrap() = merged;
+        }
+        Transform { functions }
+    }
+}
+
+impl TransformFn {
+    /// This function as an `[a, b, c, d, e, f]` matrix.
+    pub fn to_matrix(&self) -> [f64; 6] {
+        match *self {
+            TransformFn::Matrix(m) => m,
+            TransformFn::Translate { tx, ty } => [1.0, 0.0, 0.0, 1.0, tx, ty],
+            TransformFn::Scale { sx, sy } => [sx, 0.0, 0.0, sy, 0.0, 0.0],
+            TransformFn::Rotate { angle, cx, cy } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // translate(cx cy) rotate(angle) translate(-cx -cy)
+                [
+                    cos,
+                    sin,
+                    -sin,
+                    cos,
+                    cx - cos * cx + sin * cy,
+                    cy - sin * cx - cos * cy,
+                ]
+            }
+            TransformFn::SkewX(angle) => [1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0],
+            TransformFn::SkewY(angle) => [1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+        }
+    }
+}
+
+const IDENTITY: [f64; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// `lhs × rhs`: applying the result is applying `rhs`, then `lhs`.
+fn multiply(lhs: &[f64; 6], rhs: &[f64; 6]) -> [f64; 6] {
+    let [a1, b1, c1, d1, e1, f1] = *lhs;
+    let [a2, b2, c2, d2, e2, f2] = *rhs;
+    [
+        a1 * a2 + c1 * b2,
+        b1 * a2 + d1 * b2,
+        a1 * c2 + c1 * d2,
+        b1 * c2 + d1 * d2,
+        a1 * e2 + c1 * f2 + e1,
+        b1 * e2 + d1 * f2 + f1,
+    ]
+}
+
+/// Serialize a transform list. Translations are rounded to `precision`
+/// like other coordinates; the scale, rotation, and skew components get
+/// `transform_precision`, since small errors there grow with distance from
+/// the origin. Functions that round to the identity are dropped, so the
+/// result may be empty.
+pub fn serialize_transform(
+    transform: &Transform,
+    precision: u8,
+    transform_precision: u8,
+) -> String {
+    let mut out = String::new();
+    for function in &transform.functions {
+        let start = out.len();
+        if start > 0 {
+            out.push(' ');
+        }
+        if !write_function(&mut out, function, precision, transform_precision) {
+            out.truncate(start);
+        }
+    }
+    out
+}
+
+/// Append one function, returning `false` when it's a no-op at this
+/// precision (and nothing should be written).
+fn write_function(
+    out: &mut String,
+    function: &TransformFn,
+    precision: u8,
+    transform_precision: u8,
+) -> bool {
+    match *function {
+        TransformFn::Matrix(m @ [a, b, c, d, e, f]) => {
+            let linear = [a, b, c, d].map(|n| round(n, transform_precision));
+            if linear == [1.0, 0.0, 0.0, 1.0] {
+                // Only a translation survives rounding
+                let translate = TransformFn::Translate { tx: e, ty: f };
+                return write_function(out, &translate, precision, transform_precision);
+            }
+
+            let mut matrix = String::from("matrix(");
+            write_args(&mut matrix, &linear, transform_precision);
+            matrix.push(' ');
+            write_args(&mut matrix, &[e, f], precision);
+            matrix.push(')');
+
+            let expected = round_matrix(&m, precision, transform_precision);
+            let shortest = decompositions(&m)
+                .into_iter()
+                .map(|functions| {
+                    serialize_transform(&Transform { functions }, precision, transform_precision)
+                })
+                .filter(|s| {
+                    parse_transform(s).is_ok_and(|t| {
+                        round_matrix(&t.to_matrix(), precision, transform_precision) == expected
+                    })
+                })
+                .fold(
+                    matrix,
+                    |best, s| if s.len() < best.len() { s } else { best },
+                );
+            out.push_str(&shortest);
+            return true;
+        }
+        TransformFn::Translate { tx, ty } => {
+            let (tx, ty) = (round(tx, precision), round(ty, precision));
+            if tx == 0.0 && ty == 0.0 {
+                return false;
+            }
+            out.push_str("translate(");
+            let args = [tx, ty];
+            write_args(out, if ty == 0.0 { &args[..1] } else { &args }, precision);
+        }
+        TransformFn::Scale { sx, sy } => {
+            let (sx, sy) = (
+                round(sx, transform_precision),
+                round(sy, transform_precision),
+            );
+            if sx == 1.0 && sy == 1.0 {
+                return false;
+            }
+            out.push_str("scale(");
+            let args = [sx, sy];
+            write_args(
+                out,
+                if sx == sy { &args[..1] } else { &args },
+                transform_precision,
+            );
+        }
+        TransformFn::Rotate { angle, cx, cy } => {
+            let angle = round(angle, transform_precision);
+            if angle % 360.0 == 0.0 {
+                return false;
+            }
+            out.push_str("rotate(");
+            write_args(out, &[angle], transform_precision);
+            let (cx, cy) = (round(cx, precision), round(cy, precision));
+            if cx != 0.0 || cy != 0.0 {
+                out.push(' ');
+                write_args(out, &[cx, cy], precision);
+            }
+        }
+        TransformFn::SkewX(angle) | TransformFn::SkewY(angle) => {
+            let angle = round(angle, transform_precision);
+            if angle % 180.0 == 0.0 {
+                return false;
+            }
+            out.push_str(if matches!(function, TransformFn::SkewX(_)) {
+                "skewX("
+            } else {
+                "skewY("
+            });
+            write_args(out, &[angle], transform_precision);
+        }
+    }
+    out.push(')');
+    true
+}
+
+/// Round a matrix the way [`serialize_transform`] writes it.
+fn round_matrix(m: &[f64; 6], precision: u8, transform_precision: u8) -> [f64; 6] {
+    // -0 and 0 serialize the same
+    let round = |n: f64, precision: u8| round(n, precision) + 0.0;
+    let [a, b, c, d, e, f] = *m;
+    [
+        round(a, transform_precision),
+        round(b, transform_precision),
+        round(c, transform_precision),
+        round(d, transform_precision),
+        round(e, precision),
+        round(f, precision),
+    ]
+}
+
+/// Primitive function lists that may equal `m`: a scale, a rotation
+/// (possibly about a point, possibly with a uniform scale), each behind an
+/// optional translation. Callers check which ones actually match once
+/// rounded.
+fn decompositions(m: &[f64; 6]) -> Vec<Vec<TransformFn>> {
+    let [a, b, _, d, e, f] = *m;
+    let translate = TransformFn::Translate { tx: e, ty: f };
+    let mut candidates = vec![vec![translate, TransformFn::Scale { sx: a, sy: d }]];
+
+    // A rotation by θ scaled by s is [s·cos θ, s·sin θ, -s·sin θ, s·cos θ]
+    let scale = a.hypot(b);
+    if scale > 0.0 {
+        let angle = b.atan2(a).to_degrees();
+        let rotate = TransformFn::Rotate {
+            angle,
+            cx: 0.0,
+            cy: 0.0,
+        };
+        let uniform = TransformFn::Scale {
+            sx: scale,
+            sy: scale,
+        };
+        candidates.push(vec![translate, rotate, uniform]);
+
+        // rotate(θ cx cy) moves the origin to (e, f); solve for the center
+        let (sin, cos) = (b / scale, a / scale);
+        let det = 2.0 - 2.0 * cos;
+        if det > 1e-9 {
+            let cx = ((1.0 - cos) * e - sin * f) / det;
+            let cy = (sin * e + (1.0 - cos) * f) / det;
+            candidates.push(vec![TransformFn::Rotate { angle, cx, cy }, uniform]);
+        }
+    }
+
+    candidates
+}
+
+/// Append space-separated numbers. SVG 1.1 requires a separator between
+/// transform arguments even where path data wouldn't.
+fn write_args(out: &mut String, args: &[f64], precision: u8) {
+    for (i, &arg) in args.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        write_number(out, arg, precision);
+    }
+}
+
+struct TransformParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl TransformParser<'_> {
+    fn parse(&mut self) -> Result<Transform, SvagError> {
+        let mut functions = Vec::new();
+
+        self.skip_separators();
+        while self.pos < self.input.len() {
+            functions.push(self.parse_function()?);
+            self.skip_separators();
+        }
+
+        Ok(Transform { functions })
+    }
+
+    fn parse_function(&mut self) -> Result<TransformFn, SvagError> {
+        let rest = &self.input[self.pos..];
+        let name_len = rest
+            .bytes()
+            .position(|b| !b.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let name = &rest[..name_len];
+        self.pos += name_len;
+
+        self.skip_whitespace();
+        if !self.input[self.pos..].starts_with('(') {
+            return Err(self.error("Expected '('"));
+        }
+        self.pos += 1;
+
+        let mut args = Vec::new();
+        loop {
+            self.skip_separators();
+            if self.input[self.pos..].starts_with(')') {
+                self.pos += 1;
+                break;
+            }
+            if args.len() == 6 {
+                return Err(self.error("Too many arguments"));
+            }
+            args.push(self.parse_number()?);
+        }
+
+        let function = match (name, &args[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => TransformFn::Matrix([a, b, c, d, e, f]),
+            ("translate", &[tx]) => TransformFn::Translate { tx, ty: 0.0 },
+            ("translate", &[tx, ty]) => TransformFn::Translate { tx, ty },
+            ("scale", &[s]) => TransformFn::Scale { sx: s, sy: s },
+            ("scale", &[sx, sy]) => TransformFn::Scale { sx, sy },
+            ("rotate", &[angle]) => TransformFn::Rotate {
+                angle,
+                cx: 0.0,
+                cy: 0.0,
+            },
+            ("rotate", &[angle, cx, cy]) => TransformFn::Rotate { angle, cx, cy },
+            ("skewX", &[angle]) => TransformFn::SkewX(angle),
+            ("skewY", &[angle]) => TransformFn::SkewY(angle),
+            _ => {
+                return Err(SvagError::InvalidTransform(format!(
+                    "{name}() with {} arguments",
+                    args.len()
+                )));
+            }
+        };
+        Ok(function)
+    }
+
+    fn parse_number(&mut self) -> Result<f64, SvagError> {
+        let bytes = self.input.as_bytes();
+        let start = self.pos;
+        let mut end = start;
+        if matches!(bytes.get(end), Some(b'+' | b'-')) {
+            end += 1;
+        }
+        while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            end += 1;
+        }
+        if bytes.get(end) == Some(&b'.') {
+            end += 1;
+            while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                end += 1;
+            }
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            let mut exp = end + 1;
+            if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+                exp += 1;
+            }
+            if bytes.get(exp).is_some_and(u8::is_ascii_digit) {
+                end = exp;
+                while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+                    end += 1;
+                }
+            }
+        }
+
+        let number = self.input[start..end]
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| self.error("Expected number"))?;
+        self.pos = end;
+        Ok(number)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn skip_separators(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r', ',']).len();
+    }
+
+    fn error(&self, message: &str) -> SvagError {
+        SvagError::InvalidTransform(format!("{message} at position {}", self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minify(s: &str) -> String {
+        serialize_transform(&parse_transform(s).unwrap(), 2, 5)
+    }
+
+    #[test]
+    fn test_parse_transform() {
+        let t = parse_transform("translate(10) ,rotate(45, 1 2)scale(2 -3e-1)").unwrap();
+        assert_eq!(
+            t.functions,
+            vec![
+                TransformFn::Translate { tx: 10.0, ty: 0.0 },
+                TransformFn::Rotate {
+                    angle: 45.0,
+                    cx: 1.0,
+                    cy: 2.0
+                },
+                TransformFn::Scale { sx: 2.0, sy: -0.3 },
+            ]
+        );
+
+        assert!(parse_transform("rotate(45deg)").is_err());
+        assert!(parse_transform("rotate(1 2)").is_err());
+        assert!(parse_transform("translate(1").is_err());
+        assert!(parse_transform("frobnicate(1)").is_err());
+    }
+
+    #[test]
+    fn test_merge_transforms() {
+        let minify = |s| minify_transform(&parse_transform(s).unwrap(), 2, 5);
+        assert_eq!(minify("translate(10) translate(5,3)"), "translate(15 3)");
+        assert_eq!(minify("scale(2) scale(.5)"), "");
+        assert_eq!(minify("rotate(10 5 5) rotate(20 5 5)"), "rotate(30 5 5)");
+        assert_eq!(
+            minify("translate(10 20) scale(2) translate(-5 -5)"),
+            "matrix(2 0 0 2 0 10)"
+        );
+        assert_eq!(minify("translate(1) rotate(33)"), "rotate(33 .5 1.69)");
+        // Not shorter as a matrix
+        assert_eq!(minify("translate(1) skewX(33)"), "translate(1) skewX(33)");
+    }
+
+    #[test]
+    fn test_decompose_matrix() {
+        let matrix = |s| {
+            let m = parse_transform(s).unwrap().to_matrix();
+            serialize_transform(
+                &Transform {
+                    functions: vec![TransformFn::Matrix(m)],
+                },
+                2,
+                5,
+            )
+        };
+        assert_eq!(matrix("rotate(45 50 50)"), "rotate(45 50 50)");
+        assert_eq!(matrix("rotate(-90)"), "rotate(-90)");
+        assert_eq!(matrix("scale(2 -1)"), "scale(2 -1)");
+        assert_eq!(
+            matrix("translate(3 4) scale(1.23456)"),
+            "translate(3 4) scale(1.23456)"
+        );
+        assert_eq!(
+            matrix("translate(3 4) scale(1.5)"),
+            "matrix(1.5 0 0 1.5 3 4)"
+        );
+        assert_eq!(matrix("rotate(30) scale(2)"), "rotate(30) scale(2)");
+        // Skews and mirrored rotations stay matrices
+        assert_eq!(matrix("skewX(30)"), "matrix(1 0 .57735 1 0 0)");
+        assert_eq!(
+            matrix("rotate(30) scale(-1 1)"),
+            "matrix(-.86603 -.5 -.5 .86603 0 0)"
+        );
+
+        // Illustrator writes rotations as rounded matrices
+        let t = parse_transform("matrix(0.7071 0.7071 -0.7071 0.7071 0 0)").unwrap();
+        assert_eq!(serialize_transform(&t, 2, 3), "rotate(45)");
+    }
+
+    #[test]
+    fn test_to_matrix() {
+        let m = parse_transform("rotate(90 10 0)").unwrap().to_matrix();
+        let expected = [0.0, 1.0, -1.0, 0.0, 10.0, -10.0];
+        assert!(
+            m.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-12),
+            "{m:?}"
+        );
+    }
+
+    #[test]
+    fn test_serialize_transform() {
+        assert_eq!(
+            minify("matrix(1.0000001,0,0,0.9999999,12.000001,0)"),
+            "translate(12)"
+        );
+        assert_eq!(minify("matrix(1 0 0 1 0.001 0)"), "");
+        assert_eq!(
+            minify("matrix(0.7071068,0.7071068,-0.7071068,0.7071068,0,0)"),
+            "rotate(45)"
+        );
+        assert_eq!(minify("translate(0 -5)"), "translate(0 -5)");
+        assert_eq!(minify("scale(2, 2) rotate(360) skewX(0)"), "scale(2)");
+        assert_eq!(minify("rotate(-90 0 0) skewY(10)"), "rotate(-90) skewY(10)");
+    }
+}
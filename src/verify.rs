@@ -0,0 +1,139 @@
+//! Visual verification with resvg, no browser required.
+//!
+//! Both SVGs are rasterized at the original's size, composited on white and
+//! compared with [`crate::visual::ssim`].
+
+use std::sync::{Arc, OnceLock};
+
+use resvg::{tiny_skia, usvg};
+
+use crate::SvagError;
+use crate::visual::{RgbImage, ssim};
+
+/// Largest side, in pixels, that documents are rendered at.
+const MAX_RENDER_SIZE: f32 = 1024.0;
+
+/// The outcome of [`verify_fidelity`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fidelity {
+    /// SSIM between the two renderings (1.0 means identical)
+    pub score: f64,
+    /// Whether the score met the threshold
+    pub passed: bool,
+}
+
+/// Render `original` and `minified` and check that they look the same.
+///
+/// `threshold` is the minimum SSIM score to pass; the visual test suite uses
+/// 0.999.
+pub fn verify_fidelity(
+    original: &str,
+    minified: &str,
+    threshold: f64,
+) -> Result<Fidelity, SvagError> {
+    let original = parse_tree(original)?;
+    let minified = parse_tree(minified)?;
+
+    // Render both at the original's size so a changed viewport shows up
+    let size = original.size();
+    let scale = (MAX_RENDER_SIZE / size.width().max(size.height())).min(1.0);
+    let width = (size.width() * scale).ceil().max(1.0) as u32;
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+
+    let score = ssim(
+        &render(&original, width, height)?,
+        &render(&minified, width, height)?,
+    );
+    Ok(Fidelity {
+        score,
+        passed: score >= threshold,
+    })
+}
+
+fn parse_tree(svg: &str) -> Result<usvg::Tree, SvagError> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+
+    let options = usvg::Options {
+        fontdb: FONTS
+            .get_or_init(|| {
+                let mut db = usvg::fontdb::Database::new();
+                db.load_system_fonts();
+                Arc::new(db)
+            })
+            .clone(),
+        ..usvg::Options::default()
+    };
+    usvg::Tree::from_str(svg, &options).map_err(|e| SvagError::Render(e.to_string()))
+}
+
+fn render(tree: &usvg::Tree, width: u32, height: u32) -> Result<RgbImage, SvagError> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| SvagError::Render(format!("invalid size {}x{}", width, height)))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // The background is opaque, so premultiplied RGB is plain RGB
+    let rgb = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| [p.red(), p.green(), p.blue()])
+        .collect();
+    Ok(RgbImage::from_raw(width, height, rgb).expect("buffer matches dimensions"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minify;
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        <!-- A red square -->
+        <rect x="10.0001" y="10" width="80" height="80" fill="#ff0000" fill-opacity="1"/>
+    </svg>"##;
+
+    #[test]
+    fn test_verify_fidelity_passes() {
+        let fidelity = verify_fidelity(SVG, &minify(SVG).unwrap(), 0.999).unwrap();
+        assert!(fidelity.passed, "score {}", fidelity.score);
+    }
+
+    #[test]
+    fn test_verify_fidelity_fails() {
+        let broken = SVG
+            .replace("#ff0000", "#0000ff")
+            .replace("width=\"80\"", "width=\"20\"");
+        let fidelity = verify_fidelity(SVG, &broken, 0.999).unwrap();
+        assert!(!fidelity.passed);
+    }
+
+    #[test]
+    fn test_font_shorthand_renders() {
+        // resvg drops text whose `font` has a line height, and a unitless
+        // size makes the whole shorthand invalid CSS
+        for style in [
+            "font-weight:bold;font-size:14px;line-height:125%;font-family:DejaVu Sans",
+            "font-weight:bold;font-size:18.000000;font-family:DejaVu Sans",
+        ] {
+            let svg = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="40"><text x="10" y="30" style="{style}">Hello, world</text></svg>"#
+            );
+            let minified = minify(&svg).unwrap();
+            let fidelity = verify_fidelity(&svg, &minified, 0.999).unwrap();
+            assert!(fidelity.passed, "score {} for {minified}", fidelity.score);
+        }
+    }
+
+    #[test]
+    fn test_verify_fidelity_invalid() {
+        assert!(matches!(
+            verify_fidelity(SVG, "<svg", 0.999),
+            Err(SvagError::Render(_))
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! Image comparison helpers for checking visual fidelity.
+//!
+//! These are the same checks the visual regression suite uses: render the
+//! original and minified SVG (with any rasterizer), then compare the images
+//! with [`ssim`].
+//!
+//! ```ignore
+//! let (w, h) = svag::visual::dimensions(&doc).unwrap_or((480.0, 360.0));
+//! let original = render(svg, w as u32, h as u32);
+//! let minified = render(&svag::minify(svg)?, w as u32, h as u32);
+//! assert!(svag::visual::ssim(&original, &minified) >= 0.999);
+//! ```
+
+pub use image::RgbImage;
+
+use crate::Document;
+
+/// Structural similarity between two images, from 0 (unrelated) to 1
+/// (identical). Images of different sizes score 0.
+pub fn ssim(a: &RgbImage, b: &RgbImage) -> f64 {
+    if a.dimensions() != b.dimensions() {
+        return 0.0;
+    }
+
+    let a = ssim::Image::from_rgb_image(a.as_raw());
+    let b = ssim::Image::from_rgb_image(b.as_raw());
+    a.ssim(&b) as f64
+}
+
+/// The intrinsic size of a document in user units: `width`/`height` on the
+/// root, falling back to the `viewBox` size.
+///
+/// Unit suffixes like `px` are ignored; percentages are not resolved.
+pub fn dimensions(doc: &Document) -> Option<(f64, f64)> {
+    let root = &doc.root;
+    let view_box = root.get_attr("viewBox").and_then(|vb| {
+        let parts: Vec<f64> = vb
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().ok())
+            .collect::<Option<_>>()?;
+        match parts[..] {
+            [_, _, w, h] => Some((w, h)),
+            _ => None,
+        }
+    });
+
+    let width = root.get_attr("width").and_then(parse_length);
+    let height = root.get_attr("height").and_then(parse_length);
+
+    match (width, height, view_box) {
+        (Some(w), Some(h), _) => Some((w, h)),
+        // Scale the missing side by the viewBox aspect ratio
+        (Some(w), None, Some((vw, vh))) if vw > 0.0 => Some((w, w * vh / vw)),
+        (None, Some(h), Some((vw, vh))) if vh > 0.0 => Some((h * vw / vh, h)),
+        (None, None, view_box) => view_box,
+        _ => None,
+    }
+}
+
+fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.ends_with('%') {
+        return None;
+    }
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E')))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_svg;
+
+    #[test]
+    fn test_dimensions() {
+        let dims = |svg| dimensions(&parse_svg(svg).unwrap());
+        assert_eq!(
+            dims(r#"<svg width="100px" height="50"/>"#),
+            Some((100.0, 50.0))
+        );
+        assert_eq!(dims(r#"<svg viewBox="0 0 24 12"/>"#), Some((24.0, 12.0)));
+        assert_eq!(
+            dims(r#"<svg width="48" viewBox="0,0,24,12"/>"#),
+            Some((48.0, 24.0))
+        );
+        assert_eq!(dims(r#"<svg width="100%" height="100%"/>"#), None);
+    }
+
+    #[test]
+    fn test_ssim() {
+        let white = RgbImage::from_pixel(16, 16, image::Rgb([255, 255, 255]));
+        let mut dotted = white.clone();
+        dotted.put_pixel(8, 8, image::Rgb([0, 0, 0]));
+
+        assert!((ssim(&white, &white) - 1.0).abs() < 1e-6);
+        assert!(ssim(&white, &dotted) < 1.0);
+        assert_eq!(ssim(&white, &RgbImage::new(8, 8)), 0.0);
+    }
+}
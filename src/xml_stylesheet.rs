@@ -0,0 +1,157 @@
+//! Inline stylesheets linked with `<?xml-stylesheet href="…"?>`.
+//!
+//! A linked stylesheet styles the document like a `<style>` element would,
+//! but the optimizer can't see it. With a resolver to load it, it's moved
+//! into a `<style>` at the start of the root, where it keeps its place in
+//! the cascade (before any stylesheet in the document). Without one, the
+//! instruction is kept and passes that depend on knowing every stylesheet
+//! are skipped.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::*;
+
+/// Loads the CSS an `<?xml-stylesheet?>` instruction links to, given its
+/// `href`. Returning `None` keeps the instruction as it is.
+///
+/// ```
+/// let options = svag::Options {
+///     stylesheet_resolver: Some(svag::StylesheetResolver::new(|href| {
+///         (href == "theme.css").then(|| ".a{fill:red}".to_string())
+///     })),
+///     ..svag::Options::default()
+/// };
+/// let svg = r#"<?xml-stylesheet href="theme.css"?><svg><rect class="a"/></svg>"#;
+/// let out = svag::minify_with_options(svg, &options).unwrap();
+/// assert_eq!(out, r#"<svg><style>.a{fill:red}</style><rect class="a"/></svg>"#);
+/// ```
+#[derive(Clone)]
+pub struct StylesheetResolver(Arc<ResolveFn>);
+
+type ResolveFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl StylesheetResolver {
+    pub fn new(resolve: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        StylesheetResolver(Arc::new(resolve))
+    }
+
+    pub fn resolve(&self, href: &str) -> Option<String> {
+        (self.0)(href)
+    }
+}
+
+impl fmt::Debug for StylesheetResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StylesheetResolver")
+    }
+}
+
+/// Replace the `<?xml-stylesheet?>` instructions `resolver` can load with
+/// `<style>` elements.
+pub(crate) fn inline_stylesheets(doc: &mut Document, resolver: &StylesheetResolver) {
+    let mut styles = Vec::new();
+    doc.prolog.retain(|node| {
+        let Node::ProcessingInstruction { target, content } = node else {
+            return true;
+        };
+        let Some(css) = (target == "xml-stylesheet")
+            .then(|| stylesheet_css(content.as_deref().unwrap_or(""), resolver))
+            .flatten()
+        else {
+            return true;
+        };
+        let mut style = Element::new("style");
+        style.append_child(Node::Text(css.into()));
+        styles.push(style);
+        false
+    });
+
+    for (i, style) in styles.into_iter().enumerate() {
+        doc.root.insert_before(i, style);
+    }
+}
+
+/// The CSS to inline for an instruction with pseudo-attributes `content`,
+/// wrapped in `@media` if it only applies to some media.
+fn stylesheet_css(content: &str, resolver: &StylesheetResolver) -> Option<String> {
+    let attrs = pseudo_attributes(content)?;
+    let get = |name: &str| {
+        attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+
+    // XSLT and alternate stylesheets don't apply by default
+    if get("type").is_some_and(|t| !t.eq_ignore_ascii_case("text/css"))
+        || get("alternate") == Some("yes")
+    {
+        return None;
+    }
+    let css = resolver.resolve(get("href")?)?;
+    Some(match get("media").map(str::trim) {
+        None | Some("" | "all") => css,
+        Some(media) => format!("@media {media}{{{css}}}"),
+    })
+}
+
+/// Parse `name="value"` pairs, quoted with `"` or `'`. Returns `None` if
+/// the content isn't made of them.
+pub(crate) fn pseudo_attributes(content: &str) -> Option<Vec<(String, String)>> {
+    let mut attrs = Vec::new();
+    let mut rest = content.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+        let quote = after.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let (value, after) = after[1..].split_once(quote)?;
+        attrs.push((name.trim().to_string(), unescape(value)));
+        rest = after.trim_start();
+    }
+    Some(attrs)
+}
+
+/// Resolve the predefined entities pseudo-attribute values may use.
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Options, parse_svg, serialize};
+
+    #[test]
+    fn test_inline_stylesheets() {
+        let resolver = StylesheetResolver::new(|href| match href {
+            "a.css" => Some(".a{fill:red}".to_string()),
+            "print.css" => Some(".b{fill:#000}".to_string()),
+            _ => None,
+        });
+        let svg = r#"<?xml-stylesheet href="a.css"?><?xml-stylesheet type="text/css" media='print' href="print.css"?><?xml-stylesheet href="missing.css"?><?xml-stylesheet alternate="yes" href="a.css"?><svg><style>.c{}</style></svg>"#;
+        let mut doc = parse_svg(svg).unwrap();
+        inline_stylesheets(&mut doc, &resolver);
+        assert_eq!(
+            serialize(&doc, &Options::default()),
+            r#"<?xml-stylesheet href="missing.css"?><?xml-stylesheet alternate="yes" href="a.css"?><svg><style>.a{fill:red}</style><style>@media print{.b{fill:#000}}</style><style>.c{}</style></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_pseudo_attributes() {
+        assert_eq!(
+            pseudo_attributes(r#" href="a&amp;b.css"  media = 'screen' "#),
+            Some(vec![
+                ("href".to_string(), "a&b.css".to_string()),
+                ("media".to_string(), "screen".to_string()),
+            ])
+        );
+        assert_eq!(pseudo_attributes(r#"href=a.css"#), None);
+    }
+}
@@ -0,0 +1,51 @@
+//! Compile-time SVG minification.
+//!
+//! ```ignore
+//! const LOGO: &str = svag_macros::minified_svg!("assets/logo.svg");
+//! ```
+//!
+//! The path is resolved relative to the crate's `CARGO_MANIFEST_DIR`, and the
+//! file is tracked so edits trigger a rebuild.
+
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{LitStr, parse_macro_input};
+
+/// Minify an SVG file at compile time, expanding to a `&'static str`.
+#[proc_macro]
+pub fn minified_svg(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as LitStr);
+
+    match minify_file(&lit.value()) {
+        Ok((path, minified)) => {
+            let path = path.to_string_lossy();
+            quote! {
+                {
+                    // Makes cargo rebuild when the SVG changes
+                    const _: &[u8] = include_bytes!(#path);
+                    #minified
+                }
+            }
+            .into()
+        }
+        Err(message) => syn::Error::new(lit.span(), message)
+            .to_compile_error()
+            .into(),
+    }
+}
+
+fn minify_file(relative: &str) -> Result<(PathBuf, String), String> {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .ok_or("CARGO_MANIFEST_DIR is not set")?;
+    let path = root.join(relative);
+
+    let svg = std::fs::read_to_string(&path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let minified =
+        svag::minify(&svg).map_err(|e| format!("failed to minify {}: {}", path.display(), e))?;
+
+    Ok((path, minified))
+}
@@ -0,0 +1,11 @@
+use svag_macros::minified_svg;
+
+const LOGO: &str = minified_svg!("tests/fixtures/logo.svg");
+
+#[test]
+fn test_minified_svg() {
+    assert_eq!(
+        LOGO,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" height="100" width="100"><rect fill="red" height="80" width="80" x="10" y="10"/></svg>"#
+    );
+}
@@ -0,0 +1,136 @@
+//! Tests that run the `svag` binary and check its exit codes and output.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const VALID: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect width="1" height="1"/></svg>"#;
+const BROKEN: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect width="1""#;
+
+fn svag(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_svag"))
+        .current_dir(dir)
+        .arg("--quiet")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("valid.svg"), VALID).unwrap();
+    fs::write(dir.path().join("broken.svg"), BROKEN).unwrap();
+
+    let output = svag(dir.path(), &["valid.svg", "-o", "-"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    // Parse errors are EX_DATAERR
+    let output = svag(dir.path(), &["broken.svg", "-o", "-"]);
+    assert_eq!(output.status.code(), Some(65));
+    let output = svag(dir.path(), &["--bench", "."]);
+    assert_eq!(output.status.code(), Some(65));
+
+    // Unreadable inputs and unwritable outputs are EX_IOERR
+    let output = svag(dir.path(), &["missing.svg", "-o", "-"]);
+    assert_eq!(output.status.code(), Some(74));
+    let output = svag(dir.path(), &["valid.svg", "-o", "missing/out.svg"]);
+    assert_eq!(output.status.code(), Some(74));
+}
+
+#[test]
+fn test_check_and_sprite_continue_past_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.svg"), BROKEN).unwrap();
+    fs::write(dir.path().join("b.svg"), VALID).unwrap();
+    fs::write(dir.path().join("c.svg"), BROKEN).unwrap();
+
+    // Logging stays on: the errors are what's being checked
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_svag"))
+            .current_dir(dir.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    // Every file is still checked, and every broken one named
+    let output = run(&["check", "."]);
+    assert_eq!(output.status.code(), Some(65));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("a.svg") && stderr.contains("c.svg"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("3 files checked"), "{stderr}");
+
+    let output = run(&["sprite", ".", "-o", "sprite.svg"]);
+    assert_eq!(output.status.code(), Some(65));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("a.svg") && stderr.contains("c.svg"),
+        "{stderr}"
+    );
+    assert!(!dir.path().join("sprite.svg").exists());
+
+    let output = run(&["sprite", "b.svg", "missing.svg", "-o", "-"]);
+    assert_eq!(output.status.code(), Some(74));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_jobs() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..16 {
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><!-- icon {i} --><rect x="{i}" width="1" height="1"/></svg>"#
+        );
+        fs::write(dir.path().join(format!("{i}.svg")), svg).unwrap();
+    }
+
+    // The same files come out of one thread, several, and the
+    // RAYON_NUM_THREADS default
+    let minified = |args: &[&str], threads: Option<&str>| {
+        let copy = tempfile::tempdir().unwrap();
+        for entry in fs::read_dir(dir.path()).unwrap() {
+            let path = entry.unwrap().path();
+            fs::copy(&path, copy.path().join(path.file_name().unwrap())).unwrap();
+        }
+        let mut command = Command::new(env!("CARGO_BIN_EXE_svag"));
+        command
+            .current_dir(copy.path())
+            .arg("--quiet")
+            .args(args)
+            .arg(".");
+        if let Some(threads) = threads {
+            command.env("RAYON_NUM_THREADS", threads);
+        }
+        assert!(command.status().unwrap().success(), "{args:?}");
+        let mut files: Vec<_> = fs::read_dir(copy.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (
+                    path.file_name().unwrap().to_owned(),
+                    fs::read(&path).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let sequential = minified(&["--jobs", "1"], None);
+    assert_eq!(sequential.len(), 16);
+    assert!(
+        sequential
+            .iter()
+            .all(|(_, svg)| !svg.windows(4).any(|w| w == b"<!--"))
+    );
+    assert_eq!(minified(&["--jobs", "1"], None), sequential);
+    assert_eq!(minified(&["--jobs", "4"], None), sequential);
+    assert_eq!(minified(&[], Some("3")), sequential);
+
+    let output = svag(dir.path(), &["--jobs", "0", "."]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--jobs"));
+}
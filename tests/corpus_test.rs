@@ -5,7 +5,7 @@ use std::fs;
 use std::path::Path;
 
 use ignore::WalkBuilder;
-use svag::{minify, parse_svg};
+use svag::{OptimizeFor, Options, SvgTarget, check, minify, optimize, parse_svg, serialize};
 
 /// Test that all corpus SVGs can be parsed and minified without errors.
 #[test]
@@ -40,17 +40,18 @@ fn test_corpus_minification() {
             total += 1;
 
             // Test parsing and minification
-            match minify(&content) {
-                Ok(minified) => {
-                    let minified_size = minified.len();
-
-                    // Verify minified output is valid SVG
-                    if let Err(e) = parse_svg(&minified) {
-                        eprintln!(
-                            "  FAIL {}: minified output invalid: {}",
-                            rel_path.display(),
-                            e
-                        );
+            let options = Options::default();
+            match parse_svg(&content) {
+                Ok(mut doc) => {
+                    optimize(&mut doc, &options);
+                    let minified_size = serialize(&doc, &options).len();
+
+                    // Verify minified output reparses to the same structure
+                    let violations = check::roundtrip(&doc, &options);
+                    if !violations.is_empty() {
+                        for violation in &violations {
+                            eprintln!("  FAIL {}: {}", rel_path.display(), violation);
+                        }
                         failed += 1;
                         continue;
                     }
@@ -83,6 +84,67 @@ fn test_corpus_minification() {
     assert_eq!(failed, 0, "{} SVG files failed to minify", failed);
 }
 
+/// Test that minifying minified output changes nothing, with the default
+/// options and with every opt-in pass. Directories listed in `SVAG_CORPUS`
+/// (separated like `PATH`) are checked with the default options as well,
+/// for corpora too large to check in.
+#[test]
+fn test_corpus_idempotent() {
+    let option_sets = [
+        Options::default(),
+        Options {
+            remove_occluded: true,
+            regroup_siblings: true,
+            extract_classes: true,
+            ..Options::default()
+        },
+        Options {
+            optimize_for: OptimizeFor::GzipSize,
+            regroup_siblings: true,
+            extract_classes: true,
+            ..Options::default()
+        },
+        Options {
+            target: SvgTarget::Svg11,
+            precision: 0,
+            transform_precision: 1,
+            ..Options::default()
+        },
+    ];
+
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dirs = ["tests/corpus", "tests/visual_corpus"]
+        .map(|dir| (manifest_dir.join(dir), &option_sets[..]));
+    let external = std::env::var_os("SVAG_CORPUS")
+        .map(|dirs| std::env::split_paths(&dirs).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| (dir, &option_sets[..1]));
+
+    let mut failed = 0;
+    for (dir, option_sets) in dirs.into_iter().chain(external) {
+        for entry in WalkBuilder::new(&dir).git_ignore(false).build() {
+            let path = entry.unwrap().into_path();
+            if path.extension().is_none_or(|e| e != "svg") {
+                continue;
+            }
+            // An external corpus may hold files that aren't UTF-8
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            for options in option_sets {
+                let violations = check::idempotent(&content, options).unwrap();
+                for violation in &violations {
+                    eprintln!("  FAIL {}: {}", path.display(), violation);
+                }
+                failed += usize::from(!violations.is_empty());
+            }
+        }
+    }
+
+    assert_eq!(failed, 0, "{} minifications were not fixpoints", failed);
+}
+
 /// Test specific optimization behaviors.
 #[test]
 fn test_inkscape_cleanup() {
@@ -89,3 +89,50 @@ fn test_replace_and_roundtrip() {
     assert!(chars.contains(&'f'));
     assert!(chars.contains(&'n'));
 }
+
+/// Subsetting keeps the glyphs the text uses and drops the others.
+#[cfg(feature = "subset")]
+#[test]
+fn test_subset_keeps_used_glyphs() {
+    use base64::Engine;
+    use svag::{FontResolver, FontSource, subset_embedded_fonts};
+
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+  <style>@font-face { font-family: 'Tuffy'; src: url('Tuffy-ASCII.ttf'); }</style>
+  <text font-family="Tuffy">Hi <tspan>there!</tspan></text>
+</svg>"#;
+    let mut doc = parse_svg(svg).unwrap();
+    let resolver = FontResolver::from_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"));
+    assert_eq!(subset_embedded_fonts(&mut doc, &resolver).unwrap(), 1);
+
+    let faces = extract_font_faces(&doc);
+    let [FontSource::Url { url, format, .. }] = faces[0].sources.as_slice() else {
+        panic!("expected one embedded source: {:?}", faces[0].sources);
+    };
+    assert_eq!(format.as_deref(), Some("woff2"));
+    let woff2 = base64::prelude::BASE64_STANDARD
+        .decode(url.strip_prefix("data:font/woff2;base64,").unwrap())
+        .unwrap();
+    let font = fontcull::decompress_font(&woff2).unwrap();
+    let face = ttf_parser::Face::parse(&font, 0).unwrap();
+
+    for c in "Hi there!".chars() {
+        assert!(face.glyph_index(c).is_some(), "missing {c:?}");
+    }
+    for c in "ABZaxz0?".chars() {
+        assert!(face.glyph_index(c).is_none(), "kept unused {c:?}");
+    }
+
+    // The fixture itself has them all
+    let original = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/Tuffy-ASCII.ttf"
+    ))
+    .unwrap();
+    let original = ttf_parser::Face::parse(&original, 0).unwrap();
+    assert!(
+        "ABZaxz0?"
+            .chars()
+            .all(|c| original.glyph_index(c).is_some())
+    );
+}
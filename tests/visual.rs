@@ -16,6 +16,7 @@ use image::RgbImage;
 use tempfile::TempDir;
 
 use svag::minify;
+use svag::visual::{dimensions, ssim};
 
 const TEST_OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test_output");
 const VISUAL_CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/visual_corpus");
@@ -23,48 +24,13 @@ const VISUAL_CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/visu
 /// Minimum acceptable SSIM score (99.9% similarity)
 const MIN_SSIM: f64 = 0.999;
 
-/// Compute SSIM between two images using the ssim crate.
-fn compute_ssim(img1: &RgbImage, img2: &RgbImage) -> f64 {
-    let (w1, h1) = img1.dimensions();
-    let (w2, h2) = img2.dimensions();
-
-    if w1 != w2 || h1 != h2 {
-        return 0.0;
-    }
-
-    let bytes1: Vec<u8> = img1.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
-    let bytes2: Vec<u8> = img2.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
-
-    let ssim_img1 = ssim::Image::from_rgb_image(&bytes1);
-    let ssim_img2 = ssim::Image::from_rgb_image(&bytes2);
-
-    ssim_img1.ssim(&ssim_img2) as f64
-}
-
-/// Extract width and height from SVG.
-fn extract_dimensions(svg: &str) -> Option<(u32, u32)> {
-    let width = extract_attr(svg, "width")?;
-    let height = extract_attr(svg, "height")?;
-
-    let w: f64 = width
-        .trim_end_matches(|c: char| !c.is_ascii_digit() && c != '.')
-        .parse()
-        .ok()?;
-    let h: f64 = height
-        .trim_end_matches(|c: char| !c.is_ascii_digit() && c != '.')
-        .parse()
-        .ok()?;
-
+/// Rendering size for an SVG, at least 100x100.
+fn render_dimensions(svg: &str) -> Option<(u32, u32)> {
+    let doc = svag::parse_svg(svg).ok()?;
+    let (w, h) = dimensions(&doc)?;
     Some((w.max(100.0) as u32, h.max(100.0) as u32))
 }
 
-fn extract_attr(svg: &str, attr: &str) -> Option<String> {
-    let pattern = format!("{}=\"", attr);
-    let start = svg.find(&pattern)? + pattern.len();
-    let end = svg[start..].find('"')? + start;
-    Some(svg[start..end].to_string())
-}
-
 /// Render an SVG string to a PNG image using the browser.
 async fn render_svg(browser: &Browser, svg: &str, width: u32, height: u32) -> RgbImage {
     let page = browser.new_page("about:blank").await.unwrap();
@@ -125,7 +91,7 @@ async fn run_visual_tests(
             }
         };
 
-        let (width, height) = extract_dimensions(svg).unwrap_or((480, 360));
+        let (width, height) = render_dimensions(svg).unwrap_or((480, 360));
 
         // Render original
         let original_img = render_svg(browser, svg, width, height).await;
@@ -142,7 +108,7 @@ async fn run_visual_tests(
         fs::write(output_dir.join(format!("{}_original.svg", name)), svg).unwrap();
         fs::write(output_dir.join(format!("{}_minified.svg", name)), &minified).unwrap();
 
-        let ssim_score = compute_ssim(&original_img, &minified_img);
+        let ssim_score = ssim(&original_img, &minified_img);
         let passed = ssim_score >= MIN_SSIM;
 
         let status = if passed { "PASS" } else { "FAIL" };
@@ -186,7 +186,9 @@ fn fetch_wikimedia_commons(dest: &Path) -> io::Result<usize> {
 fn deduplicate(dest: &Path) -> io::Result<usize> {
     println!("Deduplicating...");
 
-    let mut seen: RapidHashMap<u64, PathBuf> = RapidHashMap::default();
+    // Files that parse are compared by what they draw, so the same icon
+    // from two exporters counts once; others by their bytes
+    let mut seen: RapidHashMap<(bool, u64), PathBuf> = RapidHashMap::default();
     let mut removed = 0;
 
     for entry in WalkBuilder::new(dest).build() {
@@ -195,7 +197,10 @@ fn deduplicate(dest: &Path) -> io::Result<usize> {
 
         if path.is_file() && path.extension().is_some_and(|e| e == "svg") {
             let contents = fs::read(path)?;
-            let hash = rapidhash::rapidhash(&contents);
+            let hash = match std::str::from_utf8(&contents).map(svag::parse_svg) {
+                Ok(Ok(doc)) => (true, svag::fingerprint(&doc)),
+                _ => (false, rapidhash::rapidhash(&contents)),
+            };
 
             if let std::collections::hash_map::Entry::Vacant(e) = seen.entry(hash) {
                 e.insert(path.to_path_buf());